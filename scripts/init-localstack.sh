@@ -20,47 +20,10 @@ while ! curl -s http://localhost:4566/health | grep -q "<Name>health</Name>"; do
     sleep 1
 done
 
-# Create DynamoDB tables
-echo "Creating DynamoDB tables..."
-
-# Create trash bins table
-aws --endpoint-url=http://localhost:4566 dynamodb create-table \
-    --table-name trash-bins \
-    --attribute-definitions \
-        AttributeName=binId,AttributeType=S \
-        AttributeName=status,AttributeType=N \
-    --key-schema \
-        AttributeName=binId,KeyType=HASH \
-    --provisioned-throughput \
-        ReadCapacityUnits=5,WriteCapacityUnits=5 \
-    --global-secondary-indexes \
-        "[
-            {
-                \"IndexName\": \"status-index\",
-                \"KeySchema\": [
-                    {\"AttributeName\":\"status\",\"KeyType\":\"HASH\"}
-                ],
-                \"Projection\": {
-                    \"ProjectionType\":\"ALL\"
-                },
-                \"ProvisionedThroughput\": {
-                    \"ReadCapacityUnits\": 5,
-                    \"WriteCapacityUnits\": 5
-                }
-            }
-        ]"
-
-# Create status reports table
-aws --endpoint-url=http://localhost:4566 dynamodb create-table \
-    --table-name status-reports \
-    --attribute-definitions \
-        AttributeName=binId,AttributeType=S \
-        AttributeName=createdAt,AttributeType=S \
-    --key-schema \
-        AttributeName=binId,KeyType=HASH \
-        AttributeName=createdAt,KeyType=RANGE \
-    --provisioned-throughput \
-        ReadCapacityUnits=5,WriteCapacityUnits=5
+# Create or update DynamoDB tables and run data migrations
+echo "Migrating DynamoDB tables..."
+(cd "$(dirname "$0")/../services/bin-status-reporter" && \
+    cargo run --quiet --bin ecoscan-migrate -- --endpoint http://localhost:4566 apply)
 
 # Create default trash bin
 echo "Creating default trash bin..."
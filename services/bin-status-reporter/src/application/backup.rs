@@ -0,0 +1,266 @@
+use std::io::{BufRead, Write};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{error, info, instrument};
+
+use crate::domain::backup::{BackupRepository, ExportCursor, Record, EXPORT_FORMAT_VERSION};
+use crate::error::AppError;
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub page_size: usize,
+    /// Stop after this many records and return where to resume.
+    pub max_records: Option<usize>,
+    pub resume: Option<ExportCursor>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self { page_size: 500, max_records: None, resume: None }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExportSummary {
+    pub records: usize,
+    /// Set when the export stopped before the end.
+    pub resume: Option<String>,
+}
+
+/// Writes every location, bin, QR code and log entry to `out` as JSON
+/// Lines, after a header with the format version. Each page is flushed
+/// before its cursor is logged, so an interrupted export can be resumed
+/// into a new file from the last cursor logged.
+#[instrument(skip_all)]
+pub async fn export<R: BackupRepository, W: Write>(
+    repo: &R,
+    out: &mut W,
+    exported_at: DateTime<Utc>,
+    options: &ExportOptions,
+) -> Result<ExportSummary, AppError> {
+    let mut summary = ExportSummary::default();
+    write_line(out, &Record::Header { version: EXPORT_FORMAT_VERSION, exported_at })?;
+
+    let mut cursor = Some(options.resume.clone().unwrap_or_else(ExportCursor::start));
+    while let Some(current) = cursor.take() {
+        let limit = match options.max_records {
+            Some(max_records) if summary.records >= max_records => {
+                cursor = Some(current);
+                break;
+            }
+            Some(max_records) => options.page_size.min(max_records - summary.records),
+            None => options.page_size,
+        };
+        let page = repo.export_page(current.section, current.after.as_deref(), limit).await?;
+        for record in &page.records {
+            write_line(out, record)?;
+        }
+        out.flush().map_err(write_error)?;
+        summary.records += page.records.len();
+
+        cursor = match page.next {
+            Some(after) => Some(ExportCursor { section: current.section, after: Some(after) }),
+            None => current.section.next().map(|section| ExportCursor { section, after: None }),
+        };
+        if let Some(next) = &cursor {
+            info!(records = summary.records, resume = %next, "Exported page");
+        }
+    }
+
+    summary.resume = cursor.map(|cursor| cursor.to_string());
+    Ok(summary)
+}
+
+fn write_line<W: Write>(out: &mut W, record: &Record) -> Result<(), AppError> {
+    let line = serde_json::to_string(record)
+        .map_err(|e| AppError::InternalError(format!("Failed to serialize record: {}", e)))?;
+    writeln!(out, "{}", line).map_err(write_error)
+}
+
+fn write_error(e: std::io::Error) -> AppError {
+    AppError::InternalError(format!("Failed to write export: {}", e))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Lines already imported by an earlier run. They are still checked
+    /// for a header, but not written again.
+    pub skip_lines: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub lines: usize,
+    pub imported: usize,
+}
+
+/// Stores the records of an export in file order. Every record replaces
+/// the stored copy, so importing a file twice, or resuming an import from
+/// an earlier line, leaves the same data. Fails on a record before the
+/// first header or in a later format version.
+#[instrument(skip_all, fields(skip_lines = options.skip_lines))]
+pub async fn import<R: BackupRepository, B: BufRead>(
+    repo: &R,
+    input: B,
+    options: &ImportOptions,
+) -> Result<ImportSummary, AppError> {
+    let mut summary = ImportSummary::default();
+    let mut versioned = false;
+
+    for (index, line) in input.lines().enumerate() {
+        let number = index + 1;
+        let line = line.map_err(|e| AppError::InternalError(format!("Failed to read line {}: {}", number, e)))?;
+        summary.lines = number;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record: Record = serde_json::from_str(&line)
+            .map_err(|e| AppError::InvalidRequest(format!("Line {}: {}", number, e)))?;
+        match record {
+            Record::Header { version, .. } if version > EXPORT_FORMAT_VERSION => {
+                return Err(AppError::InvalidRequest(format!(
+                    "Line {}: export format version {} is newer than the supported version {}",
+                    number, version, EXPORT_FORMAT_VERSION
+                )));
+            }
+            Record::Header { .. } => versioned = true,
+            _ if !versioned => {
+                return Err(AppError::InvalidRequest(format!("Line {}: record before the export header", number)));
+            }
+            _ if number <= options.skip_lines => {}
+            record => {
+                if let Err(e) = repo.import_record(&record).await {
+                    error!(line = number, "Import stopped; resume with --skip-lines {}", number - 1);
+                    return Err(e);
+                }
+                summary.imported += 1;
+                if summary.imported % 1000 == 0 {
+                    info!(line = number, imported = summary.imported, "Imported records");
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use uuid::Uuid;
+
+    use crate::domain::backup::{ExportPage, Section};
+    use crate::domain::collection::CollectionEvent;
+    use crate::domain::sensor::BinCalibration;
+    use crate::domain::{BinRepository, BinStatus, Location, QRCode, StatusReport};
+    use crate::infrastructure::memory::InMemoryRepository;
+
+    /// A municipality with one placed, calibrated bin, one loose bin, and
+    /// a collection between reports.
+    async fn municipality() -> InMemoryRepository {
+        let repo = InMemoryRepository::new();
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 19, 6, 0, 0).unwrap();
+        let location = Location {
+            id: Uuid::new_v4(),
+            address: "Náměstí Míru 1, Praha".to_string(),
+            latitude: 50.075,
+            longitude: 14.437,
+            name: "Náměstí Míru".to_string(),
+            district: Some("Praha 2".to_string()),
+        };
+        let placed = Uuid::new_v4();
+        repo.add_location(location.clone());
+        repo.add_bin(placed);
+        repo.place_bin(placed, location.id);
+        repo.set_calibration(placed, BinCalibration::new(1200, 150).unwrap());
+        repo.add_qr_code(QRCode { id: Uuid::new_v4(), url: "https://ecoscan.cz/b/abc".to_string(), trash_bin_id: placed, created_at: t0 });
+        repo.add_bin(Uuid::new_v4());
+
+        for (value, hours) in [(9, 0), (3, 2), (6, 3)] {
+            let report = StatusReport::citizen(Uuid::new_v4(), placed, BinStatus::new(value).unwrap(), t0 + Duration::hours(hours));
+            repo.update_status(&report).await.unwrap();
+            repo.add_report(&report).await.unwrap();
+        }
+        let collection = CollectionEvent { bin_id: placed, collected_at: t0 + Duration::hours(1), received_at: t0 + Duration::hours(4) };
+        repo.record_collection(&collection).await.unwrap();
+        repo
+    }
+
+    async fn exported(repo: &InMemoryRepository, options: &ExportOptions) -> (Vec<u8>, ExportSummary) {
+        let mut out = Vec::new();
+        let summary = export(repo, &mut out, Utc::now(), options).await.unwrap();
+        (out, summary)
+    }
+
+    /// Everything a backend can export, to compare two backends by.
+    async fn contents(repo: &InMemoryRepository) -> Vec<Vec<Record>> {
+        let mut sections = Vec::new();
+        for section in Section::ALL {
+            let ExportPage { records, .. } = repo.export_page(section, None, usize::MAX).await.unwrap();
+            sections.push(records);
+        }
+        sections
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_keeps_every_record() {
+        let source = municipality().await;
+        let (file, summary) = exported(&source, &ExportOptions::default()).await;
+        assert_eq!((summary.records, summary.resume), (8, None));
+
+        let target = InMemoryRepository::new();
+        let imported = import(&target, file.as_slice(), &ImportOptions::default()).await.unwrap();
+
+        assert_eq!((imported.lines, imported.imported), (9, 8));
+        assert_eq!(contents(&target).await, contents(&source).await);
+    }
+
+    #[tokio::test]
+    async fn test_import_is_idempotent() {
+        let source = municipality().await;
+        let (file, _) = exported(&source, &ExportOptions::default()).await;
+        let target = InMemoryRepository::new();
+
+        import(&target, file.as_slice(), &ImportOptions::default()).await.unwrap();
+        let once = contents(&target).await;
+        import(&target, file.as_slice(), &ImportOptions::default()).await.unwrap();
+        import(&target, file.as_slice(), &ImportOptions { skip_lines: 4 }).await.unwrap();
+
+        assert_eq!(contents(&target).await, once);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_export_covers_the_rest() {
+        let source = municipality().await;
+        let (first, stopped) =
+            exported(&source, &ExportOptions { page_size: 2, max_records: Some(3), ..ExportOptions::default() }).await;
+        assert_eq!(stopped.records, 3);
+
+        let resume = stopped.resume.unwrap().parse().unwrap();
+        let (rest, finished) =
+            exported(&source, &ExportOptions { page_size: 2, resume: Some(resume), ..ExportOptions::default() }).await;
+        assert_eq!((finished.records, finished.resume), (5, None));
+
+        // Each part is a complete file
+        let target = InMemoryRepository::new();
+        import(&target, first.as_slice(), &ImportOptions::default()).await.unwrap();
+        import(&target, rest.as_slice(), &ImportOptions::default()).await.unwrap();
+        assert_eq!(contents(&target).await, contents(&source).await);
+    }
+
+    #[tokio::test]
+    async fn test_import_checks_the_format_version() {
+        let repo = InMemoryRepository::new();
+        let bin = r#"{"kind":"bin","bin_id":"123e4567-e89b-12d3-a456-426614174000","aggregate":{"weighted_sum":0.0,"weight_total":0.0,"reports_count":0}}"#;
+
+        let headerless = import(&repo, bin.as_bytes(), &ImportOptions::default()).await;
+        assert!(matches!(headerless, Err(AppError::InvalidRequest(message)) if message.contains("header")));
+
+        let newer = format!(r#"{{"kind":"header","version":{},"exported_at":"2024-03-20T12:00:00Z"}}"#, EXPORT_FORMAT_VERSION + 1);
+        let future = import(&repo, format!("{}\n{}", newer, bin).as_bytes(), &ImportOptions::default()).await;
+        assert!(matches!(future, Err(AppError::InvalidRequest(message)) if message.contains("newer")));
+        assert!(repo.aggregate(&"123e4567-e89b-12d3-a456-426614174000".parse().unwrap()).is_none());
+    }
+}
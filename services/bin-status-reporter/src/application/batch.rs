@@ -0,0 +1,238 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use crate::domain::batch::{
+    group_by_bin, BatchItemResult, BatchStatusItem, BatchStatusRequest, BatchStatusResponse,
+    MAX_BATCH_ITEMS,
+};
+use crate::application::context::AppContext;
+use crate::application::lifecycle;
+use crate::application::metrics::{error_reason, record_outcome, record_rejection};
+use crate::domain::lifecycle::LifecycleRepository;
+use crate::domain::{BinRepository, BinStatus, ReportSource, StatusReport};
+use crate::error::AppError;
+
+/// Validates every item on its own and stores the valid ones grouped by bin.
+/// As with single reports, items for a replaced bin count towards its
+/// replacement and items for bins out of service are rejected. Only a batch
+/// that is too large fails as a whole.
+#[instrument(skip_all, fields(items = request.items.len(), source = "citizen"))]
+pub async fn handle_batch_status_update<R: BinRepository + LifecycleRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    request: BatchStatusRequest,
+) -> Result<BatchStatusResponse, AppError> {
+    if request.items.len() > MAX_BATCH_ITEMS {
+        return Err(AppError::InvalidRequest(format!(
+            "Batch contains {} items, at most {} are allowed",
+            request.items.len(),
+            MAX_BATCH_ITEMS
+        )));
+    }
+    info!("Processing batch of {} status updates", request.items.len());
+
+    let received_at = ctx.now();
+    let mut results = Vec::with_capacity(request.items.len());
+    let mut reports = Vec::new();
+    let mut indices = Vec::new();
+    let mut seen = HashSet::new();
+    let mut routes = HashMap::new();
+
+    for (index, raw) in request.items.into_iter().enumerate() {
+        let item = match validate_item(raw, ctx, received_at) {
+            Ok(report) => route(repo, &mut routes, report).await,
+            Err(rejection) => Err(rejection),
+        };
+        match item {
+            Ok(report) if !seen.insert((report.bin_id, report.observed_at)) => {
+                record_rejection(ctx.metrics.as_ref(), ReportSource::Citizen, "duplicate");
+                results.push(BatchItemResult::rejected(
+                    index,
+                    Some(report.bin_id),
+                    "Duplicate report for the same bin and timestamp".to_string(),
+                ));
+            }
+            Ok(report) => {
+                indices.push((index, report.bin_id));
+                reports.push(report);
+            }
+            Err((bin_id, e)) => {
+                warn!("Rejected batch item {}: {}", index, e);
+                record_rejection(ctx.metrics.as_ref(), ReportSource::Citizen, error_reason(&e));
+                results.push(BatchItemResult::rejected(index, bin_id, e.to_string()));
+            }
+        }
+    }
+
+    let groups = group_by_bin(reports);
+    let outcomes = repo.record_batch(&groups).await;
+
+    for (group, outcome) in groups.iter().zip(outcomes) {
+        for (index, _) in indices.iter().filter(|(_, bin_id)| *bin_id == group.bin_id) {
+            record_outcome(ctx.metrics.as_ref(), ReportSource::Citizen, &outcome);
+            results.push(match &outcome {
+                Ok(()) => BatchItemResult::accepted(*index, group.bin_id),
+                Err(e) => BatchItemResult::rejected(*index, Some(group.bin_id), e.to_string()),
+            });
+        }
+        if let Err(e) = outcome {
+            warn!("Failed to store {} reports for bin {}: {}", group.reports.len(), group.bin_id, e);
+        }
+    }
+
+    let response = BatchStatusResponse::from_results(results);
+    info!("Batch processed - Accepted: {}, Rejected: {}", response.accepted, response.rejected);
+    Ok(response)
+}
+
+/// Points a report at the bin it counts towards, looking up each bin's
+/// lifecycle once per batch.
+async fn route<R: LifecycleRepository>(
+    repo: &R,
+    routes: &mut HashMap<Uuid, Uuid>,
+    mut report: StatusReport,
+) -> Result<StatusReport, (Option<Uuid>, AppError)> {
+    let bin_id = match routes.get(&report.bin_id) {
+        Some(bin_id) => *bin_id,
+        None => {
+            let bin_id = lifecycle::reporting_bin(repo, report.bin_id)
+                .await
+                .map_err(|e| (Some(report.bin_id), e))?;
+            routes.insert(report.bin_id, bin_id);
+            bin_id
+        }
+    };
+    report.bin_id = bin_id;
+    Ok(report)
+}
+
+fn validate_item(
+    raw: serde_json::Value,
+    ctx: &AppContext,
+    received_at: DateTime<Utc>,
+) -> Result<StatusReport, (Option<Uuid>, AppError)> {
+    let bin_id = raw
+        .get("bin_id")
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.parse::<Uuid>().ok());
+
+    let item: BatchStatusItem = serde_json::from_value(raw)
+        .map_err(|e| (bin_id, AppError::InvalidRequest(format!("Malformed item: {}", e))))?;
+    // Deserialization does not range-check the status
+    let status = BinStatus::new(item.status.value()).map_err(|e| (bin_id, e))?;
+
+    let observed_at = ctx
+        .timestamps
+        .resolve(Some(item.client_timestamp), received_at)
+        .map_err(|e| (bin_id, e))?;
+
+    Ok(StatusReport::citizen(ctx.next_id(), item.bin_id, status, observed_at).with_received_at(received_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::lifecycle::handle_lifecycle_change;
+    use crate::domain::lifecycle::{LifecycleRequest, LifecycleState};
+    use crate::infrastructure::memory::InMemoryRepository;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn item(bin_id: Uuid, value: i32, minutes_ago: i64) -> serde_json::Value {
+        json!({
+            "bin_id": bin_id.to_string(),
+            "status": {"value": value},
+            "client_timestamp": (Utc::now() - Duration::minutes(minutes_ago)).to_rfc3339(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_per_item_results() {
+        let repo = InMemoryRepository::new();
+        let (known, unknown) = (Uuid::new_v4(), Uuid::new_v4());
+        repo.add_bin(known);
+
+        let request = BatchStatusRequest {
+            items: vec![
+                item(known, 2, 30),
+                item(unknown, 5, 20),
+                json!({"bin_id": "not-a-uuid", "status": {"value": 3}}),
+                item(known, 11, 10),
+                item(known, 8, 5),
+            ],
+        };
+
+        let response = handle_batch_status_update(&repo, &AppContext::default(), request).await.unwrap();
+
+        assert_eq!(response.accepted, 2);
+        assert_eq!(response.rejected, 3);
+        let success: Vec<_> = response.results.iter().map(|result| result.success).collect();
+        assert_eq!(success, vec![true, false, false, false, true]);
+        assert!(response.results[1].error.as_ref().unwrap().contains("Bin not found"));
+        assert!(response.results[2].error.as_ref().unwrap().contains("Malformed item"));
+        assert!(response.results[3].error.as_ref().unwrap().contains("between 0 and 10"));
+        assert_eq!(response.results[3].bin_id, Some(known));
+
+        assert_eq!(repo.reports(&known).len(), 2);
+        assert_eq!(repo.bin_status(&known).unwrap().value(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_future_and_duplicate_items() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let duplicate = item(bin_id, 4, 15);
+
+        let request = BatchStatusRequest {
+            items: vec![duplicate.clone(), duplicate, item(bin_id, 4, -60)],
+        };
+        let response = handle_batch_status_update(&repo, &AppContext::default(), request).await.unwrap();
+
+        assert_eq!(response.accepted, 1);
+        assert!(response.results[1].error.as_ref().unwrap().contains("Duplicate"));
+        assert!(response.results[2].error.as_ref().unwrap().contains("future"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_follows_bin_lifecycle() {
+        let repo = InMemoryRepository::new();
+        let (replaced, replacement, removed) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for bin_id in [replaced, replacement, removed] {
+            repo.add_bin(bin_id);
+        }
+        for (bin_id, state, replaced_by) in [
+            (replaced, LifecycleState::Replaced, Some(replacement)),
+            (removed, LifecycleState::Removed, None),
+        ] {
+            let request = LifecycleRequest { bin_id, state, actor: "crew-7".to_string(), reason: None, replaced_by };
+            handle_lifecycle_change(&repo, &AppContext::default(), request).await.unwrap();
+        }
+
+        let request = BatchStatusRequest {
+            items: vec![item(replaced, 6, 20), item(removed, 9, 15), item(replaced, 7, 10)],
+        };
+        let response = handle_batch_status_update(&repo, &AppContext::default(), request).await.unwrap();
+
+        assert_eq!((response.accepted, response.rejected), (2, 1));
+        assert_eq!(response.results[0].bin_id, Some(replacement));
+        assert!(response.results[1].error.as_ref().unwrap().contains("removed"));
+        assert!(repo.reports(&replaced).is_empty());
+        assert!(repo.reports(&removed).is_empty());
+        assert_eq!(repo.reports(&replacement).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_too_large() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        let request = BatchStatusRequest {
+            items: (0..=MAX_BATCH_ITEMS as i64).map(|i| item(bin_id, 1, i)).collect(),
+        };
+
+        let result = handle_batch_status_update(&repo, &AppContext::default(), request).await;
+        assert!(matches!(result, Err(AppError::InvalidRequest(_))));
+    }
+}
@@ -0,0 +1,101 @@
+use tracing::{error, info, instrument};
+
+use crate::domain::collection::{CollectionEvent, CollectionRequest};
+use crate::application::context::AppContext;
+use crate::domain::{BinRepository, StatusUpdateResponse};
+use crate::error::AppError;
+
+/// Records that a bin was emptied. Reports observed before the collection
+/// stop counting towards the estimate, even if they arrive afterwards.
+#[instrument(skip_all, fields(bin_id = %request.bin_id))]
+pub async fn handle_collection<R: BinRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    request: CollectionRequest,
+) -> Result<StatusUpdateResponse, AppError> {
+    info!("Processing collection of bin: {}", request.bin_id);
+
+    let received_at = ctx.now();
+    let event = CollectionEvent {
+        bin_id: request.bin_id,
+        collected_at: ctx.timestamps.resolve(request.collected_at, received_at)?,
+        received_at,
+    };
+
+    if let Err(e) = repo.record_collection(&event).await {
+        error!("Failed to record collection of bin {}: {}", event.bin_id, e);
+        return Err(e);
+    }
+
+    Ok(StatusUpdateResponse {
+        success: true,
+        message: format!("Collection recorded at {}", event.collected_at.to_rfc3339()),
+        updated_at: received_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::handle_status_update;
+    use crate::domain::{BinStatus, StatusUpdateRequest};
+    use crate::infrastructure::memory::InMemoryRepository;
+    use chrono::{DateTime, Duration, Utc};
+    use uuid::Uuid;
+
+    async fn report(repo: &InMemoryRepository, bin_id: Uuid, value: i32, observed_at: DateTime<Utc>) {
+        let request = StatusUpdateRequest {
+            bin_id,
+            status: BinStatus::new(value).unwrap(),
+            observed_at: Some(observed_at),
+        };
+        handle_status_update(repo, &AppContext::default(), request).await.unwrap();
+    }
+
+    async fn collect(repo: &InMemoryRepository, bin_id: Uuid, collected_at: DateTime<Utc>) {
+        let request = CollectionRequest { bin_id, collected_at: Some(collected_at) };
+        handle_collection(repo, &AppContext::default(), request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_report_older_than_collection_is_logged_but_not_counted() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let now = Utc::now();
+
+        collect(&repo, bin_id, now - Duration::hours(2)).await;
+        report(&repo, bin_id, 3, now - Duration::hours(1)).await;
+        // Uploaded late, but the bin was emptied after this was seen
+        report(&repo, bin_id, 10, now - Duration::hours(5)).await;
+
+        assert_eq!(repo.bin_status(&bin_id).unwrap().value(), 3);
+        assert_eq!(repo.reports(&bin_id).len(), 2);
+        assert_eq!(repo.aggregate(&bin_id).unwrap().last_observed_at, Some(now - Duration::hours(1)));
+    }
+
+    #[tokio::test]
+    async fn test_late_collection_keeps_newer_reports() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let now = Utc::now();
+
+        report(&repo, bin_id, 10, now - Duration::hours(4)).await;
+        report(&repo, bin_id, 2, now - Duration::minutes(30)).await;
+        // The crew synced after the citizen did
+        collect(&repo, bin_id, now - Duration::hours(1)).await;
+
+        assert_eq!(repo.bin_status(&bin_id).unwrap().value(), 2);
+        assert_eq!(repo.aggregate(&bin_id).unwrap().reports_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_collection_of_unknown_bin_fails() {
+        let repo = InMemoryRepository::new();
+        let request = CollectionRequest { bin_id: Uuid::new_v4(), collected_at: None };
+
+        let result = handle_collection(&repo, &AppContext::default(), request).await;
+        assert!(matches!(result, Err(AppError::BinNotFound(_))));
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+use tracing::{info, instrument, warn};
+
+use crate::domain::consistency::{recompute, BinHistoryRepository, Drift, ResumeToken};
+use crate::domain::estimator::FillEstimator;
+use crate::error::AppError;
+
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Write the recomputed estimate over drifted bins.
+    pub fix: bool,
+    pub page_size: usize,
+    /// Upper bound on bins checked per second, to leave read capacity for
+    /// the functions. `None` checks as fast as the table allows.
+    pub bins_per_second: Option<f64>,
+    /// Stop after this many bins and return where to resume.
+    pub max_bins: Option<usize>,
+    pub resume: Option<ResumeToken>,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self { fix: false, page_size: 100, bins_per_second: Some(50.0), max_bins: None, resume: None }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ConsistencySummary {
+    pub bins_checked: usize,
+    pub drifted: Vec<Drift>,
+    pub repaired: usize,
+    /// Drifted bins left alone because a report arrived during the check.
+    pub changed_during_check: usize,
+    /// Set when the run stopped before the end of the table.
+    pub resume: Option<String>,
+}
+
+/// Recomputes each bin's estimate from its report log and reports, or with
+/// `fix` repairs, the bins whose stored estimate differs. Each page logs
+/// its resume token, so an interrupted run can continue from there.
+#[instrument(skip_all, fields(fix = options.fix))]
+pub async fn run_consistency_check<R: BinHistoryRepository>(
+    repo: &R,
+    estimator: &FillEstimator,
+    options: &CheckOptions,
+) -> Result<ConsistencySummary, AppError> {
+    let mut summary = ConsistencySummary::default();
+    let mut resume = options.resume.clone();
+
+    loop {
+        let limit = match options.max_bins {
+            Some(max_bins) if summary.bins_checked >= max_bins => break,
+            Some(max_bins) => options.page_size.min(max_bins - summary.bins_checked),
+            None => options.page_size,
+        };
+        let started = Instant::now();
+        let page = repo.scan_bins(resume.as_ref(), limit).await?;
+
+        for stored in &page.bins {
+            let events = repo.bin_events(&stored.bin_id, stored.aggregate.collected_at, None).await?;
+            let recomputed = recompute(estimator, stored.aggregate.collected_at, &events);
+            summary.bins_checked += 1;
+
+            let Some(drift) = Drift::detect(stored, &recomputed) else {
+                continue;
+            };
+            warn!("Drift in {}", drift);
+            if options.fix {
+                if repo.repair_bin(stored, &recomputed).await? {
+                    summary.repaired += 1;
+                } else {
+                    summary.changed_during_check += 1;
+                }
+            }
+            summary.drifted.push(drift);
+        }
+
+        resume = page.next;
+        match &resume {
+            Some(token) => info!(bins_checked = summary.bins_checked, resume = %token, "Checked page"),
+            None => break,
+        }
+
+        if let Some(rate) = options.bins_per_second.filter(|rate| *rate > 0.0) {
+            let budget = Duration::from_secs_f64(page.bins.len() as f64 / rate);
+            tokio::time::sleep_until(started + budget).await;
+        }
+    }
+
+    summary.resume = resume.map(|token| token.to_string());
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::domain::estimator::BinAggregate;
+    use crate::domain::{BinRepository, BinStatus, StatusReport};
+    use crate::infrastructure::memory::InMemoryRepository;
+
+    fn unthrottled() -> CheckOptions {
+        CheckOptions { bins_per_second: None, page_size: 2, ..CheckOptions::default() }
+    }
+
+    /// Three bins, one of which lost the update for its second report.
+    async fn drifted_repository() -> (InMemoryRepository, Uuid) {
+        let repo = InMemoryRepository::new();
+        let mut drifted = Uuid::nil();
+        for value in [2, 5, 9] {
+            let bin_id = Uuid::new_v4();
+            repo.add_bin(bin_id);
+            let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), Utc::now());
+            repo.update_status(&report).await.unwrap();
+            repo.add_report(&report).await.unwrap();
+            if value == 5 {
+                let lost = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), Utc::now());
+                repo.add_report(&lost).await.unwrap();
+                drifted = bin_id;
+            }
+        }
+        (repo, drifted)
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_drift_without_fixing() {
+        let (repo, drifted) = drifted_repository().await;
+
+        let summary = run_consistency_check(&repo, &FillEstimator::default(), &unthrottled()).await.unwrap();
+
+        assert_eq!(summary.bins_checked, 3);
+        assert_eq!(summary.drifted.iter().map(|drift| drift.bin_id).collect::<Vec<_>>(), vec![drifted]);
+        assert_eq!(summary.repaired, 0);
+        assert_eq!(summary.resume, None);
+        assert_eq!(repo.bin_status(&drifted).unwrap().value(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_fix_repairs_drift() {
+        let (repo, drifted) = drifted_repository().await;
+        let options = CheckOptions { fix: true, ..unthrottled() };
+
+        let summary = run_consistency_check(&repo, &FillEstimator::default(), &options).await.unwrap();
+
+        assert_eq!(summary.repaired, 1);
+        assert_eq!(repo.bin_status(&drifted).unwrap().value(), 8);
+        let again = run_consistency_check(&repo, &FillEstimator::default(), &unthrottled()).await.unwrap();
+        assert!(again.drifted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resumes_where_it_stopped() {
+        let (repo, _) = drifted_repository().await;
+        let first = run_consistency_check(
+            &repo,
+            &FillEstimator::default(),
+            &CheckOptions { max_bins: Some(2), ..unthrottled() },
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.bins_checked, 2);
+
+        let resume = first.resume.unwrap().parse().unwrap();
+        let rest = run_consistency_check(
+            &repo,
+            &FillEstimator::default(),
+            &CheckOptions { resume: Some(resume), ..unthrottled() },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(rest.bins_checked, 1);
+        assert_eq!(first.drifted.len() + rest.drifted.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_repair_skips_bins_changed_since_the_scan() {
+        let (repo, drifted) = drifted_repository().await;
+        let page = repo.scan_bins(None, 10).await.unwrap();
+        let stored = page.bins.iter().find(|bin| bin.bin_id == drifted).unwrap();
+
+        let report = StatusReport::citizen(Uuid::new_v4(), drifted, BinStatus::empty(), Utc::now());
+        repo.update_status(&report).await.unwrap();
+
+        assert!(!repo.repair_bin(stored, &BinAggregate::default()).await.unwrap());
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use shared::clock::{Clock, IdGenerator, SystemClock, UuidGenerator};
+use shared::metrics::{MetricsRecorder, NoopRecorder};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::domain::device_health::HealthPolicy;
+use crate::domain::timing::TimestampPolicy;
+
+/// What the use cases need besides a repository: the time, new ids, the
+/// bounds for client timestamps, the device health thresholds and somewhere
+/// to send metrics. Tests swap
+/// in a fake clock and sequential ids to get exact results.
+#[derive(Clone)]
+pub struct AppContext {
+    pub clock: Arc<dyn Clock>,
+    pub ids: Arc<dyn IdGenerator>,
+    pub timestamps: TimestampPolicy,
+    pub health: HealthPolicy,
+    pub metrics: Arc<dyn MetricsRecorder>,
+}
+
+impl AppContext {
+    pub fn new(clock: Arc<dyn Clock>, ids: Arc<dyn IdGenerator>, timestamps: TimestampPolicy) -> Self {
+        Self { clock, ids, timestamps, health: HealthPolicy::default(), metrics: Arc::new(NoopRecorder) }
+    }
+
+    pub fn with_health_policy(self, health: HealthPolicy) -> Self {
+        Self { health, ..self }
+    }
+
+    pub fn with_metrics(self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        Self { metrics, ..self }
+    }
+
+    pub fn system(timestamps: TimestampPolicy) -> Self {
+        Self::new(Arc::new(SystemClock), Arc::new(UuidGenerator), timestamps)
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    pub fn next_id(&self) -> Uuid {
+        self.ids.next_id()
+    }
+}
+
+impl Default for AppContext {
+    fn default() -> Self {
+        Self::system(TimestampPolicy::default())
+    }
+}
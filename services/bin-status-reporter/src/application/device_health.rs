@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, warn};
+
+use crate::domain::device_health::{
+    AlertNotifier, DeviceAlert, DeviceHealth, DeviceHealthRepository, HealthPolicy,
+};
+use crate::error::AppError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthCheckSummary {
+    pub devices_checked: usize,
+    pub devices_flagged: usize,
+    pub alerts_sent: usize,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Scheduled job: re-evaluates every device against the policy, stores the
+/// resulting flags and alerts once per newly raised flag. A flag that stays
+/// raised across runs is not re-sent.
+#[instrument(skip_all, fields(checked_at = %now))]
+pub async fn run_device_health_check<R, N>(
+    repo: &R,
+    notifier: &N,
+    policy: &HealthPolicy,
+    now: DateTime<Utc>,
+) -> Result<HealthCheckSummary, AppError>
+where
+    R: DeviceHealthRepository,
+    N: AlertNotifier,
+{
+    let devices = repo.list_health().await?;
+    let devices_checked = devices.len();
+    info!("Checking health of {} devices", devices_checked);
+
+    let mut devices_flagged = 0;
+    let mut alerts_sent = 0;
+
+    for mut health in devices {
+        let flags = policy.evaluate(&health, now);
+        if !flags.is_empty() {
+            devices_flagged += 1;
+        }
+
+        let raised: Vec<_> = flags
+            .iter()
+            .filter(|flag| !health.flags.iter().any(|previous| previous.kind() == flag.kind()))
+            .cloned()
+            .collect();
+
+        if flags != health.flags {
+            health.flags = flags;
+            repo.save_health(&health).await?;
+        }
+
+        for flag in raised {
+            warn!("Device {} raised health flag: {}", health.device_id, flag.kind());
+            let alert = DeviceAlert {
+                device_id: health.device_id.clone(),
+                bin_id: health.bin_id,
+                flag,
+                raised_at: now,
+            };
+            match notifier.notify(&alert).await {
+                Ok(()) => alerts_sent += 1,
+                Err(e) => error!("Failed to send alert for device {}: {}", health.device_id, e),
+            }
+        }
+    }
+
+    Ok(HealthCheckSummary {
+        devices_checked,
+        devices_flagged,
+        alerts_sent,
+        checked_at: now,
+    })
+}
+
+/// Admin query: devices with at least one open flag, worst first.
+pub async fn list_flagged_devices<R: DeviceHealthRepository>(repo: &R) -> Result<Vec<DeviceHealth>, AppError> {
+    let mut flagged: Vec<_> = repo
+        .list_health()
+        .await?
+        .into_iter()
+        .filter(|health| !health.flags.is_empty())
+        .collect();
+    flagged.sort_by(|a, b| b.flags.len().cmp(&a.flags.len()).then(a.last_seen.cmp(&b.last_seen)));
+    Ok(flagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::device_health::{DeviceTelemetry, HealthFlag};
+    use async_trait::async_trait;
+    use chrono::Duration;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct Registry {
+        devices: Mutex<HashMap<String, DeviceHealth>>,
+    }
+
+    #[async_trait]
+    impl DeviceHealthRepository for Registry {
+        async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+            Ok(self.devices.lock().unwrap().get(device_id).cloned())
+        }
+
+        async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+            self.devices.lock().unwrap().insert(health.device_id.clone(), health.clone());
+            Ok(())
+        }
+
+        async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+            let mut devices: Vec<_> = self.devices.lock().unwrap().values().cloned().collect();
+            devices.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+            Ok(devices)
+        }
+    }
+
+    #[derive(Default)]
+    struct CapturingNotifier {
+        alerts: Mutex<Vec<DeviceAlert>>,
+    }
+
+    #[async_trait]
+    impl AlertNotifier for CapturingNotifier {
+        async fn notify(&self, alert: &DeviceAlert) -> Result<(), AppError> {
+            self.alerts.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    fn seen(registry: &Registry, device_id: &str, seen_at: DateTime<Utc>, battery_mv: u16) {
+        let telemetry = DeviceTelemetry {
+            device_id: device_id.to_string(),
+            seen_at,
+            distance_mm: 500,
+            battery_mv: Some(battery_mv),
+            rssi_dbm: None,
+            firmware_version: None,
+        };
+        registry
+            .devices
+            .lock()
+            .unwrap()
+            .insert(device_id.to_string(), DeviceHealth::first_seen(Uuid::new_v4(), &telemetry));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_flags_and_alerts_once() {
+        let registry = Registry::default();
+        let notifier = CapturingNotifier::default();
+        let now = Utc::now();
+        seen(&registry, "healthy", now - Duration::minutes(10), 3600);
+        seen(&registry, "silent", now - Duration::days(1), 3600);
+        seen(&registry, "weak", now - Duration::minutes(10), 3100);
+
+        let summary = run_device_health_check(&registry, &notifier, &HealthPolicy::default(), now).await.unwrap();
+        assert_eq!(summary.devices_flagged, 2);
+        assert_eq!(summary.alerts_sent, 2);
+
+        let summary = run_device_health_check(&registry, &notifier, &HealthPolicy::default(), now).await.unwrap();
+        assert_eq!(summary.devices_flagged, 2);
+        assert_eq!(summary.alerts_sent, 0);
+
+        let alerts = notifier.alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 2);
+        assert!(matches!(alerts[0].flag, HealthFlag::Silent { .. }));
+        assert!(matches!(alerts[1].flag, HealthFlag::LowBattery { battery_mv: 3100 }));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_clears_recovered_flags() {
+        let registry = Registry::default();
+        let notifier = CapturingNotifier::default();
+        let now = Utc::now();
+        seen(&registry, "silent", now - Duration::days(1), 3600);
+        run_device_health_check(&registry, &notifier, &HealthPolicy::default(), now).await.unwrap();
+
+        seen(&registry, "silent", now, 3600);
+        run_device_health_check(&registry, &notifier, &HealthPolicy::default(), now).await.unwrap();
+
+        assert!(registry.get_health("silent").await.unwrap().unwrap().flags.is_empty());
+        assert!(list_flagged_devices(&registry).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_flagged_devices_orders_worst_first() {
+        let registry = Registry::default();
+        let notifier = CapturingNotifier::default();
+        let now = Utc::now();
+        seen(&registry, "weak", now - Duration::minutes(10), 3100);
+        seen(&registry, "silent-and-weak", now - Duration::days(1), 3000);
+        seen(&registry, "healthy", now, 3600);
+        run_device_health_check(&registry, &notifier, &HealthPolicy::default(), now).await.unwrap();
+
+        let flagged = list_flagged_devices(&registry).await.unwrap();
+        let ids: Vec<_> = flagged.iter().map(|health| health.device_id.as_str()).collect();
+        assert_eq!(ids, vec!["silent-and-weak", "weak"]);
+    }
+}
@@ -0,0 +1,257 @@
+use chrono::Duration;
+use tracing::{error, info, instrument, warn};
+
+use crate::application::context::AppContext;
+use crate::application::{lifecycle, metered, record_report};
+use crate::domain::incident::{
+    IncidentDispatcher, IncidentQuery, IncidentReport, IncidentRepository, ReportDetails, ReportKind, ReportRequest,
+    ReportResponse,
+};
+use crate::domain::lifecycle::{LifecycleRepository, LifecycleRequest, LifecycleState};
+use crate::domain::{BinRepository, BinStatus, ReportSource, StatusReport};
+use crate::error::AppError;
+
+/// Incidents listed when the query sets no limit, and the most it may ask for.
+pub const DEFAULT_INCIDENT_LIMIT: usize = 50;
+pub const MAX_INCIDENT_LIMIT: usize = 500;
+/// How far back incidents are listed when the query sets no start.
+const DEFAULT_INCIDENT_DAYS: i64 = 7;
+
+/// Who damage reports move a bin to damaged as, in its lifecycle trail.
+pub const CITIZEN_REPORT_ACTOR: &str = "citizen report";
+
+/// Records a citizen report of any kind. Fill reports update the estimate
+/// as before. Other kinds are kept in the report log and routed: damage
+/// marks an active bin damaged and opens a maintenance ticket, overflow
+/// also counts as a full bin and is escalated with fire. An action that
+/// cannot be delivered is logged without failing the stored report.
+#[instrument(skip_all, fields(bin_id = %request.bin_id, kind = request.details.kind().as_str()))]
+pub async fn handle_bin_report<R, D>(
+    repo: &R,
+    ctx: &AppContext,
+    dispatcher: &D,
+    request: ReportRequest,
+) -> Result<ReportResponse, AppError>
+where
+    R: BinRepository + LifecycleRepository + IncidentRepository,
+    D: IncidentDispatcher,
+{
+    let details = request.details.validated()?;
+    let received_at = ctx.now();
+    let observed_at = ctx.timestamps.resolve(request.observed_at, received_at)?;
+    let bin_id = lifecycle::reporting_bin(repo, request.bin_id).await?;
+    let kind = details.kind();
+
+    let fill = match &details {
+        ReportDetails::Fill { status } => Some(status.clone()),
+        ReportDetails::Overflow { .. } => Some(BinStatus::full()),
+        _ => None,
+    };
+    let report_id = ctx.next_id();
+    let mut message = match fill {
+        Some(status) => {
+            // An overflow's fill report is a report of its own
+            let fill_id = if kind == ReportKind::Fill { report_id } else { ctx.next_id() };
+            let report = StatusReport::citizen(fill_id, bin_id, status, observed_at).with_received_at(received_at);
+            metered(ctx, ReportSource::Citizen, record_report(repo, ctx, &report)).await?.message
+        }
+        None => String::new(),
+    };
+
+    if kind != ReportKind::Fill {
+        let incident = IncidentReport { report_id, bin_id, details, observed_at, received_at };
+        repo.add_incident(&incident).await?;
+        info!("Recorded {} report {} for bin {}", kind.as_str(), report_id, bin_id);
+        if kind == ReportKind::Damage {
+            mark_damaged(repo, ctx, &incident).await;
+        }
+        for action in incident.actions() {
+            if let Err(e) = dispatcher.dispatch(&action).await {
+                error!("Failed to dispatch action for report {}: {}", report_id, e);
+            }
+        }
+        message = if message.is_empty() {
+            format!("Recorded {} report", kind.as_str())
+        } else {
+            format!("Recorded {} report; {}", kind.as_str(), message)
+        };
+    }
+
+    if bin_id != request.bin_id {
+        message = format!("{} (bin {} was replaced by {})", message, request.bin_id, bin_id);
+    }
+    Ok(ReportResponse {
+        success: true,
+        report_id,
+        bin_id,
+        kind,
+        message,
+        received_at,
+    })
+}
+
+/// Moves an active bin to damaged, leaving bins already damaged or moved
+/// on by a crew as they are.
+async fn mark_damaged<R: LifecycleRepository>(repo: &R, ctx: &AppContext, incident: &IncidentReport) {
+    let request = LifecycleRequest {
+        bin_id: incident.bin_id,
+        state: LifecycleState::Damaged,
+        actor: CITIZEN_REPORT_ACTOR.to_string(),
+        reason: Some(format!("Damage report {}", incident.report_id)),
+        replaced_by: None,
+    };
+    let result = async {
+        match repo.bin_lifecycle(&incident.bin_id).await? {
+            Some(lifecycle) if lifecycle.state == LifecycleState::Active => {
+                let transition = lifecycle.transition(&request, ctx.now())?;
+                repo.record_transition(&incident.bin_id, &transition).await
+            }
+            _ => Ok(false),
+        }
+    };
+    match result.await {
+        Ok(true) => info!("Bin {} marked damaged by report {}", incident.bin_id, incident.report_id),
+        Ok(false) => {}
+        Err(e) => warn!("Failed to mark bin {} damaged: {}", incident.bin_id, e),
+    }
+}
+
+/// Incidents of one kind across all bins, newest first, by default from
+/// the last 7 days.
+#[instrument(skip(repo, ctx))]
+pub async fn handle_list_incidents<R: IncidentRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    query: IncidentQuery,
+) -> Result<Vec<IncidentReport>, AppError> {
+    if query.kind == ReportKind::Fill {
+        return Err(AppError::InvalidRequest("Fill reports are read per bin, not listed".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_INCIDENT_LIMIT);
+    if limit == 0 || limit > MAX_INCIDENT_LIMIT {
+        return Err(AppError::InvalidRequest(format!("limit must be between 1 and {}", MAX_INCIDENT_LIMIT)));
+    }
+    let since = query.since.unwrap_or_else(|| ctx.now() - Duration::days(DEFAULT_INCIDENT_DAYS));
+    repo.incidents(query.kind, since, limit).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::application::lifecycle::handle_get_lifecycle;
+    use crate::domain::incident::{DamagedPart, IncidentAction};
+    use crate::domain::WasteType;
+    use crate::infrastructure::memory::InMemoryRepository;
+
+    #[derive(Default)]
+    struct CapturingDispatcher {
+        actions: Mutex<Vec<IncidentAction>>,
+    }
+
+    #[async_trait]
+    impl IncidentDispatcher for CapturingDispatcher {
+        async fn dispatch(&self, action: &IncidentAction) -> Result<(), AppError> {
+            self.actions.lock().unwrap().push(action.clone());
+            Ok(())
+        }
+    }
+
+    async fn report(repo: &InMemoryRepository, dispatcher: &CapturingDispatcher, bin_id: Uuid, details: ReportDetails) -> Result<ReportResponse, AppError> {
+        let request = ReportRequest { bin_id, details, observed_at: None };
+        handle_bin_report(repo, &AppContext::default(), dispatcher, request).await
+    }
+
+    fn query(kind: ReportKind) -> IncidentQuery {
+        IncidentQuery { kind, since: None, limit: None }
+    }
+
+    #[tokio::test]
+    async fn test_damage_opens_a_ticket_and_marks_the_bin_damaged() {
+        let repo = InMemoryRepository::new();
+        let dispatcher = CapturingDispatcher::default();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+
+        let damage = ReportDetails::Damage { part: DamagedPart::Lid, description: Some("Hinge snapped".to_string()) };
+        let response = report(&repo, &dispatcher, bin_id, damage).await.unwrap();
+
+        let actions = dispatcher.actions.lock().unwrap().clone();
+        assert!(matches!(&actions[..], [IncidentAction::OpenTicket(ticket)] if ticket.report_id == response.report_id));
+        let lifecycle = handle_get_lifecycle(&repo, &bin_id).await.unwrap();
+        assert_eq!(lifecycle.state, LifecycleState::Damaged);
+        assert_eq!(lifecycle.history[0].actor, CITIZEN_REPORT_ACTOR);
+        assert_eq!(repo.bin_status(&bin_id), Some(BinStatus::new(0).unwrap()));
+        // A damaged bin is reported again without a second transition
+        report(&repo, &dispatcher, bin_id, ReportDetails::Damage { part: DamagedPart::Body, description: None }).await.unwrap();
+        assert_eq!(handle_get_lifecycle(&repo, &bin_id).await.unwrap().history.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_overflow_escalates_and_counts_as_full() {
+        let repo = InMemoryRepository::new();
+        let dispatcher = CapturingDispatcher::default();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+
+        report(&repo, &dispatcher, bin_id, ReportDetails::Overflow { waste_on_ground: true }).await.unwrap();
+
+        assert_eq!(repo.bin_status(&bin_id), Some(BinStatus::full()));
+        assert_eq!(repo.reports(&bin_id).len(), 1);
+        let actions = dispatcher.actions.lock().unwrap().clone();
+        assert!(matches!(&actions[..], [IncidentAction::Escalate(escalation)] if escalation.kind == ReportKind::Overflow));
+        assert_eq!(handle_get_lifecycle(&repo, &bin_id).await.unwrap().state, LifecycleState::Active);
+    }
+
+    #[tokio::test]
+    async fn test_incidents_are_listed_by_kind_newest_first() {
+        let repo = InMemoryRepository::new();
+        let dispatcher = CapturingDispatcher::default();
+        let ctx = AppContext::default();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        repo.add_bin(first);
+        repo.add_bin(second);
+        let sorting = ReportDetails::WrongSorting { found: vec![WasteType::Glass], description: None };
+        let earlier = ReportRequest { bin_id: first, details: sorting.clone(), observed_at: Some(Utc::now() - Duration::hours(2)) };
+        handle_bin_report(&repo, &ctx, &dispatcher, earlier).await.unwrap();
+        report(&repo, &dispatcher, second, sorting).await.unwrap();
+        report(&repo, &dispatcher, first, ReportDetails::Fire { still_burning: false }).await.unwrap();
+        report(&repo, &dispatcher, first, ReportDetails::Fill { status: BinStatus::new(4).unwrap() }).await.unwrap();
+
+        let listed = handle_list_incidents(&repo, &ctx, query(ReportKind::WrongSorting)).await.unwrap();
+
+        assert_eq!(listed.iter().map(|incident| incident.bin_id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(handle_list_incidents(&repo, &ctx, query(ReportKind::Fire)).await.unwrap().len(), 1);
+        assert!(handle_list_incidents(&repo, &ctx, query(ReportKind::Fill)).await.is_err());
+        assert!(dispatcher.actions.lock().unwrap().iter().all(|action| matches!(action, IncidentAction::Escalate(_))));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_and_out_of_service_reports_are_refused() {
+        let repo = InMemoryRepository::new();
+        let dispatcher = CapturingDispatcher::default();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+
+        let vague = ReportDetails::Damage { part: DamagedPart::Other, description: None };
+        assert!(matches!(report(&repo, &dispatcher, bin_id, vague).await, Err(AppError::InvalidRequest(_))));
+        let fire = ReportDetails::Fire { still_burning: true };
+        assert!(matches!(report(&repo, &dispatcher, Uuid::new_v4(), fire.clone()).await, Err(AppError::BinNotFound(_))));
+
+        let removed = LifecycleRequest {
+            bin_id,
+            state: LifecycleState::Removed,
+            actor: "crew-7".to_string(),
+            reason: None,
+            replaced_by: None,
+        };
+        lifecycle::handle_lifecycle_change(&repo, &AppContext::default(), removed).await.unwrap();
+        assert!(matches!(report(&repo, &dispatcher, bin_id, fire).await, Err(AppError::BinOutOfService(_))));
+        assert!(dispatcher.actions.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use crate::application::context::AppContext;
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleRequest, LifecycleState, ReportRouting, MAX_REPLACEMENTS};
+use crate::error::{AppError, DatabaseError};
+
+/// Moves a bin to a new lifecycle state, recording who made the change and
+/// why. A replaced bin's reports go to the bin named as its replacement,
+/// which must not itself be out of use for good.
+#[instrument(skip_all, fields(bin_id = %request.bin_id, state = %request.state))]
+pub async fn handle_lifecycle_change<R: LifecycleRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    request: LifecycleRequest,
+) -> Result<BinLifecycle, AppError> {
+    let mut lifecycle = get_lifecycle(repo, &request.bin_id).await?;
+    if let Some(replaced_by) = request.replaced_by {
+        let replacement = get_lifecycle(repo, &replaced_by).await?;
+        if matches!(replacement.state, LifecycleState::Removed | LifecycleState::Replaced) {
+            return Err(AppError::InvalidRequest(format!(
+                "Bin {} cannot take over, it is {}",
+                replaced_by, replacement.state
+            )));
+        }
+    }
+
+    let transition = lifecycle.transition(&request, ctx.now())?;
+    if !repo.record_transition(&request.bin_id, &transition).await? {
+        let current = get_lifecycle(repo, &request.bin_id).await?;
+        // A retry after a lost response finds its own transition applied
+        if current.history.last() == Some(&transition) {
+            return Ok(current);
+        }
+        warn!("Bin {} moved to {} before the change to {} was stored", request.bin_id, current.state, request.state);
+        return Err(AppError::DatabaseError(DatabaseError::Conflict(format!(
+            "Bin {} changed state to {} meanwhile",
+            request.bin_id, current.state
+        ))));
+    }
+
+    info!("Bin {} moved from {} to {} by {}", request.bin_id, transition.from, transition.to, transition.actor);
+    lifecycle.apply(transition);
+    Ok(lifecycle)
+}
+
+/// The bin's lifecycle state with its audit trail.
+#[instrument(skip(repo))]
+pub async fn handle_get_lifecycle<R: LifecycleRepository>(repo: &R, bin_id: &Uuid) -> Result<BinLifecycle, AppError> {
+    get_lifecycle(repo, bin_id).await
+}
+
+/// The bin a report addressed to `bin_id` counts towards: the bin itself
+/// while it is in service, or the bin that replaced it. Reports for bins
+/// that are planned, under maintenance or removed are refused.
+pub async fn reporting_bin<R: LifecycleRepository>(repo: &R, bin_id: Uuid) -> Result<Uuid, AppError> {
+    let mut current = bin_id;
+    for _ in 0..=MAX_REPLACEMENTS {
+        match get_lifecycle(repo, &current).await?.routing() {
+            ReportRouting::Accept => return Ok(current),
+            ReportRouting::Redirect(replaced_by) => {
+                info!("Bin {} was replaced by {}, following", current, replaced_by);
+                current = replaced_by;
+            }
+            ReportRouting::Reject(state) => {
+                return Err(AppError::BinOutOfService(format!("Bin {} is {}", current, state)));
+            }
+        }
+    }
+    Err(AppError::BinOutOfService(format!("Bin {} was replaced too many times to follow", bin_id)))
+}
+
+async fn get_lifecycle<R: LifecycleRepository>(repo: &R, bin_id: &Uuid) -> Result<BinLifecycle, AppError> {
+    repo.bin_lifecycle(bin_id)
+        .await?
+        .ok_or_else(|| AppError::BinNotFound(bin_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{handle_get_location_status, handle_status_update};
+    use crate::domain::{BinStatus, StatusUpdateRequest};
+    use crate::infrastructure::memory::InMemoryRepository;
+
+    fn request(bin_id: Uuid, state: LifecycleState, replaced_by: Option<Uuid>) -> LifecycleRequest {
+        LifecycleRequest { bin_id, state, actor: "crew-7".to_string(), reason: None, replaced_by }
+    }
+
+    async fn change(repo: &InMemoryRepository, bin_id: Uuid, state: LifecycleState, replaced_by: Option<Uuid>) -> Result<BinLifecycle, AppError> {
+        handle_lifecycle_change(repo, &AppContext::default(), request(bin_id, state, replaced_by)).await
+    }
+
+    async fn report(repo: &InMemoryRepository, bin_id: Uuid) -> Result<String, AppError> {
+        let request = StatusUpdateRequest { bin_id, status: BinStatus::full(), observed_at: None };
+        handle_status_update(repo, &AppContext::default(), request).await.map(|response| response.message)
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_kept_as_an_audit_trail() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+
+        change(&repo, bin_id, LifecycleState::Damaged, None).await.unwrap();
+        change(&repo, bin_id, LifecycleState::UnderMaintenance, None).await.unwrap();
+        let rejected = change(&repo, bin_id, LifecycleState::Planned, None).await;
+
+        assert!(matches!(rejected, Err(AppError::InvalidRequest(_))));
+        let lifecycle = handle_get_lifecycle(&repo, &bin_id).await.unwrap();
+        let trail: Vec<(LifecycleState, LifecycleState)> = lifecycle.history.iter().map(|t| (t.from, t.to)).collect();
+        assert_eq!(lifecycle.state, LifecycleState::UnderMaintenance);
+        assert_eq!(trail, vec![
+            (LifecycleState::Active, LifecycleState::Damaged),
+            (LifecycleState::Damaged, LifecycleState::UnderMaintenance),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_reports_for_out_of_service_bins_are_refused() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+
+        change(&repo, bin_id, LifecycleState::Damaged, None).await.unwrap();
+        assert!(report(&repo, bin_id).await.is_ok());
+        change(&repo, bin_id, LifecycleState::Removed, None).await.unwrap();
+
+        assert!(matches!(report(&repo, bin_id).await, Err(AppError::BinOutOfService(_))));
+        assert_eq!(repo.reports(&bin_id).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reports_for_replaced_bins_go_to_the_replacement() {
+        let repo = InMemoryRepository::new();
+        let (old, new) = (Uuid::new_v4(), Uuid::new_v4());
+        repo.add_bin(old);
+        repo.add_bin(new);
+
+        assert!(change(&repo, old, LifecycleState::Replaced, Some(Uuid::new_v4())).await.is_err());
+        change(&repo, old, LifecycleState::Replaced, Some(new)).await.unwrap();
+        let message = report(&repo, old).await.unwrap();
+
+        assert!(message.contains(&new.to_string()));
+        assert!(repo.reports(&old).is_empty());
+        assert_eq!(repo.bin_status(&new), Some(BinStatus::full()));
+        // A bin that was replaced cannot take over another
+        let other = Uuid::new_v4();
+        repo.add_bin(other);
+        assert!(change(&repo, other, LifecycleState::Replaced, Some(old)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_removed_bins_leave_their_location() {
+        let repo = InMemoryRepository::new();
+        let location_id = Uuid::new_v4();
+        let (kept, removed) = (Uuid::new_v4(), Uuid::new_v4());
+        for bin_id in [kept, removed] {
+            repo.add_bin(bin_id);
+            repo.place_bin(bin_id, location_id);
+        }
+        report(&repo, removed).await.unwrap();
+
+        change(&repo, removed, LifecycleState::Removed, None).await.unwrap();
+        let status = handle_get_location_status(&repo, &location_id, None).await.unwrap();
+
+        assert_eq!((status.bins_count, status.overflowing_bins), (1, 0));
+        assert_eq!(status.fullest_bin.unwrap().bin_id, kept);
+    }
+}
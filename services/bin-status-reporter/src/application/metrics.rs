@@ -0,0 +1,51 @@
+use shared::metrics::MetricsRecorder;
+
+use crate::domain::{ReportSource, StatusChange};
+use crate::error::{AppError, DatabaseError};
+
+// Names follow CloudWatch conventions; the Prometheus recorder converts them
+pub const REPORTS_ACCEPTED: &str = "ReportsAccepted";
+pub const REPORTS_REJECTED: &str = "ReportsRejected";
+pub const FILL_CATEGORY_TRANSITIONS: &str = "FillCategoryTransitions";
+pub const COLD_STARTS: &str = "ColdStarts";
+
+/// Short, bounded label for why a call failed, safe to use as a dimension.
+pub fn error_reason(error: &AppError) -> &'static str {
+    match error {
+        AppError::InvalidRequest(_) => "invalid_request",
+        AppError::BinNotFound(_) => "bin_not_found",
+        AppError::LocationNotFound(_) => "location_not_found",
+        AppError::BinOutOfService(_) => "bin_out_of_service",
+        AppError::Unauthorized(_) => "unauthorized",
+        AppError::InternalError(_) => "internal",
+        AppError::DatabaseError(e) => match e {
+            DatabaseError::Throttled(_) => "throttled",
+            DatabaseError::Conflict(_) => "conflict",
+            DatabaseError::Timeout(_) => "timeout",
+            DatabaseError::Unavailable(_) => "unavailable",
+            DatabaseError::CircuitOpen(_) => "circuit_open",
+            DatabaseError::Permanent(_) => "database",
+            DatabaseError::Malformed(_) => "malformed_item",
+        },
+    }
+}
+
+pub fn record_outcome<T>(metrics: &dyn MetricsRecorder, source: ReportSource, result: &Result<T, AppError>) {
+    match result {
+        Ok(_) => metrics.increment(REPORTS_ACCEPTED, &[("Source", source.as_str())]),
+        Err(e) => record_rejection(metrics, source, error_reason(e)),
+    }
+}
+
+pub fn record_rejection(metrics: &dyn MetricsRecorder, source: ReportSource, reason: &str) {
+    metrics.increment(REPORTS_REJECTED, &[("Source", source.as_str()), ("Reason", reason)]);
+}
+
+pub fn record_transition(metrics: &dyn MetricsRecorder, change: &StatusChange) {
+    if change.category_changed() {
+        metrics.increment(
+            FILL_CATEGORY_TRANSITIONS,
+            &[("From", change.previous.fill_category()), ("To", change.current.fill_category())],
+        );
+    }
+}
@@ -1,31 +1,337 @@
-use chrono::Utc;
-use tracing::{info, error};
+use std::future::Future;
 
-use crate::domain::{BinRepository, StatusUpdateRequest, StatusUpdateResponse};
+use chrono::{DateTime, Utc};
+use tracing::{field, info, error, instrument, warn, Span};
+use uuid::Uuid;
+
+pub mod backup;
+pub mod batch;
+pub mod collection;
+pub mod consistency;
+pub mod context;
+pub mod device_health;
+pub mod incident;
+pub mod lifecycle;
+pub mod metrics;
+pub mod retention;
+pub mod rollup;
+
+use crate::domain::lorawan::{
+    verify_signature, DecoderRegistry, LorawanUplink, WebhookRequest, SIGNATURE_HEADER,
+};
+use crate::domain::consistency::BinHistoryRepository;
+use crate::domain::device_health::{DeviceHealth, DeviceHealthRepository, DeviceTelemetry};
+use crate::domain::estimator::FillEstimator;
+use crate::domain::history::{fold, BinEvent};
+use crate::domain::lifecycle::LifecycleRepository;
+use crate::domain::location::{LocationRepository, LocationStatus};
+use crate::domain::sensor::{Device, DeviceRepository, SensorReadingRequest, TelemetryMessage};
+use context::AppContext;
+use crate::domain::{
+    BinProfile, BinProfileRepository, BinProfileRequest, BinRepository, BinStatusView, ReportSource, StatusReport,
+    StatusUpdateRequest, StatusUpdateResponse, WasteType,
+};
+use metrics::{record_outcome, record_transition};
 use crate::error::AppError;
 
-pub async fn handle_status_update<R: BinRepository>(
+/// Records a citizen's report. Reports for a replaced bin count towards
+/// its replacement; bins that are not in service refuse them.
+#[instrument(skip_all, fields(bin_id = %request.bin_id, status = request.status.value(), source = "citizen"))]
+pub async fn handle_status_update<R: BinRepository + LifecycleRepository>(
     repo: &R,
+    ctx: &AppContext,
     request: StatusUpdateRequest,
 ) -> Result<StatusUpdateResponse, AppError> {
     info!("Processing status update for bin: {}", request.bin_id);
     
-    let timestamp = Utc::now();
-    let status = request.status.clone();
+    metered(ctx, ReportSource::Citizen, async {
+        let received_at = ctx.now();
+        let observed_at = ctx.timestamps.resolve(request.observed_at, received_at)?;
+        let bin_id = lifecycle::reporting_bin(repo, request.bin_id).await?;
+        let report = StatusReport::citizen(ctx.next_id(), bin_id, request.status, observed_at)
+            .with_received_at(received_at);
+        let mut response = record_report(repo, ctx, &report).await?;
+        if bin_id != request.bin_id {
+            response.message = format!("{} (bin {} was replaced by {})", response.message, request.bin_id, bin_id);
+        }
+        Ok(response)
+    })
+    .await
+}
+
+#[instrument(skip(repo))]
+pub async fn handle_get_bin_status<R: BinRepository + BinProfileRepository>(
+    repo: &R,
+    bin_id: &Uuid,
+) -> Result<BinStatusView, AppError> {
+    let aggregate = repo
+        .get_bin(bin_id)
+        .await?
+        .ok_or_else(|| AppError::BinNotFound(bin_id.to_string()))?;
+    let profile = repo.bin_profile(bin_id).await?.unwrap_or_default();
+    Ok(BinStatusView::new(*bin_id, &aggregate, profile))
+}
+
+/// Records the container a bin is, replacing what was recorded before.
+#[instrument(skip_all, fields(bin_id = %request.bin_id, waste_type = %request.waste_type))]
+pub async fn handle_set_bin_profile<R: BinProfileRepository>(
+    repo: &R,
+    request: BinProfileRequest,
+) -> Result<BinProfile, AppError> {
+    let profile = BinProfile::new(request.waste_type, request.capacity_liters, request.container_model)?;
+    repo.set_bin_profile(&request.bin_id, &profile).await?;
+    info!("Bin {} recorded as a {} bin", request.bin_id, profile.waste_type);
+    Ok(profile)
+}
+
+/// The location's status from the current estimates of its bins in
+/// service, or of those of `waste_type` only. A location without bins is
+/// reported as not found, as the two-table layout only knows locations
+/// through their bins.
+#[instrument(skip(repo))]
+pub async fn handle_get_location_status<R: LocationRepository>(
+    repo: &R,
+    location_id: &Uuid,
+    waste_type: Option<WasteType>,
+) -> Result<LocationStatus, AppError> {
+    let mut bins = repo.location_bins(location_id).await?;
+    if bins.is_empty() {
+        return Err(AppError::LocationNotFound(location_id.to_string()));
+    }
+    bins.retain(|bin| {
+        bin.lifecycle.is_in_service() && waste_type.is_none_or(|waste_type| bin.profile.waste_type == waste_type)
+    });
+    Ok(LocationStatus::new(*location_id, &bins))
+}
+
+/// The bin's estimate as it stood at `as_of`, rebuilt from its logged
+/// history rather than read from the stored estimate.
+#[instrument(skip(repo, estimator))]
+pub async fn handle_get_bin_status_at<R: BinRepository + BinHistoryRepository + BinProfileRepository + LifecycleRepository>(
+    repo: &R,
+    estimator: &FillEstimator,
+    bin_id: &Uuid,
+    as_of: DateTime<Utc>,
+) -> Result<BinStatusView, AppError> {
+    let current = repo
+        .get_bin(bin_id)
+        .await?
+        .ok_or_else(|| AppError::BinNotFound(bin_id.to_string()))?;
+
+    // A collection resets the estimate, so nothing before the last one up
+    // to `as_of` is needed
+    let since = current.collected_at.filter(|collected_at| *collected_at <= as_of);
+    let mut events = repo.bin_events(bin_id, since, Some(as_of)).await?;
+    // Collections recorded before they were logged are only on the bin
+    events.extend(current.collected_at.map(|at| BinEvent::Collected { at }));
+    if let Some(lifecycle) = repo.bin_lifecycle(bin_id).await? {
+        events.extend(lifecycle.events());
+    }
+
+    let state = fold(estimator, &events, Some(as_of));
+    let profile = repo.bin_profile(bin_id).await?.unwrap_or_default();
+    Ok(BinStatusView::new(*bin_id, &state.estimate, profile))
+}
+
+#[instrument(skip_all, fields(device_id = %request.device_id, source = "sensor"))]
+pub async fn handle_sensor_reading<R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    request: SensorReadingRequest,
+) -> Result<StatusUpdateResponse, AppError> {
+    info!("Processing sensor reading from device: {}", request.device_id);
+
+    metered(ctx, ReportSource::Sensor, async {
+        let device = match repo.get_device(&request.device_id).await? {
+            Some(device) if device.is_active && device.verify_key(&request.device_key) => device,
+            _ => {
+                warn!("Rejected reading from unknown or unauthenticated device: {}", request.device_id);
+                return Err(AppError::Unauthorized(format!(
+                    "Invalid credentials for device {}",
+                    request.device_id
+                )));
+            }
+        };
+
+        let telemetry = DeviceTelemetry {
+            device_id: device.device_id.clone(),
+            seen_at: ctx.now(),
+            distance_mm: request.distance_mm,
+            battery_mv: request.battery_mv,
+            rssi_dbm: request.rssi_dbm,
+            firmware_version: request.firmware_version,
+        };
+        record_sensor_distance(repo, ctx, &device, telemetry).await
+    })
+    .await
+}
+
+#[instrument(skip_all, fields(device_id = field::Empty, source = "sensor"))]
+pub async fn handle_lorawan_webhook<R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    decoders: &DecoderRegistry,
+    webhook_secret: &[u8],
+    request: WebhookRequest,
+) -> Result<StatusUpdateResponse, AppError> {
+    metered(ctx, ReportSource::Sensor, async {
+        let body = request.body_bytes()?;
+        let signature = request
+            .header(SIGNATURE_HEADER)
+            .ok_or_else(|| AppError::Unauthorized("Missing webhook signature".to_string()))?;
+        verify_signature(webhook_secret, &body, signature)?;
+
+        let uplink: LorawanUplink = serde_json::from_slice(&body)
+            .map_err(|e| AppError::InvalidRequest(format!("Invalid uplink JSON: {}", e)))?;
+        let dev_eui = uplink.dev_eui();
+        Span::current().record("device_id", dev_eui.as_str());
+        info!("Processing LoRaWAN uplink from device: {}", dev_eui);
+
+        let device = registered_device(repo, &dev_eui).await?;
+
+        let decoder = decoders.get(&device.vendor).ok_or_else(|| {
+            AppError::InternalError(format!("No payload decoder for vendor {}", device.vendor))
+        })?;
+        let frame = decoder.decode(uplink.uplink_message.f_port, &uplink.frame()?)?;
+        info!(
+            "Decoded {} frame from {}: distance {} mm, battery {} mV, temperature {:?} C, tilt {:?} deg",
+            device.vendor, dev_eui, frame.distance_mm, frame.battery_mv, frame.temperature_c, frame.tilt_deg
+        );
+
+        let telemetry = DeviceTelemetry {
+            device_id: device.device_id.clone(),
+            seen_at: ctx.now(),
+            distance_mm: frame.distance_mm,
+            battery_mv: Some(frame.battery_mv),
+            rssi_dbm: uplink.best_rssi(),
+            firmware_version: None,
+        };
+        record_sensor_distance(repo, ctx, &device, telemetry).await
+    })
+    .await
+}
+
+#[instrument(skip_all, fields(device_id = %message.device_id, source = "sensor"))]
+pub async fn handle_device_telemetry<R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    message: TelemetryMessage,
+) -> Result<StatusUpdateResponse, AppError> {
+    info!("Processing telemetry from device: {}", message.device_id);
+
+    metered(ctx, ReportSource::Sensor, async {
+        let device = registered_device(repo, &message.device_id).await?;
+        let telemetry = DeviceTelemetry {
+            device_id: device.device_id.clone(),
+            seen_at: ctx.now(),
+            distance_mm: message.frame.distance_mm,
+            battery_mv: Some(message.frame.battery_mv),
+            rssi_dbm: message.rssi_dbm,
+            firmware_version: message.firmware_version,
+        };
+        record_sensor_distance(repo, ctx, &device, telemetry).await
+    })
+    .await
+}
+
+/// Looks up a device on a transport that has already authenticated it.
+async fn registered_device<R: DeviceRepository>(repo: &R, device_id: &str) -> Result<Device, AppError> {
+    match repo.get_device(device_id).await? {
+        Some(device) if device.is_active => Ok(device),
+        _ => {
+            warn!("Reading from unregistered or inactive device: {}", device_id);
+            Err(AppError::Unauthorized(format!("Device {} is not registered", device_id)))
+        }
+    }
+}
+
+async fn record_sensor_distance<R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    device: &Device,
+    telemetry: DeviceTelemetry,
+) -> Result<StatusUpdateResponse, AppError> {
+    let calibration = repo.get_calibration(&device.bin_id).await?.ok_or_else(|| {
+        AppError::InvalidRequest(format!("Bin {} has no sensor calibration", device.bin_id))
+    })?;
+
+    let status = calibration.status_for_distance(telemetry.distance_mm);
+    info!(
+        "Device {} measured {} mm, bin {} is {}",
+        device.device_id, telemetry.distance_mm, device.bin_id, status
+    );
+
+    let bin_id = lifecycle::reporting_bin(repo, device.bin_id).await?;
+    let report = StatusReport::sensor(ctx.next_id(), bin_id, status, telemetry.seen_at);
+    let response = record_report(repo, ctx, &report).await?;
+
+    // The reading is already stored; a registry hiccup must not reject it
+    if let Err(e) = update_device_health(repo, ctx, device, &telemetry).await {
+        warn!("Failed to update health of device {}: {}", device.device_id, e);
+    }
+    Ok(response)
+}
+
+async fn update_device_health<R: DeviceHealthRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    device: &Device,
+    telemetry: &DeviceTelemetry,
+) -> Result<(), AppError> {
+    let health = match repo.get_health(&device.device_id).await? {
+        Some(mut health) => {
+            health.observe(telemetry, &ctx.health);
+            health
+        }
+        None => DeviceHealth::first_seen(device.bin_id, telemetry),
+    };
+    repo.save_health(&health).await
+}
+
+/// Counts the outcome of a report, whichever transport it came in on.
+async fn metered<Fut>(ctx: &AppContext, source: ReportSource, handle: Fut) -> Result<StatusUpdateResponse, AppError>
+where
+    Fut: Future<Output = Result<StatusUpdateResponse, AppError>>,
+{
+    let result = handle.await;
+    record_outcome(ctx.metrics.as_ref(), source, &result);
+    result
+}
+
+#[instrument(
+    skip_all,
+    fields(report_id = %report.report_id, bin_id = %report.bin_id, status = report.status.value(), fill_category = field::Empty)
+)]
+async fn record_report<R: BinRepository>(
+    repo: &R,
+    ctx: &AppContext,
+    report: &StatusReport,
+) -> Result<StatusUpdateResponse, AppError> {
+    let status = &report.status;
     
-    info!("Updating bin status to: {} (value: {})", status, status.value());
+    info!("Updating bin status to: {} (value: {}, source: {})", status, status.value(), report.source.as_str());
     
-    match repo.update_status(&request.bin_id, status.clone(), timestamp).await {
-        Ok(_) => {
+    match repo.update_status(report).await {
+        Ok(change) => {
             info!("Successfully updated bin status in database");
+            Span::current().record("fill_category", change.current.fill_category());
+            record_transition(ctx.metrics.as_ref(), &change);
         }
         Err(e) => {
             error!("Failed to update bin status: {}", e);
+            // The estimate may include the report without us hearing back,
+            // so log it all the same: the log then agrees with the estimate,
+            // or the consistency check repairs the estimate from it
+            if matches!(&e, AppError::DatabaseError(db) if db.may_have_applied()) {
+                if let Err(log_error) = repo.add_report(report).await {
+                    warn!("Failed to log report {} after an ambiguous update: {}", report.report_id, log_error);
+                }
+            }
             return Err(e);
         }
     }
     
-    match repo.add_report(&request.bin_id, status.clone(), timestamp).await {
+    match repo.add_report(report).await {
         Ok(_) => {
             info!("Successfully added status report to database");
         }
@@ -37,8 +343,8 @@ pub async fn handle_status_update<R: BinRepository>(
 
     let response = StatusUpdateResponse {
         success: true,
-        message: format!("Bin status updated to {}", request.status),
-        updated_at: timestamp,
+        message: format!("Bin status updated to {}", report.status),
+        updated_at: report.received_at,
     };
     
     info!("Status update completed successfully: {}", response.message);
@@ -48,20 +354,44 @@ pub async fn handle_status_update<R: BinRepository>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::BinStatus;
+    use crate::domain::collection::CollectionEvent;
+    use crate::domain::device_health::HealthPolicy;
+    use crate::domain::estimator::BinAggregate;
+    use crate::domain::timing::TimestampPolicy;
+    use crate::infrastructure::memory::InMemoryRepository;
+    use shared::metrics::CapturingRecorder;
+    use crate::error::DatabaseError;
+    use shared::clock::{Clock, FakeClock, SequentialIds};
+    use crate::domain::sensor::{hash_device_key, BinCalibration};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use crate::domain::lifecycle::{BinLifecycle, LifecycleRequest, LifecycleState, LifecycleTransition};
+    use crate::domain::{BinProfile, BinProfileRepository, BinStatus, StatusChange};
     use chrono::{DateTime, Utc};
     use uuid::Uuid;
     use async_trait::async_trait;
+    use std::collections::HashMap;
     use std::sync::Arc;
     use tokio::sync::Mutex;
 
+    type RecordedCall = (Uuid, BinStatus, DateTime<Utc>);
+
+    fn fixed_context() -> (AppContext, FakeClock) {
+        let clock = FakeClock::new(DateTime::parse_from_rfc3339("2024-03-20T12:00:00Z").unwrap().with_timezone(&Utc));
+        let ctx = AppContext::new(Arc::new(clock.clone()), Arc::new(SequentialIds::new()), TimestampPolicy::default());
+        (ctx, clock)
+    }
+
     // Mock repository for isolated unit testing
     #[derive(Debug, Clone)]
     struct MockBinRepository {
-        update_status_calls: Arc<Mutex<Vec<(Uuid, BinStatus, DateTime<Utc>)>>>,
-        add_report_calls: Arc<Mutex<Vec<(Uuid, BinStatus, DateTime<Utc>)>>>,
+        update_status_calls: Arc<Mutex<Vec<RecordedCall>>>,
+        add_report_calls: Arc<Mutex<Vec<RecordedCall>>>,
         should_fail_update: Arc<Mutex<bool>>,
         should_fail_report: Arc<Mutex<bool>>,
+        devices: Arc<Mutex<HashMap<String, Device>>>,
+        calibrations: Arc<Mutex<HashMap<Uuid, BinCalibration>>>,
+        health: Arc<Mutex<HashMap<String, DeviceHealth>>>,
     }
 
     impl MockBinRepository {
@@ -71,14 +401,17 @@ mod tests {
                 add_report_calls: Arc::new(Mutex::new(Vec::new())),
                 should_fail_update: Arc::new(Mutex::new(false)),
                 should_fail_report: Arc::new(Mutex::new(false)),
+                devices: Arc::new(Mutex::new(HashMap::new())),
+                calibrations: Arc::new(Mutex::new(HashMap::new())),
+                health: Arc::new(Mutex::new(HashMap::new())),
             }
         }
 
-        async fn get_update_status_calls(&self) -> Vec<(Uuid, BinStatus, DateTime<Utc>)> {
+        async fn get_update_status_calls(&self) -> Vec<RecordedCall> {
             self.update_status_calls.lock().await.clone()
         }
 
-        async fn get_add_report_calls(&self) -> Vec<(Uuid, BinStatus, DateTime<Utc>)> {
+        async fn get_add_report_calls(&self) -> Vec<RecordedCall> {
             self.add_report_calls.lock().await.clone()
         }
 
@@ -89,43 +422,96 @@ mod tests {
         async fn set_should_fail_report(&self, should_fail: bool) {
             *self.should_fail_report.lock().await = should_fail;
         }
+
+        async fn register_device(&self, device_id: &str, key: &str, bin_id: Uuid, is_active: bool) {
+            self.devices.lock().await.insert(
+                device_id.to_string(),
+                Device {
+                    device_id: device_id.to_string(),
+                    bin_id,
+                    key_hash: hash_device_key(key),
+                    vendor: "ecoscan".to_string(),
+                    is_active,
+                },
+            );
+        }
+
+        async fn calibrate(&self, bin_id: Uuid, calibration: BinCalibration) {
+            self.calibrations.lock().await.insert(bin_id, calibration);
+        }
     }
 
     #[async_trait]
     impl BinRepository for MockBinRepository {
-        async fn update_status(
-            &self,
-            bin_id: &Uuid,
-            status: BinStatus,
-            timestamp: DateTime<Utc>,
-        ) -> Result<(), AppError> {
+        async fn get_bin(&self, _bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+            Ok(None)
+        }
+
+        async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
             if *self.should_fail_update.lock().await {
-                return Err(AppError::DatabaseError("Mock update failure".to_string()));
+                return Err(DatabaseError::Permanent("Mock update failure".to_string()).into());
             }
             
             self.update_status_calls
                 .lock()
                 .await
-                .push((*bin_id, status, timestamp));
-            Ok(())
+                .push((report.bin_id, report.status.clone(), report.observed_at));
+            Ok(StatusChange::unchanged(report.status.clone()))
         }
 
-        async fn add_report(
-            &self,
-            bin_id: &Uuid,
-            status: BinStatus,
-            timestamp: DateTime<Utc>,
-        ) -> Result<(), AppError> {
+        async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
             if *self.should_fail_report.lock().await {
-                return Err(AppError::DatabaseError("Mock report failure".to_string()));
+                return Err(DatabaseError::Permanent("Mock report failure".to_string()).into());
             }
             
             self.add_report_calls
                 .lock()
                 .await
-                .push((*bin_id, status, timestamp));
+                .push((report.bin_id, report.status.clone(), report.observed_at));
+            Ok(())
+        }
+
+        async fn record_collection(&self, _event: &CollectionEvent) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl LifecycleRepository for MockBinRepository {
+        async fn bin_lifecycle(&self, _bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+            Ok(Some(BinLifecycle::default()))
+        }
+
+        async fn record_transition(&self, _bin_id: &Uuid, _transition: &LifecycleTransition) -> Result<bool, AppError> {
+            Ok(true)
+        }
+    }
+
+    #[async_trait]
+    impl DeviceRepository for MockBinRepository {
+        async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError> {
+            Ok(self.devices.lock().await.get(device_id).cloned())
+        }
+
+        async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError> {
+            Ok(self.calibrations.lock().await.get(bin_id).cloned())
+        }
+    }
+
+    #[async_trait]
+    impl DeviceHealthRepository for MockBinRepository {
+        async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+            Ok(self.health.lock().await.get(device_id).cloned())
+        }
+
+        async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+            self.health.lock().await.insert(health.device_id.clone(), health.clone());
             Ok(())
         }
+
+        async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+            Ok(self.health.lock().await.values().cloned().collect())
+        }
     }
 
     #[tokio::test]
@@ -137,9 +523,10 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: status.clone(),
+            observed_at: None,
         };
 
-        let result = handle_status_update(&mock_repo, request).await;
+        let result = handle_status_update(&mock_repo, &AppContext::default(), request).await;
         
         assert!(result.is_ok());
         let response = result.unwrap();
@@ -167,9 +554,10 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: status.clone(),
+            observed_at: None,
         };
 
-        let result = handle_status_update(&mock_repo, request).await;
+        let result = handle_status_update(&mock_repo, &AppContext::default(), request).await;
         
         assert!(result.is_ok());
         let response = result.unwrap();
@@ -186,9 +574,10 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: status.clone(),
+            observed_at: None,
         };
 
-        let result = handle_status_update(&mock_repo, request).await;
+        let result = handle_status_update(&mock_repo, &AppContext::default(), request).await;
         
         assert!(result.is_ok());
         let response = result.unwrap();
@@ -205,13 +594,14 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: BinStatus::ok(),
+            observed_at: None,
         };
 
-        let result = handle_status_update(&mock_repo, request).await;
+        let result = handle_status_update(&mock_repo, &AppContext::default(), request).await;
         
         assert!(result.is_err());
         match result.unwrap_err() {
-            AppError::DatabaseError(msg) => {
+            AppError::DatabaseError(DatabaseError::Permanent(msg)) => {
                 assert_eq!(msg, "Mock update failure");
             }
             _ => panic!("Expected DatabaseError"),
@@ -234,13 +624,14 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: BinStatus::ok(),
+            observed_at: None,
         };
 
-        let result = handle_status_update(&mock_repo, request).await;
+        let result = handle_status_update(&mock_repo, &AppContext::default(), request).await;
         
         assert!(result.is_err());
         match result.unwrap_err() {
-            AppError::DatabaseError(msg) => {
+            AppError::DatabaseError(DatabaseError::Permanent(msg)) => {
                 assert_eq!(msg, "Mock report failure");
             }
             _ => panic!("Expected DatabaseError"),
@@ -262,17 +653,410 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: BinStatus::ok(),
+            observed_at: None,
         };
 
-        let before_call = Utc::now();
-        let result = handle_status_update(&mock_repo, request).await;
-        let after_call = Utc::now();
+        let (ctx, clock) = fixed_context();
+        let result = handle_status_update(&mock_repo, &ctx, request).await;
         
         assert!(result.is_ok());
         let response = result.unwrap();
         
-        // Verify timestamp is within reasonable bounds
-        assert!(response.updated_at >= before_call);
-        assert!(response.updated_at <= after_call);
+        assert_eq!(response.updated_at, clock.now());
+    }
+
+    #[tokio::test]
+    async fn test_handle_status_update_keeps_client_observation_time() {
+        let mock_repo = MockBinRepository::new();
+        let (ctx, clock) = fixed_context();
+        let observed_at = clock.now() - chrono::Duration::hours(2);
+
+        let request = StatusUpdateRequest {
+            bin_id: Uuid::new_v4(),
+            status: BinStatus::full(),
+            observed_at: Some(observed_at),
+        };
+        clock.advance(chrono::Duration::seconds(3));
+        let response = handle_status_update(&mock_repo, &ctx, request).await.unwrap();
+
+        assert_eq!(response.updated_at, observed_at + chrono::Duration::hours(2) + chrono::Duration::seconds(3));
+        assert_eq!(mock_repo.get_update_status_calls().await[0].2, observed_at);
+        assert_eq!(mock_repo.get_add_report_calls().await[0].2, observed_at);
+    }
+
+    #[tokio::test]
+    async fn test_handle_status_update_rejects_stale_observation() {
+        let mock_repo = MockBinRepository::new();
+        let request = StatusUpdateRequest {
+            bin_id: Uuid::new_v4(),
+            status: BinStatus::full(),
+            observed_at: Some(Utc::now() - chrono::Duration::days(30)),
+        };
+
+        let result = handle_status_update(&mock_repo, &AppContext::default(), request).await;
+
+        assert!(matches!(result, Err(AppError::InvalidRequest(_))));
+        assert!(mock_repo.get_update_status_calls().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_status_update_records_metrics() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let recorder = CapturingRecorder::new();
+        let ctx = AppContext::default().with_metrics(Arc::new(recorder.clone()));
+        let update = |bin_id, status, observed_at| StatusUpdateRequest { bin_id, status, observed_at };
+
+        handle_status_update(&repo, &ctx, update(bin_id, BinStatus::full(), None)).await.unwrap();
+        handle_status_update(&repo, &ctx, update(bin_id, BinStatus::full(), None)).await.unwrap();
+        let _ = handle_status_update(&repo, &ctx, update(Uuid::new_v4(), BinStatus::ok(), None)).await;
+        let stale = Some(Utc::now() - chrono::Duration::days(30));
+        let _ = handle_status_update(&repo, &ctx, update(bin_id, BinStatus::ok(), stale)).await;
+
+        assert_eq!(recorder.total(metrics::REPORTS_ACCEPTED, &[("Source", "citizen")]), 2.0);
+        assert_eq!(recorder.total(metrics::REPORTS_REJECTED, &[("Reason", "bin_not_found")]), 1.0);
+        assert_eq!(recorder.total(metrics::REPORTS_REJECTED, &[("Reason", "invalid_request")]), 1.0);
+        // Only the first report moved the bin out of its fill category
+        assert_eq!(recorder.total(metrics::FILL_CATEGORY_TRANSITIONS, &[]), 1.0);
+        assert_eq!(recorder.total(metrics::FILL_CATEGORY_TRANSITIONS, &[("From", "low"), ("To", "full")]), 1.0);
+    }
+
+    fn sensor_reading(device_id: &str, key: &str, distance_mm: u32) -> SensorReadingRequest {
+        SensorReadingRequest {
+            device_id: device_id.to_string(),
+            device_key: key.to_string(),
+            distance_mm,
+            battery_mv: Some(3600),
+            rssi_dbm: None,
+            firmware_version: Some("1.4.2".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_sensor_reading_success() {
+        let mock_repo = MockBinRepository::new();
+        let bin_id = Uuid::new_v4();
+        mock_repo.register_device("sensor-1", "s3cret", bin_id, true).await;
+        mock_repo.calibrate(bin_id, BinCalibration::new(1000, 200).unwrap()).await;
+
+        let (ctx, clock) = fixed_context();
+        let result = handle_sensor_reading(&mock_repo, &ctx, sensor_reading("sensor-1", "s3cret", 440)).await;
+
+        let response = result.unwrap();
+        assert!(response.success);
+        assert_eq!(response.message, "Bin status updated to 70%");
+
+        let update_calls = mock_repo.get_update_status_calls().await;
+        let report_calls = mock_repo.get_add_report_calls().await;
+        assert_eq!(update_calls.len(), 1);
+        assert_eq!(report_calls.len(), 1);
+        assert_eq!(update_calls[0].0, bin_id);
+        assert_eq!(update_calls[0].1, BinStatus::new(7).unwrap());
+        assert_eq!(update_calls[0].2, clock.now());
+
+        let health = mock_repo.get_health("sensor-1").await.unwrap().unwrap();
+        assert_eq!(health.bin_id, bin_id);
+        assert_eq!(health.last_seen, clock.now());
+        assert_eq!(health.battery_mv, Some(3600));
+        assert_eq!(health.firmware_version.as_deref(), Some("1.4.2"));
+        assert_eq!(health.last_distance_mm, 440);
+    }
+
+    #[tokio::test]
+    async fn test_handle_sensor_reading_uses_configured_health_policy() {
+        let mock_repo = MockBinRepository::new();
+        let bin_id = Uuid::new_v4();
+        mock_repo.register_device("sensor-1", "s3cret", bin_id, true).await;
+        mock_repo.calibrate(bin_id, BinCalibration::new(1000, 200).unwrap()).await;
+
+        let (ctx, clock) = fixed_context();
+        let ctx = ctx.with_health_policy(HealthPolicy { stuck_tolerance_mm: 50, ..HealthPolicy::default() });
+        handle_sensor_reading(&mock_repo, &ctx, sensor_reading("sensor-1", "s3cret", 440)).await.unwrap();
+        clock.advance(chrono::Duration::minutes(10));
+        handle_sensor_reading(&mock_repo, &ctx, sensor_reading("sensor-1", "s3cret", 420)).await.unwrap();
+
+        // 20 mm is within the configured tolerance, so the sensor has not moved
+        let health = mock_repo.get_health("sensor-1").await.unwrap().unwrap();
+        assert_eq!(health.last_distance_mm, 440);
+        assert_eq!(health.last_seen, clock.now());
+    }
+
+    #[tokio::test]
+    async fn test_handle_sensor_reading_rejects_bad_credentials() {
+        let mock_repo = MockBinRepository::new();
+        let bin_id = Uuid::new_v4();
+        mock_repo.register_device("sensor-1", "s3cret", bin_id, true).await;
+        mock_repo.register_device("sensor-2", "other", bin_id, false).await;
+        mock_repo.calibrate(bin_id, BinCalibration::new(1000, 200).unwrap()).await;
+
+        for request in [
+            sensor_reading("sensor-1", "wrong", 500),
+            sensor_reading("sensor-2", "other", 500),
+            sensor_reading("unknown", "s3cret", 500),
+        ] {
+            let result = handle_sensor_reading(&mock_repo, &AppContext::default(), request).await;
+            assert!(matches!(result, Err(AppError::Unauthorized(_))));
+        }
+
+        assert!(mock_repo.get_update_status_calls().await.is_empty());
+        assert!(mock_repo.get_add_report_calls().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_sensor_reading_requires_calibration() {
+        let mock_repo = MockBinRepository::new();
+        mock_repo.register_device("sensor-1", "s3cret", Uuid::new_v4(), true).await;
+
+        let result = handle_sensor_reading(&mock_repo, &AppContext::default(), sensor_reading("sensor-1", "s3cret", 500)).await;
+
+        match result {
+            Err(AppError::InvalidRequest(msg)) => assert!(msg.contains("no sensor calibration")),
+            other => panic!("Expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    /// A calibrated bin carrying `device_id`, replaced by a new bin.
+    async fn replaced_bin_with_device(repo: &InMemoryRepository, device_id: &str) -> (Uuid, Uuid) {
+        let (old, new) = (Uuid::new_v4(), Uuid::new_v4());
+        repo.add_bin(old);
+        repo.add_bin(new);
+        repo.set_calibration(old, BinCalibration::new(1000, 200).unwrap());
+        repo.register_device(Device {
+            device_id: device_id.to_string(),
+            bin_id: old,
+            key_hash: hash_device_key("s3cret"),
+            vendor: "ecoscan".to_string(),
+            is_active: true,
+        });
+        change_lifecycle(repo, old, LifecycleState::Replaced, Some(new)).await;
+        (old, new)
+    }
+
+    async fn change_lifecycle(repo: &InMemoryRepository, bin_id: Uuid, state: LifecycleState, replaced_by: Option<Uuid>) {
+        let request = LifecycleRequest { bin_id, state, actor: "crew-7".to_string(), reason: None, replaced_by };
+        lifecycle::handle_lifecycle_change(repo, &AppContext::default(), request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_sensor_reading_follows_bin_lifecycle() {
+        let repo = InMemoryRepository::new();
+        let (old, new) = replaced_bin_with_device(&repo, "sensor-1").await;
+
+        handle_sensor_reading(&repo, &AppContext::default(), sensor_reading("sensor-1", "s3cret", 440)).await.unwrap();
+        change_lifecycle(&repo, new, LifecycleState::Removed, None).await;
+        let refused = handle_sensor_reading(&repo, &AppContext::default(), sensor_reading("sensor-1", "s3cret", 200)).await;
+
+        assert!(matches!(refused, Err(AppError::BinOutOfService(_))));
+        assert!(repo.reports(&old).is_empty());
+        assert_eq!(repo.reports(&new).len(), 1);
+        assert_eq!(repo.bin_status(&new).unwrap().value(), 7);
+    }
+
+    const WEBHOOK_SECRET: &[u8] = b"webhook-secret";
+
+    fn signed_webhook(body: &str) -> WebhookRequest {
+        let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET).unwrap();
+        mac.update(body.as_bytes());
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        WebhookRequest {
+            headers: HashMap::from([("X-Webhook-Signature".to_string(), signature)]),
+            body: body.to_string(),
+            is_base64_encoded: false,
+        }
+    }
+
+    fn uplink_body(dev_eui: &str, frm_payload: &str) -> String {
+        format!(
+            r#"{{"end_device_ids":{{"dev_eui":"{}"}},"uplink_message":{{"f_port":1,"frm_payload":"{}"}}}}"#,
+            dev_eui, frm_payload
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handle_lorawan_webhook_success() {
+        let mock_repo = MockBinRepository::new();
+        let bin_id = Uuid::new_v4();
+        mock_repo.register_device("70B3D57ED005A1B2", "unused", bin_id, true).await;
+        mock_repo.calibrate(bin_id, BinCalibration::new(1000, 200).unwrap()).await;
+
+        // EcoScan frame: 440 mm, 3656 mV, 23.5 C, 5 deg tilt
+        let request = signed_webhook(&uplink_body("70b3d57ed005a1b2", "AQG4DkgA6wU="));
+        let result = handle_lorawan_webhook(&mock_repo, &AppContext::default(), &DecoderRegistry::default(), WEBHOOK_SECRET, request).await;
+
+        assert_eq!(result.unwrap().message, "Bin status updated to 70%");
+        let update_calls = mock_repo.get_update_status_calls().await;
+        assert_eq!(update_calls.len(), 1);
+        assert_eq!(update_calls[0].0, bin_id);
+
+        let health = mock_repo.get_health("70B3D57ED005A1B2").await.unwrap().unwrap();
+        assert_eq!(health.battery_mv, Some(3656));
+    }
+
+    #[tokio::test]
+    async fn test_handle_lorawan_webhook_follows_bin_lifecycle() {
+        let repo = InMemoryRepository::new();
+        let (old, new) = replaced_bin_with_device(&repo, "70B3D57ED005A1B2").await;
+        let uplink = || signed_webhook(&uplink_body("70B3D57ED005A1B2", "AQG4DkgA6wU="));
+        let (ctx, decoders) = (AppContext::default(), DecoderRegistry::default());
+        let webhook = |request| handle_lorawan_webhook(&repo, &ctx, &decoders, WEBHOOK_SECRET, request);
+
+        webhook(uplink()).await.unwrap();
+        change_lifecycle(&repo, new, LifecycleState::UnderMaintenance, None).await;
+        let refused = webhook(uplink()).await;
+
+        assert!(matches!(refused, Err(AppError::BinOutOfService(_))));
+        assert!(repo.reports(&old).is_empty());
+        assert_eq!(repo.reports(&new).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_lorawan_webhook_rejects_bad_signature() {
+        let mock_repo = MockBinRepository::new();
+        let mut request = signed_webhook(&uplink_body("70B3D57ED005A1B2", "AQG4DkgA6wU="));
+        request.body = request.body.replace("70B3", "0000");
+
+        let result = handle_lorawan_webhook(&mock_repo, &AppContext::default(), &DecoderRegistry::default(), WEBHOOK_SECRET, request).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+
+        request = signed_webhook("{}");
+        request.headers.clear();
+        let result = handle_lorawan_webhook(&mock_repo, &AppContext::default(), &DecoderRegistry::default(), WEBHOOK_SECRET, request).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_handle_lorawan_webhook_unknown_device() {
+        let mock_repo = MockBinRepository::new();
+        let request = signed_webhook(&uplink_body("0011223344556677", "AQG4DkgA6wU="));
+
+        let result = handle_lorawan_webhook(&mock_repo, &AppContext::default(), &DecoderRegistry::default(), WEBHOOK_SECRET, request).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_handle_lorawan_webhook_without_decoder() {
+        let mock_repo = MockBinRepository::new();
+        let bin_id = Uuid::new_v4();
+        mock_repo.register_device("70B3D57ED005A1B2", "unused", bin_id, true).await;
+        mock_repo.calibrate(bin_id, BinCalibration::new(1000, 200).unwrap()).await;
+
+        let request = signed_webhook(&uplink_body("70B3D57ED005A1B2", "AQG4DkgA6wU="));
+        let result = handle_lorawan_webhook(&mock_repo, &AppContext::default(), &DecoderRegistry::empty(), WEBHOOK_SECRET, request).await;
+
+        assert!(matches!(result, Err(AppError::InternalError(_))));
+        assert!(mock_repo.get_update_status_calls().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_get_bin_status_at_rebuilds_past_estimate() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let tuesday = DateTime::parse_from_rfc3339("2024-03-19T08:00:00Z").unwrap().with_timezone(&Utc);
+
+        for (value, hours) in [(9, -2), (7, -1), (1, 3)] {
+            let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), tuesday + chrono::Duration::hours(hours));
+            repo.update_status(&report).await.unwrap();
+            repo.add_report(&report).await.unwrap();
+        }
+        let collection = CollectionEvent { bin_id, collected_at: tuesday + chrono::Duration::hours(2), received_at: tuesday };
+        repo.record_collection(&collection).await.unwrap();
+
+        let then = handle_get_bin_status_at(&repo, &FillEstimator::default(), &bin_id, tuesday).await.unwrap();
+        let now = handle_get_bin_status(&repo, &bin_id).await.unwrap();
+
+        assert_eq!((then.status.value(), then.reports_count, then.collected_at), (8, 2, None));
+        assert_eq!(now.status.value(), 1);
+        assert!(matches!(
+            handle_get_bin_status_at(&repo, &FillEstimator::default(), &Uuid::new_v4(), tuesday).await,
+            Err(AppError::BinNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_handle_get_bin_status_at_skips_reports_while_out_of_service() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let tuesday = DateTime::parse_from_rfc3339("2024-03-19T08:00:00Z").unwrap().with_timezone(&Utc);
+
+        for (value, hours) in [(2, -3), (10, -1)] {
+            let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), tuesday + chrono::Duration::hours(hours));
+            repo.add_report(&report).await.unwrap();
+        }
+        for (from, to, hours) in [
+            (LifecycleState::Active, LifecycleState::UnderMaintenance, -2),
+            (LifecycleState::UnderMaintenance, LifecycleState::Active, 0),
+        ] {
+            let transition = LifecycleTransition {
+                from,
+                to,
+                at: tuesday + chrono::Duration::hours(hours),
+                actor: "crew-7".to_string(),
+                reason: None,
+                replaced_by: None,
+            };
+            assert!(repo.record_transition(&bin_id, &transition).await.unwrap());
+        }
+
+        let during = handle_get_bin_status_at(&repo, &FillEstimator::default(), &bin_id, tuesday - chrono::Duration::minutes(30)).await.unwrap();
+        let after = handle_get_bin_status_at(&repo, &FillEstimator::default(), &bin_id, tuesday).await.unwrap();
+
+        assert_eq!((during.status.value(), during.reports_count), (2, 1));
+        assert_eq!((after.status.value(), after.reports_count), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_handle_get_location_status_follows_member_bins() {
+        let repo = InMemoryRepository::new();
+        let location_id = Uuid::new_v4();
+        let (glass, paper) = (Uuid::new_v4(), Uuid::new_v4());
+        for bin_id in [glass, paper] {
+            repo.add_bin(bin_id);
+            repo.place_bin(bin_id, location_id);
+        }
+        let at = DateTime::parse_from_rfc3339("2024-03-19T08:00:00Z").unwrap().with_timezone(&Utc);
+        repo.update_status(&StatusReport::citizen(Uuid::new_v4(), glass, BinStatus::new(3).unwrap(), at)).await.unwrap();
+
+        repo.set_bin_profile(&glass, &BinProfile::new(WasteType::Glass, Some(1500), None).unwrap()).await.unwrap();
+        repo.set_bin_profile(&paper, &BinProfile::new(WasteType::Paper, Some(1100), None).unwrap()).await.unwrap();
+
+        let before = handle_get_location_status(&repo, &location_id, None).await.unwrap();
+        repo.update_status(&StatusReport::citizen(Uuid::new_v4(), paper, BinStatus::full(), at)).await.unwrap();
+        let after = handle_get_location_status(&repo, &location_id, None).await.unwrap();
+        let glass_only = handle_get_location_status(&repo, &location_id, Some(WasteType::Glass)).await.unwrap();
+
+        assert_eq!((before.fullest_bin.unwrap().bin_id, before.overflowing_bins), (glass, 0));
+        assert_eq!((after.fullest_bin.unwrap().bin_id, after.overflowing_bins), (paper, 1));
+        assert_eq!((glass_only.bins_count, glass_only.overflowing_bins), (1, 0));
+        assert_eq!(glass_only.by_waste_type[0].filled_liters, 450);
+        assert!(matches!(
+            handle_get_location_status(&repo, &Uuid::new_v4(), None).await,
+            Err(AppError::LocationNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_handle_set_bin_profile_shows_in_bin_status() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let request = |capacity_liters| BinProfileRequest {
+            bin_id,
+            waste_type: WasteType::Glass,
+            capacity_liters,
+            container_model: Some(" ESE 1100 ".to_string()),
+        };
+
+        let profile = handle_set_bin_profile(&repo, request(Some(1500))).await.unwrap();
+        let status = handle_get_bin_status(&repo, &bin_id).await.unwrap();
+
+        assert_eq!(profile.container_model.as_deref(), Some("ESE 1100"));
+        assert_eq!(status.profile, profile);
+        assert!(matches!(handle_set_bin_profile(&repo, request(Some(0))).await, Err(AppError::InvalidRequest(_))));
+        let unknown = BinProfileRequest { bin_id: Uuid::new_v4(), ..request(None) };
+        assert!(matches!(handle_set_bin_profile(&repo, unknown).await, Err(AppError::BinNotFound(_))));
+        assert_eq!(handle_get_bin_status(&repo, &bin_id).await.unwrap().profile, profile);
     }
 }
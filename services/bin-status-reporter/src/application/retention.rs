@@ -0,0 +1,378 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use tokio::time::Instant;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use crate::domain::consistency::{BinHistoryRepository, ResumeToken};
+use crate::domain::retention::{
+    by_day, ArchivedDay, ReportArchive, RetentionCheckpoint, RetentionPolicy, RetentionRepository,
+};
+use crate::domain::rollup::{DailyRollup, Rollup, RollupRepository, RollupUpdate};
+use crate::error::{AppError, DatabaseError};
+
+#[derive(Debug, Clone)]
+pub struct RetentionOptions {
+    pub page_size: usize,
+    /// Stop after this many bins and return where to resume.
+    pub max_bins: Option<usize>,
+    /// Stop before starting another page after this instant, e.g. ahead of
+    /// the Lambda timeout.
+    pub deadline: Option<Instant>,
+    pub resume: Option<ResumeToken>,
+}
+
+impl Default for RetentionOptions {
+    fn default() -> Self {
+        Self { page_size: 100, max_bins: None, deadline: None, resume: None }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionSummary {
+    pub bins_checked: usize,
+    /// Bin days rolled up and archived.
+    pub days_archived: usize,
+    /// Raw reports moved out of the report log.
+    pub reports_archived: usize,
+    /// Incidents moved out of the report log.
+    pub incidents_archived: usize,
+    /// Set when the run stopped before the end of the table.
+    pub resume: Option<String>,
+}
+
+/// Moves reports and incidents past the retention period out of the report
+/// log: each bin day is archived and rolled up before its raw entries are
+/// deleted.
+#[instrument(skip_all, fields(raw_days = policy.raw_days))]
+pub async fn run_retention<R, A>(
+    repo: &R,
+    archive: &A,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    options: &RetentionOptions,
+) -> Result<RetentionSummary, AppError>
+where
+    R: BinHistoryRepository + RetentionRepository + RollupRepository,
+    A: ReportArchive,
+{
+    let mut summary = RetentionSummary::default();
+    let mut resume = options.resume.clone();
+    let incidents_before = policy.archive_incidents_before(now);
+
+    loop {
+        if options.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        let limit = match options.max_bins {
+            Some(max_bins) if summary.bins_checked >= max_bins => break,
+            Some(max_bins) => options.page_size.min(max_bins - summary.bins_checked),
+            None => options.page_size,
+        };
+        let page = repo.scan_bins(resume.as_ref(), limit).await?;
+
+        for stored in &page.bins {
+            summary.bins_checked += 1;
+            let reports = match policy.archive_before(now, stored.aggregate.collected_at) {
+                Some(before) => repo.reports_before(&stored.bin_id, before).await?,
+                None => Vec::new(),
+            };
+            let incidents = match incidents_before {
+                Some(before) => repo.incidents_before(&stored.bin_id, before).await?,
+                None => Vec::new(),
+            };
+            for (day, raw) in by_day(reports, incidents) {
+                archive_day(repo, archive, stored.bin_id, day, &raw, now).await?;
+                summary.days_archived += 1;
+                summary.reports_archived += raw.reports.len();
+                summary.incidents_archived += raw.incidents.len();
+            }
+        }
+
+        resume = page.next;
+        match &resume {
+            Some(token) => info!(bins_checked = summary.bins_checked, resume = %token, "Archived page"),
+            None => break,
+        }
+    }
+
+    summary.resume = resume.map(|token| token.to_string());
+    Ok(summary)
+}
+
+/// A run of the schedule: without a token in `options` it continues where
+/// the previous run stopped, and it saves where it stops in turn, so runs
+/// cut short by the timeout still get through every bin.
+pub async fn run_scheduled_retention<R, A>(
+    repo: &R,
+    archive: &A,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    options: &RetentionOptions,
+) -> Result<RetentionSummary, AppError>
+where
+    R: BinHistoryRepository + RetentionRepository + RollupRepository,
+    A: ReportArchive + RetentionCheckpoint,
+{
+    let mut options = options.clone();
+    if options.resume.is_none() {
+        options.resume = archive.resume_token().await?;
+        if let Some(token) = &options.resume {
+            info!(resume = %token, "Continuing the previous retention run");
+        }
+    }
+
+    let summary = run_retention(repo, archive, policy, now, &options).await?;
+    let resume = summary.resume.as_deref().map(str::parse::<ResumeToken>).transpose()?;
+    archive.save_resume_token(resume.as_ref()).await?;
+    Ok(summary)
+}
+
+/// Merges the day's raw reports and incidents into its archive, rolls the
+/// archived reports up and only then deletes the raw entries. The archive
+/// keeps what an earlier, interrupted run already deleted, so rerunning
+/// gives the same archive and rollup.
+async fn archive_day<R: RetentionRepository + RollupRepository, A: ReportArchive>(
+    repo: &R,
+    archive: &A,
+    bin_id: Uuid,
+    day: NaiveDate,
+    raw: &ArchivedDay,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let mut archived = archive.get(&bin_id, day).await?.unwrap_or_default();
+    let previously_archived = (archived.reports.len(), archived.incidents.len());
+    for report in &raw.reports {
+        if !archived.reports.iter().any(|stored| stored.report_id == report.report_id) {
+            archived.reports.push(report.clone());
+        }
+    }
+    for incident in &raw.incidents {
+        if !archived.incidents.iter().any(|stored| stored.report_id == incident.report_id) {
+            archived.incidents.push(incident.clone());
+        }
+    }
+    if (archived.reports.len(), archived.incidents.len()) != previously_archived {
+        archived.reports.sort_by_key(|report| report.observed_at);
+        archived.incidents.sort_by_key(|incident| incident.observed_at);
+        archive.put(&bin_id, day, &archived, now).await?;
+    }
+
+    if !raw.incidents.is_empty() {
+        repo.delete_incidents(&bin_id, &raw.incidents).await?;
+    }
+    // Incidents say nothing about the fill level, and a day without raw
+    // reports was rolled up before they were deleted
+    if raw.reports.is_empty() {
+        return Ok(());
+    }
+
+    // The report stream rolls the day up as its reports arrive, so this
+    // only adds what the stream missed, passing it on to the location and
+    // district too. Archives hold no full time, so the stream's is kept.
+    let stored: Vec<Rollup> = repo.daily_rollups(&bin_id, day, day).await?.iter().map(DailyRollup::rollup).collect();
+    let mut rollup = DailyRollup::from_reports(bin_id, day, &archived.reports).rollup();
+    rollup.stats.full_seconds = stored.first().map_or(0, |stored| stored.stats.full_seconds);
+    let update = RollupUpdate::plan(&[rollup], &stored, &repo.bin_placement(&bin_id).await?);
+    if !repo.apply_rollup_update(&update).await? {
+        return Err(AppError::DatabaseError(DatabaseError::Conflict(format!(
+            "Rollup of bin {} for {} changed while archiving it",
+            bin_id, day
+        ))));
+    }
+    repo.delete_reports(&bin_id, &raw.reports).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    use crate::application::consistency::{run_consistency_check, CheckOptions};
+    use crate::application::rollup::apply_log_entries;
+    use crate::domain::history::BinEvent;
+    use crate::domain::rollup::{Resolution, RollupScope};
+    use crate::domain::collection::CollectionEvent;
+    use crate::domain::estimator::FillEstimator;
+    use crate::domain::incident::{IncidentReport, IncidentRepository, ReportDetails};
+    use crate::domain::{BinRepository, BinStatus, StatusReport};
+    use crate::infrastructure::archive::InMemoryArchive;
+    use crate::infrastructure::memory::InMemoryRepository;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 30, 12, 0, 0).unwrap()
+    }
+
+    /// Reports 40 and 39 days ago, a collection 20 days ago, and reports
+    /// since then, one of them past the 7 day retention period.
+    async fn history() -> (InMemoryRepository, Uuid) {
+        let repo = InMemoryRepository::new();
+        let bin_id = add_history(&repo).await;
+        (repo, bin_id)
+    }
+
+    async fn add_history(repo: &InMemoryRepository) -> Uuid {
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        for (value, days, hours) in [(8, 40, 3), (10, 40, 1), (3, 39, 1), (2, 10, 1), (4, 1, 1)] {
+            let observed_at = now() - Duration::days(days) - Duration::hours(hours);
+            let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), observed_at);
+            repo.update_status(&report).await.unwrap();
+            repo.add_report(&report).await.unwrap();
+        }
+        let collected_at = now() - Duration::days(20);
+        repo.record_collection(&CollectionEvent { bin_id, collected_at, received_at: collected_at }).await.unwrap();
+        bin_id
+    }
+
+    fn policy() -> RetentionPolicy {
+        RetentionPolicy::new(7).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_archives_and_rolls_up_days_before_the_collection() {
+        let (repo, bin_id) = history().await;
+        let archive = InMemoryArchive::new();
+
+        let summary = run_retention(&repo, &archive, &policy(), now(), &RetentionOptions::default()).await.unwrap();
+
+        assert_eq!((summary.days_archived, summary.reports_archived), (2, 3));
+        // The report 10 days ago still counts towards the estimate
+        assert_eq!(repo.reports(&bin_id).iter().map(|report| report.status.value()).collect::<Vec<_>>(), vec![2, 4]);
+
+        let first_day = (now() - Duration::days(40) - Duration::hours(1)).date_naive();
+        assert_eq!(archive.get(&bin_id, first_day).await.unwrap().unwrap().reports.len(), 2);
+        let rollups = repo.daily_rollups(&bin_id, first_day, now().date_naive()).await.unwrap();
+        assert_eq!(rollups.iter().map(|rollup| rollup.stats.max).collect::<Vec<_>>(), vec![10, 3]);
+        assert_eq!(rollups[0].stats.mean(), Some(9.0));
+
+        // The estimate is still what the remaining log gives
+        let check = CheckOptions { bins_per_second: None, ..CheckOptions::default() };
+        assert!(run_consistency_check(&repo, &FillEstimator::default(), &check).await.unwrap().drifted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rerun_changes_nothing() {
+        let (repo, bin_id) = history().await;
+        let archive = InMemoryArchive::new();
+        let from = (now() - Duration::days(60)).date_naive();
+
+        run_retention(&repo, &archive, &policy(), now(), &RetentionOptions::default()).await.unwrap();
+        let rollups = repo.daily_rollups(&bin_id, from, now().date_naive()).await.unwrap();
+        let again = run_retention(&repo, &archive, &policy(), now(), &RetentionOptions::default()).await.unwrap();
+
+        assert_eq!(again.days_archived, 0);
+        assert_eq!(repo.daily_rollups(&bin_id, from, now().date_naive()).await.unwrap(), rollups);
+    }
+
+    #[tokio::test]
+    async fn test_finishes_a_day_interrupted_after_its_archive() {
+        let (repo, bin_id) = history().await;
+        let archive = InMemoryArchive::new();
+        let first_day = (now() - Duration::days(40) - Duration::hours(1)).date_naive();
+
+        // An earlier run archived the day and deleted one of its reports
+        let reports: Vec<StatusReport> = repo.reports(&bin_id).into_iter().filter(|r| r.observed_at.date_naive() == first_day).collect();
+        let raw = ArchivedDay { reports, incidents: Vec::new() };
+        archive.put(&bin_id, first_day, &raw, now()).await.unwrap();
+        repo.delete_reports(&bin_id, &raw.reports[..1]).await.unwrap();
+
+        run_retention(&repo, &archive, &policy(), now(), &RetentionOptions::default()).await.unwrap();
+
+        assert_eq!(archive.get(&bin_id, first_day).await.unwrap().unwrap(), raw);
+        let rollups = repo.daily_rollups(&bin_id, first_day, first_day).await.unwrap();
+        assert_eq!(rollups[0].stats.reports_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_adds_only_what_the_stream_missed_to_the_location() {
+        let (repo, bin_id) = history().await;
+        let location_id = Uuid::new_v4();
+        repo.place_bin(bin_id, location_id);
+        let archive = InMemoryArchive::new();
+        let first_day = (now() - Duration::days(40) - Duration::hours(1)).date_naive();
+        // The stream saw the first day's reports, but not the next day's
+        let streamed: Vec<(Uuid, BinEvent)> = repo
+            .reports(&bin_id)
+            .into_iter()
+            .filter(|report| report.observed_at.date_naive() == first_day)
+            .map(|report| (bin_id, BinEvent::Reported(report)))
+            .collect();
+        apply_log_entries(&repo, &streamed).await.unwrap();
+        let streamed_day = repo.daily_rollups(&bin_id, first_day, first_day).await.unwrap();
+
+        run_retention(&repo, &archive, &policy(), now(), &RetentionOptions::default()).await.unwrap();
+
+        assert_eq!(repo.daily_rollups(&bin_id, first_day, first_day).await.unwrap(), streamed_day);
+        let from = first_day.and_time(chrono::NaiveTime::MIN).and_utc();
+        let location = repo.rollups(&RollupScope::Location(location_id), Resolution::Daily, from, now()).await.unwrap();
+        let bin = repo.rollups(&RollupScope::Bin(bin_id), Resolution::Daily, from, now()).await.unwrap();
+        assert_eq!(location.iter().map(|rollup| rollup.stats.reports_count).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(location.iter().map(|rollup| &rollup.stats).collect::<Vec<_>>(), bin.iter().map(|rollup| &rollup.stats).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_redelivered_report_is_archived_once() {
+        let (repo, bin_id) = history().await;
+        let archive = InMemoryArchive::new();
+        let first_day = (now() - Duration::days(40) - Duration::hours(1)).date_naive();
+        let raw: Vec<StatusReport> = repo.reports(&bin_id).into_iter().filter(|r| r.observed_at.date_naive() == first_day).collect();
+        let redelivered = ArchivedDay { reports: raw.iter().chain(&raw[..1]).cloned().collect(), incidents: Vec::new() };
+
+        archive_day(&repo, &archive, bin_id, first_day, &redelivered, now()).await.unwrap();
+
+        assert_eq!(archive.get(&bin_id, first_day).await.unwrap().unwrap().reports.len(), 2);
+        let rollups = repo.daily_rollups(&bin_id, first_day, first_day).await.unwrap();
+        assert_eq!(rollups[0].stats.reports_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_archives_incidents_past_retention_even_before_a_collection() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let archive = InMemoryArchive::new();
+        let observed_at = now() - Duration::days(30);
+        let fire = |still_burning| IncidentReport {
+            report_id: Uuid::new_v4(),
+            bin_id,
+            details: ReportDetails::Fire { still_burning },
+            observed_at,
+            received_at: observed_at,
+        };
+        // Two reports of the same kind at the same instant, and a recent one
+        let (old, same_instant) = (fire(true), fire(false));
+        let recent = IncidentReport { observed_at: now() - Duration::days(1), ..fire(false) };
+        for incident in [&old, &same_instant, &recent] {
+            repo.add_incident(incident).await.unwrap();
+        }
+
+        let summary = run_retention(&repo, &archive, &policy(), now(), &RetentionOptions::default()).await.unwrap();
+
+        assert_eq!((summary.days_archived, summary.reports_archived, summary.incidents_archived), (1, 0, 2));
+        let archived = archive.get(&bin_id, observed_at.date_naive()).await.unwrap().unwrap();
+        assert_eq!(archived.incidents.len(), 2);
+        assert!(archived.incidents.contains(&old) && archived.incidents.contains(&same_instant));
+        assert!(repo.daily_rollups(&bin_id, observed_at.date_naive(), now().date_naive()).await.unwrap().is_empty());
+        let left = repo.incidents_before(&bin_id, now()).await.unwrap();
+        assert_eq!(left, vec![recent]);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_runs_continue_where_the_last_one_stopped() {
+        let (repo, _) = history().await;
+        add_history(&repo).await;
+        let archive = InMemoryArchive::new();
+        let one_bin = RetentionOptions { page_size: 1, max_bins: Some(1), ..RetentionOptions::default() };
+
+        let first = run_scheduled_retention(&repo, &archive, &policy(), now(), &one_bin).await.unwrap();
+        let stopped_at = archive.resume_token().await.unwrap();
+        let second = run_scheduled_retention(&repo, &archive, &policy(), now(), &one_bin).await.unwrap();
+
+        assert_eq!(first.resume.as_deref(), stopped_at.as_ref().map(|token| token.to_string()).as_deref());
+        assert!(stopped_at.is_some());
+        assert_eq!((first.days_archived, second.days_archived), (2, 2));
+        assert_eq!(second.resume, None);
+        assert_eq!(archive.resume_token().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use crate::domain::history::BinEvent;
+use crate::domain::rollup::{bin_rollups, Resolution, Rollup, RollupRepository, RollupScope, RollupUpdate};
+use crate::error::{AppError, DatabaseError};
+
+/// How far around a new log entry its bin's rollups are recomputed. A bin
+/// left full for longer without a report only counts the last month.
+const MAX_REPLAY_DAYS: i64 = 31;
+
+/// Recomputes after a concurrent change before giving up on a bin.
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Default, Serialize)]
+pub struct RollupSummary {
+    pub bins: usize,
+    /// Bin rollups replaced, not counting the shared ones.
+    pub periods_updated: usize,
+}
+
+/// Brings the rollups up to date with log entries that were just written.
+/// Each bin's rollups around the entries are recomputed from its log and
+/// only the change is added to its location and district, so entries
+/// delivered twice or out of order count once.
+#[instrument(skip_all, fields(entries = entries.len()))]
+pub async fn apply_log_entries<R: RollupRepository>(
+    repo: &R,
+    entries: &[(Uuid, BinEvent)],
+) -> Result<RollupSummary, AppError> {
+    let mut by_bin: BTreeMap<Uuid, (DateTime<Utc>, DateTime<Utc>)> = BTreeMap::new();
+    for (bin_id, event) in entries {
+        let (first, last) = by_bin.entry(*bin_id).or_insert((event.at(), event.at()));
+        *first = (*first).min(event.at());
+        *last = (*last).max(event.at());
+    }
+
+    let mut summary = RollupSummary::default();
+    for (bin_id, (first, last)) in by_bin {
+        summary.bins += 1;
+        summary.periods_updated += update_bin(repo, bin_id, first, last).await?;
+    }
+    Ok(summary)
+}
+
+/// Recomputes the days from the entry before `first` to the entry after
+/// `last`, as the full time between them may have changed.
+async fn update_bin<R: RollupRepository>(
+    repo: &R,
+    bin_id: Uuid,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let around = repo.bin_timeline(&bin_id, first, last + Duration::nanoseconds(1)).await?;
+    let from = around.before.map_or(first, |event| event.at()).max(first - Duration::days(MAX_REPLAY_DAYS));
+    let to = around.after.map_or(last, |event| event.at()).min(last + Duration::days(MAX_REPLAY_DAYS));
+    let from = Resolution::Daily.period_start(from);
+    let to = Resolution::Daily.period_start(to) + Duration::days(1);
+    let placement = repo.bin_placement(&bin_id).await?;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let timeline = repo.bin_timeline(&bin_id, from, to).await?;
+        let recomputed = bin_rollups(bin_id, &timeline, from, to);
+        let mut stored = Vec::new();
+        for resolution in Resolution::ALL {
+            stored.extend(repo.rollups(&RollupScope::Bin(bin_id), resolution, from, to).await?);
+        }
+
+        // One update per day keeps each within a DynamoDB transaction
+        let mut updated = 0;
+        let mut conflict = false;
+        let mut day = from;
+        while day < to {
+            let in_day = |rollup: &&Rollup| Resolution::Daily.period_start(rollup.period_start) == day;
+            let recomputed: Vec<Rollup> = recomputed.iter().filter(in_day).cloned().collect();
+            let stored: Vec<Rollup> = stored.iter().filter(in_day).cloned().collect();
+            let update = RollupUpdate::plan(&recomputed, &stored, &placement);
+            if !update.is_empty() {
+                if !repo.apply_rollup_update(&update).await? {
+                    conflict = true;
+                    break;
+                }
+                updated += update.bins.len();
+            }
+            day += Duration::days(1);
+        }
+
+        if !conflict {
+            return Ok(updated);
+        }
+        warn!(bin_id = %bin_id, attempt, "Rollups changed while updating them, recomputing");
+    }
+    Err(AppError::DatabaseError(DatabaseError::Conflict(format!(
+        "Rollups of bin {} kept changing while updating them",
+        bin_id
+    ))))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FillHistoryPoint {
+    pub period_start: DateTime<Utc>,
+    pub reports_count: u32,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub mean: Option<f64>,
+    pub full_seconds: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FillHistory {
+    pub resolution: Resolution,
+    pub points: Vec<FillHistoryPoint>,
+}
+
+/// Rollups of `scope` covering `[from, to)`, hourly for short ranges and
+/// daily otherwise unless `resolution` is given.
+#[instrument(skip_all, fields(scope = ?scope))]
+pub async fn fill_history<R: RollupRepository>(
+    repo: &R,
+    scope: &RollupScope,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    resolution: Option<Resolution>,
+) -> Result<FillHistory, AppError> {
+    if from >= to {
+        return Err(AppError::InvalidRequest("The history range must end after it starts".to_string()));
+    }
+    let resolution = resolution.unwrap_or_else(|| Resolution::for_range(from, to));
+    let rollups = repo.rollups(scope, resolution, resolution.period_start(from), to).await?;
+    info!(resolution = %resolution, periods = rollups.len(), "Read fill history");
+
+    let points = rollups
+        .into_iter()
+        .map(|rollup| {
+            let has_reports = rollup.stats.reports_count > 0;
+            FillHistoryPoint {
+                period_start: rollup.period_start,
+                reports_count: rollup.stats.reports_count,
+                min: has_reports.then_some(rollup.stats.min),
+                max: has_reports.then_some(rollup.stats.max),
+                mean: rollup.stats.mean(),
+                full_seconds: rollup.stats.full_seconds,
+            }
+        })
+        .collect();
+    Ok(FillHistory { resolution, points })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::domain::{BinRepository, BinStatus, Location, StatusReport};
+    use crate::infrastructure::memory::InMemoryRepository;
+
+    fn day() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 19, 0, 0, 0).unwrap()
+    }
+
+    /// Two bins at one location in Praha 2.
+    fn placed_bins(repo: &InMemoryRepository) -> (Uuid, Uuid, Uuid) {
+        let location_id = Uuid::new_v4();
+        repo.add_location(Location {
+            id: location_id,
+            address: "Náměstí Míru 1, Praha".to_string(),
+            latitude: 50.075,
+            longitude: 14.437,
+            name: "Náměstí Míru".to_string(),
+            district: Some("Praha 2".to_string()),
+        });
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        for bin_id in [first, second] {
+            repo.add_bin(bin_id);
+            repo.place_bin(bin_id, location_id);
+        }
+        (location_id, first, second)
+    }
+
+    async fn log(repo: &InMemoryRepository, bin_id: Uuid, value: i32, at: DateTime<Utc>) -> (Uuid, BinEvent) {
+        let report = StatusReport::sensor(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), at);
+        repo.add_report(&report).await.unwrap();
+        (bin_id, BinEvent::Reported(report))
+    }
+
+    async fn daily(repo: &InMemoryRepository, scope: RollupScope) -> Vec<Rollup> {
+        repo.rollups(&scope, Resolution::Daily, day(), day() + Duration::days(1)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bins_add_up_to_location_and_district() {
+        let repo = InMemoryRepository::new();
+        let (location_id, first, second) = placed_bins(&repo);
+
+        let entries = vec![
+            log(&repo, first, 10, day() + Duration::hours(8)).await,
+            log(&repo, first, 2, day() + Duration::hours(10)).await,
+            log(&repo, second, 6, day() + Duration::hours(9)).await,
+        ];
+        let summary = apply_log_entries(&repo, &entries).await.unwrap();
+
+        assert_eq!(summary.bins, 2);
+        let location = daily(&repo, RollupScope::Location(location_id)).await;
+        let stats = &location[0].stats;
+        assert_eq!((stats.reports_count, stats.min, stats.max, stats.sum), (3, 2, 10, 18));
+        assert_eq!(stats.full_seconds, 2 * 3600);
+        assert_eq!(daily(&repo, RollupScope::District("Praha 2".to_string())).await[0].stats, *stats);
+    }
+
+    #[tokio::test]
+    async fn test_redelivered_and_late_entries_count_once() {
+        let repo = InMemoryRepository::new();
+        let (location_id, bin_id, _) = placed_bins(&repo);
+
+        let full = log(&repo, bin_id, 9, day() + Duration::hours(8)).await;
+        let emptied = log(&repo, bin_id, 1, day() + Duration::hours(12)).await;
+        apply_log_entries(&repo, &[full.clone(), emptied]).await.unwrap();
+        apply_log_entries(&repo, &[full]).await.unwrap();
+        // A late report shows the bin was only full until 10:00
+        let late = log(&repo, bin_id, 5, day() + Duration::hours(10)).await;
+        apply_log_entries(&repo, &[late]).await.unwrap();
+
+        let bin = daily(&repo, RollupScope::Bin(bin_id)).await;
+        assert_eq!((bin[0].stats.reports_count, bin[0].stats.full_seconds), (3, 2 * 3600));
+        assert_eq!(daily(&repo, RollupScope::Location(location_id)).await[0].stats, bin[0].stats);
+    }
+
+    #[tokio::test]
+    async fn test_history_picks_resolution_for_the_range() {
+        let repo = InMemoryRepository::new();
+        let (_, bin_id, _) = placed_bins(&repo);
+        let entry = log(&repo, bin_id, 4, day() + Duration::hours(8)).await;
+        apply_log_entries(&repo, &[entry]).await.unwrap();
+        let scope = RollupScope::Bin(bin_id);
+
+        let week = fill_history(&repo, &scope, day(), day() + Duration::days(2), None).await.unwrap();
+        let quarter = fill_history(&repo, &scope, day() - Duration::days(60), day() + Duration::days(30), None).await.unwrap();
+
+        // Periods without reports or full time are not stored
+        assert_eq!((week.resolution, week.points.len()), (Resolution::Hourly, 1));
+        assert_eq!(week.points[0].period_start, day() + Duration::hours(8));
+        assert_eq!(week.points[0].mean, Some(4.0));
+        assert_eq!((quarter.resolution, quarter.points.len()), (Resolution::Daily, 1));
+        assert!(fill_history(&repo, &scope, day(), day(), None).await.is_err());
+    }
+}
@@ -0,0 +1,10 @@
+use lambda_runtime::{run, service_fn, Error};
+use bin_status_reporter::infrastructure::telemetry;
+use bin_status_reporter::set_bin_profile;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    telemetry::init();
+
+    run(service_fn(set_bin_profile)).await
+}
@@ -0,0 +1,10 @@
+use lambda_runtime::{run, service_fn, Error};
+use bin_status_reporter::infrastructure::telemetry;
+use bin_status_reporter::get_flagged_devices;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    telemetry::init();
+
+    run(service_fn(get_flagged_devices)).await
+}
@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use aws_sdk_dynamodb::config::retry::RetryConfig;
+use clap::{Parser, Subcommand};
+use lambda_runtime::Error;
+use shared::logging::{LogFormat, LoggingConfig};
+use bin_status_reporter::application::backup::{export, import, ExportOptions, ImportOptions};
+use bin_status_reporter::application::context::AppContext;
+use bin_status_reporter::domain::backup::{BackupRepository, ExportCursor};
+use bin_status_reporter::infrastructure::dynamodb::{connect, estimator_from_env, DynamoDbRepository};
+use bin_status_reporter::infrastructure::schema::TableNames;
+use bin_status_reporter::infrastructure::single_table::SingleTableRepository;
+
+/// Exports a deployment's locations, bins, QR codes and report log to a
+/// JSON Lines file, or imports such a file. Table names come from the
+/// same variables the functions use.
+#[derive(Parser)]
+#[command(name = "ecoscan-backup")]
+struct Cli {
+    #[arg(long, env = "DYNAMODB_ENDPOINT_URL")]
+    endpoint: Option<String>,
+
+    /// Use the single-table layout in this table instead of the two tables
+    #[arg(long)]
+    single_table: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write every record to a new file
+    Export {
+        #[arg(long)]
+        output: PathBuf,
+
+        /// Items read per scan request
+        #[arg(long, default_value_t = 500)]
+        page_size: usize,
+
+        /// Stop after this many records and print a resume cursor
+        #[arg(long)]
+        max_records: Option<usize>,
+
+        /// Continue an interrupted export from the cursor it logged or printed
+        #[arg(long)]
+        resume: Option<ExportCursor>,
+    },
+    /// Store the records of an export, replacing stored copies
+    Import {
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Lines an interrupted import already stored
+        #[arg(long, default_value_t = 0)]
+        skip_lines: usize,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+
+    let mut logging = LoggingConfig::from_env();
+    if std::env::var("LOG_FORMAT").is_err() {
+        logging.format = LogFormat::Pretty;
+    }
+    shared::logging::init(&logging);
+
+    let client = connect(cli.endpoint.as_deref(), RetryConfig::standard()).await;
+    let estimator = estimator_from_env()?;
+    match cli.single_table {
+        Some(table) => run(&SingleTableRepository::new(client, table, estimator), cli.command).await,
+        None => run(&DynamoDbRepository::with_client(client, &TableNames::from_env(), estimator), cli.command).await,
+    }
+}
+
+async fn run<R: BackupRepository>(repo: &R, command: Command) -> Result<(), Error> {
+    match command {
+        Command::Export { output, page_size, max_records, resume } => {
+            // A resumed export goes to a new file, so an earlier part is never overwritten
+            let mut out = BufWriter::new(File::create_new(&output)?);
+            let options = ExportOptions { page_size, max_records, resume };
+            let summary = export(repo, &mut out, AppContext::default().now(), &options).await?;
+            println!("Exported {} records to {}", summary.records, output.display());
+            if let Some(resume) = &summary.resume {
+                println!("Resume with --resume {}", resume);
+            }
+        }
+        Command::Import { input, skip_lines } => {
+            let summary = import(repo, BufReader::new(File::open(&input)?), &ImportOptions { skip_lines }).await?;
+            println!("Imported {} records from {} lines of {}", summary.imported, summary.lines, input.display());
+        }
+    }
+    Ok(())
+}
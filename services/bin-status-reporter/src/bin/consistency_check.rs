@@ -0,0 +1,76 @@
+use aws_sdk_dynamodb::config::retry::RetryConfig;
+use clap::Parser;
+use lambda_runtime::Error;
+use shared::logging::{LogFormat, LoggingConfig};
+use bin_status_reporter::application::consistency::{run_consistency_check, CheckOptions};
+use bin_status_reporter::domain::consistency::ResumeToken;
+use bin_status_reporter::infrastructure::dynamodb::{connect, estimator_from_env, DynamoDbRepository};
+use bin_status_reporter::infrastructure::schema::TableNames;
+
+/// Recomputes every bin's estimate from `status-reports` and lists the bins
+/// whose stored estimate in `trash-bins` differs. Report weights come from
+/// the same variables the functions use.
+#[derive(Parser)]
+#[command(name = "ecoscan-consistency")]
+struct Cli {
+    #[arg(long, env = "DYNAMODB_ENDPOINT_URL")]
+    endpoint: Option<String>,
+
+    /// Write the recomputed estimate over drifted bins
+    #[arg(long)]
+    fix: bool,
+
+    /// Bins read per scan request
+    #[arg(long, default_value_t = 100)]
+    page_size: usize,
+
+    /// Upper bound on bins checked per second; 0 disables the limit
+    #[arg(long, default_value_t = 50.0)]
+    rate: f64,
+
+    /// Stop after this many bins and print a resume token
+    #[arg(long)]
+    max_bins: Option<usize>,
+
+    /// Continue a previous run from the token it printed
+    #[arg(long)]
+    resume: Option<ResumeToken>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+
+    let mut logging = LoggingConfig::from_env();
+    if std::env::var("LOG_FORMAT").is_err() {
+        logging.format = LogFormat::Pretty;
+    }
+    shared::logging::init(&logging);
+
+    let client = connect(cli.endpoint.as_deref(), RetryConfig::standard()).await;
+    let estimator = estimator_from_env()?;
+    let repo = DynamoDbRepository::with_client(client, &TableNames::from_env(), estimator.clone());
+    let options = CheckOptions {
+        fix: cli.fix,
+        page_size: cli.page_size,
+        bins_per_second: Some(cli.rate).filter(|rate| *rate > 0.0),
+        max_bins: cli.max_bins,
+        resume: cli.resume,
+    };
+
+    let summary = run_consistency_check(&repo, &estimator, &options).await?;
+    for drift in &summary.drifted {
+        println!("{}", drift);
+    }
+    println!(
+        "Checked {} bins: {} drifted, {} repaired, {} changed during the check",
+        summary.bins_checked,
+        summary.drifted.len(),
+        summary.repaired,
+        summary.changed_during_check
+    );
+    if let Some(resume) = &summary.resume {
+        println!("Resume with --resume {}", resume);
+    }
+    Ok(())
+}
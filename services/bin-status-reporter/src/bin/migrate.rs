@@ -0,0 +1,91 @@
+use aws_sdk_dynamodb::config::retry::RetryConfig;
+use clap::{Parser, Subcommand};
+use lambda_runtime::Error;
+use shared::logging::{LogFormat, LoggingConfig};
+use bin_status_reporter::application::context::AppContext;
+use bin_status_reporter::infrastructure::dynamodb::connect;
+use bin_status_reporter::infrastructure::migrations::Migrator;
+use bin_status_reporter::infrastructure::schema::{self, TableNames};
+use bin_status_reporter::infrastructure::single_table::copy_two_table_layout;
+
+/// Creates and updates the EcoScan DynamoDB tables and runs pending data
+/// migrations. Table names come from the same variables the functions use.
+#[derive(Parser)]
+#[command(name = "ecoscan-migrate")]
+struct Cli {
+    /// DynamoDB endpoint, e.g. http://localhost:4566 for LocalStack or
+    /// http://localhost:8000 for DynamoDB Local
+    #[arg(long, env = "DYNAMODB_ENDPOINT_URL")]
+    endpoint: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the schema changes and migrations that would run
+    Plan,
+    /// Apply schema changes, then pending migrations
+    Apply,
+    /// List the migrations recorded as applied
+    Status,
+    /// Create the single table if needed and copy bins and reports into it
+    CopyToSingleTable {
+        #[arg(long, env = "ECOSCAN_TABLE", default_value = "ecoscan")]
+        table: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+
+    let mut logging = LoggingConfig::from_env();
+    if std::env::var("LOG_FORMAT").is_err() {
+        logging.format = LogFormat::Pretty;
+    }
+    shared::logging::init(&logging);
+
+    let client = connect(cli.endpoint.as_deref(), RetryConfig::standard()).await;
+    let tables = TableNames::from_env();
+    let migrator = Migrator::new(client.clone(), tables.clone());
+
+    match cli.command {
+        Command::Plan => {
+            let plan = migrator.plan().await?;
+            if plan.is_empty() {
+                println!("Schema and data are up to date");
+            }
+            for change in &plan.schema_changes {
+                println!("schema: {}", change);
+            }
+            for (version, description) in &plan.pending {
+                println!("migration {}: {}", version, description);
+            }
+        }
+        Command::Apply => {
+            let report = migrator.apply(AppContext::default().now()).await?;
+            println!("Applied {} schema changes", report.schema_changes);
+            for (version, changed) in &report.applied {
+                println!("migration {}: {} items changed", version, changed);
+            }
+        }
+        Command::Status => {
+            for version in migrator.applied_versions().await? {
+                println!("migration {}: applied", version);
+            }
+        }
+        Command::CopyToSingleTable { table } => {
+            let desired = schema::single_table(&table);
+            let current = schema::describe(&client, &table).await?;
+            for change in schema::plan(&desired, current.as_ref())? {
+                println!("schema: {}", change);
+                schema::apply(&client, &desired, &change).await?;
+            }
+            let report = copy_two_table_layout(&client, &tables, &table).await?;
+            println!("Copied {} bins and {} reports into {}", report.bins, report.reports, table);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use lambda_runtime::Error;
+use tracing::{error, info};
+use bin_status_reporter::health_policy_from_env;
+use bin_status_reporter::infrastructure::telemetry;
+use bin_status_reporter::application::context::AppContext;
+use bin_status_reporter::infrastructure::dynamodb::DynamoDbRepository;
+use bin_status_reporter::infrastructure::metrics::PrometheusRecorder;
+use bin_status_reporter::infrastructure::mqtt::{MqttBridge, MqttBridgeConfig};
+use bin_status_reporter::infrastructure::resilience::ResilientRepository;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    telemetry::init();
+
+    let config = MqttBridgeConfig::from_env()?;
+    let metrics = Arc::new(PrometheusRecorder::new());
+    if let Ok(addr) = std::env::var("METRICS_ADDR") {
+        let addr = addr.parse().map_err(|e| format!("Invalid METRICS_ADDR {}: {}", addr, e))?;
+        tokio::spawn(metrics.clone().serve(addr));
+    }
+
+    let repo = ResilientRepository::new(DynamoDbRepository::new().await?).with_metrics(metrics.clone());
+    let ctx = AppContext::default().with_metrics(metrics).with_health_policy(health_policy_from_env()?);
+    info!("Starting MQTT bridge for topics {:?}", config.topics);
+
+    tokio::select! {
+        result = MqttBridge::new(config, repo).with_context(ctx).run() => {
+            if let Err(e) = &result {
+                error!("MQTT bridge stopped: {}", e);
+            }
+            Ok(result?)
+        }
+        _ = tokio::signal::ctrl_c() => {
+            info!("Received shutdown signal, stopping MQTT bridge");
+            Ok(())
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::env;
 
+use shared::logging::{LogFormat, LoggingConfig};
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub dynamodb_endpoint: Option<String>,
@@ -7,6 +9,7 @@ pub struct Config {
     pub status_reports_table: String,
     pub aws_region: String,
     pub log_level: String,
+    pub log_format: LogFormat,
 }
 
 impl Config {
@@ -21,9 +24,18 @@ impl Config {
                 .unwrap_or_else(|_| "eu-central-1".to_string()),
             log_level: env::var("LOG_LEVEL")
                 .unwrap_or_else(|_| "INFO".to_string()),
+            log_format: env::var("LOG_FORMAT")
+                .ok()
+                .and_then(|format| format.parse().ok())
+                .unwrap_or_default(),
         }
     }
 
+    /// `log_level` takes any `EnvFilter` directives, not just a level.
+    pub fn logging(&self) -> LoggingConfig {
+        LoggingConfig::new(&self.log_level, self.log_format)
+    }
+
     pub fn is_local_development(&self) -> bool {
         self.dynamodb_endpoint.is_some()
     }
@@ -42,6 +54,7 @@ mod tests {
         
         assert_eq!(config.trash_bins_table, "test-bins");
         assert_eq!(config.log_level, "DEBUG");
+        assert_eq!(config.logging().directives, "DEBUG");
     }
 
     #[test]
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::{BinStatus, StatusReport};
+
+/// Largest batch accepted in one request. Every bin's group must fit into a
+/// single DynamoDB transaction together with the bin update.
+pub const MAX_BATCH_ITEMS: usize = 50;
+
+/// Items are kept as raw JSON so that one malformed entry is reported on its
+/// own instead of failing deserialization of the whole batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatusRequest {
+    pub items: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatusItem {
+    pub bin_id: Uuid,
+    pub status: BinStatus,
+    pub client_timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub bin_id: Option<Uuid>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl BatchItemResult {
+    pub fn accepted(index: usize, bin_id: Uuid) -> Self {
+        Self { index, bin_id: Some(bin_id), success: true, error: None }
+    }
+
+    pub fn rejected(index: usize, bin_id: Option<Uuid>, error: String) -> Self {
+        Self { index, bin_id, success: false, error: Some(error) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatusResponse {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub results: Vec<BatchItemResult>,
+}
+
+impl BatchStatusResponse {
+    pub fn from_results(mut results: Vec<BatchItemResult>) -> Self {
+        results.sort_by_key(|result| result.index);
+        let accepted = results.iter().filter(|result| result.success).count();
+        Self { accepted, rejected: results.len() - accepted, results }
+    }
+}
+
+/// Reports for one bin, oldest first, stored together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportGroup {
+    pub bin_id: Uuid,
+    pub reports: Vec<StatusReport>,
+}
+
+/// Groups reports by bin, keeping first-seen bin order and sorting each
+/// group by timestamp so the estimate is folded in observation order.
+pub fn group_by_bin(reports: Vec<StatusReport>) -> Vec<ReportGroup> {
+    let mut groups: Vec<ReportGroup> = Vec::new();
+    for report in reports {
+        match groups.iter_mut().find(|group| group.bin_id == report.bin_id) {
+            Some(group) => group.reports.push(report),
+            None => groups.push(ReportGroup { bin_id: report.bin_id, reports: vec![report] }),
+        }
+    }
+    for group in &mut groups {
+        group.reports.sort_by_key(|report| report.observed_at);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_group_by_bin_orders_reports() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        let groups = group_by_bin(vec![
+            StatusReport::citizen(Uuid::new_v4(), a, BinStatus::full(), now),
+            StatusReport::citizen(Uuid::new_v4(), b, BinStatus::ok(), now),
+            StatusReport::citizen(Uuid::new_v4(), a, BinStatus::empty(), now - Duration::hours(1)),
+        ]);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].bin_id, a);
+        assert_eq!(groups[0].reports[0].status, BinStatus::empty());
+        assert_eq!(groups[0].reports[1].status, BinStatus::full());
+        assert_eq!(groups[1].reports.len(), 1);
+    }
+
+    #[test]
+    fn test_response_counts_and_orders_results() {
+        let bin_id = Uuid::new_v4();
+        let response = BatchStatusResponse::from_results(vec![
+            BatchItemResult::rejected(1, None, "bad".to_string()),
+            BatchItemResult::accepted(0, bin_id),
+            BatchItemResult::accepted(2, bin_id),
+        ]);
+
+        assert_eq!(response.accepted, 2);
+        assert_eq!(response.rejected, 1);
+        let indices: Vec<_> = response.results.iter().map(|result| result.index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A crew emptying a bin. Reports observed before the collection describe
+/// the old contents and no longer count towards the fill estimate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionEvent {
+    pub bin_id: Uuid,
+    pub collected_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionRequest {
+    pub bin_id: Uuid,
+    /// When the bin was emptied, if the crew app recorded it offline.
+    #[serde(default)]
+    pub collected_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::estimator::{BinAggregate, FillEstimator};
+use crate::domain::history::{fold, BinEvent};
+use crate::domain::BinStatus;
+use crate::error::AppError;
+
+/// Weights are sums of small decimals, so they are compared with a margin.
+const WEIGHT_TOLERANCE: f64 = 1e-6;
+
+/// Where a bin scan stopped. Passing it back continues after the last bin
+/// returned, so a large table can be checked over several runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeToken(pub Uuid);
+
+impl fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ResumeToken {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s)
+            .map(ResumeToken)
+            .map_err(|_| AppError::InvalidRequest(format!("Invalid resume token: {}", s)))
+    }
+}
+
+/// A bin as stored, including the `status` column, which older writers
+/// computed separately from the weights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBin {
+    pub bin_id: Uuid,
+    pub status: BinStatus,
+    pub aggregate: BinAggregate,
+}
+
+#[derive(Debug, Default)]
+pub struct BinPage {
+    pub bins: Vec<StoredBin>,
+    /// `None` once the scan has reached the end.
+    pub next: Option<ResumeToken>,
+}
+
+#[async_trait]
+pub trait BinHistoryRepository: Send + Sync {
+    /// Up to `limit` bins after `resume`, or from the start.
+    async fn scan_bins(&self, resume: Option<&ResumeToken>, limit: usize) -> Result<BinPage, AppError>;
+
+    /// The bin's logged events from `since` up to and including `until`.
+    /// A missing bound leaves that end open.
+    async fn bin_events(
+        &self,
+        bin_id: &Uuid,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BinEvent>, AppError>;
+
+    /// Replaces the stored estimate with `recomputed` if the bin still
+    /// matches `stored`. Returns `false` if it changed in the meantime.
+    async fn repair_bin(&self, stored: &StoredBin, recomputed: &BinAggregate) -> Result<bool, AppError>;
+}
+
+/// The aggregate the report log implies, starting from the bin's stored
+/// collection. A later collection in the log takes over from it.
+pub fn recompute(estimator: &FillEstimator, collected_at: Option<DateTime<Utc>>, events: &[BinEvent]) -> BinAggregate {
+    let mut history: Vec<BinEvent> = collected_at.map(|at| BinEvent::Collected { at }).into_iter().collect();
+    history.extend_from_slice(events);
+    fold(estimator, &history, None).estimate
+}
+
+/// A bin whose stored estimate disagrees with its report log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Drift {
+    pub bin_id: Uuid,
+    pub stored_status: i32,
+    pub recomputed_status: i32,
+    pub stored_reports: u32,
+    pub recomputed_reports: u32,
+}
+
+impl Drift {
+    pub fn detect(stored: &StoredBin, recomputed: &BinAggregate) -> Option<Self> {
+        let aggregate = &stored.aggregate;
+        let consistent = stored.status == recomputed.status()
+            && aggregate.reports_count == recomputed.reports_count
+            && aggregate.collected_at == recomputed.collected_at
+            && (aggregate.weighted_sum - recomputed.weighted_sum).abs() < WEIGHT_TOLERANCE
+            && (aggregate.weight_total - recomputed.weight_total).abs() < WEIGHT_TOLERANCE;
+
+        (!consistent).then(|| Self {
+            bin_id: stored.bin_id,
+            stored_status: stored.status.value(),
+            recomputed_status: recomputed.status().value(),
+            stored_reports: aggregate.reports_count,
+            recomputed_reports: recomputed.reports_count,
+        })
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bin {}: stored status {} from {} reports, log gives {} from {} reports",
+            self.bin_id, self.stored_status, self.stored_reports, self.recomputed_status, self.recomputed_reports
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    use crate::domain::StatusReport;
+
+    fn reported(report: StatusReport) -> BinEvent {
+        BinEvent::Reported(report)
+    }
+
+    #[test]
+    fn test_recompute_folds_in_observation_order_after_collection() {
+        let bin_id = Uuid::new_v4();
+        let now = Utc::now();
+        let events = vec![
+            reported(StatusReport::sensor(Uuid::new_v4(), bin_id, BinStatus::new(8).unwrap(), now)),
+            reported(StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), now - Duration::hours(3))),
+            reported(StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::empty(), now - Duration::hours(1))),
+        ];
+
+        let aggregate = recompute(&FillEstimator::default(), Some(now - Duration::hours(2)), &events);
+
+        assert_eq!(aggregate.reports_count, 2);
+        assert_eq!(aggregate.status().value(), 6);
+        assert_eq!(aggregate.last_observed_at, Some(now));
+
+        // A collection logged but never applied to the bin
+        let mut missed = events.clone();
+        missed.push(BinEvent::Collected { at: now - Duration::minutes(30) });
+        let aggregate = recompute(&FillEstimator::default(), Some(now - Duration::hours(2)), &missed);
+        assert_eq!((aggregate.reports_count, aggregate.collected_at), (1, Some(now - Duration::minutes(30))));
+    }
+
+    #[test]
+    fn test_drift_compares_stored_status_and_weights() {
+        let estimator = FillEstimator::default();
+        let bin_id = Uuid::new_v4();
+        let events = vec![
+            reported(StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(3).unwrap(), Utc::now())),
+            reported(StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(4).unwrap(), Utc::now())),
+        ];
+        let recomputed = recompute(&estimator, None, &events);
+        let stored = StoredBin { bin_id, status: recomputed.status(), aggregate: recomputed.clone() };
+        assert_eq!(Drift::detect(&stored, &recomputed), None);
+
+        // Truncating averages stored 3 where the log gives 3.5, rounded to 4
+        let truncated = StoredBin { status: BinStatus::new(3).unwrap(), ..stored.clone() };
+        assert_eq!(Drift::detect(&truncated, &recomputed).unwrap().stored_status, 3);
+
+        let lost_report = StoredBin { aggregate: BinAggregate { reports_count: 1, ..recomputed.clone() }, ..stored };
+        assert_eq!(Drift::detect(&lost_report, &recomputed).unwrap().recomputed_reports, 2);
+    }
+
+    #[test]
+    fn test_resume_token_round_trip() {
+        let token = ResumeToken(Uuid::new_v4());
+        assert_eq!(token.to_string().parse::<ResumeToken>().unwrap(), token);
+        assert!("page-2".parse::<ResumeToken>().is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// Everything a device told us about itself in one transmission, beyond the
+/// fill distance it measured.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceTelemetry {
+    pub device_id: String,
+    pub seen_at: DateTime<Utc>,
+    pub distance_mm: u32,
+    pub battery_mv: Option<u16>,
+    pub rssi_dbm: Option<i16>,
+    pub firmware_version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HealthFlag {
+    Silent { last_seen: DateTime<Utc> },
+    LowBattery { battery_mv: u16 },
+    Stuck { distance_mm: u32, since: DateTime<Utc> },
+}
+
+impl HealthFlag {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HealthFlag::Silent { .. } => "silent",
+            HealthFlag::LowBattery { .. } => "low_battery",
+            HealthFlag::Stuck { .. } => "stuck",
+        }
+    }
+}
+
+/// Latest known state of a device as kept by the device registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceHealth {
+    pub device_id: String,
+    pub bin_id: Uuid,
+    pub last_seen: DateTime<Utc>,
+    pub battery_mv: Option<u16>,
+    pub rssi_dbm: Option<i16>,
+    pub firmware_version: Option<String>,
+    pub last_distance_mm: u32,
+    /// When the distance last moved by more than the stuck tolerance.
+    pub distance_changed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub flags: Vec<HealthFlag>,
+}
+
+impl DeviceHealth {
+    pub fn first_seen(bin_id: Uuid, telemetry: &DeviceTelemetry) -> Self {
+        Self {
+            device_id: telemetry.device_id.clone(),
+            bin_id,
+            last_seen: telemetry.seen_at,
+            battery_mv: telemetry.battery_mv,
+            rssi_dbm: telemetry.rssi_dbm,
+            firmware_version: telemetry.firmware_version.clone(),
+            last_distance_mm: telemetry.distance_mm,
+            distance_changed_at: telemetry.seen_at,
+            flags: Vec::new(),
+        }
+    }
+
+    /// Folds a new transmission in. Fields the device did not send keep
+    /// their previous value.
+    pub fn observe(&mut self, telemetry: &DeviceTelemetry, policy: &HealthPolicy) {
+        if telemetry.seen_at < self.last_seen {
+            return;
+        }
+        if telemetry.distance_mm.abs_diff(self.last_distance_mm) > policy.stuck_tolerance_mm {
+            self.distance_changed_at = telemetry.seen_at;
+            self.last_distance_mm = telemetry.distance_mm;
+        }
+        self.last_seen = telemetry.seen_at;
+        self.battery_mv = telemetry.battery_mv.or(self.battery_mv);
+        self.rssi_dbm = telemetry.rssi_dbm.or(self.rssi_dbm);
+        if telemetry.firmware_version.is_some() {
+            self.firmware_version = telemetry.firmware_version.clone();
+        }
+    }
+}
+
+/// Thresholds for the scheduled device health check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthPolicy {
+    pub silent_after: Duration,
+    pub low_battery_mv: u16,
+    pub stuck_after: Duration,
+    pub stuck_tolerance_mm: u32,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            silent_after: Duration::hours(6),
+            low_battery_mv: 3300,
+            stuck_after: Duration::days(3),
+            stuck_tolerance_mm: 10,
+        }
+    }
+}
+
+impl HealthPolicy {
+    pub fn evaluate(&self, health: &DeviceHealth, now: DateTime<Utc>) -> Vec<HealthFlag> {
+        let mut flags = Vec::new();
+        if now - health.last_seen > self.silent_after {
+            flags.push(HealthFlag::Silent { last_seen: health.last_seen });
+        }
+        if let Some(battery_mv) = health.battery_mv.filter(|mv| *mv < self.low_battery_mv) {
+            flags.push(HealthFlag::LowBattery { battery_mv });
+        }
+        // A silent sensor is trivially unchanged, so only report live ones as stuck
+        if now - health.last_seen <= self.silent_after && now - health.distance_changed_at > self.stuck_after {
+            flags.push(HealthFlag::Stuck {
+                distance_mm: health.last_distance_mm,
+                since: health.distance_changed_at,
+            });
+        }
+        flags
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceAlert {
+    pub device_id: String,
+    pub bin_id: Uuid,
+    pub flag: HealthFlag,
+    pub raised_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait DeviceHealthRepository {
+    async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError>;
+
+    async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError>;
+
+    async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError>;
+}
+
+/// A channel alerts can be delivered through.
+#[async_trait]
+pub trait AlertNotifier {
+    async fn notify(&self, alert: &DeviceAlert) -> Result<(), AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telemetry(seen_at: DateTime<Utc>, distance_mm: u32, battery_mv: Option<u16>) -> DeviceTelemetry {
+        DeviceTelemetry {
+            device_id: "s-1".to_string(),
+            seen_at,
+            distance_mm,
+            battery_mv,
+            rssi_dbm: Some(-97),
+            firmware_version: None,
+        }
+    }
+
+    #[test]
+    fn test_observe_tracks_distance_changes_beyond_tolerance() {
+        let policy = HealthPolicy::default();
+        let start = Utc::now() - Duration::days(5);
+        let mut health = DeviceHealth::first_seen(Uuid::new_v4(), &telemetry(start, 500, Some(3600)));
+
+        health.observe(&telemetry(start + Duration::hours(1), 505, None), &policy);
+        assert_eq!(health.distance_changed_at, start);
+        assert_eq!(health.battery_mv, Some(3600));
+
+        health.observe(&telemetry(start + Duration::hours(2), 420, Some(3550)), &policy);
+        assert_eq!(health.distance_changed_at, start + Duration::hours(2));
+        assert_eq!(health.last_distance_mm, 420);
+        assert_eq!(health.battery_mv, Some(3550));
+    }
+
+    #[test]
+    fn test_observe_ignores_stale_telemetry() {
+        let policy = HealthPolicy::default();
+        let now = Utc::now();
+        let mut health = DeviceHealth::first_seen(Uuid::new_v4(), &telemetry(now, 500, Some(3600)));
+
+        health.observe(&telemetry(now - Duration::hours(1), 100, Some(3000)), &policy);
+        assert_eq!(health.last_seen, now);
+        assert_eq!(health.battery_mv, Some(3600));
+    }
+
+    #[test]
+    fn test_evaluate_flags() {
+        let policy = HealthPolicy::default();
+        let now = Utc::now();
+
+        let healthy = DeviceHealth::first_seen(Uuid::new_v4(), &telemetry(now, 500, Some(3600)));
+        assert!(policy.evaluate(&healthy, now).is_empty());
+
+        let silent = DeviceHealth::first_seen(Uuid::new_v4(), &telemetry(now - Duration::days(1), 500, Some(3100)));
+        let kinds: Vec<_> = policy.evaluate(&silent, now).iter().map(HealthFlag::kind).collect();
+        assert_eq!(kinds, vec!["silent", "low_battery"]);
+
+        let mut stuck = DeviceHealth::first_seen(Uuid::new_v4(), &telemetry(now - Duration::days(4), 500, Some(3600)));
+        stuck.observe(&telemetry(now - Duration::minutes(5), 502, None), &policy);
+        let flags = policy.evaluate(&stuck, now);
+        assert_eq!(flags.len(), 1);
+        assert!(matches!(flags[0], HealthFlag::Stuck { distance_mm: 500, .. }));
+    }
+
+    #[test]
+    fn test_flag_serialization() {
+        let flag = HealthFlag::LowBattery { battery_mv: 3100 };
+        let json = serde_json::to_string(&flag).unwrap();
+        assert_eq!(json, r#"{"kind":"low_battery","battery_mv":3100}"#);
+    }
+}
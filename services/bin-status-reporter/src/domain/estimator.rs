@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{BinStatus, ReportSource, StatusReport};
+use crate::error::AppError;
+
+/// Relative trust placed in each report source when a bin estimate is
+/// computed. A calibrated sensor is more reliable than a glance at the lid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillEstimator {
+    citizen_weight: f64,
+    sensor_weight: f64,
+}
+
+impl FillEstimator {
+    pub fn new(citizen_weight: f64, sensor_weight: f64) -> Result<Self, AppError> {
+        if citizen_weight <= 0.0 || sensor_weight <= 0.0 {
+            return Err(AppError::InvalidRequest(format!(
+                "Report weights must be positive, got citizen={} sensor={}",
+                citizen_weight, sensor_weight
+            )));
+        }
+        Ok(Self { citizen_weight, sensor_weight })
+    }
+
+    pub fn weight(&self, source: ReportSource) -> f64 {
+        match source {
+            ReportSource::Citizen => self.citizen_weight,
+            ReportSource::Sensor => self.sensor_weight,
+        }
+    }
+}
+
+impl Default for FillEstimator {
+    fn default() -> Self {
+        Self { citizen_weight: 1.0, sensor_weight: 3.0 }
+    }
+}
+
+/// Weighted running average of the reports applied to a bin since it was
+/// last collected.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BinAggregate {
+    pub weighted_sum: f64,
+    pub weight_total: f64,
+    pub reports_count: u32,
+    /// Latest observation folded in, which is not necessarily the latest
+    /// report received.
+    #[serde(default)]
+    pub last_observed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub collected_at: Option<DateTime<Utc>>,
+}
+
+impl BinAggregate {
+    /// Rebuilds an aggregate from the unweighted `status`/`reportsCount`
+    /// pair stored before sources were weighted.
+    pub fn from_legacy(status: i32, reports_count: u32) -> Self {
+        Self {
+            weighted_sum: f64::from(status) * f64::from(reports_count),
+            weight_total: f64::from(reports_count),
+            reports_count,
+            ..Self::default()
+        }
+    }
+
+    /// An empty aggregate for a bin emptied at `collected_at`.
+    pub fn collected(collected_at: DateTime<Utc>) -> Self {
+        Self { collected_at: Some(collected_at), ..Self::default() }
+    }
+
+    /// Folds the report in unless it was observed before the last
+    /// collection. Returns whether it was applied.
+    pub fn apply_report(&mut self, estimator: &FillEstimator, report: &StatusReport) -> bool {
+        if self.collected_at.is_some_and(|collected_at| report.observed_at < collected_at) {
+            return false;
+        }
+        self.apply(estimator, report.source, &report.status);
+        self.last_observed_at = self.last_observed_at.max(Some(report.observed_at));
+        true
+    }
+
+    pub fn apply(&mut self, estimator: &FillEstimator, source: ReportSource, status: &BinStatus) {
+        let weight = estimator.weight(source);
+        self.weighted_sum += weight * f64::from(status.value());
+        self.weight_total += weight;
+        self.reports_count += 1;
+    }
+
+    pub fn status(&self) -> BinStatus {
+        if self.weight_total <= 0.0 {
+            return BinStatus::empty();
+        }
+        BinStatus::from((self.weighted_sum / self.weight_total).round() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_estimator_rejects_non_positive_weights() {
+        assert!(FillEstimator::new(0.0, 1.0).is_err());
+        assert!(FillEstimator::new(1.0, -2.0).is_err());
+        assert!(FillEstimator::new(1.0, 2.0).is_ok());
+    }
+
+    #[test]
+    fn test_empty_aggregate_is_empty() {
+        assert_eq!(BinAggregate::default().status(), BinStatus::empty());
+    }
+
+    #[test]
+    fn test_aggregate_weights_sensor_higher() {
+        let estimator = FillEstimator::new(1.0, 3.0).unwrap();
+        let mut aggregate = BinAggregate::default();
+        aggregate.apply(&estimator, ReportSource::Citizen, &BinStatus::empty());
+        aggregate.apply(&estimator, ReportSource::Sensor, &BinStatus::new(8).unwrap());
+
+        // (0 * 1 + 8 * 3) / 4 = 6
+        assert_eq!(aggregate.status().value(), 6);
+        assert_eq!(aggregate.reports_count, 2);
+    }
+
+    #[test]
+    fn test_aggregate_rounds_instead_of_truncating() {
+        let estimator = FillEstimator::default();
+        let mut aggregate = BinAggregate::default();
+        aggregate.apply(&estimator, ReportSource::Citizen, &BinStatus::new(5).unwrap());
+        aggregate.apply(&estimator, ReportSource::Citizen, &BinStatus::new(6).unwrap());
+
+        assert_eq!(aggregate.status().value(), 6);
+    }
+
+    #[test]
+    fn test_from_legacy_continues_unweighted_average() {
+        let estimator = FillEstimator::default();
+        let mut aggregate = BinAggregate::from_legacy(4, 3);
+        aggregate.apply(&estimator, ReportSource::Citizen, &BinStatus::new(8).unwrap());
+
+        assert_eq!(aggregate.status().value(), 5);
+        assert_eq!(aggregate.reports_count, 4);
+    }
+
+    #[test]
+    fn test_apply_report_skips_reports_before_collection() {
+        let estimator = FillEstimator::default();
+        let now = Utc::now();
+        let mut aggregate = BinAggregate::collected(now - Duration::hours(1));
+
+        let before = StatusReport::citizen(Uuid::new_v4(), Uuid::new_v4(), BinStatus::full(), now - Duration::hours(2));
+        assert!(!aggregate.apply_report(&estimator, &before));
+        assert_eq!(aggregate.reports_count, 0);
+
+        let after = StatusReport::citizen(Uuid::new_v4(), before.bin_id, BinStatus::new(2).unwrap(), now);
+        let late = StatusReport::citizen(Uuid::new_v4(), before.bin_id, BinStatus::new(4).unwrap(), now - Duration::minutes(30));
+        assert!(aggregate.apply_report(&estimator, &after));
+        assert!(aggregate.apply_report(&estimator, &late));
+        assert_eq!(aggregate.status().value(), 3);
+        assert_eq!(aggregate.last_observed_at, Some(now));
+    }
+}
@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::estimator::{BinAggregate, FillEstimator};
+use crate::domain::{BinStatus, StatusReport};
+
+/// Something that happened to a bin. A bin's state at any time is the
+/// fold of its events up to then.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinEvent {
+    Reported(StatusReport),
+    Collected { at: DateTime<Utc> },
+    Activated { at: DateTime<Utc> },
+    Deactivated { at: DateTime<Utc> },
+}
+
+impl BinEvent {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            BinEvent::Reported(report) => report.observed_at,
+            BinEvent::Collected { at } | BinEvent::Activated { at } | BinEvent::Deactivated { at } => *at,
+        }
+    }
+
+    /// A report observed at the instant of a collection counts towards the
+    /// new estimate, so state changes go first.
+    pub(crate) fn order(&self) -> (DateTime<Utc>, u8) {
+        match self {
+            BinEvent::Reported(_) => (self.at(), 1),
+            _ => (self.at(), 0),
+        }
+    }
+}
+
+/// How reports combine into a fill estimate. The live estimate uses the
+/// `FillEstimator` weights; other strategies can be tried by replaying
+/// stored history through them.
+pub trait Averaging {
+    type Estimate: Clone;
+
+    /// The estimate right after the bin was emptied.
+    fn collected(&self, at: Option<DateTime<Utc>>) -> Self::Estimate;
+
+    fn apply(&self, estimate: &mut Self::Estimate, report: &StatusReport);
+
+    fn status(&self, estimate: &Self::Estimate) -> BinStatus;
+}
+
+impl Averaging for FillEstimator {
+    type Estimate = BinAggregate;
+
+    fn collected(&self, at: Option<DateTime<Utc>>) -> BinAggregate {
+        BinAggregate { collected_at: at, ..BinAggregate::default() }
+    }
+
+    fn apply(&self, estimate: &mut BinAggregate, report: &StatusReport) {
+        estimate.apply_report(self, report);
+    }
+
+    fn status(&self, estimate: &BinAggregate) -> BinStatus {
+        estimate.status()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinState<E> {
+    /// Reports made while a bin is deactivated do not count.
+    pub active: bool,
+    pub collected_at: Option<DateTime<Utc>>,
+    pub last_observed_at: Option<DateTime<Utc>>,
+    pub estimate: E,
+}
+
+/// Folds `events` in observation order, leaving out those after `as_of`.
+/// The order the events arrive in does not matter.
+pub fn fold<A: Averaging>(strategy: &A, events: &[BinEvent], as_of: Option<DateTime<Utc>>) -> BinState<A::Estimate> {
+    let mut ordered: Vec<&BinEvent> = events
+        .iter()
+        .filter(|event| as_of.is_none_or(|as_of| event.at() <= as_of))
+        .collect();
+    ordered.sort_by_key(|event| event.order());
+
+    let mut state = BinState {
+        active: true,
+        collected_at: None,
+        last_observed_at: None,
+        estimate: strategy.collected(None),
+    };
+    for event in ordered {
+        match event {
+            BinEvent::Reported(report) if state.active => {
+                strategy.apply(&mut state.estimate, report);
+                state.last_observed_at = Some(report.observed_at);
+            }
+            BinEvent::Reported(_) => {}
+            BinEvent::Collected { at } => {
+                state.collected_at = Some(*at);
+                state.estimate = strategy.collected(Some(*at));
+            }
+            BinEvent::Activated { .. } => state.active = true,
+            BinEvent::Deactivated { .. } => state.active = false,
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use uuid::Uuid;
+
+    fn report(bin_id: Uuid, value: i32, at: DateTime<Utc>) -> BinEvent {
+        BinEvent::Reported(StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), at))
+    }
+
+    /// Ignores all but the latest report.
+    struct LatestReport;
+
+    impl Averaging for LatestReport {
+        type Estimate = Option<BinStatus>;
+
+        fn collected(&self, _at: Option<DateTime<Utc>>) -> Option<BinStatus> {
+            None
+        }
+
+        fn apply(&self, estimate: &mut Option<BinStatus>, report: &StatusReport) {
+            *estimate = Some(report.status.clone());
+        }
+
+        fn status(&self, estimate: &Option<BinStatus>) -> BinStatus {
+            estimate.clone().unwrap_or_else(BinStatus::empty)
+        }
+    }
+
+    #[test]
+    fn test_fold_is_independent_of_arrival_order() {
+        let bin_id = Uuid::new_v4();
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 19, 6, 0, 0).unwrap();
+        let mut events = vec![
+            report(bin_id, 9, t0),
+            BinEvent::Collected { at: t0 + Duration::hours(1) },
+            report(bin_id, 2, t0 + Duration::hours(1)),
+            report(bin_id, 6, t0 + Duration::hours(3)),
+        ];
+        let in_order = fold(&FillEstimator::default(), &events, None);
+        events.reverse();
+
+        assert_eq!(fold(&FillEstimator::default(), &events, None), in_order);
+        assert_eq!(in_order.estimate.status().value(), 4);
+        assert_eq!(in_order.estimate.reports_count, 2);
+        assert_eq!(in_order.collected_at, Some(t0 + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_fold_as_of_a_past_time() {
+        let bin_id = Uuid::new_v4();
+        let tuesday = Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap();
+        let events = vec![
+            report(bin_id, 8, tuesday - Duration::hours(2)),
+            BinEvent::Collected { at: tuesday + Duration::hours(1) },
+            report(bin_id, 1, tuesday + Duration::hours(2)),
+        ];
+
+        let then = fold(&FillEstimator::default(), &events, Some(tuesday));
+
+        assert_eq!(then.estimate.status().value(), 8);
+        assert_eq!(then.collected_at, None);
+    }
+
+    #[test]
+    fn test_deactivated_bins_ignore_reports() {
+        let bin_id = Uuid::new_v4();
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 19, 6, 0, 0).unwrap();
+        let events = vec![
+            report(bin_id, 4, t0),
+            BinEvent::Deactivated { at: t0 + Duration::hours(1) },
+            report(bin_id, 10, t0 + Duration::hours(2)),
+            BinEvent::Activated { at: t0 + Duration::hours(3) },
+            report(bin_id, 6, t0 + Duration::hours(4)),
+        ];
+
+        let state = fold(&FillEstimator::default(), &events, None);
+
+        assert!(state.active);
+        assert_eq!((state.estimate.status().value(), state.estimate.reports_count), (5, 2));
+    }
+
+    #[test]
+    fn test_replay_under_another_strategy() {
+        let bin_id = Uuid::new_v4();
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 19, 6, 0, 0).unwrap();
+        let events = vec![report(bin_id, 2, t0), report(bin_id, 9, t0 + Duration::hours(1))];
+
+        let averaged = fold(&FillEstimator::default(), &events, None);
+        let latest = fold(&LatestReport, &events, None);
+
+        assert_eq!(FillEstimator::default().status(&averaged.estimate).value(), 6);
+        assert_eq!(LatestReport.status(&latest.estimate).value(), 9);
+        assert_eq!(latest.last_observed_at, averaged.last_observed_at);
+    }
+}
@@ -0,0 +1,317 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::{BinStatus, WasteType};
+use crate::error::AppError;
+
+/// Longest free-text description kept with a report.
+pub const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// What a citizen report is about. Fill reports feed the estimate; the
+/// others are incidents kept in the report log next to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportKind {
+    Fill,
+    Damage,
+    Overflow,
+    Fire,
+    WrongSorting,
+}
+
+impl ReportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportKind::Fill => "fill",
+            ReportKind::Damage => "damage",
+            ReportKind::Overflow => "overflow",
+            ReportKind::Fire => "fire",
+            ReportKind::WrongSorting => "wrong_sorting",
+        }
+    }
+}
+
+impl FromStr for ReportKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fill" => Ok(ReportKind::Fill),
+            "damage" => Ok(ReportKind::Damage),
+            "overflow" => Ok(ReportKind::Overflow),
+            "fire" => Ok(ReportKind::Fire),
+            "wrong_sorting" => Ok(ReportKind::WrongSorting),
+            other => Err(AppError::InvalidRequest(format!("Unknown report kind: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamagedPart {
+    Lid,
+    Body,
+    Wheels,
+    Lock,
+    Other,
+}
+
+/// A citizen report with the fields of its kind.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReportDetails {
+    Fill {
+        status: BinStatus,
+    },
+    Damage {
+        part: DamagedPart,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
+    /// The bin is full and waste lies around it.
+    Overflow {
+        #[serde(default)]
+        waste_on_ground: bool,
+    },
+    Fire {
+        #[serde(default)]
+        still_burning: bool,
+    },
+    /// Waste of other streams thrown in.
+    WrongSorting {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        found: Vec<WasteType>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
+}
+
+impl ReportDetails {
+    pub fn kind(&self) -> ReportKind {
+        match self {
+            ReportDetails::Fill { .. } => ReportKind::Fill,
+            ReportDetails::Damage { .. } => ReportKind::Damage,
+            ReportDetails::Overflow { .. } => ReportKind::Overflow,
+            ReportDetails::Fire { .. } => ReportKind::Fire,
+            ReportDetails::WrongSorting { .. } => ReportKind::WrongSorting,
+        }
+    }
+
+    /// Checks the fields of the kind, trimming descriptions and dropping
+    /// empty ones.
+    pub fn validated(self) -> Result<Self, AppError> {
+        match self {
+            ReportDetails::Fill { status } => Ok(ReportDetails::Fill { status: BinStatus::new(status.value())? }),
+            ReportDetails::Damage { part, description } => {
+                let description = description_of(description)?;
+                if part == DamagedPart::Other && description.is_none() {
+                    return Err(AppError::InvalidRequest("Describe the damage to an other part".to_string()));
+                }
+                Ok(ReportDetails::Damage { part, description })
+            }
+            ReportDetails::WrongSorting { mut found, description } => {
+                let description = description_of(description)?;
+                found.sort();
+                found.dedup();
+                if found.is_empty() && description.is_none() {
+                    return Err(AppError::InvalidRequest("Say what was wrongly sorted".to_string()));
+                }
+                Ok(ReportDetails::WrongSorting { found, description })
+            }
+            details @ (ReportDetails::Overflow { .. } | ReportDetails::Fire { .. }) => Ok(details),
+        }
+    }
+}
+
+fn description_of(description: Option<String>) -> Result<Option<String>, AppError> {
+    let description = description.map(|text| text.trim().to_string()).filter(|text| !text.is_empty());
+    if description.as_ref().is_some_and(|text| text.chars().count() > MAX_DESCRIPTION_CHARS) {
+        return Err(AppError::InvalidRequest(format!(
+            "Descriptions are limited to {} characters",
+            MAX_DESCRIPTION_CHARS
+        )));
+    }
+    Ok(description)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportRequest {
+    pub bin_id: Uuid,
+    #[serde(flatten)]
+    pub details: ReportDetails,
+    /// When the citizen saw the bin, if the report was queued offline.
+    #[serde(default)]
+    pub observed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportResponse {
+    pub success: bool,
+    pub report_id: Uuid,
+    /// The bin the report was recorded against, which differs from the
+    /// requested one when that bin was replaced.
+    pub bin_id: Uuid,
+    pub kind: ReportKind,
+    pub message: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// A report of any kind but fill, as stored in the report log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncidentReport {
+    pub report_id: Uuid,
+    pub bin_id: Uuid,
+    pub details: ReportDetails,
+    pub observed_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Opened for a damaged bin, so a crew comes to repair it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceTicket {
+    pub bin_id: Uuid,
+    pub report_id: Uuid,
+    pub part: DamagedPart,
+    pub description: Option<String>,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// An incident someone has to act on now.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Escalation {
+    pub bin_id: Uuid,
+    pub report_id: Uuid,
+    pub kind: ReportKind,
+    pub summary: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum IncidentAction {
+    OpenTicket(MaintenanceTicket),
+    Escalate(Escalation),
+}
+
+impl IncidentReport {
+    pub fn kind(&self) -> ReportKind {
+        self.details.kind()
+    }
+
+    /// Where the incident goes: damage opens a maintenance ticket, overflow
+    /// and fire are escalated, and wrong sorting is only logged for the
+    /// crews.
+    pub fn actions(&self) -> Vec<IncidentAction> {
+        let escalate = |summary: &str| {
+            IncidentAction::Escalate(Escalation {
+                bin_id: self.bin_id,
+                report_id: self.report_id,
+                kind: self.kind(),
+                summary: summary.to_string(),
+                raised_at: self.received_at,
+            })
+        };
+        match &self.details {
+            ReportDetails::Damage { part, description } => vec![IncidentAction::OpenTicket(MaintenanceTicket {
+                bin_id: self.bin_id,
+                report_id: self.report_id,
+                part: *part,
+                description: description.clone(),
+                opened_at: self.received_at,
+            })],
+            ReportDetails::Overflow { waste_on_ground: true } => vec![escalate("Bin overflowing, waste on the ground")],
+            ReportDetails::Overflow { waste_on_ground: false } => vec![escalate("Bin overflowing")],
+            ReportDetails::Fire { still_burning: true } => vec![escalate("Bin on fire")],
+            ReportDetails::Fire { still_burning: false } => vec![escalate("Bin burnt out")],
+            ReportDetails::Fill { .. } | ReportDetails::WrongSorting { .. } => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentQuery {
+    pub kind: ReportKind,
+    /// Defaults to the last 7 days.
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[async_trait]
+pub trait IncidentRepository: Send + Sync {
+    /// Appends the incident to its bin's report log.
+    async fn add_incident(&self, report: &IncidentReport) -> Result<(), AppError>;
+
+    /// Incidents of `kind` across all bins observed at or after `since`,
+    /// newest first, at most `limit`.
+    async fn incidents(&self, kind: ReportKind, since: DateTime<Utc>, limit: usize) -> Result<Vec<IncidentReport>, AppError>;
+}
+
+/// Hands incident actions to whoever acts on them.
+#[async_trait]
+pub trait IncidentDispatcher {
+    async fn dispatch(&self, action: &IncidentAction) -> Result<(), AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn incident(details: ReportDetails) -> IncidentReport {
+        let at = Utc::now();
+        IncidentReport { report_id: Uuid::new_v4(), bin_id: Uuid::new_v4(), details, observed_at: at, received_at: at }
+    }
+
+    #[test]
+    fn test_requests_carry_the_fields_of_their_kind() {
+        let bin_id = Uuid::new_v4();
+        let request: ReportRequest = serde_json::from_value(json!({
+            "bin_id": bin_id,
+            "kind": "wrong_sorting",
+            "found": ["glass", "glass", "bio"],
+            "description": "  "
+        }))
+        .unwrap();
+
+        let details = request.details.validated().unwrap();
+
+        assert_eq!(details, ReportDetails::WrongSorting { found: vec![WasteType::Glass, WasteType::Bio], description: None });
+        assert!(serde_json::from_value::<ReportRequest>(json!({"bin_id": bin_id, "kind": "damage"})).is_err());
+        assert!(serde_json::from_value::<ReportRequest>(json!({"bin_id": bin_id, "kind": "graffiti"})).is_err());
+    }
+
+    #[test]
+    fn test_invalid_details_are_rejected() {
+        let long = "x".repeat(MAX_DESCRIPTION_CHARS + 1);
+
+        assert!(ReportDetails::Damage { part: DamagedPart::Other, description: None }.validated().is_err());
+        assert!(ReportDetails::Damage { part: DamagedPart::Lid, description: Some(long) }.validated().is_err());
+        assert!(ReportDetails::WrongSorting { found: Vec::new(), description: Some(" ".to_string()) }.validated().is_err());
+        assert!(ReportDetails::Fire { still_burning: true }.validated().is_ok());
+    }
+
+    #[test]
+    fn test_kinds_route_differently() {
+        let damage = incident(ReportDetails::Damage { part: DamagedPart::Lid, description: None });
+        let overflow = incident(ReportDetails::Overflow { waste_on_ground: true });
+        let sorting = incident(ReportDetails::WrongSorting { found: vec![WasteType::Glass], description: None });
+
+        assert!(matches!(damage.actions()[..], [IncidentAction::OpenTicket(ref ticket)] if ticket.report_id == damage.report_id));
+        assert!(matches!(overflow.actions()[..], [IncidentAction::Escalate(ref escalation)] if escalation.kind == ReportKind::Overflow));
+        assert!(sorting.actions().is_empty());
+    }
+
+    #[test]
+    fn test_report_kinds_round_trip() {
+        for kind in [ReportKind::Fill, ReportKind::Damage, ReportKind::Overflow, ReportKind::Fire, ReportKind::WrongSorting] {
+            assert_eq!(kind.as_str().parse::<ReportKind>().unwrap(), kind);
+            assert_eq!(serde_json::to_string(&kind).unwrap(), format!("\"{}\"", kind.as_str()));
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use shared::domain::LifecycleState;
+
+use crate::domain::history::BinEvent;
+use crate::error::AppError;
+
+/// How many replacements a report follows before giving up, so a cycle
+/// written by hand cannot loop forever.
+pub const MAX_REPLACEMENTS: usize = 5;
+
+/// One change of a bin's lifecycle state. The changes of a bin are kept as
+/// its audit trail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleTransition {
+    pub from: LifecycleState,
+    pub to: LifecycleState,
+    pub at: DateTime<Utc>,
+    /// Who made the change: a crew member, an operator or a job.
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The bin taking over from a replaced one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<Uuid>,
+}
+
+/// A bin's lifecycle state with the transitions that led to it.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BinLifecycle {
+    #[serde(default)]
+    pub state: LifecycleState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<Uuid>,
+    /// Oldest first.
+    #[serde(default)]
+    pub history: Vec<LifecycleTransition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleRequest {
+    pub bin_id: Uuid,
+    pub state: LifecycleState,
+    pub actor: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Required when the bin is replaced, and only then.
+    #[serde(default)]
+    pub replaced_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinLifecycleQuery {
+    pub bin_id: Uuid,
+}
+
+/// What to do with a report for a bin, given its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportRouting {
+    Accept,
+    /// Record it against the bin that replaced this one.
+    Redirect(Uuid),
+    Reject(LifecycleState),
+}
+
+impl BinLifecycle {
+    /// Validates the request against the current state, returning the
+    /// transition to store.
+    pub fn transition(&self, request: &LifecycleRequest, at: DateTime<Utc>) -> Result<LifecycleTransition, AppError> {
+        if !self.state.can_become(request.state) {
+            return Err(AppError::InvalidRequest(format!(
+                "Bin {} cannot go from {} to {}",
+                request.bin_id, self.state, request.state
+            )));
+        }
+        match (request.state, request.replaced_by) {
+            (LifecycleState::Replaced, None) => {
+                return Err(AppError::InvalidRequest("A replaced bin needs the bin replacing it".to_string()));
+            }
+            (LifecycleState::Replaced, Some(replaced_by)) if replaced_by == request.bin_id => {
+                return Err(AppError::InvalidRequest("A bin cannot replace itself".to_string()));
+            }
+            (state, Some(_)) if state != LifecycleState::Replaced => {
+                return Err(AppError::InvalidRequest("Only a replaced bin has a replacement".to_string()));
+            }
+            _ => {}
+        }
+        let actor = request.actor.trim();
+        if actor.is_empty() {
+            return Err(AppError::InvalidRequest("A lifecycle change needs an actor".to_string()));
+        }
+
+        Ok(LifecycleTransition {
+            from: self.state,
+            to: request.state,
+            at,
+            actor: actor.to_string(),
+            reason: request.reason.as_ref().map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty()),
+            replaced_by: request.replaced_by,
+        })
+    }
+
+    pub fn apply(&mut self, transition: LifecycleTransition) {
+        self.state = transition.to;
+        self.replaced_by = transition.replaced_by;
+        self.history.push(transition);
+    }
+
+    /// The moves into and out of service in the audit trail, as events a
+    /// bin's history is folded with.
+    pub fn events(&self) -> Vec<BinEvent> {
+        self.history
+            .iter()
+            .filter(|transition| transition.from.is_in_service() != transition.to.is_in_service())
+            .map(|transition| match transition.to.is_in_service() {
+                true => BinEvent::Activated { at: transition.at },
+                false => BinEvent::Deactivated { at: transition.at },
+            })
+            .collect()
+    }
+
+    pub fn routing(&self) -> ReportRouting {
+        match (self.state, self.replaced_by) {
+            (state, _) if state.is_in_service() => ReportRouting::Accept,
+            (LifecycleState::Replaced, Some(replaced_by)) => ReportRouting::Redirect(replaced_by),
+            (state, _) => ReportRouting::Reject(state),
+        }
+    }
+}
+
+#[async_trait]
+pub trait LifecycleRepository: Send + Sync {
+    /// The bin's lifecycle, or `None` if the bin does not exist.
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError>;
+
+    /// Moves the bin to the transition's state and appends the transition
+    /// to its trail, provided the bin is still in the `from` state. Returns
+    /// false if another change got there first.
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn request(bin_id: Uuid, state: LifecycleState, replaced_by: Option<Uuid>) -> LifecycleRequest {
+        LifecycleRequest { bin_id, state, actor: " crew-7 ".to_string(), reason: Some(" ".to_string()), replaced_by }
+    }
+
+    #[test]
+    fn test_transition_is_recorded_in_the_trail() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap();
+        let bin_id = Uuid::new_v4();
+        let mut lifecycle = BinLifecycle::default();
+
+        let transition = lifecycle.transition(&request(bin_id, LifecycleState::Damaged, None), at).unwrap();
+        lifecycle.apply(transition);
+
+        assert_eq!(lifecycle.state, LifecycleState::Damaged);
+        let recorded = &lifecycle.history[0];
+        assert_eq!((recorded.from, recorded.actor.as_str(), recorded.reason.as_ref()), (LifecycleState::Active, "crew-7", None));
+        assert_eq!(lifecycle.routing(), ReportRouting::Accept);
+    }
+
+    #[test]
+    fn test_invalid_transitions_are_rejected() {
+        let at = Utc::now();
+        let bin_id = Uuid::new_v4();
+        let planned = BinLifecycle { state: LifecycleState::Planned, ..BinLifecycle::default() };
+        let active = BinLifecycle::default();
+
+        assert!(planned.transition(&request(bin_id, LifecycleState::UnderMaintenance, None), at).is_err());
+        assert!(active.transition(&request(bin_id, LifecycleState::Replaced, None), at).is_err());
+        assert!(active.transition(&request(bin_id, LifecycleState::Replaced, Some(bin_id)), at).is_err());
+        assert!(active.transition(&request(bin_id, LifecycleState::Removed, Some(Uuid::new_v4())), at).is_err());
+        let anonymous = LifecycleRequest { actor: String::new(), ..request(bin_id, LifecycleState::Removed, None) };
+        assert!(active.transition(&anonymous, at).is_err());
+    }
+
+    #[test]
+    fn test_reports_follow_the_replacement() {
+        let replacement = Uuid::new_v4();
+        let mut lifecycle = BinLifecycle::default();
+        let transition = lifecycle.transition(&request(Uuid::new_v4(), LifecycleState::Replaced, Some(replacement)), Utc::now()).unwrap();
+        lifecycle.apply(transition);
+
+        assert_eq!(lifecycle.routing(), ReportRouting::Redirect(replacement));
+        let removed = BinLifecycle { state: LifecycleState::Removed, ..BinLifecycle::default() };
+        assert_eq!(removed.routing(), ReportRouting::Reject(LifecycleState::Removed));
+    }
+
+    #[test]
+    fn test_only_moves_into_and_out_of_service_are_events() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap();
+        let bin_id = Uuid::new_v4();
+        let mut lifecycle = BinLifecycle::default();
+        for (state, hours) in [(LifecycleState::Damaged, 0), (LifecycleState::UnderMaintenance, 1), (LifecycleState::Active, 2)] {
+            let transition = lifecycle.transition(&request(bin_id, state, None), at + chrono::Duration::hours(hours)).unwrap();
+            lifecycle.apply(transition);
+        }
+
+        assert_eq!(
+            lifecycle.events(),
+            vec![
+                BinEvent::Deactivated { at: at + chrono::Duration::hours(1) },
+                BinEvent::Activated { at: at + chrono::Duration::hours(2) },
+            ]
+        );
+    }
+}
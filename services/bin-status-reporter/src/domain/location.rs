@@ -0,0 +1,169 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::estimator::BinAggregate;
+use crate::domain::lifecycle::LifecycleState;
+use crate::domain::{BinProfile, BinStatus, BinStatusView, WasteType};
+use crate::error::AppError;
+
+/// A bin placed at a location, with its stored estimate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedBin {
+    pub bin_id: Uuid,
+    pub aggregate: BinAggregate,
+    pub profile: BinProfile,
+    pub lifecycle: LifecycleState,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocationStatusQuery {
+    pub location_id: Uuid,
+    /// Only count bins of this waste stream.
+    #[serde(default)]
+    pub waste_type: Option<WasteType>,
+}
+
+/// The bins of one waste stream at a location.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WasteTypeFill {
+    pub waste_type: WasteType,
+    pub bins_count: usize,
+    pub fullest: BinStatus,
+    pub overflowing_bins: usize,
+    /// Total capacity of the bins whose capacity is known.
+    pub capacity_liters: u64,
+    /// Estimated liters in use in those bins.
+    pub filled_liters: u64,
+}
+
+/// A sorting site's status, derived from the bins placed at it so the map
+/// can show one marker per site.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationStatus {
+    pub location_id: Uuid,
+    pub bins_count: usize,
+    pub fullest_bin: Option<BinStatusView>,
+    /// Bins estimated at 100%.
+    pub overflowing_bins: usize,
+    /// Latest observation of any of the bins.
+    pub last_observed_at: Option<DateTime<Utc>>,
+    /// One entry per waste stream present, in `WasteType` order.
+    pub by_waste_type: Vec<WasteTypeFill>,
+    /// Fullest first.
+    pub bins: Vec<BinStatusView>,
+}
+
+impl LocationStatus {
+    pub fn new(location_id: Uuid, placed: &[PlacedBin]) -> Self {
+        let mut bins: Vec<BinStatusView> = placed
+            .iter()
+            .map(|bin| BinStatusView::new(bin.bin_id, &bin.aggregate, bin.profile.clone()))
+            .collect();
+        // Of equally full bins, the one seen last is the most reliable
+        bins.sort_by_key(|bin| (Reverse(bin.status.value()), Reverse(bin.last_observed_at), bin.bin_id));
+
+        // The first bin of each stream is its fullest, as the bins are sorted
+        let mut by_waste_type: BTreeMap<WasteType, WasteTypeFill> = BTreeMap::new();
+        for bin in &bins {
+            let fill = by_waste_type.entry(bin.profile.waste_type).or_insert_with(|| WasteTypeFill {
+                waste_type: bin.profile.waste_type,
+                bins_count: 0,
+                fullest: bin.status.clone(),
+                overflowing_bins: 0,
+                capacity_liters: 0,
+                filled_liters: 0,
+            });
+            fill.bins_count += 1;
+            fill.overflowing_bins += usize::from(is_overflowing(bin));
+            fill.capacity_liters += u64::from(bin.profile.capacity_liters.unwrap_or_default());
+            fill.filled_liters += bin.profile.filled_liters(&bin.status).unwrap_or_default();
+        }
+
+        Self {
+            location_id,
+            bins_count: bins.len(),
+            fullest_bin: bins.first().cloned(),
+            overflowing_bins: bins.iter().filter(|bin| is_overflowing(bin)).count(),
+            last_observed_at: bins.iter().filter_map(|bin| bin.last_observed_at).max(),
+            by_waste_type: by_waste_type.into_values().collect(),
+            bins,
+        }
+    }
+}
+
+fn is_overflowing(bin: &BinStatusView) -> bool {
+    bin.status == BinStatus::full()
+}
+
+#[async_trait]
+pub trait LocationRepository: Send + Sync {
+    /// The bins placed at the location with their current estimates, read
+    /// together so the status follows every change to a member bin.
+    async fn location_bins(&self, location_id: &Uuid) -> Result<Vec<PlacedBin>, AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    use crate::domain::estimator::FillEstimator;
+    use crate::domain::ReportSource;
+
+    fn placed(value: i32, observed_at: DateTime<Utc>, waste_type: WasteType, capacity_liters: Option<u32>) -> PlacedBin {
+        let mut aggregate = BinAggregate::default();
+        aggregate.apply(&FillEstimator::default(), ReportSource::Citizen, &BinStatus::new(value).unwrap());
+        aggregate.last_observed_at = Some(observed_at);
+        let profile = BinProfile::new(waste_type, capacity_liters, None).unwrap();
+        PlacedBin { bin_id: Uuid::new_v4(), aggregate, profile, lifecycle: LifecycleState::Active }
+    }
+
+    #[test]
+    fn test_status_follows_the_fullest_bin() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap();
+        let bins = vec![
+            placed(4, at, WasteType::Paper, None),
+            placed(10, at - Duration::hours(3), WasteType::Paper, None),
+            placed(10, at - Duration::hours(1), WasteType::Paper, None),
+        ];
+
+        let status = LocationStatus::new(Uuid::new_v4(), &bins);
+
+        assert_eq!((status.bins_count, status.overflowing_bins), (3, 2));
+        assert_eq!(status.fullest_bin.unwrap().bin_id, bins[2].bin_id);
+        assert_eq!(status.bins.last().unwrap().bin_id, bins[0].bin_id);
+        assert_eq!(status.last_observed_at, Some(at));
+    }
+
+    #[test]
+    fn test_fill_is_broken_down_by_waste_type() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap();
+        let bins = vec![
+            placed(10, at, WasteType::Glass, Some(1500)),
+            placed(4, at, WasteType::Glass, Some(1500)),
+            placed(6, at, WasteType::Plastic, None),
+        ];
+
+        let status = LocationStatus::new(Uuid::new_v4(), &bins);
+
+        let types: Vec<WasteType> = status.by_waste_type.iter().map(|fill| fill.waste_type).collect();
+        assert_eq!(types, vec![WasteType::Plastic, WasteType::Glass]);
+        let glass = &status.by_waste_type[1];
+        assert_eq!((glass.bins_count, glass.fullest.value(), glass.overflowing_bins), (2, 10, 1));
+        assert_eq!((glass.capacity_liters, glass.filled_liters), (3000, 2100));
+        assert_eq!(status.by_waste_type[0].capacity_liters, 0);
+    }
+
+    #[test]
+    fn test_location_without_bins_has_no_fullest_bin() {
+        let status = LocationStatus::new(Uuid::new_v4(), &[]);
+
+        assert_eq!((status.bins_count, status.overflowing_bins, status.fullest_bin), (0, 0, None));
+        assert!(status.by_waste_type.is_empty());
+    }
+}
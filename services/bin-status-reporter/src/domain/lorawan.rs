@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::domain::sensor::SensorFrame;
+use crate::error::AppError;
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// The subset of an API Gateway proxy request the webhook needs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookRequest {
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub is_base64_encoded: bool,
+}
+
+impl WebhookRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body_bytes(&self) -> Result<Vec<u8>, AppError> {
+        if self.is_base64_encoded {
+            BASE64
+                .decode(&self.body)
+                .map_err(|e| AppError::InvalidRequest(format!("Body is not valid base64: {}", e)))
+        } else {
+            Ok(self.body.as_bytes().to_vec())
+        }
+    }
+}
+
+/// Checks the `sha256=<hex>` HMAC the network server computes over the raw
+/// request body with the shared webhook secret.
+pub fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> Result<(), AppError> {
+    let digest = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+        .ok_or_else(|| AppError::Unauthorized("Malformed webhook signature".to_string()))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+        .map_err(|e| AppError::InternalError(e.to_string()))?;
+    mac.update(body);
+    mac.verify_slice(&digest)
+        .map_err(|_| AppError::Unauthorized("Webhook signature mismatch".to_string()))
+}
+
+/// Uplink message as POSTed by The Things Stack (v3) style network servers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LorawanUplink {
+    pub end_device_ids: EndDeviceIds,
+    pub uplink_message: UplinkMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndDeviceIds {
+    pub dev_eui: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UplinkMessage {
+    #[serde(default)]
+    pub f_port: u8,
+    pub frm_payload: String,
+    #[serde(default)]
+    pub rx_metadata: Vec<RxMetadata>,
+}
+
+/// Reception details from one gateway that heard the uplink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RxMetadata {
+    #[serde(default)]
+    pub rssi: Option<i16>,
+}
+
+impl LorawanUplink {
+    /// Device EUIs are registered upper-case without separators.
+    pub fn dev_eui(&self) -> String {
+        self.end_device_ids
+            .dev_eui
+            .chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .collect::<String>()
+            .to_ascii_uppercase()
+    }
+
+    /// Signal strength at the gateway that heard the device best.
+    pub fn best_rssi(&self) -> Option<i16> {
+        self.uplink_message.rx_metadata.iter().filter_map(|rx| rx.rssi).max()
+    }
+
+    pub fn frame(&self) -> Result<Vec<u8>, AppError> {
+        BASE64
+            .decode(&self.uplink_message.frm_payload)
+            .map_err(|e| AppError::InvalidRequest(format!("frm_payload is not valid base64: {}", e)))
+    }
+}
+
+/// Decodes a vendor's binary uplink frame.
+pub trait PayloadDecoder: Send + Sync {
+    fn vendor(&self) -> &'static str;
+
+    fn decode(&self, f_port: u8, frame: &[u8]) -> Result<SensorFrame, AppError>;
+}
+
+/// Frame sent by EcoScan's own sensor firmware, all fields big-endian:
+///
+/// | byte | field                                  |
+/// |------|----------------------------------------|
+/// | 0    | frame version (`0x01`)                 |
+/// | 1-2  | fill distance, mm                      |
+/// | 3-4  | battery, mV                            |
+/// | 5-6  | temperature, signed, 0.1 °C            |
+/// | 7    | tilt from vertical, degrees            |
+pub struct EcoScanDecoder;
+
+impl PayloadDecoder for EcoScanDecoder {
+    fn vendor(&self) -> &'static str {
+        "ecoscan"
+    }
+
+    fn decode(&self, _f_port: u8, frame: &[u8]) -> Result<SensorFrame, AppError> {
+        if frame.len() != 8 {
+            return Err(frame_error(self.vendor(), format!("expected 8 bytes, got {}", frame.len())));
+        }
+        if frame[0] != 0x01 {
+            return Err(frame_error(self.vendor(), format!("unsupported frame version {}", frame[0])));
+        }
+        Ok(SensorFrame {
+            distance_mm: u32::from(u16::from_be_bytes([frame[1], frame[2]])),
+            battery_mv: u16::from_be_bytes([frame[3], frame[4]]),
+            temperature_c: Some(f32::from(i16::from_be_bytes([frame[5], frame[6]])) / 10.0),
+            tilt_deg: Some(frame[7]),
+        })
+    }
+}
+
+/// Dragino LDDS75 ultrasonic distance sensor, 8-byte frame on port 2:
+/// battery (2, lower 14 bits mV), distance (2, mm), interrupt flag (1),
+/// temperature (2, signed, 0.1 °C), sensor flag (1). It has no tilt sensor.
+pub struct DraginoLdds75Decoder;
+
+impl PayloadDecoder for DraginoLdds75Decoder {
+    fn vendor(&self) -> &'static str {
+        "dragino-ldds75"
+    }
+
+    fn decode(&self, f_port: u8, frame: &[u8]) -> Result<SensorFrame, AppError> {
+        if f_port != 2 {
+            return Err(frame_error(self.vendor(), format!("unexpected port {}", f_port)));
+        }
+        if frame.len() != 8 {
+            return Err(frame_error(self.vendor(), format!("expected 8 bytes, got {}", frame.len())));
+        }
+        let distance_mm = u16::from_be_bytes([frame[2], frame[3]]);
+        if distance_mm == 0 {
+            return Err(frame_error(self.vendor(), "no echo received".to_string()));
+        }
+        Ok(SensorFrame {
+            distance_mm: u32::from(distance_mm),
+            battery_mv: u16::from_be_bytes([frame[0], frame[1]]) & 0x3FFF,
+            temperature_c: Some(f32::from(i16::from_be_bytes([frame[5], frame[6]])) / 10.0),
+            tilt_deg: None,
+        })
+    }
+}
+
+fn frame_error(vendor: &str, reason: String) -> AppError {
+    AppError::InvalidRequest(format!("Invalid {} frame: {}", vendor, reason))
+}
+
+/// Decoders keyed by the vendor name stored on each registered device.
+pub struct DecoderRegistry {
+    decoders: HashMap<&'static str, Box<dyn PayloadDecoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn empty() -> Self {
+        Self { decoders: HashMap::new() }
+    }
+
+    pub fn register(mut self, decoder: Box<dyn PayloadDecoder>) -> Self {
+        self.decoders.insert(decoder.vendor(), decoder);
+        self
+    }
+
+    pub fn get(&self, vendor: &str) -> Option<&dyn PayloadDecoder> {
+        self.decoders.get(vendor).map(|decoder| decoder.as_ref())
+    }
+}
+
+impl Default for DecoderRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .register(Box::new(EcoScanDecoder))
+            .register(Box::new(DraginoLdds75Decoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &[u8], body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = br#"{"hello":"world"}"#;
+        let signature = sign(b"secret", body);
+
+        assert!(verify_signature(b"secret", body, &signature).is_ok());
+        assert!(matches!(
+            verify_signature(b"other", body, &signature),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            verify_signature(b"secret", b"tampered", &signature),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            verify_signature(b"secret", body, "md5=abcd"),
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_lookup_is_case_insensitive() {
+        let request = WebhookRequest {
+            headers: HashMap::from([("X-Webhook-Signature".to_string(), "sha256=00".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(request.header(SIGNATURE_HEADER), Some("sha256=00"));
+    }
+
+    #[test]
+    fn test_uplink_parsing() {
+        let json = r#"{
+            "end_device_ids": {"device_id": "bin-7", "dev_eui": "70b3d57ed005a1b2"},
+            "uplink_message": {
+                "f_port": 1,
+                "frm_payload": "AQG4DkgA6wU=",
+                "rx_metadata": [{"rssi": -112}, {"rssi": -97}, {}]
+            }
+        }"#;
+        let uplink: LorawanUplink = serde_json::from_str(json).unwrap();
+
+        assert_eq!(uplink.dev_eui(), "70B3D57ED005A1B2");
+        assert_eq!(uplink.best_rssi(), Some(-97));
+        assert_eq!(uplink.frame().unwrap(), vec![0x01, 0x01, 0xB8, 0x0E, 0x48, 0x00, 0xEB, 0x05]);
+    }
+
+    #[test]
+    fn test_ecoscan_decoder() {
+        let frame = [0x01, 0x01, 0xB8, 0x0E, 0x48, 0xFF, 0xCE, 0x05];
+        let decoded = EcoScanDecoder.decode(1, &frame).unwrap();
+
+        assert_eq!(decoded.distance_mm, 440);
+        assert_eq!(decoded.battery_mv, 3656);
+        assert_eq!(decoded.temperature_c, Some(-5.0));
+        assert_eq!(decoded.tilt_deg, Some(5));
+    }
+
+    #[test]
+    fn test_ecoscan_decoder_rejects_bad_frames() {
+        assert!(EcoScanDecoder.decode(1, &[0x01, 0x02]).is_err());
+        assert!(EcoScanDecoder.decode(1, &[0x02, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_dragino_decoder() {
+        let frame = [0x4B, 0x0C, 0x02, 0x26, 0x00, 0x00, 0xD7, 0x01];
+        let decoded = DraginoLdds75Decoder.decode(2, &frame).unwrap();
+
+        assert_eq!(decoded.battery_mv, 0x0B0C);
+        assert_eq!(decoded.distance_mm, 550);
+        assert_eq!(decoded.temperature_c, Some(21.5));
+        assert_eq!(decoded.tilt_deg, None);
+        assert!(DraginoLdds75Decoder.decode(1, &frame).is_err());
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let registry = DecoderRegistry::default();
+        assert_eq!(registry.get("ecoscan").unwrap().vendor(), "ecoscan");
+        assert!(registry.get("dragino-ldds75").is_some());
+        assert!(registry.get("unknown").is_none());
+    }
+}
@@ -2,9 +2,26 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::fmt;
+use std::str::FromStr;
 use async_trait::async_trait;
 use crate::error::AppError;
 
+pub mod backup;
+pub mod batch;
+pub mod collection;
+pub mod consistency;
+pub mod device_health;
+pub mod estimator;
+pub mod history;
+pub mod incident;
+pub mod lifecycle;
+pub mod location;
+pub mod lorawan;
+pub mod retention;
+pub mod rollup;
+pub mod sensor;
+pub mod timing;
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct BinStatus {
     value: i32,
@@ -12,7 +29,7 @@ pub struct BinStatus {
 
 impl BinStatus {
     pub fn new(value: i32) -> Result<Self, AppError> {
-        if value < 0 || value > 10 {
+        if !(0..=10).contains(&value) {
             return Err(AppError::InvalidRequest(format!(
                 "Bin status must be between 0 and 10, got {}",
                 value
@@ -37,6 +54,11 @@ impl BinStatus {
     pub fn full() -> Self {
         Self { value: 10 }
     }
+
+    /// Coarse fill level used on the map: low, medium, high or full.
+    pub fn fill_category(&self) -> &'static str {
+        shared::utils::calculate_fill_level_category(self.value * 10)
+    }
 }
 
 impl fmt::Display for BinStatus {
@@ -57,13 +79,67 @@ impl From<i32> for BinStatus {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Location {
     pub id: Uuid,
     pub address: String,
     pub latitude: f64,
     pub longitude: f64,
     pub name: String,
+    /// The city district, which rollups are also kept for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub district: Option<String>,
+}
+
+pub use shared::domain::WasteType;
+
+/// Larger than any street container, so a capacity mistyped in milliliters
+/// is refused.
+pub const MAX_CAPACITY_LITERS: u32 = 100_000;
+
+/// The container a bin is: its waste stream, capacity and model.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BinProfile {
+    #[serde(default)]
+    pub waste_type: WasteType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity_liters: Option<u32>,
+    /// Manufacturer's model, e.g. `ESE 1100`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_model: Option<String>,
+}
+
+impl BinProfile {
+    pub fn new(
+        waste_type: WasteType,
+        capacity_liters: Option<u32>,
+        container_model: Option<String>,
+    ) -> Result<Self, AppError> {
+        if capacity_liters == Some(0) {
+            return Err(AppError::InvalidRequest("Bin capacity must be at least one liter".to_string()));
+        }
+        if capacity_liters.is_some_and(|capacity| capacity > MAX_CAPACITY_LITERS) {
+            return Err(AppError::InvalidRequest(format!("Bin capacity must be at most {} liters", MAX_CAPACITY_LITERS)));
+        }
+        let container_model = container_model.map(|model| model.trim().to_string()).filter(|model| !model.is_empty());
+        Ok(Self { waste_type, capacity_liters, container_model })
+    }
+
+    /// Liters in use at `status`, if the capacity is known.
+    pub fn filled_liters(&self, status: &BinStatus) -> Option<u64> {
+        self.capacity_liters.map(|capacity| u64::from(capacity) * u64::from(status.value().unsigned_abs()) / 10)
+    }
+}
+
+/// An operator recording the container a bin is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinProfileRequest {
+    pub bin_id: Uuid,
+    pub waste_type: WasteType,
+    #[serde(default)]
+    pub capacity_liters: Option<u32>,
+    #[serde(default)]
+    pub container_model: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -74,9 +150,11 @@ pub struct TrashBin {
     pub qr_code_id: Uuid,
     pub status: BinStatus,
     pub last_updated: DateTime<Utc>,
+    #[serde(flatten)]
+    pub profile: BinProfile,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct QRCode {
     pub id: Uuid,
     pub url: String,
@@ -84,10 +162,75 @@ pub struct QRCode {
     pub created_at: DateTime<Utc>,
 }
 
+/// Where a status report came from. Sources are weighted differently when
+/// the bin estimate is computed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSource {
+    #[default]
+    Citizen,
+    Sensor,
+}
+
+impl ReportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportSource::Citizen => "citizen",
+            ReportSource::Sensor => "sensor",
+        }
+    }
+}
+
+impl FromStr for ReportSource {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "citizen" => Ok(ReportSource::Citizen),
+            "sensor" => Ok(ReportSource::Sensor),
+            other => Err(AppError::InvalidRequest(format!(
+                "Unknown report source: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A single status observation for a bin, as stored in the report log.
+/// `observed_at` is when the bin was looked at, `received_at` when the
+/// report reached us; they differ for reports uploaded after the fact.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StatusReport {
+    pub report_id: Uuid,
+    pub bin_id: Uuid,
+    pub status: BinStatus,
+    pub source: ReportSource,
+    pub observed_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl StatusReport {
+    pub fn citizen(report_id: Uuid, bin_id: Uuid, status: BinStatus, observed_at: DateTime<Utc>) -> Self {
+        Self { report_id, bin_id, status, source: ReportSource::Citizen, observed_at, received_at: observed_at }
+    }
+
+    pub fn sensor(report_id: Uuid, bin_id: Uuid, status: BinStatus, observed_at: DateTime<Utc>) -> Self {
+        Self { report_id, bin_id, status, source: ReportSource::Sensor, observed_at, received_at: observed_at }
+    }
+
+    pub fn with_received_at(mut self, received_at: DateTime<Utc>) -> Self {
+        self.received_at = received_at;
+        self
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StatusUpdateRequest {
     pub bin_id: Uuid,
     pub status: BinStatus,
+    /// When the citizen saw the bin, if the report was queued offline.
+    #[serde(default)]
+    pub observed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -97,21 +240,105 @@ pub struct StatusUpdateResponse {
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BinStatusQuery {
+    pub bin_id: Uuid,
+    /// Rebuild the estimate as it stood at this time instead of reading
+    /// the current one.
+    #[serde(default)]
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+/// A bin's current estimate, as shown on the QR landing page and the map.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BinStatusView {
+    pub bin_id: Uuid,
+    pub status: BinStatus,
+    pub reports_count: u32,
+    pub last_observed_at: Option<DateTime<Utc>>,
+    pub collected_at: Option<DateTime<Utc>>,
+    /// The container, so a reader can tell a full glass bank from a full
+    /// paper bin.
+    #[serde(flatten)]
+    pub profile: BinProfile,
+}
+
+impl BinStatusView {
+    pub fn new(bin_id: Uuid, aggregate: &estimator::BinAggregate, profile: BinProfile) -> Self {
+        Self {
+            bin_id,
+            status: aggregate.status(),
+            reports_count: aggregate.reports_count,
+            last_observed_at: aggregate.last_observed_at,
+            collected_at: aggregate.collected_at,
+            profile,
+        }
+    }
+}
+
+/// A bin's estimate before and after a report was folded in. Both are the
+/// same when the report was left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub previous: BinStatus,
+    pub current: BinStatus,
+}
+
+impl StatusChange {
+    pub fn unchanged(status: BinStatus) -> Self {
+        Self { previous: status.clone(), current: status }
+    }
+
+    pub fn category_changed(&self) -> bool {
+        self.previous.fill_category() != self.current.fill_category()
+    }
+}
+
 #[async_trait]
-pub trait BinRepository {
-    async fn update_status(
-        &self,
-        bin_id: &Uuid,
-        status: BinStatus,
-        timestamp: DateTime<Utc>,
-    ) -> Result<(), AppError>;
-
-    async fn add_report(
-        &self,
-        bin_id: &Uuid,
-        status: BinStatus,
-        timestamp: DateTime<Utc>,
-    ) -> Result<(), AppError>;
+pub trait BinRepository: Send + Sync {
+    /// The bin's stored estimate, or `None` if the bin does not exist.
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<estimator::BinAggregate>, AppError>;
+
+    /// Folds the report into the bin's stored estimate. Reports observed
+    /// before the bin's last collection are left out of the estimate.
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError>;
+
+    /// Appends the report to the report log.
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError>;
+
+    /// Resets the bin's estimate as of the collection, keeping any reports
+    /// observed after it. A collection older than the last one is ignored.
+    async fn record_collection(&self, event: &collection::CollectionEvent) -> Result<(), AppError>;
+
+    /// Stores several bins' reports, returning one result per group in the
+    /// same order. Backends that support it should apply each group
+    /// atomically; this default applies reports one at a time.
+    async fn record_batch(&self, groups: &[batch::ReportGroup]) -> Vec<Result<(), AppError>> {
+        let mut results = Vec::with_capacity(groups.len());
+        for group in groups {
+            let mut result = Ok(());
+            for report in &group.reports {
+                result = match self.update_status(report).await {
+                    Ok(_) => self.add_report(report).await,
+                    Err(e) => Err(e),
+                };
+                if result.is_err() {
+                    break;
+                }
+            }
+            results.push(result);
+        }
+        results
+    }
+}
+
+#[async_trait]
+pub trait BinProfileRepository: Send + Sync {
+    /// The bin's profile, or `None` if the bin does not exist.
+    async fn bin_profile(&self, bin_id: &Uuid) -> Result<Option<BinProfile>, AppError>;
+
+    /// Replaces the profile of an existing bin, leaving its estimate as is.
+    async fn set_bin_profile(&self, bin_id: &Uuid, profile: &BinProfile) -> Result<(), AppError>;
 }
 
 #[cfg(test)]
@@ -216,6 +443,51 @@ mod tests {
         }
     }
 
+    mod report_source_tests {
+        use super::*;
+
+        #[test]
+        fn test_report_source_round_trip() {
+            for source in [ReportSource::Citizen, ReportSource::Sensor] {
+                assert_eq!(source.as_str().parse::<ReportSource>().unwrap(), source);
+            }
+            assert!("drone".parse::<ReportSource>().is_err());
+        }
+
+        #[test]
+        fn test_report_source_serialization() {
+            assert_eq!(serde_json::to_string(&ReportSource::Sensor).unwrap(), "\"sensor\"");
+            assert_eq!(ReportSource::default(), ReportSource::Citizen);
+        }
+    }
+
+    mod bin_profile_tests {
+        use super::*;
+
+        #[test]
+        fn test_bin_profile_validation() {
+            assert!(BinProfile::new(WasteType::Glass, Some(0), None).is_err());
+            assert!(BinProfile::new(WasteType::Glass, Some(MAX_CAPACITY_LITERS + 1), None).is_err());
+
+            let profile = BinProfile::new(WasteType::Glass, Some(1500), Some("  ".to_string())).unwrap();
+            assert_eq!(profile.container_model, None);
+            assert_eq!(profile.filled_liters(&BinStatus::new(7).unwrap()), Some(1050));
+            assert_eq!(BinProfile::default().filled_liters(&BinStatus::full()), None);
+            // Profiles read from storage are not validated
+            let stored = BinProfile { capacity_liters: Some(u32::MAX), ..BinProfile::default() };
+            assert_eq!(stored.filled_liters(&BinStatus::full()), Some(u64::from(u32::MAX)));
+        }
+
+        #[test]
+        fn test_bin_profile_defaults_to_mixed() {
+            let profile: BinProfile = serde_json::from_str("{}").unwrap();
+            assert_eq!(profile.waste_type, WasteType::Mixed);
+
+            let json = serde_json::to_string(&BinProfile::new(WasteType::Paper, Some(240), None).unwrap()).unwrap();
+            assert_eq!(json, r#"{"waste_type":"paper","capacity_liters":240}"#);
+        }
+    }
+
     mod request_response_tests {
         use super::*;
 
@@ -225,6 +497,7 @@ mod tests {
             let request = StatusUpdateRequest {
                 bin_id,
                 status: BinStatus::new(5).unwrap(),
+                observed_at: None,
             };
 
             let json = serde_json::to_string(&request).unwrap();
@@ -234,6 +507,13 @@ mod tests {
             assert_eq!(request.status, deserialized.status);
         }
 
+        #[test]
+        fn test_status_update_request_observed_at_is_optional() {
+            let json = r#"{"bin_id":"123e4567-e89b-12d3-a456-426614174000","status":{"value":3}}"#;
+            let request: StatusUpdateRequest = serde_json::from_str(json).unwrap();
+            assert!(request.observed_at.is_none());
+        }
+
         #[test]
         fn test_status_update_response_serialization() {
             let response = StatusUpdateResponse {
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::consistency::ResumeToken;
+use crate::domain::incident::IncidentReport;
+use crate::domain::rollup::DailyRollup;
+use crate::domain::StatusReport;
+use crate::error::AppError;
+
+/// How long raw reports stay in the report log before they are rolled up
+/// and archived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub raw_days: u32,
+}
+
+impl RetentionPolicy {
+    pub fn new(raw_days: u32) -> Result<Self, AppError> {
+        if raw_days == 0 {
+            return Err(AppError::InvalidRequest("Report retention must be at least one day".to_string()));
+        }
+        Ok(Self { raw_days })
+    }
+
+    /// Reports observed before the returned instant can be archived. Only
+    /// whole UTC days are archived, and never reports that still count
+    /// towards the estimate, so a bin not collected since keeps its
+    /// reports past the retention period. `None` if nothing can go yet.
+    pub fn archive_before(&self, now: DateTime<Utc>, collected_at: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        let retained_from = self.retained_from(now)?;
+        let first_kept = retained_from.min(collected_at?.date_naive());
+        Some(start_of(first_kept))
+    }
+
+    /// Incidents observed before the returned instant can be archived.
+    /// They do not count towards the estimate, so only the retention
+    /// period holds them back.
+    pub fn archive_incidents_before(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.retained_from(now).map(start_of)
+    }
+
+    fn retained_from(&self, now: DateTime<Utc>) -> Option<NaiveDate> {
+        now.date_naive().checked_sub_days(Days::new(u64::from(self.raw_days)))
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self { raw_days: 90 }
+    }
+}
+
+fn start_of(day: NaiveDate) -> DateTime<Utc> {
+    day.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// A bin's reports and incidents of one day.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchivedDay {
+    pub reports: Vec<StatusReport>,
+    pub incidents: Vec<IncidentReport>,
+}
+
+/// Groups reports and incidents by the UTC day they were observed on.
+pub fn by_day(reports: Vec<StatusReport>, incidents: Vec<IncidentReport>) -> BTreeMap<NaiveDate, ArchivedDay> {
+    let mut days: BTreeMap<NaiveDate, ArchivedDay> = BTreeMap::new();
+    for report in reports {
+        days.entry(report.observed_at.date_naive()).or_default().reports.push(report);
+    }
+    for incident in incidents {
+        days.entry(incident.observed_at.date_naive()).or_default().incidents.push(incident);
+    }
+    days
+}
+
+/// Raw reports and incidents kept outside the report log, one object per
+/// bin and day.
+#[async_trait]
+pub trait ReportArchive: Send + Sync {
+    async fn get(&self, bin_id: &Uuid, day: NaiveDate) -> Result<Option<ArchivedDay>, AppError>;
+
+    /// Replaces the archive of the bin and day, stamping it with
+    /// `archived_at`.
+    async fn put(&self, bin_id: &Uuid, day: NaiveDate, archived: &ArchivedDay, archived_at: DateTime<Utc>) -> Result<(), AppError>;
+}
+
+/// Where an unfinished retention run stopped, kept so the next scheduled
+/// run continues from there instead of starting over.
+#[async_trait]
+pub trait RetentionCheckpoint: Send + Sync {
+    async fn resume_token(&self) -> Result<Option<ResumeToken>, AppError>;
+
+    /// Stores where to resume, or clears it once a run reached the end.
+    async fn save_resume_token(&self, resume: Option<&ResumeToken>) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait RetentionRepository: Send + Sync {
+    /// The bin's reports observed before `before`. Logged collections are
+    /// kept, so they are not included.
+    async fn reports_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<StatusReport>, AppError>;
+
+    /// Removes these reports of the bin, ignoring ones already gone.
+    async fn delete_reports(&self, bin_id: &Uuid, reports: &[StatusReport]) -> Result<(), AppError>;
+
+    /// The bin's incidents observed before `before`.
+    async fn incidents_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<IncidentReport>, AppError>;
+
+    /// Removes these incidents of the bin, ignoring ones already gone.
+    async fn delete_incidents(&self, bin_id: &Uuid, incidents: &[IncidentReport]) -> Result<(), AppError>;
+
+    /// The bin's rollups for the days from `from` to `to`, inclusive.
+    async fn daily_rollups(&self, bin_id: &Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyRollup>, AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_archives_whole_days_before_retention_and_collection() {
+        let policy = RetentionPolicy::new(30).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 3, 31, 15, 0, 0).unwrap();
+        let collected_recently = Utc.with_ymd_and_hms(2024, 3, 30, 8, 0, 0).unwrap();
+        let collected_long_ago = Utc.with_ymd_and_hms(2024, 2, 10, 8, 0, 0).unwrap();
+
+        assert_eq!(
+            policy.archive_before(now, Some(collected_recently)),
+            Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap())
+        );
+        // Reports since the collection on 10 February are in the estimate
+        assert_eq!(
+            policy.archive_before(now, Some(collected_long_ago)),
+            Some(Utc.with_ymd_and_hms(2024, 2, 10, 0, 0, 0).unwrap())
+        );
+        assert_eq!(policy.archive_before(now, None), None);
+        assert_eq!(
+            policy.archive_incidents_before(now),
+            Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert!(RetentionPolicy::new(0).is_err());
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::history::BinEvent;
+use crate::domain::{BinStatus, StatusReport};
+use crate::error::AppError;
+
+/// Summary of the fill levels reported over a period.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct FillStats {
+    pub reports_count: u32,
+    pub min: i32,
+    pub max: i32,
+    pub sum: i64,
+    /// Time a bin spent full, summed over the bins rolled up.
+    #[serde(default)]
+    pub full_seconds: i64,
+}
+
+impl FillStats {
+    pub fn add(&mut self, status: &BinStatus) {
+        let value = status.value();
+        self.merge(&FillStats { reports_count: 1, min: value, max: value, sum: i64::from(value), full_seconds: 0 });
+    }
+
+    /// Combines stats of the same period from another bin, or a change to
+    /// them. `min` and `max` of stats without reports are ignored.
+    pub fn merge(&mut self, other: &FillStats) {
+        if other.reports_count > 0 {
+            if self.reports_count == 0 {
+                self.min = other.min;
+                self.max = other.max;
+            } else {
+                self.min = self.min.min(other.min);
+                self.max = self.max.max(other.max);
+            }
+        }
+        self.reports_count += other.reports_count;
+        self.sum += other.sum;
+        self.full_seconds += other.full_seconds;
+    }
+
+    /// What merging into `stored` must add to give these stats, or `None`
+    /// if they are the same. Recomputed stats never have fewer reports.
+    pub fn change_from(&self, stored: Option<&FillStats>) -> Option<FillStats> {
+        let stored = stored.cloned().unwrap_or_default();
+        if *self == stored {
+            return None;
+        }
+        Some(FillStats {
+            reports_count: self.reports_count.saturating_sub(stored.reports_count),
+            min: self.min,
+            max: self.max,
+            sum: self.sum - stored.sum,
+            full_seconds: self.full_seconds - stored.full_seconds,
+        })
+    }
+
+    /// `None` for a period without reports.
+    pub fn mean(&self) -> Option<f64> {
+        (self.reports_count > 0).then(|| self.sum as f64 / f64::from(self.reports_count))
+    }
+}
+
+/// The reports one bin received over one UTC day, kept after the raw
+/// reports are archived.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyRollup {
+    pub bin_id: Uuid,
+    pub day: NaiveDate,
+    pub stats: FillStats,
+}
+
+impl DailyRollup {
+    pub fn from_reports(bin_id: Uuid, day: NaiveDate, reports: &[StatusReport]) -> Self {
+        let mut stats = FillStats::default();
+        for report in reports {
+            stats.add(&report.status);
+        }
+        Self { bin_id, day, stats }
+    }
+
+    /// The bin's daily rollup for the day, which the report stream keeps
+    /// up to date as well.
+    pub fn rollup(&self) -> Rollup {
+        Rollup {
+            scope: RollupScope::Bin(self.bin_id),
+            resolution: Resolution::Daily,
+            period_start: self.day.and_time(NaiveTime::MIN).and_utc(),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Ranges up to this long are answered with hourly rollups.
+pub const MAX_HOURLY_RANGE_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    Hourly,
+    Daily,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 2] = [Resolution::Hourly, Resolution::Daily];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Hourly => "hourly",
+            Resolution::Daily => "daily",
+        }
+    }
+
+    /// The finest resolution that keeps a chart of the range readable.
+    pub fn for_range(from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        if to - from <= Duration::days(MAX_HOURLY_RANGE_DAYS) {
+            Resolution::Hourly
+        } else {
+            Resolution::Daily
+        }
+    }
+
+    pub fn length(&self) -> Duration {
+        match self {
+            Resolution::Hourly => Duration::hours(1),
+            Resolution::Daily => Duration::days(1),
+        }
+    }
+
+    /// Start of the UTC hour or day `at` falls in.
+    pub fn period_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let day = at.date_naive();
+        match self {
+            Resolution::Hourly => day.and_time(NaiveTime::MIN).and_utc() + Duration::hours(i64::from(at.hour())),
+            Resolution::Daily => day.and_time(NaiveTime::MIN).and_utc(),
+        }
+    }
+
+    /// The stored sort key of a period, `2024-03-19` or `2024-03-19T08`.
+    pub fn period_key(&self, start: DateTime<Utc>) -> String {
+        match self {
+            Resolution::Hourly => start.format("%Y-%m-%dT%H").to_string(),
+            Resolution::Daily => start.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    pub fn parse_period_key(&self, key: &str) -> Option<DateTime<Utc>> {
+        match self {
+            Resolution::Hourly => NaiveDateTime::parse_from_str(&format!("{}:00", key), "%Y-%m-%dT%H:%M").ok(),
+            Resolution::Daily => NaiveDate::parse_from_str(key, "%Y-%m-%d").ok().map(|day| day.and_time(NaiveTime::MIN)),
+        }
+        .map(|start| start.and_utc())
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hourly" => Ok(Resolution::Hourly),
+            "daily" => Ok(Resolution::Daily),
+            other => Err(AppError::InvalidRequest(format!("Unknown rollup resolution: {}", other))),
+        }
+    }
+}
+
+/// What a rollup covers: one bin, or every bin at a location or in a
+/// district.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RollupScope {
+    Bin(Uuid),
+    Location(Uuid),
+    District(String),
+}
+
+impl RollupScope {
+    /// Rollups of one scope and resolution form a series, stored as
+    /// `bin#<id>#hourly`, `location#<id>#daily` or `district#<name>#daily`.
+    pub fn series(&self, resolution: Resolution) -> String {
+        match self {
+            RollupScope::Bin(bin_id) => format!("bin#{}#{}", bin_id, resolution),
+            RollupScope::Location(location_id) => format!("location#{}#{}", location_id, resolution),
+            RollupScope::District(district) => format!("district#{}#{}", district, resolution),
+        }
+    }
+
+    pub fn parse_series(series: &str) -> Option<(RollupScope, Resolution)> {
+        let (kind, rest) = series.split_once('#')?;
+        let (id, resolution) = rest.rsplit_once('#')?;
+        Some((RollupScope::new(kind, id).ok()?, resolution.parse().ok()?))
+    }
+
+    /// A scope from its kind, `bin`, `location` or `district`, and the bin
+    /// or location id or the district name.
+    pub fn new(kind: &str, id: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::InvalidRequest(format!("Invalid {} id: {}", kind, id));
+        match kind {
+            "bin" => id.parse().map(RollupScope::Bin).map_err(|_| invalid()),
+            "location" => id.parse().map(RollupScope::Location).map_err(|_| invalid()),
+            "district" if !id.trim().is_empty() => Ok(RollupScope::District(id.to_string())),
+            "district" => Err(invalid()),
+            other => Err(AppError::InvalidRequest(format!("Unknown rollup scope: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillHistoryQuery {
+    /// `bin`, `location` or `district`.
+    pub scope: String,
+    /// The bin or location id, or the district name.
+    pub id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Defaults to hourly for ranges up to 7 days and daily beyond.
+    #[serde(default)]
+    pub resolution: Option<Resolution>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rollup {
+    pub scope: RollupScope,
+    pub resolution: Resolution,
+    pub period_start: DateTime<Utc>,
+    pub stats: FillStats,
+}
+
+/// Where a bin stands, which decides the rollups its reports count in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Placement {
+    pub location_id: Option<Uuid>,
+    pub district: Option<String>,
+}
+
+impl Placement {
+    pub fn scopes(&self) -> Vec<RollupScope> {
+        let location = self.location_id.map(RollupScope::Location);
+        let district = self.district.clone().map(RollupScope::District);
+        location.into_iter().chain(district).collect()
+    }
+}
+
+/// A bin's log entries observed in a time range, with the last entry
+/// before it and the first one after it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Timeline {
+    pub before: Option<BinEvent>,
+    pub events: Vec<BinEvent>,
+    pub after: Option<BinEvent>,
+}
+
+/// A bin counts as full from a report in the full category until its next
+/// report or collection.
+fn is_full(event: &BinEvent) -> bool {
+    match event {
+        BinEvent::Reported(report) => report.status.fill_category() == "full",
+        _ => false,
+    }
+}
+
+/// The bin's hourly and daily rollups for every period in `[from, to)`,
+/// which must start and end on UTC days. Time after the last entry is not
+/// counted until the next one arrives.
+pub fn bin_rollups(bin_id: Uuid, timeline: &Timeline, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Rollup> {
+    let mut periods: BTreeMap<(Resolution, DateTime<Utc>), FillStats> = BTreeMap::new();
+    for resolution in Resolution::ALL {
+        let mut start = from;
+        while start < to {
+            periods.insert((resolution, start), FillStats::default());
+            start += resolution.length();
+        }
+    }
+
+    let mut ordered: Vec<&BinEvent> = timeline.before.iter().chain(&timeline.events).chain(&timeline.after).collect();
+    ordered.sort_by_key(|event| event.order());
+
+    for (i, event) in ordered.iter().enumerate() {
+        if let BinEvent::Reported(report) = event {
+            if (from..to).contains(&report.observed_at) {
+                for resolution in Resolution::ALL {
+                    if let Some(stats) = periods.get_mut(&(resolution, resolution.period_start(report.observed_at))) {
+                        stats.add(&report.status);
+                    }
+                }
+            }
+        }
+        let Some(next) = ordered.get(i + 1) else { break };
+        if !is_full(event) {
+            continue;
+        }
+        let (full_from, full_to) = (event.at().max(from), next.at().min(to));
+        for resolution in Resolution::ALL {
+            let mut start = resolution.period_start(full_from);
+            while start < full_to {
+                let end = start + resolution.length();
+                let overlap = end.min(full_to) - start.max(full_from);
+                if let Some(stats) = periods.get_mut(&(resolution, start)) {
+                    stats.full_seconds += overlap.num_seconds();
+                }
+                start = end;
+            }
+        }
+    }
+
+    periods
+        .into_iter()
+        .map(|((resolution, period_start), stats)| Rollup {
+            scope: RollupScope::Bin(bin_id),
+            resolution,
+            period_start,
+            stats,
+        })
+        .collect()
+}
+
+/// A bin rollup to replace, with the stats it was recomputed over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinRollupChange {
+    pub stored: Option<FillStats>,
+    pub rollup: Rollup,
+}
+
+/// Recomputed bin rollups and what they add to the rollups of the bin's
+/// location and district. Applied all or nothing, so the shared rollups
+/// stay the sum of their bins.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RollupUpdate {
+    pub bins: Vec<BinRollupChange>,
+    /// Merged into the stored rollups of the same scope and period.
+    pub shared: Vec<Rollup>,
+}
+
+impl RollupUpdate {
+    /// Compares recomputed bin rollups with the stored ones. A stored
+    /// rollup with more reports than the log still holds was rolled up
+    /// before its reports were archived, and is kept.
+    pub fn plan(recomputed: &[Rollup], stored: &[Rollup], placement: &Placement) -> Self {
+        let stored: BTreeMap<(Resolution, DateTime<Utc>), &FillStats> = stored
+            .iter()
+            .map(|rollup| ((rollup.resolution, rollup.period_start), &rollup.stats))
+            .collect();
+        let scopes = placement.scopes();
+
+        let mut update = RollupUpdate::default();
+        for rollup in recomputed {
+            let previous = stored.get(&(rollup.resolution, rollup.period_start)).copied();
+            if previous.is_some_and(|previous| previous.reports_count > rollup.stats.reports_count) {
+                continue;
+            }
+            let Some(change) = rollup.stats.change_from(previous) else {
+                continue;
+            };
+            for scope in &scopes {
+                update.shared.push(Rollup { scope: scope.clone(), stats: change.clone(), ..rollup.clone() });
+            }
+            update.bins.push(BinRollupChange { stored: previous.cloned(), rollup: rollup.clone() });
+        }
+        update
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bins.is_empty()
+    }
+}
+
+#[async_trait]
+pub trait RollupRepository: Send + Sync {
+    /// The bin's reports and collections observed in `[from, to)`.
+    async fn bin_timeline(&self, bin_id: &Uuid, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Timeline, AppError>;
+
+    async fn bin_placement(&self, bin_id: &Uuid) -> Result<Placement, AppError>;
+
+    /// Stored rollups of the series with periods starting in `[from, to)`,
+    /// in period order.
+    async fn rollups(
+        &self,
+        scope: &RollupScope,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Rollup>, AppError>;
+
+    /// Replaces the bin rollups if they still hold the stats they were
+    /// recomputed over, and merges the shared ones. Returns `false`, having
+    /// changed nothing, if a bin rollup changed in the meantime.
+    async fn apply_rollup_update(&self, update: &RollupUpdate) -> Result<bool, AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn report(bin_id: Uuid, value: i32, at: DateTime<Utc>) -> BinEvent {
+        BinEvent::Reported(StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), at))
+    }
+
+    fn stats(rollups: &[Rollup], resolution: Resolution, start: DateTime<Utc>) -> &FillStats {
+        &rollups.iter().find(|r| r.resolution == resolution && r.period_start == start).unwrap().stats
+    }
+
+    #[test]
+    fn test_stats_track_range_and_mean() {
+        let mut stats = FillStats::default();
+        assert_eq!(stats.mean(), None);
+
+        for value in [4, 9, 2] {
+            stats.add(&BinStatus::new(value).unwrap());
+        }
+
+        assert_eq!((stats.reports_count, stats.min, stats.max), (3, 2, 9));
+        assert_eq!(stats.mean(), Some(5.0));
+    }
+
+    #[test]
+    fn test_periods_and_series_round_trip() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 19, 8, 41, 5).unwrap();
+        let hour = Resolution::Hourly.period_start(at);
+
+        assert_eq!(Resolution::Hourly.period_key(hour), "2024-03-19T08");
+        assert_eq!(Resolution::Hourly.parse_period_key("2024-03-19T08"), Some(hour));
+        assert_eq!(Resolution::Daily.parse_period_key("2024-03-19"), Some(Resolution::Daily.period_start(at)));
+        assert_eq!(Resolution::for_range(at, at + Duration::days(7)), Resolution::Hourly);
+        assert_eq!(Resolution::for_range(at, at + Duration::days(30)), Resolution::Daily);
+
+        let district = RollupScope::District("Praha#2".to_string());
+        let series = district.series(Resolution::Daily);
+        assert_eq!(RollupScope::parse_series(&series), Some((district, Resolution::Daily)));
+        assert!(RollupScope::new("location", "Praha 2").is_err());
+        assert!(RollupScope::new("street", "Korunní").is_err());
+    }
+
+    #[test]
+    fn test_full_time_is_split_across_periods() {
+        let bin_id = Uuid::new_v4();
+        let day = Utc.with_ymd_and_hms(2024, 3, 19, 0, 0, 0).unwrap();
+        let timeline = Timeline {
+            // Full since the evening before
+            before: Some(report(bin_id, 9, day - Duration::hours(2))),
+            events: vec![
+                report(bin_id, 3, day + Duration::minutes(30)),
+                report(bin_id, 10, day + Duration::hours(5) + Duration::minutes(15)),
+                BinEvent::Collected { at: day + Duration::hours(7) },
+            ],
+            after: None,
+        };
+
+        let rollups = bin_rollups(bin_id, &timeline, day, day + Duration::days(1));
+
+        assert_eq!(rollups.len(), 25);
+        assert_eq!(stats(&rollups, Resolution::Hourly, day).full_seconds, 30 * 60);
+        assert_eq!(stats(&rollups, Resolution::Hourly, day + Duration::hours(5)).full_seconds, 45 * 60);
+        assert_eq!(stats(&rollups, Resolution::Hourly, day + Duration::hours(6)).full_seconds, 3600);
+        let daily = stats(&rollups, Resolution::Daily, day);
+        assert_eq!((daily.reports_count, daily.min, daily.max, daily.full_seconds), (2, 3, 10, (30 + 105) * 60));
+    }
+
+    #[test]
+    fn test_plan_shares_only_the_change() {
+        let bin_id = Uuid::new_v4();
+        let location_id = Uuid::new_v4();
+        let day = Utc.with_ymd_and_hms(2024, 3, 19, 0, 0, 0).unwrap();
+        let rollup = |count: u32, sum: i64| Rollup {
+            scope: RollupScope::Bin(bin_id),
+            resolution: Resolution::Daily,
+            period_start: day,
+            stats: FillStats { reports_count: count, min: 2, max: 8, sum, full_seconds: 0 },
+        };
+        let placement = Placement { location_id: Some(location_id), district: Some("Brno-střed".to_string()) };
+
+        let update = RollupUpdate::plan(&[rollup(3, 15)], &[rollup(2, 10)], &placement);
+
+        assert_eq!(update.bins.len(), 1);
+        assert_eq!(update.shared.len(), 2);
+        assert_eq!(update.shared[0].scope, RollupScope::Location(location_id));
+        assert_eq!((update.shared[0].stats.reports_count, update.shared[0].stats.sum), (1, 5));
+        assert!(RollupUpdate::plan(&[rollup(3, 15)], &[rollup(3, 15)], &placement).is_empty());
+        // Reports archived since the rollup was stored
+        assert!(RollupUpdate::plan(&[rollup(1, 5)], &[rollup(3, 15)], &placement).is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::domain::BinStatus;
+use crate::error::AppError;
+
+/// Distances measured by an ultrasonic sensor mounted under the lid of a
+/// specific bin: what it reads when the bin is empty and when it is full.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinCalibration {
+    pub empty_distance_mm: u32,
+    pub full_distance_mm: u32,
+}
+
+impl BinCalibration {
+    pub fn new(empty_distance_mm: u32, full_distance_mm: u32) -> Result<Self, AppError> {
+        if full_distance_mm >= empty_distance_mm {
+            return Err(AppError::InvalidRequest(format!(
+                "Empty distance ({} mm) must be greater than full distance ({} mm)",
+                empty_distance_mm, full_distance_mm
+            )));
+        }
+        Ok(Self { empty_distance_mm, full_distance_mm })
+    }
+
+    /// Converts a raw distance reading into a fill level. Readings outside
+    /// the calibrated range are clamped rather than rejected, since echoes
+    /// off a heaped lid or the bin floor are common.
+    pub fn status_for_distance(&self, distance_mm: u32) -> BinStatus {
+        let distance = distance_mm.clamp(self.full_distance_mm, self.empty_distance_mm);
+        let depth = f64::from(self.empty_distance_mm - self.full_distance_mm);
+        let filled = f64::from(self.empty_distance_mm - distance);
+        BinStatus::from((filled / depth * 10.0).round() as i32)
+    }
+}
+
+/// A registered fill-level sensor and the bin it is mounted in. LoRaWAN
+/// devices are registered under their DevEUI and name the payload `vendor`
+/// whose decoder reads their frames.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    pub device_id: String,
+    pub bin_id: Uuid,
+    pub key_hash: String,
+    pub vendor: String,
+    pub is_active: bool,
+}
+
+impl Device {
+    pub fn verify_key(&self, key: &str) -> bool {
+        let candidate = hash_device_key(key);
+        // Compare in constant time so the hash cannot be probed byte by byte.
+        candidate.len() == self.key_hash.len()
+            && candidate
+                .bytes()
+                .zip(self.key_hash.bytes())
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+/// Device keys are only ever stored as hex-encoded SHA-256 digests.
+pub fn hash_device_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Measurements carried by one sensor frame, normalised across vendors and
+/// transports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorFrame {
+    pub distance_mm: u32,
+    pub battery_mv: u16,
+    #[serde(default)]
+    pub temperature_c: Option<f32>,
+    #[serde(default)]
+    pub tilt_deg: Option<u8>,
+}
+
+/// A frame pushed over a transport that authenticates devices itself, such
+/// as an MQTT broker with per-device credentials, so it carries no key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryMessage {
+    pub device_id: String,
+    #[serde(flatten)]
+    pub frame: SensorFrame,
+    #[serde(default)]
+    pub rssi_dbm: Option<i16>,
+    #[serde(default)]
+    pub firmware_version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SensorReadingRequest {
+    pub device_id: String,
+    pub device_key: String,
+    pub distance_mm: u32,
+    #[serde(default)]
+    pub battery_mv: Option<u16>,
+    #[serde(default)]
+    pub rssi_dbm: Option<i16>,
+    #[serde(default)]
+    pub firmware_version: Option<String>,
+}
+
+#[async_trait]
+pub trait DeviceRepository {
+    async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError>;
+
+    async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_rejects_inverted_range() {
+        assert!(BinCalibration::new(200, 1000).is_err());
+        assert!(BinCalibration::new(500, 500).is_err());
+        assert!(BinCalibration::new(1000, 200).is_ok());
+    }
+
+    #[test]
+    fn test_status_for_distance() {
+        let calibration = BinCalibration::new(1000, 200).unwrap();
+        assert_eq!(calibration.status_for_distance(1000), BinStatus::empty());
+        assert_eq!(calibration.status_for_distance(200), BinStatus::full());
+        assert_eq!(calibration.status_for_distance(600).value(), 5);
+        assert_eq!(calibration.status_for_distance(440).value(), 7);
+    }
+
+    #[test]
+    fn test_status_for_distance_clamps_out_of_range() {
+        let calibration = BinCalibration::new(1000, 200).unwrap();
+        assert_eq!(calibration.status_for_distance(1500), BinStatus::empty());
+        assert_eq!(calibration.status_for_distance(50), BinStatus::full());
+    }
+
+    #[test]
+    fn test_device_key_verification() {
+        let device = Device {
+            device_id: "sensor-1".to_string(),
+            bin_id: Uuid::new_v4(),
+            key_hash: hash_device_key("s3cret"),
+            vendor: "ecoscan".to_string(),
+            is_active: true,
+        };
+        assert!(device.verify_key("s3cret"));
+        assert!(!device.verify_key("s3cret "));
+        assert!(!device.verify_key(""));
+    }
+}
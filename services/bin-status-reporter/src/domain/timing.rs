@@ -0,0 +1,85 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::error::AppError;
+
+/// Bounds for reports that carry their own observation time, such as a
+/// citizen report queued on a phone while offline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampPolicy {
+    /// How far a client clock may run ahead of ours before the report is
+    /// rejected. Timestamps within this margin are clamped to receipt time.
+    pub max_clock_skew: Duration,
+    /// How long after the observation a report is still accepted.
+    pub max_report_age: Duration,
+}
+
+impl Default for TimestampPolicy {
+    fn default() -> Self {
+        Self {
+            max_clock_skew: Duration::minutes(5),
+            max_report_age: Duration::days(7),
+        }
+    }
+}
+
+impl TimestampPolicy {
+    /// Returns the observation time to store for a report received at
+    /// `received_at`. Reports without a client timestamp were observed when
+    /// they were received.
+    pub fn resolve(
+        &self,
+        observed_at: Option<DateTime<Utc>>,
+        received_at: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, AppError> {
+        let Some(observed_at) = observed_at else {
+            return Ok(received_at);
+        };
+
+        if observed_at > received_at + self.max_clock_skew {
+            return Err(AppError::InvalidRequest(format!(
+                "Observation time {} is in the future",
+                observed_at.to_rfc3339()
+            )));
+        }
+        if received_at - observed_at > self.max_report_age {
+            return Err(AppError::InvalidRequest(format!(
+                "Observation time {} is older than {} hours",
+                observed_at.to_rfc3339(),
+                self.max_report_age.num_hours()
+            )));
+        }
+        Ok(observed_at.min(received_at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_timestamp_uses_receipt_time() {
+        let now = Utc::now();
+        assert_eq!(TimestampPolicy::default().resolve(None, now).unwrap(), now);
+    }
+
+    #[test]
+    fn test_small_skew_is_clamped() {
+        let now = Utc::now();
+        let policy = TimestampPolicy::default();
+
+        assert_eq!(policy.resolve(Some(now + Duration::minutes(2)), now).unwrap(), now);
+        let earlier = now - Duration::hours(3);
+        assert_eq!(policy.resolve(Some(earlier), now).unwrap(), earlier);
+    }
+
+    #[test]
+    fn test_rejects_future_and_stale_timestamps() {
+        let now = Utc::now();
+        let policy = TimestampPolicy::default();
+
+        let future = policy.resolve(Some(now + Duration::minutes(10)), now);
+        assert!(matches!(future, Err(AppError::InvalidRequest(msg)) if msg.contains("future")));
+        let stale = policy.resolve(Some(now - Duration::days(8)), now);
+        assert!(matches!(stale, Err(AppError::InvalidRequest(msg)) if msg.contains("older than 168 hours")));
+    }
+}
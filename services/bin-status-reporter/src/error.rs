@@ -3,7 +3,7 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
-    DatabaseError(String),
+    DatabaseError(#[from] DatabaseError),
 
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
@@ -11,12 +11,104 @@ pub enum AppError {
     #[error("Bin not found: {0}")]
     BinNotFound(String),
 
+    #[error("Location not found: {0}")]
+    LocationNotFound(String),
+
+    /// The bin exists but its lifecycle state does not take reports.
+    #[error("Bin out of service: {0}")]
+    BinOutOfService(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
     #[error("Internal server error: {0}")]
     InternalError(String),
 }
 
+impl AppError {
+    /// Whether retrying the same call may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, AppError::DatabaseError(e) if e.is_transient())
+    }
+}
+
+/// Storage failures, classified by what the caller can do about them.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DatabaseError {
+    /// The request was rejected for exceeding capacity and was not applied.
+    #[error("throttled: {0}")]
+    Throttled(String),
+
+    /// A condition or transaction conflict rejected the write; nothing was
+    /// applied and a fresh read may succeed.
+    #[error("conflict: {0}")]
+    Conflict(String),
+
+    /// No response arrived in time. The write may or may not have applied.
+    #[error("timed out: {0}")]
+    Timeout(String),
+
+    /// The service or network failed. The write may or may not have applied.
+    #[error("unavailable: {0}")]
+    Unavailable(String),
+
+    /// Calls are being short-circuited after repeated failures.
+    #[error("circuit open: {0}")]
+    CircuitOpen(String),
+
+    /// Retrying will not help: bad request, missing table.
+    #[error("{0}")]
+    Permanent(String),
+
+    /// A stored item does not have the shape its schema version declares.
+    #[error(transparent)]
+    Malformed(#[from] MalformedItem),
+}
+
+/// An item that could not be mapped onto its type. Reading it again will
+/// fail the same way, so it is reported rather than read as defaults.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("malformed {entity} item {key}: {reason}")]
+pub struct MalformedItem {
+    pub entity: &'static str,
+    pub key: String,
+    pub reason: String,
+}
+
+impl From<MalformedItem> for AppError {
+    fn from(error: MalformedItem) -> Self {
+        AppError::DatabaseError(error.into())
+    }
+}
+
+impl DatabaseError {
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DatabaseError::Throttled(_)
+                | DatabaseError::Conflict(_)
+                | DatabaseError::Timeout(_)
+                | DatabaseError::Unavailable(_)
+        )
+    }
+
+    /// Whether the failed call certainly had no effect, which makes it safe
+    /// to retry even a write that is not idempotent.
+    pub fn was_rejected(&self) -> bool {
+        matches!(
+            self,
+            DatabaseError::Throttled(_) | DatabaseError::Conflict(_) | DatabaseError::CircuitOpen(_)
+        )
+    }
+
+    /// Whether the failed write may have applied all the same.
+    pub fn may_have_applied(&self) -> bool {
+        matches!(self, DatabaseError::Timeout(_) | DatabaseError::Unavailable(_))
+    }
+}
+
 impl From<AppError> for String {
     fn from(error: AppError) -> Self {
         error.to_string()
     }
-} 
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::domain::backup::{Record, EXPORT_FORMAT_VERSION};
+use crate::domain::consistency::ResumeToken;
+use crate::domain::retention::{ArchivedDay, ReportArchive, RetentionCheckpoint, RetentionPolicy};
+use crate::error::{AppError, DatabaseError};
+use crate::infrastructure::dynamodb::db_error;
+
+/// `REPORT_RETENTION_DAYS`, or the default of 90 days.
+pub fn retention_from_env() -> Result<RetentionPolicy, AppError> {
+    match std::env::var("REPORT_RETENTION_DAYS") {
+        Ok(value) => RetentionPolicy::new(value.parse().map_err(|_| {
+            AppError::InternalError(format!("REPORT_RETENTION_DAYS must be a whole number of days, got {}", value))
+        })?),
+        Err(_) => Ok(RetentionPolicy::default()),
+    }
+}
+
+/// Gzipped JSON Lines in the export format, so an archive can be restored
+/// with `ecoscan-backup import` after unpacking.
+pub fn encode(archived: &ArchivedDay, archived_at: DateTime<Utc>) -> Result<Vec<u8>, AppError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let header = Record::Header { version: EXPORT_FORMAT_VERSION, exported_at: archived_at };
+    let lines = std::iter::once(header)
+        .chain(archived.reports.iter().cloned().map(Record::Report))
+        .chain(archived.incidents.iter().cloned().map(Record::Incident));
+    for record in lines {
+        serde_json::to_writer(&mut encoder, &record)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize archived report: {}", e)))?;
+        encoder.write_all(b"\n").map_err(compression_error)?;
+    }
+    encoder.finish().map_err(compression_error)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<ArchivedDay, AppError> {
+    let mut archived = ArchivedDay::default();
+    for line in BufReader::new(GzDecoder::new(bytes)).lines() {
+        let line = line.map_err(compression_error)?;
+        match serde_json::from_str(&line).map_err(|e| malformed(e.to_string()))? {
+            Record::Header { version, .. } if version > EXPORT_FORMAT_VERSION => {
+                return Err(malformed(format!("archive format version {} is not supported", version)));
+            }
+            Record::Header { .. } => {}
+            Record::Report(report) => archived.reports.push(report),
+            Record::Incident(incident) => archived.incidents.push(incident),
+            other => return Err(malformed(format!("unexpected record {:?}", other))),
+        }
+    }
+    Ok(archived)
+}
+
+fn compression_error(e: std::io::Error) -> AppError {
+    AppError::InternalError(format!("Failed to compress or decompress archive: {}", e))
+}
+
+fn malformed(reason: String) -> AppError {
+    AppError::DatabaseError(DatabaseError::Permanent(format!("Malformed report archive: {}", reason)))
+}
+
+/// Archived reports and incidents in S3 under
+/// `<prefix>reports/day=<YYYY-MM-DD>/bin=<id>.jsonl.gz`, partitioned by day
+/// so lifecycle rules and Athena can work on whole days.
+pub struct S3Archive {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Archive {
+    /// Uses `ARCHIVE_BUCKET` and `ARCHIVE_PREFIX`, and `S3_ENDPOINT_URL` for
+    /// LocalStack.
+    pub async fn from_env() -> Result<Self, AppError> {
+        let bucket = std::env::var("ARCHIVE_BUCKET")
+            .map_err(|_| AppError::InternalError("ARCHIVE_BUCKET is not set".to_string()))?;
+        let prefix = std::env::var("ARCHIVE_PREFIX").unwrap_or_default();
+        let endpoint_url = std::env::var("S3_ENDPOINT_URL").ok();
+        Ok(Self::new(connect_s3(endpoint_url.as_deref()).await, bucket, prefix))
+    }
+
+    pub fn new(client: aws_sdk_s3::Client, bucket: impl Into<String>, prefix: impl Into<String>) -> Self {
+        Self { client, bucket: bucket.into(), prefix: prefix.into() }
+    }
+
+    pub fn key(&self, bin_id: &Uuid, day: NaiveDate) -> String {
+        format!("{}reports/day={}/bin={}.jsonl.gz", self.prefix, day, bin_id)
+    }
+
+    /// Outside `reports/`, so it is not taken for an archived day.
+    pub fn checkpoint_key(&self) -> String {
+        format!("{}retention/resume", self.prefix)
+    }
+}
+
+#[async_trait]
+impl ReportArchive for S3Archive {
+    #[instrument(skip_all, fields(bin_id = %bin_id, %day))]
+    async fn get(&self, bin_id: &Uuid, day: NaiveDate) -> Result<Option<ArchivedDay>, AppError> {
+        let result = self.client.get_object().bucket(&self.bucket).key(self.key(bin_id, day)).send().await;
+        let output = match result {
+            Ok(output) => output,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => return Err(db_error(e)),
+        };
+        let bytes = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| AppError::DatabaseError(DatabaseError::Unavailable(format!("Failed to read archive: {}", e))))?
+            .into_bytes();
+        decode(&bytes).map(Some)
+    }
+
+    #[instrument(skip_all, fields(bin_id = %bin_id, %day, reports = archived.reports.len(), incidents = archived.incidents.len()))]
+    async fn put(&self, bin_id: &Uuid, day: NaiveDate, archived: &ArchivedDay, archived_at: DateTime<Utc>) -> Result<(), AppError> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.key(bin_id, day))
+            .content_type("application/gzip")
+            .body(ByteStream::from(encode(archived, archived_at)?))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RetentionCheckpoint for S3Archive {
+    async fn resume_token(&self) -> Result<Option<ResumeToken>, AppError> {
+        let result = self.client.get_object().bucket(&self.bucket).key(self.checkpoint_key()).send().await;
+        let output = match result {
+            Ok(output) => output,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => return Err(db_error(e)),
+        };
+        let bytes = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| AppError::DatabaseError(DatabaseError::Unavailable(format!("Failed to read checkpoint: {}", e))))?
+            .into_bytes();
+        let token = String::from_utf8_lossy(&bytes);
+        token.trim().parse().map(Some).map_err(|_| malformed(format!("resume token {:?}", token)))
+    }
+
+    async fn save_resume_token(&self, resume: Option<&ResumeToken>) -> Result<(), AppError> {
+        match resume {
+            Some(token) => {
+                self.client
+                    .put_object()
+                    .bucket(&self.bucket)
+                    .key(self.checkpoint_key())
+                    .content_type("text/plain")
+                    .body(ByteStream::from(token.to_string().into_bytes()))
+                    .send()
+                    .await
+                    .map_err(db_error)?;
+            }
+            None => {
+                self.client
+                    .delete_object()
+                    .bucket(&self.bucket)
+                    .key(self.checkpoint_key())
+                    .send()
+                    .await
+                    .map_err(db_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An S3 client for `endpoint_url`, e.g. LocalStack, with path-style
+/// addressing so bucket names need no DNS.
+pub async fn connect_s3(endpoint_url: Option<&str>) -> aws_sdk_s3::Client {
+    let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        .region(aws_config::meta::region::RegionProviderChain::default_provider().or_else("eu-central-1"))
+        .load()
+        .await;
+    let mut builder = aws_sdk_s3::config::Builder::from(&config);
+    if let Some(endpoint_url) = endpoint_url {
+        builder = builder.endpoint_url(endpoint_url).force_path_style(true);
+    }
+    aws_sdk_s3::Client::from_conf(builder.build())
+}
+
+type Objects = HashMap<(Uuid, NaiveDate), Vec<u8>>;
+
+/// Process-local archive for tests and for running without S3. Clones
+/// share the same objects.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryArchive {
+    objects: Arc<RwLock<Objects>>,
+    checkpoint: Arc<RwLock<Option<ResumeToken>>>,
+}
+
+impl InMemoryArchive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ReportArchive for InMemoryArchive {
+    async fn get(&self, bin_id: &Uuid, day: NaiveDate) -> Result<Option<ArchivedDay>, AppError> {
+        let objects = self.objects.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        objects.get(&(*bin_id, day)).map(|bytes| decode(bytes)).transpose()
+    }
+
+    async fn put(&self, bin_id: &Uuid, day: NaiveDate, archived: &ArchivedDay, archived_at: DateTime<Utc>) -> Result<(), AppError> {
+        let bytes = encode(archived, archived_at)?;
+        self.objects.write().unwrap_or_else(|poisoned| poisoned.into_inner()).insert((*bin_id, day), bytes);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RetentionCheckpoint for InMemoryArchive {
+    async fn resume_token(&self) -> Result<Option<ResumeToken>, AppError> {
+        Ok(self.checkpoint.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone())
+    }
+
+    async fn save_resume_token(&self, resume: Option<&ResumeToken>) -> Result<(), AppError> {
+        *self.checkpoint.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = resume.cloned();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::types::{BucketLocationConstraint, CreateBucketConfiguration};
+    use chrono::TimeZone;
+
+    use crate::domain::incident::{IncidentReport, ReportDetails};
+    use crate::domain::{BinStatus, StatusReport};
+
+    fn day(bin_id: Uuid) -> ArchivedDay {
+        let at = Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap();
+        ArchivedDay {
+            reports: vec![
+                StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(3).unwrap(), at),
+                StatusReport::sensor(Uuid::new_v4(), bin_id, BinStatus::full(), at + chrono::Duration::hours(6)),
+            ],
+            incidents: vec![IncidentReport {
+                report_id: Uuid::new_v4(),
+                bin_id,
+                details: ReportDetails::Fire { still_burning: false },
+                observed_at: at,
+                received_at: at,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let archived = day(Uuid::new_v4());
+
+        let bytes = encode(&archived, Utc::now()).unwrap();
+
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+        assert_eq!(decode(&bytes).unwrap(), archived);
+        assert!(decode(b"not gzip").is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires LocalStack S3 on localhost:4566"]
+    async fn test_s3_archive_against_localstack() {
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let client = connect_s3(Some("http://localhost:4566")).await;
+        let bucket = format!("archive-{}", Uuid::new_v4());
+        let location = CreateBucketConfiguration::builder()
+            .location_constraint(BucketLocationConstraint::EuCentral1)
+            .build();
+        client.create_bucket().bucket(&bucket).create_bucket_configuration(location).send().await.unwrap();
+
+        let archive = S3Archive::new(client, bucket, "test/");
+        let bin_id = Uuid::new_v4();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 19).unwrap();
+        assert_eq!(archive.get(&bin_id, day).await.unwrap(), None);
+
+        let mut archived = self::day(bin_id);
+        archive.put(&bin_id, day, &archived, Utc::now()).await.unwrap();
+        archived.reports.truncate(1);
+        archive.put(&bin_id, day, &archived, Utc::now()).await.unwrap();
+        assert_eq!(archive.get(&bin_id, day).await.unwrap().unwrap(), archived);
+        assert_eq!(archive.key(&bin_id, day), format!("test/reports/day=2024-03-19/bin={}.jsonl.gz", bin_id));
+
+        assert_eq!(archive.resume_token().await.unwrap(), None);
+        let token = ResumeToken(bin_id);
+        archive.save_resume_token(Some(&token)).await.unwrap();
+        assert_eq!(archive.resume_token().await.unwrap(), Some(token));
+        archive.save_resume_token(None).await.unwrap();
+        assert_eq!(archive.resume_token().await.unwrap(), None);
+    }
+}
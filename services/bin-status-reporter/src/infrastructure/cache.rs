@@ -0,0 +1,475 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use lru::LruCache;
+use shared::clock::{Clock, SystemClock};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::consistency::{BinHistoryRepository, BinPage, ResumeToken, StoredBin};
+use crate::domain::retention::RetentionRepository;
+use crate::domain::rollup::{DailyRollup, Placement, Resolution, Rollup, RollupRepository, RollupScope, RollupUpdate, Timeline};
+use crate::domain::device_health::{DeviceHealth, DeviceHealthRepository};
+use crate::domain::estimator::BinAggregate;
+use crate::domain::history::BinEvent;
+use crate::domain::incident::{IncidentReport, IncidentRepository, ReportKind};
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleTransition};
+use crate::domain::location::{LocationRepository, PlacedBin};
+use crate::domain::sensor::{BinCalibration, Device, DeviceRepository};
+use crate::domain::{BinProfile, BinProfileRepository, BinRepository, StatusChange, StatusReport};
+use crate::error::AppError;
+
+/// How many bins to keep and for how long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachePolicy {
+    pub capacity: NonZeroUsize,
+    /// Bounds how stale an entry can get when another process writes the
+    /// bin, since only writes through this decorator invalidate it. Reports
+    /// arrive through other functions than the status query, so the default
+    /// is kept short.
+    pub ttl: Duration,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self { capacity: NonZeroUsize::new(1024).expect("non-zero"), ttl: Duration::from_secs(5) }
+    }
+}
+
+/// Where cached bins live. A failing store is treated as a miss, so the
+/// cache can only slow reads down, never fail them.
+#[async_trait]
+pub trait BinCache: Send + Sync {
+    async fn get(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError>;
+
+    async fn put(&self, bin_id: &Uuid, aggregate: &BinAggregate) -> Result<(), AppError>;
+
+    async fn invalidate(&self, bin_id: &Uuid) -> Result<(), AppError>;
+}
+
+/// In-process LRU with a per-entry expiry, which survives between
+/// invocations of a warm Lambda container.
+pub struct LruBinCache {
+    entries: Mutex<LruCache<Uuid, (BinAggregate, DateTime<Utc>)>>,
+    ttl: chrono::Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl LruBinCache {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self::with_clock(policy, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(policy: CachePolicy, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(policy.capacity)),
+            ttl: chrono::Duration::from_std(policy.ttl).unwrap_or(chrono::Duration::MAX),
+            clock,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<Uuid, (BinAggregate, DateTime<Utc>)>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl BinCache for LruBinCache {
+    async fn get(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        let now = self.clock.now();
+        let mut entries = self.lock();
+        match entries.get(bin_id) {
+            Some((aggregate, expires_at)) if *expires_at > now => Ok(Some(aggregate.clone())),
+            Some(_) => {
+                entries.pop(bin_id);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, bin_id: &Uuid, aggregate: &BinAggregate) -> Result<(), AppError> {
+        let expires_at = self.clock.now() + self.ttl;
+        self.lock().put(*bin_id, (aggregate.clone(), expires_at));
+        Ok(())
+    }
+
+    async fn invalidate(&self, bin_id: &Uuid) -> Result<(), AppError> {
+        self.lock().pop(bin_id);
+        Ok(())
+    }
+}
+
+/// Cache shared between Lambda containers, so a write in one is seen by
+/// reads in all of them.
+#[cfg(feature = "redis-cache")]
+pub struct RedisBinCache {
+    connection: redis::aio::ConnectionManager,
+    ttl: Duration,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisBinCache {
+    pub async fn connect(url: &str, ttl: Duration) -> Result<Self, AppError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| AppError::InternalError(format!("Invalid Redis URL: {}", e)))?;
+        let connection = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to connect to Redis: {}", e)))?;
+        Ok(Self { connection, ttl })
+    }
+
+    fn key(bin_id: &Uuid) -> String {
+        format!("ecoscan:bin:{}", bin_id)
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+#[async_trait]
+impl BinCache for RedisBinCache {
+    async fn get(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        let value: Option<String> = redis::cmd("GET")
+            .arg(Self::key(bin_id))
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| AppError::InternalError(format!("Redis GET failed: {}", e)))?;
+        match value {
+            Some(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| AppError::InternalError(format!("Malformed cached bin {}: {}", bin_id, e))),
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, bin_id: &Uuid, aggregate: &BinAggregate) -> Result<(), AppError> {
+        let json = serde_json::to_string(aggregate)
+            .map_err(|e| AppError::InternalError(e.to_string()))?;
+        redis::cmd("SET")
+            .arg(Self::key(bin_id))
+            .arg(json)
+            .arg("PX")
+            .arg(self.ttl.as_millis() as u64)
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .map_err(|e| AppError::InternalError(format!("Redis SET failed: {}", e)))
+    }
+
+    async fn invalidate(&self, bin_id: &Uuid) -> Result<(), AppError> {
+        redis::cmd("DEL")
+            .arg(Self::key(bin_id))
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .map_err(|e| AppError::InternalError(format!("Redis DEL failed: {}", e)))
+    }
+}
+
+/// Read-through cache in front of `get_bin`. Every write that can change a
+/// bin's estimate drops its entry, whether or not the write succeeded,
+/// since a failed write may still have been applied.
+pub struct CachedRepository<R> {
+    inner: R,
+    cache: Arc<dyn BinCache>,
+}
+
+impl<R> CachedRepository<R> {
+    pub fn new(inner: R, cache: Arc<dyn BinCache>) -> Self {
+        Self { inner, cache }
+    }
+
+    /// Caches in process with the given policy.
+    pub fn in_process(inner: R, policy: CachePolicy) -> Self {
+        Self::new(inner, Arc::new(LruBinCache::new(policy)))
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    async fn invalidate(&self, bin_id: &Uuid) {
+        if let Err(e) = self.cache.invalidate(bin_id).await {
+            warn!("Failed to invalidate cached bin {}: {}", bin_id, e);
+        }
+    }
+}
+
+#[async_trait]
+impl<R: BinRepository> BinRepository for CachedRepository<R> {
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        match self.cache.get(bin_id).await {
+            Ok(Some(aggregate)) => return Ok(Some(aggregate)),
+            Ok(None) => {}
+            Err(e) => warn!("Bin cache read failed, reading through: {}", e),
+        }
+
+        // Unknown bins are not cached, so a newly registered bin shows up at once
+        let aggregate = self.inner.get_bin(bin_id).await?;
+        if let Some(aggregate) = &aggregate {
+            if let Err(e) = self.cache.put(bin_id, aggregate).await {
+                warn!("Failed to cache bin {}: {}", bin_id, e);
+            }
+        }
+        Ok(aggregate)
+    }
+
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        let result = self.inner.update_status(report).await;
+        self.invalidate(&report.bin_id).await;
+        result
+    }
+
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+        self.inner.add_report(report).await
+    }
+
+    async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+        let result = self.inner.record_collection(event).await;
+        self.invalidate(&event.bin_id).await;
+        result
+    }
+
+    async fn record_batch(&self, groups: &[ReportGroup]) -> Vec<Result<(), AppError>> {
+        let results = self.inner.record_batch(groups).await;
+        for group in groups {
+            self.invalidate(&group.bin_id).await;
+        }
+        results
+    }
+}
+
+#[async_trait]
+impl<R: BinHistoryRepository> BinHistoryRepository for CachedRepository<R> {
+    async fn scan_bins(&self, resume: Option<&ResumeToken>, limit: usize) -> Result<BinPage, AppError> {
+        self.inner.scan_bins(resume, limit).await
+    }
+
+    async fn bin_events(
+        &self,
+        bin_id: &Uuid,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        self.inner.bin_events(bin_id, since, until).await
+    }
+
+    async fn repair_bin(&self, stored: &StoredBin, recomputed: &BinAggregate) -> Result<bool, AppError> {
+        let result = self.inner.repair_bin(stored, recomputed).await;
+        self.invalidate(&stored.bin_id).await;
+        result
+    }
+}
+
+#[async_trait]
+impl<R: RetentionRepository> RetentionRepository for CachedRepository<R> {
+    async fn reports_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<StatusReport>, AppError> {
+        self.inner.reports_before(bin_id, before).await
+    }
+
+    async fn incidents_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<IncidentReport>, AppError> {
+        self.inner.incidents_before(bin_id, before).await
+    }
+
+    async fn delete_incidents(&self, bin_id: &Uuid, incidents: &[IncidentReport]) -> Result<(), AppError> {
+        self.inner.delete_incidents(bin_id, incidents).await
+    }
+
+    async fn delete_reports(&self, bin_id: &Uuid, reports: &[StatusReport]) -> Result<(), AppError> {
+        self.inner.delete_reports(bin_id, reports).await
+    }
+
+    async fn daily_rollups(&self, bin_id: &Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyRollup>, AppError> {
+        self.inner.daily_rollups(bin_id, from, to).await
+    }
+}
+
+#[async_trait]
+impl<R: RollupRepository> RollupRepository for CachedRepository<R> {
+    async fn bin_timeline(&self, bin_id: &Uuid, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Timeline, AppError> {
+        self.inner.bin_timeline(bin_id, from, to).await
+    }
+
+    async fn bin_placement(&self, bin_id: &Uuid) -> Result<Placement, AppError> {
+        self.inner.bin_placement(bin_id).await
+    }
+
+    async fn rollups(
+        &self,
+        scope: &RollupScope,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Rollup>, AppError> {
+        self.inner.rollups(scope, resolution, from, to).await
+    }
+
+    async fn apply_rollup_update(&self, update: &RollupUpdate) -> Result<bool, AppError> {
+        self.inner.apply_rollup_update(update).await
+    }
+}
+
+#[async_trait]
+impl<R: BinProfileRepository> BinProfileRepository for CachedRepository<R> {
+    async fn bin_profile(&self, bin_id: &Uuid) -> Result<Option<BinProfile>, AppError> {
+        self.inner.bin_profile(bin_id).await
+    }
+
+    async fn set_bin_profile(&self, bin_id: &Uuid, profile: &BinProfile) -> Result<(), AppError> {
+        self.inner.set_bin_profile(bin_id, profile).await
+    }
+}
+
+#[async_trait]
+impl<R: LifecycleRepository> LifecycleRepository for CachedRepository<R> {
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+        self.inner.bin_lifecycle(bin_id).await
+    }
+
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError> {
+        self.inner.record_transition(bin_id, transition).await
+    }
+}
+
+#[async_trait]
+impl<R: IncidentRepository> IncidentRepository for CachedRepository<R> {
+    async fn add_incident(&self, report: &IncidentReport) -> Result<(), AppError> {
+        self.inner.add_incident(report).await
+    }
+
+    async fn incidents(&self, kind: ReportKind, since: DateTime<Utc>, limit: usize) -> Result<Vec<IncidentReport>, AppError> {
+        self.inner.incidents(kind, since, limit).await
+    }
+}
+
+#[async_trait]
+impl<R: LocationRepository> LocationRepository for CachedRepository<R> {
+    /// Read past the cache, which a writer elsewhere may have left stale.
+    async fn location_bins(&self, location_id: &Uuid) -> Result<Vec<PlacedBin>, AppError> {
+        self.inner.location_bins(location_id).await
+    }
+}
+
+#[async_trait]
+impl<R: DeviceRepository + Send + Sync> DeviceRepository for CachedRepository<R> {
+    async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError> {
+        self.inner.get_device(device_id).await
+    }
+
+    async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError> {
+        self.inner.get_calibration(bin_id).await
+    }
+}
+
+#[async_trait]
+impl<R: DeviceHealthRepository + Send + Sync> DeviceHealthRepository for CachedRepository<R> {
+    async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+        self.inner.get_health(device_id).await
+    }
+
+    async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+        self.inner.save_health(health).await
+    }
+
+    async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+        self.inner.list_health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::BinStatus;
+    use crate::infrastructure::memory::InMemoryRepository;
+    use shared::clock::FakeClock;
+
+    fn cached(capacity: usize) -> (CachedRepository<InMemoryRepository>, InMemoryRepository, FakeClock) {
+        let memory = InMemoryRepository::new();
+        let clock = FakeClock::new(Utc::now());
+        let policy = CachePolicy { capacity: NonZeroUsize::new(capacity).unwrap(), ttl: Duration::from_secs(30) };
+        let cache = LruBinCache::with_clock(policy, Arc::new(clock.clone()));
+        (CachedRepository::new(memory.clone(), Arc::new(cache)), memory, clock)
+    }
+
+    fn report(bin_id: Uuid, status: BinStatus) -> StatusReport {
+        StatusReport::citizen(Uuid::new_v4(), bin_id, status, Utc::now())
+    }
+
+    async fn cached_status(repo: &CachedRepository<InMemoryRepository>, bin_id: &Uuid) -> BinStatus {
+        repo.get_bin(bin_id).await.unwrap().unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_serves_repeat_reads_until_ttl_expires() {
+        let (repo, memory, clock) = cached(8);
+        let bin_id = Uuid::new_v4();
+        memory.add_bin(bin_id);
+        assert_eq!(cached_status(&repo, &bin_id).await, BinStatus::empty());
+
+        // A write that bypasses the cache stays invisible until expiry
+        memory.update_status(&report(bin_id, BinStatus::full())).await.unwrap();
+        assert_eq!(cached_status(&repo, &bin_id).await, BinStatus::empty());
+
+        clock.advance(chrono::Duration::seconds(31));
+        assert_eq!(cached_status(&repo, &bin_id).await, BinStatus::full());
+    }
+
+    #[tokio::test]
+    async fn test_writes_through_the_decorator_invalidate() {
+        let (repo, memory, _) = cached(8);
+        let bin_id = Uuid::new_v4();
+        memory.add_bin(bin_id);
+        cached_status(&repo, &bin_id).await;
+
+        repo.update_status(&report(bin_id, BinStatus::full())).await.unwrap();
+        assert_eq!(cached_status(&repo, &bin_id).await, BinStatus::full());
+
+        repo.record_collection(&CollectionEvent { bin_id, collected_at: Utc::now(), received_at: Utc::now() })
+            .await
+            .unwrap();
+        assert_eq!(repo.get_bin(&bin_id).await.unwrap().unwrap().reports_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let (repo, memory, _) = cached(2);
+        let bins: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for bin_id in &bins {
+            memory.add_bin(*bin_id);
+        }
+
+        cached_status(&repo, &bins[0]).await;
+        cached_status(&repo, &bins[1]).await;
+        cached_status(&repo, &bins[0]).await;
+        cached_status(&repo, &bins[2]).await;
+
+        for bin_id in &bins {
+            memory.update_status(&report(*bin_id, BinStatus::full())).await.unwrap();
+        }
+        // bins[1] was evicted and is read fresh, the others are still cached
+        assert_eq!(cached_status(&repo, &bins[0]).await, BinStatus::empty());
+        assert_eq!(cached_status(&repo, &bins[2]).await, BinStatus::empty());
+        assert_eq!(cached_status(&repo, &bins[1]).await, BinStatus::full());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_bins_are_not_cached() {
+        let (repo, memory, _) = cached(8);
+        let bin_id = Uuid::new_v4();
+        assert!(repo.get_bin(&bin_id).await.unwrap().is_none());
+
+        memory.add_bin(bin_id);
+        assert!(repo.get_bin(&bin_id).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,441 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::incident::{DamagedPart, IncidentReport, IncidentRepository, ReportDetails, ReportKind};
+use crate::domain::lifecycle::{LifecycleRepository, LifecycleState, LifecycleTransition};
+use crate::domain::location::LocationRepository;
+use crate::domain::rollup::{bin_rollups, Resolution, RollupRepository, RollupScope, RollupUpdate};
+use crate::domain::{BinProfile, BinProfileRepository, BinRepository, BinStatus, Location, StatusReport, WasteType};
+use crate::error::AppError;
+use crate::infrastructure::dynamodb::DynamoDbRepository;
+use crate::infrastructure::memory::InMemoryRepository;
+use crate::infrastructure::single_table::SingleTableRepository;
+
+/// A backend the conformance checks can run against.
+#[async_trait]
+pub trait Fixture:
+    BinRepository + BinProfileRepository + IncidentRepository + LifecycleRepository + LocationRepository + RollupRepository
+{
+    async fn create_bin(&self, bin_id: Uuid) {
+        self.create_placed_bin(bin_id, None).await;
+    }
+
+    async fn create_placed_bin(&self, bin_id: Uuid, location_id: Option<Uuid>);
+
+    /// Stores the location, or returns false for a backend that keeps none.
+    async fn create_location(&self, location: &Location) -> bool;
+}
+
+#[async_trait]
+impl Fixture for InMemoryRepository {
+    async fn create_placed_bin(&self, bin_id: Uuid, location_id: Option<Uuid>) {
+        self.add_bin(bin_id);
+        if let Some(location_id) = location_id {
+            self.place_bin(bin_id, location_id);
+        }
+    }
+
+    async fn create_location(&self, location: &Location) -> bool {
+        self.add_location(location.clone());
+        true
+    }
+}
+
+#[async_trait]
+impl Fixture for DynamoDbRepository {
+    async fn create_placed_bin(&self, bin_id: Uuid, location_id: Option<Uuid>) {
+        DynamoDbRepository::create_bin(self, bin_id, location_id).await.unwrap();
+    }
+
+    async fn create_location(&self, _location: &Location) -> bool {
+        false
+    }
+}
+
+#[async_trait]
+impl Fixture for SingleTableRepository {
+    async fn create_placed_bin(&self, bin_id: Uuid, location_id: Option<Uuid>) {
+        SingleTableRepository::create_bin(self, bin_id, location_id).await.unwrap();
+    }
+
+    async fn create_location(&self, location: &Location) -> bool {
+        self.put_location(location).await.unwrap();
+        true
+    }
+}
+
+/// The behaviour every `BinRepository` must share, with the default
+/// estimator weights.
+pub async fn check_all<R: Fixture>(repo: &R) {
+    unknown_bins(repo).await;
+    reports_are_weighted(repo).await;
+    collection_rebuilds_from_later_reports(repo).await;
+    redelivered_reports_count_once(repo).await;
+    batches_report_per_group(repo).await;
+    location_bins_follow_their_estimates(repo).await;
+    profiles_survive_reports(repo).await;
+    transitions_are_conditional(repo).await;
+    incidents_are_listed_by_kind(repo).await;
+    rollups_add_up_per_location_and_district(repo).await;
+}
+
+async fn stored(repo: &impl Fixture, report: &StatusReport) {
+    repo.update_status(report).await.unwrap();
+    repo.add_report(report).await.unwrap();
+}
+
+async fn status(repo: &impl Fixture, bin_id: &Uuid) -> i32 {
+    repo.get_bin(bin_id).await.unwrap().unwrap().status().value()
+}
+
+async fn unknown_bins(repo: &impl Fixture) {
+    let bin_id = Uuid::new_v4();
+    let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::ok(), Utc::now());
+    let collection = CollectionEvent { bin_id, collected_at: Utc::now(), received_at: Utc::now() };
+
+    assert!(repo.get_bin(&bin_id).await.unwrap().is_none());
+    assert!(matches!(repo.update_status(&report).await, Err(AppError::BinNotFound(_))));
+    assert!(matches!(repo.record_collection(&collection).await, Err(AppError::BinNotFound(_))));
+}
+
+async fn reports_are_weighted(repo: &impl Fixture) {
+    let bin_id = Uuid::new_v4();
+    repo.create_bin(bin_id).await;
+    let now = Utc::now();
+
+    stored(repo, &StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::empty(), now - Duration::minutes(2))).await;
+    let change = repo
+        .update_status(&StatusReport::sensor(Uuid::new_v4(), bin_id, BinStatus::new(8).unwrap(), now))
+        .await
+        .unwrap();
+
+    assert_eq!((change.previous.value(), change.current.value()), (0, 6));
+    assert_eq!(repo.get_bin(&bin_id).await.unwrap().unwrap().reports_count, 2);
+}
+
+async fn collection_rebuilds_from_later_reports(repo: &impl Fixture) {
+    let bin_id = Uuid::new_v4();
+    repo.create_bin(bin_id).await;
+    let now = Utc::now();
+
+    for (value, hours_ago) in [(9, 3), (2, 1)] {
+        let observed_at = now - Duration::hours(hours_ago);
+        stored(repo, &StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), observed_at)).await;
+    }
+
+    let collection = CollectionEvent { bin_id, collected_at: now - Duration::hours(2), received_at: now };
+    repo.record_collection(&collection).await.unwrap();
+    assert_eq!(status(repo, &bin_id).await, 2);
+
+    let older = CollectionEvent { collected_at: now - Duration::hours(4), ..collection };
+    repo.record_collection(&older).await.unwrap();
+    let aggregate = repo.get_bin(&bin_id).await.unwrap().unwrap();
+    assert_eq!(aggregate.collected_at.map(|t| t.timestamp()), Some(collection.collected_at.timestamp()));
+
+    // Reports from before the collection no longer count
+    let late = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), now - Duration::hours(3));
+    assert_eq!(repo.update_status(&late).await.unwrap().current.value(), 2);
+}
+
+async fn redelivered_reports_count_once(repo: &impl Fixture) {
+    let bin_id = Uuid::new_v4();
+    repo.create_bin(bin_id).await;
+    let observed_at = Utc::now() - Duration::minutes(30);
+
+    // A redelivered report is stored once rather than adding to the count
+    let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), observed_at);
+    repo.add_report(&report).await.unwrap();
+    repo.add_report(&report).await.unwrap();
+
+    let collection = CollectionEvent { bin_id, collected_at: observed_at - Duration::minutes(1), received_at: Utc::now() };
+    repo.record_collection(&collection).await.unwrap();
+    let aggregate = repo.get_bin(&bin_id).await.unwrap().unwrap();
+    assert_eq!((aggregate.status(), aggregate.reports_count), (BinStatus::full(), 1));
+
+    // A distinct report observed at the same instant is kept next to it
+    repo.add_report(&StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), observed_at)).await.unwrap();
+    repo.record_collection(&CollectionEvent { collected_at: observed_at - Duration::seconds(30), ..collection }).await.unwrap();
+    let aggregate = repo.get_bin(&bin_id).await.unwrap().unwrap();
+    assert_eq!((aggregate.status(), aggregate.reports_count), (BinStatus::full(), 2));
+}
+
+async fn batches_report_per_group(repo: &impl Fixture) {
+    let known = Uuid::new_v4();
+    let unknown = Uuid::new_v4();
+    repo.create_bin(known).await;
+    let now = Utc::now();
+
+    let groups = vec![
+        ReportGroup {
+            bin_id: known,
+            reports: vec![
+                StatusReport::citizen(Uuid::new_v4(), known, BinStatus::new(4).unwrap(), now - Duration::minutes(2)),
+                StatusReport::citizen(Uuid::new_v4(), known, BinStatus::new(8).unwrap(), now - Duration::minutes(1)),
+            ],
+        },
+        ReportGroup {
+            bin_id: unknown,
+            reports: vec![StatusReport::citizen(Uuid::new_v4(), unknown, BinStatus::full(), now)],
+        },
+    ];
+
+    let results = repo.record_batch(&groups).await;
+
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(AppError::BinNotFound(_))));
+    assert_eq!(status(repo, &known).await, 6);
+    assert!(repo.get_bin(&unknown).await.unwrap().is_none());
+}
+
+async fn location_bins_follow_their_estimates(repo: &impl Fixture) {
+    let location_id = Uuid::new_v4();
+    let (placed, elsewhere) = (Uuid::new_v4(), Uuid::new_v4());
+    repo.create_placed_bin(placed, Some(location_id)).await;
+    repo.create_bin(elsewhere).await;
+    stored(repo, &StatusReport::citizen(Uuid::new_v4(), placed, BinStatus::full(), Utc::now())).await;
+
+    let bins = repo.location_bins(&location_id).await.unwrap();
+
+    assert_eq!(bins.iter().map(|bin| bin.bin_id).collect::<Vec<_>>(), vec![placed]);
+    assert_eq!(bins[0].aggregate.status(), BinStatus::full());
+    assert!(repo.location_bins(&Uuid::new_v4()).await.unwrap().is_empty());
+}
+
+async fn profiles_survive_reports(repo: &impl Fixture) {
+    let location_id = Uuid::new_v4();
+    let bin_id = Uuid::new_v4();
+    repo.create_placed_bin(bin_id, Some(location_id)).await;
+    assert_eq!(repo.bin_profile(&bin_id).await.unwrap(), Some(BinProfile::default()));
+
+    let glass = BinProfile::new(WasteType::Glass, Some(1500), Some("Reflex 1500".to_string())).unwrap();
+    repo.set_bin_profile(&bin_id, &glass).await.unwrap();
+    stored(repo, &StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::ok(), Utc::now())).await;
+
+    assert_eq!(repo.bin_profile(&bin_id).await.unwrap(), Some(glass.clone()));
+    assert_eq!(repo.location_bins(&location_id).await.unwrap()[0].profile, glass);
+
+    // Unknown parts of a new profile are cleared
+    let unknown_container = BinProfile { capacity_liters: None, container_model: None, ..glass };
+    repo.set_bin_profile(&bin_id, &unknown_container).await.unwrap();
+    assert_eq!(repo.bin_profile(&bin_id).await.unwrap(), Some(unknown_container.clone()));
+
+    let unknown = Uuid::new_v4();
+    assert!(repo.bin_profile(&unknown).await.unwrap().is_none());
+    assert!(matches!(repo.set_bin_profile(&unknown, &unknown_container).await, Err(AppError::BinNotFound(_))));
+}
+
+async fn transitions_are_conditional(repo: &impl Fixture) {
+    let location_id = Uuid::new_v4();
+    let (bin_id, replacement) = (Uuid::new_v4(), Uuid::new_v4());
+    repo.create_placed_bin(bin_id, Some(location_id)).await;
+    let transition = |from, to, replaced_by| LifecycleTransition {
+        from,
+        to,
+        at: Utc::now(),
+        actor: "conformance".to_string(),
+        reason: None,
+        replaced_by,
+    };
+
+    let damaged = transition(LifecycleState::Active, LifecycleState::Damaged, None);
+    assert!(repo.record_transition(&bin_id, &damaged).await.unwrap());
+    // Stale: the bin is no longer active
+    let removed = transition(LifecycleState::Active, LifecycleState::Removed, None);
+    assert!(!repo.record_transition(&bin_id, &removed).await.unwrap());
+    let replaced = transition(LifecycleState::Damaged, LifecycleState::Replaced, Some(replacement));
+    assert!(repo.record_transition(&bin_id, &replaced).await.unwrap());
+    stored(repo, &StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::ok(), Utc::now())).await;
+
+    let lifecycle = repo.bin_lifecycle(&bin_id).await.unwrap().unwrap();
+    assert_eq!((lifecycle.state, lifecycle.replaced_by), (LifecycleState::Replaced, Some(replacement)));
+    let trail: Vec<LifecycleState> = lifecycle.history.iter().map(|transition| transition.to).collect();
+    assert_eq!(trail, vec![LifecycleState::Damaged, LifecycleState::Replaced]);
+    assert_eq!(repo.location_bins(&location_id).await.unwrap()[0].lifecycle, LifecycleState::Replaced);
+
+    let unknown = Uuid::new_v4();
+    assert!(repo.bin_lifecycle(&unknown).await.unwrap().is_none());
+    assert!(!repo.record_transition(&unknown, &damaged).await.unwrap());
+}
+
+async fn incidents_are_listed_by_kind(repo: &impl Fixture) {
+    let bin_id = Uuid::new_v4();
+    repo.create_bin(bin_id).await;
+    let now = Utc::now();
+    let incident = |details, minutes_ago| IncidentReport {
+        report_id: Uuid::new_v4(),
+        bin_id,
+        details,
+        observed_at: now - Duration::minutes(minutes_ago),
+        received_at: now,
+    };
+    let older = incident(ReportDetails::Damage { part: DamagedPart::Lid, description: None }, 20);
+    let newer = incident(ReportDetails::Damage { part: DamagedPart::Wheels, description: Some("Two missing".to_string()) }, 10);
+    let fire = incident(ReportDetails::Fire { still_burning: false }, 10);
+    for report in [&older, &newer, &fire] {
+        repo.add_incident(report).await.unwrap();
+    }
+
+    // Other runs may have left incidents of the same kind behind
+    let listed = |kind| async move {
+        let incidents = repo.incidents(kind, now - Duration::minutes(30), 1000).await.unwrap();
+        incidents.into_iter().filter(|incident| incident.bin_id == bin_id).collect::<Vec<_>>()
+    };
+    assert_eq!(listed(ReportKind::Damage).await, vec![newer.clone(), older]);
+    assert_eq!(listed(ReportKind::Fire).await, vec![fire]);
+    let recent = repo.incidents(ReportKind::Damage, now - Duration::minutes(15), 1000).await.unwrap();
+    assert!(recent.iter().all(|incident| incident.observed_at >= now - Duration::minutes(15)));
+}
+
+async fn rollups_add_up_per_location_and_district(repo: &impl Fixture) {
+    // A district of its own, as other runs may have rolled up the same day
+    let district = format!("Praha {}", Uuid::new_v4());
+    let location = Location {
+        id: Uuid::new_v4(),
+        address: "Náměstí Míru 1, Praha".to_string(),
+        latitude: 50.075,
+        longitude: 14.437,
+        name: "Náměstí Míru".to_string(),
+        district: Some(district.clone()),
+    };
+    let keeps_locations = repo.create_location(&location).await;
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    let day = Resolution::Daily.period_start(Utc::now()) - Duration::days(1);
+    let next_day = day + Duration::days(1);
+    for (bin_id, value, hours) in [(first, 10, 8), (second, 4, 9)] {
+        repo.create_placed_bin(bin_id, Some(location.id)).await;
+        let report = StatusReport::sensor(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), day + Duration::hours(hours));
+        repo.add_report(&report).await.unwrap();
+    }
+
+    for bin_id in [first, second] {
+        let timeline = repo.bin_timeline(&bin_id, day, next_day).await.unwrap();
+        let placement = repo.bin_placement(&bin_id).await.unwrap();
+        let update = RollupUpdate::plan(&bin_rollups(bin_id, &timeline, day, next_day), &[], &placement);
+        assert!(repo.apply_rollup_update(&update).await.unwrap());
+    }
+
+    let daily = |scope| async move { repo.rollups(&scope, Resolution::Daily, day, next_day).await.unwrap() };
+    let at_location = daily(RollupScope::Location(location.id)).await;
+    let stats = &at_location[0].stats;
+    assert_eq!((stats.reports_count, stats.min, stats.max, stats.sum), (2, 4, 10, 14));
+    let in_district = daily(RollupScope::District(district)).await;
+    if keeps_locations {
+        assert_eq!(in_district[0].stats, *stats);
+    } else {
+        assert!(in_district.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::config::retry::RetryConfig;
+    use aws_sdk_dynamodb::Client;
+
+    use crate::domain::estimator::FillEstimator;
+    use crate::domain::QRCode;
+    use crate::infrastructure::dynamodb::connect;
+    use crate::infrastructure::schema::{self, TableNames, TableSchema};
+    use crate::infrastructure::single_table::copy_two_table_layout;
+
+    async fn dynamodb_local() -> Client {
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let endpoint = std::env::var("DYNAMODB_LOCAL_URL").unwrap_or_else(|_| "http://localhost:8000".to_string());
+        connect(Some(&endpoint), RetryConfig::standard()).await
+    }
+
+    async fn provision(client: &Client, tables: Vec<TableSchema>) {
+        for table in tables {
+            for change in schema::plan(&table, None).unwrap() {
+                schema::apply(client, &table, &change).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_repository_conforms() {
+        check_all(&InMemoryRepository::new()).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DynamoDB Local on localhost:8000"]
+    async fn test_two_table_repository_conforms_against_dynamodb_local() {
+        let client = dynamodb_local().await;
+        let tables = TableNames::with_prefix(&format!("conformance-{}-", Uuid::new_v4()));
+        provision(&client, schema::tables(&tables)).await;
+
+        check_all(&DynamoDbRepository::with_client(client, &tables, FillEstimator::default())).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DynamoDB Local on localhost:8000"]
+    async fn test_single_table_repository_conforms_against_dynamodb_local() {
+        let client = dynamodb_local().await;
+        let table = format!("conformance-{}-ecoscan", Uuid::new_v4());
+        let rollups_table = format!("conformance-{}-fill-rollups", Uuid::new_v4());
+        provision(&client, vec![schema::single_table(&table), schema::rollups_table(&rollups_table)]).await;
+
+        let repo = SingleTableRepository::new(client, table, FillEstimator::default()).with_rollups_table(rollups_table);
+        check_all(&repo).await;
+
+        let location = Location {
+            id: Uuid::new_v4(),
+            address: "Náměstí Míru 1, Praha".to_string(),
+            latitude: 50.075,
+            longitude: 14.437,
+            name: "Náměstí Míru".to_string(),
+            district: None,
+        };
+        let bin_id = Uuid::new_v4();
+        repo.put_location(&location).await.unwrap();
+        repo.create_bin(bin_id, Some(location.id)).await.unwrap();
+        repo.create_bin(Uuid::new_v4(), None).await.unwrap();
+
+        let placed = repo.location_with_bins(&location.id).await.unwrap().unwrap();
+        assert_eq!(placed.location.name, location.name);
+        assert_eq!(placed.bins.iter().map(|bin| bin.bin_id).collect::<Vec<_>>(), vec![bin_id]);
+        assert!(repo.location_with_bins(&Uuid::new_v4()).await.unwrap().is_none());
+
+        let qr_code = QRCode { id: Uuid::new_v4(), url: "https://ecoscan.cz/b/abc".to_string(), trash_bin_id: bin_id, created_at: Utc::now() };
+        repo.put_qr_code(&qr_code).await.unwrap();
+        assert_eq!(repo.bin_for_qr_code(&qr_code.id).await.unwrap(), Some(bin_id));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DynamoDB Local on localhost:8000"]
+    async fn test_copy_to_single_table_against_dynamodb_local() {
+        let client = dynamodb_local().await;
+        let prefix = format!("copy-{}-", Uuid::new_v4());
+        let tables = TableNames::with_prefix(&prefix);
+        let target = format!("{}ecoscan", prefix);
+        provision(&client, schema::tables(&tables)).await;
+        provision(&client, vec![schema::single_table(&target)]).await;
+
+        let two_table = DynamoDbRepository::with_client(client.clone(), &tables, FillEstimator::default());
+        let bin_id = Uuid::new_v4();
+        two_table.create_bin(bin_id, None).await.unwrap();
+        let now = Utc::now();
+        for (value, minutes_ago) in [(3, 20), (7, 10)] {
+            let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), now - Duration::minutes(minutes_ago));
+            two_table.update_status(&report).await.unwrap();
+            two_table.add_report(&report).await.unwrap();
+        }
+
+        let first = copy_two_table_layout(&client, &tables, &target).await.unwrap();
+        let again = copy_two_table_layout(&client, &tables, &target).await.unwrap();
+        assert_eq!((first.bins, first.reports), (1, 2));
+        assert_eq!(again, first);
+
+        let single = SingleTableRepository::new(client, target, FillEstimator::default());
+        assert_eq!(single.get_bin(&bin_id).await.unwrap(), two_table.get_bin(&bin_id).await.unwrap());
+
+        // The copied reports are in the bin's partition
+        let collection = CollectionEvent { bin_id, collected_at: now - Duration::minutes(15), received_at: now };
+        single.record_collection(&collection).await.unwrap();
+        assert_eq!(status(&single, &bin_id).await, 7);
+    }
+}
@@ -1,41 +1,82 @@
-use aws_sdk_dynamodb::{types::AttributeValue, Client, config::Builder};
+use aws_sdk_dynamodb::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_dynamodb::config::retry::RetryConfig;
+use aws_sdk_dynamodb::operation::update_item::UpdateItemError;
+use aws_sdk_dynamodb::{types::{AttributeValue, DeleteRequest, KeysAndAttributes, Put, TransactWriteItem, Update, WriteRequest}, Client, config::Builder};
 use aws_config::meta::region::RegionProviderChain;
-use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use uuid::Uuid;
 use async_trait::async_trait;
+use tracing::{info, instrument, warn};
 
-use crate::error::AppError;
-use crate::domain::{BinRepository, BinStatus};
+use crate::error::{AppError, DatabaseError};
+use crate::domain::backup::{BackupRepository, ExportPage, Record, Section};
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::consistency::{BinHistoryRepository, BinPage, ResumeToken, StoredBin};
+use crate::domain::device_health::{DeviceHealth, DeviceHealthRepository};
+use crate::domain::estimator::{BinAggregate, FillEstimator};
+use crate::domain::history::BinEvent;
+use crate::domain::incident::{IncidentReport, IncidentRepository, ReportKind};
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleState, LifecycleTransition};
+use crate::domain::location::{LocationRepository, PlacedBin};
+use crate::domain::retention::RetentionRepository;
+use crate::domain::rollup::{DailyRollup, Placement, Resolution, Rollup, RollupRepository, RollupScope, RollupUpdate, Timeline};
+use crate::domain::sensor::{BinCalibration, Device, DeviceRepository};
+use crate::domain::{
+    BinProfile, BinProfileRepository, BinRepository, BinStatus, ReportSource, StatusChange, StatusReport,
+};
+use crate::infrastructure::schema::{TableNames, LOCATION_INDEX, REPORT_KIND_INDEX};
+use crate::infrastructure::items::{
+    to_item, BinItem, CollectionItem, DeviceHealthItem, DeviceItem, IncidentItem, LogItem, ReportItem, RollupItem, TransitionItem, BIN_SCHEMA_VERSION,
+    ROLLUP_SCHEMA_VERSION, SCHEMA_VERSION_ATTRIBUTE,
+};
 
 pub struct DynamoDbRepository {
     client: Client,
     bins_table: String,
     reports_table: String,
+    devices_table: String,
+    rollups_table: String,
+    estimator: FillEstimator,
 }
 
 impl DynamoDbRepository {
     pub async fn new() -> Result<Self, AppError> {
-        let region_provider = RegionProviderChain::default_provider().or_else("eu-central-1");
-        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
-            .region(region_provider)
-            .load()
-            .await;
+        // Retries happen in ResilientRepository, where the circuit breaker sees them
+        let endpoint_url = std::env::var("DYNAMODB_ENDPOINT_URL").ok();
+        let client = connect(endpoint_url.as_deref(), RetryConfig::disabled()).await;
+
+        Ok(Self::with_client(client, &TableNames::from_env(), estimator_from_env()?))
+    }
+
+    pub fn with_client(client: Client, tables: &TableNames, estimator: FillEstimator) -> Self {
+        Self {
+            client,
+            bins_table: tables.bins.clone(),
+            reports_table: tables.reports.clone(),
+            devices_table: tables.devices.clone(),
+            rollups_table: tables.rollups.clone(),
+            estimator,
+        }
+    }
+
+    /// Registers a bin with an empty estimate. An existing bin is left as is.
+    pub async fn create_bin(&self, bin_id: Uuid, location_id: Option<Uuid>) -> Result<(), AppError> {
+        let bin = BinItem { location_id, ..BinItem::new(bin_id) };
+        let result = self.client
+            .put_item()
+            .table_name(&self.bins_table)
+            .set_item(Some(to_item(&bin)?))
+            .condition_expression("attribute_not_exists(binId)")
+            .send()
+            .await
+            .map_err(db_error);
 
-        let mut builder = Builder::from(&config);
-        
-        // Check if we're running in local development mode
-        if let Ok(endpoint_url) = std::env::var("DYNAMODB_ENDPOINT_URL") {
-            builder = builder.endpoint_url(endpoint_url);
+        match result {
+            Ok(_) | Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(()),
+            Err(e) => Err(e),
         }
-        
-        let client = Client::from_conf(builder.build());
-        
-        let bins_table = std::env::var("TRASH_BINS_TABLE")
-            .unwrap_or_else(|_| "trash-bins".to_string());
-        let reports_table = std::env::var("STATUS_REPORTS_TABLE")
-            .unwrap_or_else(|_| "status-reports".to_string());
-            
-        Ok(Self { client, bins_table, reports_table })
     }
 
     pub async fn get_average_status(&self, bin_id: &Uuid) -> Result<f64, AppError> {
@@ -45,98 +86,1369 @@ impl DynamoDbRepository {
             .key("binId", AttributeValue::S(bin_id.to_string()))
             .send()
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(db_error)?;
 
-        let item = result.item().ok_or_else(|| AppError::DatabaseError("Bin not found".to_string()))?;
-        
-        let status = item.get("status")
-            .and_then(|v| v.as_n().ok())
-            .and_then(|n| n.parse::<i32>().ok())
-            .unwrap_or(0);
-            
-        let reports_count = item.get("reportsCount")
-            .and_then(|v| v.as_n().ok())
-            .and_then(|n| n.parse::<i32>().ok())
-            .unwrap_or(0);
+        let item = result.item().ok_or_else(|| AppError::BinNotFound(bin_id.to_string()))?;
+        let bin = BinItem::parse(item)?;
 
-        if reports_count == 0 {
+        if bin.reports_count == 0 {
             return Ok(0.0);
         }
 
-        Ok(status as f64)
+        Ok(f64::from(bin.status))
     }
 }
 
+/// How often a report is reapplied when the bin changes between reading
+/// and writing its estimate.
+const MAX_UPDATE_ATTEMPTS: u32 = 3;
+
 #[async_trait]
 impl BinRepository for DynamoDbRepository {
-    async fn update_status(
-        &self,
-        bin_id: &Uuid,
-        status: BinStatus,
-        timestamp: DateTime<Utc>,
-    ) -> Result<(), AppError> {
-        let status_value = status.value();
-        
-        // First get current status and count
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", bin_id = %bin_id))]
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
         let result = self.client
             .get_item()
             .table_name(&self.bins_table)
             .key("binId", AttributeValue::S(bin_id.to_string()))
             .send()
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
-        let item = result.item().ok_or_else(|| AppError::DatabaseError("Bin not found".to_string()))?;
-        
-        let current_status = item.get("status")
-            .and_then(|v| v.as_n().ok())
-            .and_then(|n| n.parse::<i32>().ok())
-            .unwrap_or(0);
-            
-        let reports_count = item.get("reportsCount")
-            .and_then(|v| v.as_n().ok())
-            .and_then(|n| n.parse::<i32>().ok())
-            .unwrap_or(0);
-
-        // Calculate new average
-        let new_average = if reports_count == 0 {
-            status_value
-        } else {
-            ((current_status * reports_count) + status_value) / (reports_count + 1)
+            .map_err(db_error)?;
+
+        Ok(result.item().map(BinItem::parse).transpose()?.map(|bin| bin.aggregate()))
+    }
+
+    /// Applies the report to the stored estimate with a write conditioned
+    /// on the estimate it read, so a concurrent report or collection is not
+    /// overwritten. On a conflict the estimate is read again and the report
+    /// reapplied, up to `MAX_UPDATE_ATTEMPTS` times.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %report.bin_id))]
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        let mut attempt = 1;
+        loop {
+            match self.try_update_status(report).await {
+                Err(AppError::DatabaseError(DatabaseError::Conflict(reason))) if attempt < MAX_UPDATE_ATTEMPTS => {
+                    warn!(bin_id = %report.bin_id, attempt, "Bin changed while applying a report, rereading: {}", reason);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "PutItem", bin_id = %report.bin_id))]
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+        self.client
+            .put_item()
+            .table_name(&self.reports_table)
+            .set_item(Some(to_item(&ReportItem::from(report))?))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Rebuilds the estimate from the reports observed since the collection.
+    /// The write is conditioned on the stored collection time, so a newer
+    /// collection recorded concurrently wins.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %event.bin_id))]
+    async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(event.bin_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+        let item = result.item().ok_or_else(|| AppError::BinNotFound(event.bin_id.to_string()))?;
+
+        // The log comes first, so a failed update below is repaired from it
+        self.client
+            .put_item()
+            .table_name(&self.reports_table)
+            .set_item(Some(to_item(&CollectionItem::from(event))?))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        if BinItem::parse(item)?.last_collected_at.is_some_and(|collected_at| collected_at >= event.collected_at) {
+            info!("Ignoring collection of bin {} older than the last one", event.bin_id);
+            return Ok(());
+        }
+
+        let mut aggregate = BinAggregate::collected(event.collected_at);
+        for logged in self.events_between(&event.bin_id, Some(event.collected_at), None).await? {
+            if let BinEvent::Reported(report) = logged {
+                aggregate.apply_report(&self.estimator, &report);
+            }
+        }
+
+        let result = self.client
+            .update_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(event.bin_id.to_string()))
+            .update_expression("SET #s = :s, #u = :u, #ws = :ws, #wt = :wt, #rc = :rc, #c = :c, #v = :v")
+            .condition_expression("attribute_not_exists(#c) OR #c < :c")
+            .expression_attribute_names("#s", "status")
+            .expression_attribute_names("#u", "lastUpdated")
+            .expression_attribute_names("#ws", "weightedSum")
+            .expression_attribute_names("#wt", "weightTotal")
+            .expression_attribute_names("#rc", "reportsCount")
+            .expression_attribute_names("#v", SCHEMA_VERSION_ATTRIBUTE)
+            .expression_attribute_names("#c", "lastCollectedAt")
+            .expression_attribute_values(":s", AttributeValue::N(aggregate.status().value().to_string()))
+            .expression_attribute_values(":u", AttributeValue::S(aggregate.last_observed_at.unwrap_or(event.collected_at).to_rfc3339()))
+            .expression_attribute_values(":ws", AttributeValue::N(aggregate.weighted_sum.to_string()))
+            .expression_attribute_values(":wt", AttributeValue::N(aggregate.weight_total.to_string()))
+            .expression_attribute_values(":rc", AttributeValue::N(aggregate.reports_count.to_string()))
+            .expression_attribute_values(":v", AttributeValue::N(BIN_SCHEMA_VERSION.to_string()))
+            .expression_attribute_values(":c", AttributeValue::S(event.collected_at.to_rfc3339()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.as_service_error(), Some(UpdateItemError::ConditionalCheckFailedException(_))) => {
+                info!("A newer collection of bin {} was recorded concurrently", event.bin_id);
+                Ok(())
+            }
+            Err(e) => Err(db_error(e)),
+        }
+    }
+
+    /// Loads every bin with `BatchGetItem`, then writes each bin's update
+    /// and its reports in one `TransactWriteItems` call. The bin update is
+    /// conditioned on the report count read, so a concurrent single update
+    /// fails the group instead of being overwritten.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "TransactWriteItems", groups = groups.len()))]
+    async fn record_batch(&self, groups: &[ReportGroup]) -> Vec<Result<(), AppError>> {
+        let aggregates = match self.get_aggregates(groups.iter().map(|group| group.bin_id)).await {
+            Ok(aggregates) => aggregates,
+            Err(e) => {
+                // Every group failed the same way, keep the classification
+                let error = match e {
+                    AppError::DatabaseError(error) => error,
+                    other => DatabaseError::Permanent(other.to_string()),
+                };
+                return groups.iter().map(|_| Err(error.clone().into())).collect();
+            }
         };
-        
+
+        let mut results = Vec::with_capacity(groups.len());
+        for group in groups {
+            results.push(match aggregates.get(&group.bin_id) {
+                Some(aggregate) => self.write_group(group, aggregate.clone()).await,
+                None => Err(AppError::BinNotFound(group.bin_id.to_string())),
+            });
+        }
+        results
+    }
+}
+
+impl DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", bin_id = %bin_id))]
+    async fn events_between(
+        &self,
+        bin_id: &Uuid,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        let mut events = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let query = self.client
+                .query()
+                .table_name(&self.reports_table)
+                .expression_attribute_values(":b", AttributeValue::S(bin_id.to_string()));
+            // Logged keys start with the observation time, followed by `#`,
+            // so `~` sorts after every key of the instant `until`
+            let query = match (since, until) {
+                (Some(since), Some(until)) => query
+                    .key_condition_expression("binId = :b AND createdAt BETWEEN :t AND :u")
+                    .expression_attribute_values(":t", AttributeValue::S(since.to_rfc3339()))
+                    .expression_attribute_values(":u", AttributeValue::S(format!("{}~", until.to_rfc3339()))),
+                (Some(since), None) => query
+                    .key_condition_expression("binId = :b AND createdAt >= :t")
+                    .expression_attribute_values(":t", AttributeValue::S(since.to_rfc3339())),
+                (None, Some(until)) => query
+                    .key_condition_expression("binId = :b AND createdAt <= :u")
+                    .expression_attribute_values(":u", AttributeValue::S(format!("{}~", until.to_rfc3339()))),
+                (None, None) => query.key_condition_expression("binId = :b"),
+            };
+            let result = query
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                events.extend(LogItem::parse(item)?.event()?.filter(|event| until.is_none_or(|until| event.at() <= until)));
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(events)
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "BatchGetItem"))]
+    async fn get_aggregates(
+        &self,
+        bin_ids: impl Iterator<Item = Uuid>,
+    ) -> Result<HashMap<Uuid, BinAggregate>, AppError> {
+        let keys: Vec<HashMap<String, AttributeValue>> = bin_ids
+            .map(|bin_id| HashMap::from([("binId".to_string(), AttributeValue::S(bin_id.to_string()))]))
+            .collect();
+        let mut aggregates = HashMap::new();
+
+        // BatchGetItem reads at most 100 keys per call
+        for chunk in keys.chunks(100) {
+            let mut pending = Some(
+                KeysAndAttributes::builder()
+                    .set_keys(Some(chunk.to_vec()))
+                    .consistent_read(true)
+                    .build()
+                    .map_err(|e| AppError::InternalError(e.to_string()))?,
+            );
+
+            while let Some(request) = pending.take() {
+                let result = self.client
+                    .batch_get_item()
+                    .request_items(&self.bins_table, request)
+                    .send()
+                    .await
+                    .map_err(db_error)?;
+
+                for item in result.responses().and_then(|tables| tables.get(&self.bins_table)).into_iter().flatten() {
+                    let bin = BinItem::parse(item)?;
+                    aggregates.insert(bin.bin_id, bin.aggregate());
+                }
+                pending = result
+                    .unprocessed_keys()
+                    .and_then(|tables| tables.get(&self.bins_table))
+                    .filter(|keys| !keys.keys().is_empty())
+                    .cloned();
+            }
+        }
+        Ok(aggregates)
+    }
+
+    async fn write_group(&self, group: &ReportGroup, mut aggregate: BinAggregate) -> Result<(), AppError> {
+        let expected_count = aggregate.reports_count;
+        for report in &group.reports {
+            aggregate.apply_report(&self.estimator, report);
+        }
+        let last_updated = aggregate
+            .last_observed_at
+            .or(aggregate.collected_at)
+            .ok_or_else(|| AppError::InvalidRequest("Empty report group".to_string()))?;
+
+        let bin_update = Update::builder()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(group.bin_id.to_string()))
+            .update_expression("SET #s = :s, #u = :u, #ws = :ws, #wt = :wt, #rc = :rc, #v = :v")
+            .condition_expression("attribute_exists(binId) AND (attribute_not_exists(#rc) OR #rc = :expected)")
+            .expression_attribute_names("#s", "status")
+            .expression_attribute_names("#u", "lastUpdated")
+            .expression_attribute_names("#ws", "weightedSum")
+            .expression_attribute_names("#wt", "weightTotal")
+            .expression_attribute_names("#rc", "reportsCount")
+            .expression_attribute_names("#v", SCHEMA_VERSION_ATTRIBUTE)
+            .expression_attribute_values(":s", AttributeValue::N(aggregate.status().value().to_string()))
+            .expression_attribute_values(":u", AttributeValue::S(last_updated.to_rfc3339()))
+            .expression_attribute_values(":ws", AttributeValue::N(aggregate.weighted_sum.to_string()))
+            .expression_attribute_values(":wt", AttributeValue::N(aggregate.weight_total.to_string()))
+            .expression_attribute_values(":rc", AttributeValue::N(aggregate.reports_count.to_string()))
+            .expression_attribute_values(":v", AttributeValue::N(BIN_SCHEMA_VERSION.to_string()))
+            .expression_attribute_values(":expected", AttributeValue::N(expected_count.to_string()))
+            .build()
+            .map_err(|e| AppError::InternalError(e.to_string()))?;
+
+        let mut items = vec![TransactWriteItem::builder().update(bin_update).build()];
+        for report in &group.reports {
+            let put = Put::builder()
+                .table_name(&self.reports_table)
+                .set_item(Some(to_item(&ReportItem::from(report))?))
+                .build()
+                .map_err(|e| AppError::InternalError(e.to_string()))?;
+            items.push(TransactWriteItem::builder().put(put).build());
+        }
+
         self.client
+            .transact_write_items()
+            .set_transact_items(Some(items))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BinHistoryRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Scan", limit))]
+    async fn scan_bins(&self, resume: Option<&ResumeToken>, limit: usize) -> Result<BinPage, AppError> {
+        let start_key = resume
+            .map(|token| HashMap::from([("binId".to_string(), AttributeValue::S(token.to_string()))]));
+        let result = self.client
+            .scan()
+            .table_name(&self.bins_table)
+            .limit(i32::try_from(limit).unwrap_or(i32::MAX))
+            .set_exclusive_start_key(start_key)
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let mut bins = Vec::with_capacity(result.items().len());
+        for item in result.items() {
+            let bin = BinItem::parse(item)?;
+            bins.push(StoredBin {
+                bin_id: bin.bin_id,
+                // Validated by parse
+                status: BinStatus::new(bin.status)?,
+                aggregate: bin.aggregate(),
+            });
+        }
+        let next = match result.last_evaluated_key().and_then(|key| string_attr(key, "binId")) {
+            Some(bin_id) => Some(bin_id.parse()?),
+            None => None,
+        };
+        Ok(BinPage { bins, next })
+    }
+
+    async fn bin_events(
+        &self,
+        bin_id: &Uuid,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        self.events_between(bin_id, since, until).await
+    }
+
+    /// Conditioned on the report count and collection time scanned, so a
+    /// report or collection recorded since is not overwritten.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %stored.bin_id))]
+    async fn repair_bin(&self, stored: &StoredBin, recomputed: &BinAggregate) -> Result<bool, AppError> {
+        let mut update = self.client
             .update_item()
             .table_name(&self.bins_table)
-            .key("binId", AttributeValue::S(bin_id.to_string()))
-            .update_expression("SET #s = :s, #u = :u, #rc = #rc + :one")
+            .key("binId", AttributeValue::S(stored.bin_id.to_string()))
             .expression_attribute_names("#s", "status")
             .expression_attribute_names("#u", "lastUpdated")
+            .expression_attribute_names("#ws", "weightedSum")
+            .expression_attribute_names("#wt", "weightTotal")
             .expression_attribute_names("#rc", "reportsCount")
-            .expression_attribute_values(":s", AttributeValue::N(new_average.to_string()))
-            .expression_attribute_values(":u", AttributeValue::S(timestamp.to_rfc3339()))
-            .expression_attribute_values(":one", AttributeValue::N("1".to_string()))
+            .expression_attribute_names("#v", SCHEMA_VERSION_ATTRIBUTE)
+            .expression_attribute_names("#c", "lastCollectedAt")
+            .expression_attribute_values(":s", AttributeValue::N(recomputed.status().value().to_string()))
+            .expression_attribute_values(":ws", AttributeValue::N(recomputed.weighted_sum.to_string()))
+            .expression_attribute_values(":wt", AttributeValue::N(recomputed.weight_total.to_string()))
+            .expression_attribute_values(":rc", AttributeValue::N(recomputed.reports_count.to_string()))
+            .expression_attribute_values(":v", AttributeValue::N(BIN_SCHEMA_VERSION.to_string()))
+            .expression_attribute_values(":expected", AttributeValue::N(stored.aggregate.reports_count.to_string()));
+
+        let mut set = "SET #s = :s, #ws = :ws, #wt = :wt, #rc = :rc, #v = :v".to_string();
+        if let Some(last_updated) = recomputed.last_observed_at {
+            set.push_str(", #u = :u");
+            update = update.expression_attribute_values(":u", AttributeValue::S(last_updated.to_rfc3339()));
+        }
+        // A collection in the log that never reached the bin
+        if let Some(collected_at) = recomputed.collected_at.filter(|at| Some(*at) != stored.aggregate.collected_at) {
+            set.push_str(", #c = :nc");
+            update = update.expression_attribute_values(":nc", AttributeValue::S(collected_at.to_rfc3339()));
+        }
+        if recomputed.last_observed_at.is_none() {
+            set.push_str(" REMOVE #u");
+        }
+        let update = update.update_expression(set);
+        let update = match stored.aggregate.collected_at {
+            Some(collected_at) => update
+                .condition_expression("(attribute_not_exists(#rc) OR #rc = :expected) AND #c = :c")
+                .expression_attribute_values(":c", AttributeValue::S(collected_at.to_rfc3339())),
+            None => update.condition_expression(
+                "attribute_exists(binId) AND (attribute_not_exists(#rc) OR #rc = :expected) AND attribute_not_exists(#c)",
+            ),
+        };
+
+        match update.send().await.map_err(db_error) {
+            Ok(_) => Ok(true),
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl BackupRepository for DynamoDbRepository {
+    /// Scans `trash-bins` and `status-reports`. The two-table layout keeps
+    /// no locations or QR codes, so those sections are empty.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Scan", section = section.as_str()))]
+    async fn export_page(&self, section: Section, after: Option<&str>, limit: usize) -> Result<ExportPage, AppError> {
+        let (table, key) = match section {
+            Section::Locations | Section::QrCodes => return Ok(ExportPage::default()),
+            Section::Bins => (&self.bins_table, &["binId"][..]),
+            Section::Log => (&self.reports_table, &["binId", "createdAt"][..]),
+        };
+        let result = self.client
+            .scan()
+            .table_name(table)
+            .limit(i32::try_from(limit).unwrap_or(i32::MAX))
+            .set_exclusive_start_key(after.map(|token| parse_key_token(token, key)).transpose()?)
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let mut records = Vec::with_capacity(result.items().len());
+        for item in result.items() {
+            records.push(match section {
+                Section::Bins => Record::Bin(BinItem::parse(item)?.record()?),
+                _ => LogItem::parse(item)?.record()?,
+            });
+        }
+        let next = result.last_evaluated_key().and_then(|last| key_token(last, key));
+        Ok(ExportPage { records, next })
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "PutItem"))]
+    async fn import_record(&self, record: &Record) -> Result<(), AppError> {
+        let (table, item) = match record {
+            Record::Header { .. } => return Ok(()),
+            Record::Location(_) | Record::QrCode(_) => {
+                return Err(AppError::InvalidRequest(
+                    "The two-table layout keeps no locations or QR codes; import into the single-table layout".to_string(),
+                ))
+            }
+            Record::Bin(bin) => (&self.bins_table, to_item(&BinItem::from(bin))?),
+            Record::Report(report) => (&self.reports_table, to_item(&ReportItem::from(report))?),
+            Record::Collection(event) => (&self.reports_table, to_item(&CollectionItem::from(event))?),
+            Record::Incident(incident) => (&self.reports_table, to_item(&IncidentItem::from(incident))?),
+        };
+        self.client
+            .put_item()
+            .table_name(table)
+            .set_item(Some(item))
             .send()
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(db_error)?;
         Ok(())
     }
+}
+
+#[async_trait]
+impl RetentionRepository for DynamoDbRepository {
+    async fn reports_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<StatusReport>, AppError> {
+        let mut reports = Vec::new();
+        for entry in self.log_before(bin_id, before).await? {
+            if let LogItem::Report(report) = entry {
+                reports.push(report.report()?);
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Reports stored before the report id joined the sort key are deleted
+    /// by their observation time too, which is safe as every report
+    /// observed then is archived together.
+    async fn delete_reports(&self, bin_id: &Uuid, reports: &[StatusReport]) -> Result<(), AppError> {
+        let keys = reports
+            .iter()
+            .flat_map(|report| [ReportItem::sort_key(report), ReportItem::legacy_sort_key(report.observed_at)])
+            .collect();
+        self.delete_log_entries(bin_id, keys).await
+    }
+
+    async fn incidents_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<IncidentReport>, AppError> {
+        let mut incidents = Vec::new();
+        for entry in self.log_before(bin_id, before).await? {
+            if let LogItem::Incident(incident) = entry {
+                incidents.push(incident.incident()?);
+            }
+        }
+        Ok(incidents)
+    }
+
+    /// Incidents stored before the report id joined the sort key are
+    /// deleted by their observation time and kind too.
+    async fn delete_incidents(&self, bin_id: &Uuid, incidents: &[IncidentReport]) -> Result<(), AppError> {
+        let keys = incidents
+            .iter()
+            .flat_map(|incident| [IncidentItem::sort_key(incident), IncidentItem::legacy_sort_key(incident)])
+            .collect();
+        self.delete_log_entries(bin_id, keys).await
+    }
 
-    async fn add_report(
+    async fn daily_rollups(&self, bin_id: &Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyRollup>, AppError> {
+        let from = from.and_time(NaiveTime::MIN).and_utc();
+        let to = to.and_time(NaiveTime::MIN).and_utc() + chrono::Duration::days(1);
+        let rollups = self.rollups(&RollupScope::Bin(*bin_id), Resolution::Daily, from, to).await?;
+        Ok(rollups
+            .into_iter()
+            .map(|rollup| DailyRollup { bin_id: *bin_id, day: rollup.period_start.date_naive(), stats: rollup.stats })
+            .collect())
+    }
+}
+
+impl DynamoDbRepository {
+    async fn try_update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(report.bin_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let item = result.item().ok_or_else(|| AppError::BinNotFound(report.bin_id.to_string()))?;
+
+        let mut aggregate = BinItem::parse(item)?.aggregate();
+        let previous = aggregate.status();
+        let expected_count = aggregate.reports_count;
+        let collected_at = aggregate.collected_at;
+
+        if !aggregate.apply_report(&self.estimator, report) {
+            info!(
+                "Report for bin {} observed at {} predates the last collection, keeping estimate",
+                report.bin_id,
+                report.observed_at.to_rfc3339()
+            );
+            return Ok(StatusChange::unchanged(previous));
+        }
+
+        let update = self.client
+            .update_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(report.bin_id.to_string()))
+            .update_expression("SET #s = :s, #u = :u, #ws = :ws, #wt = :wt, #rc = :rc, #v = :v")
+            .expression_attribute_names("#s", "status")
+            .expression_attribute_names("#u", "lastUpdated")
+            .expression_attribute_names("#ws", "weightedSum")
+            .expression_attribute_names("#wt", "weightTotal")
+            .expression_attribute_names("#rc", "reportsCount")
+            .expression_attribute_names("#v", SCHEMA_VERSION_ATTRIBUTE)
+            .expression_attribute_names("#c", "lastCollectedAt")
+            .expression_attribute_values(":s", AttributeValue::N(aggregate.status().value().to_string()))
+            .expression_attribute_values(":u", AttributeValue::S(aggregate.last_observed_at.unwrap_or(report.observed_at).to_rfc3339()))
+            .expression_attribute_values(":ws", AttributeValue::N(aggregate.weighted_sum.to_string()))
+            .expression_attribute_values(":wt", AttributeValue::N(aggregate.weight_total.to_string()))
+            .expression_attribute_values(":rc", AttributeValue::N(aggregate.reports_count.to_string()))
+            .expression_attribute_values(":v", AttributeValue::N(BIN_SCHEMA_VERSION.to_string()))
+            .expression_attribute_values(":expected", AttributeValue::N(expected_count.to_string()));
+        // Bins written before reports were counted have no count to compare
+        let update = match collected_at {
+            Some(collected_at) => update
+                .condition_expression("attribute_exists(binId) AND (attribute_not_exists(#rc) OR #rc = :expected) AND #c = :c")
+                .expression_attribute_values(":c", AttributeValue::S(collected_at.to_rfc3339())),
+            None => update.condition_expression(
+                "attribute_exists(binId) AND (attribute_not_exists(#rc) OR #rc = :expected) AND attribute_not_exists(#c)",
+            ),
+        };
+        update.send().await.map_err(db_error)?;
+        Ok(StatusChange { previous, current: aggregate.status() })
+    }
+
+    /// The bin's report log entries observed before `before`, of every kind.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", bin_id = %bin_id))]
+    async fn log_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<LogItem>, AppError> {
+        let mut entries = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.reports_table)
+                .key_condition_expression("binId = :b AND createdAt < :t")
+                .expression_attribute_values(":b", AttributeValue::S(bin_id.to_string()))
+                .expression_attribute_values(":t", AttributeValue::S(before.to_rfc3339()))
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                entries.push(LogItem::parse(item)?);
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Deletes the bin's log entries under these sort keys in batches of
+    /// 25, retrying the keys DynamoDB leaves unprocessed. Deleting a
+    /// missing item succeeds.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "BatchWriteItem", bin_id = %bin_id))]
+    async fn delete_log_entries(&self, bin_id: &Uuid, keys: BTreeSet<String>) -> Result<(), AppError> {
+        for chunk in keys.into_iter().collect::<Vec<_>>().chunks(25) {
+            let mut requests = chunk
+                .iter()
+                .map(|key| {
+                    let delete = DeleteRequest::builder()
+                        .key("binId", AttributeValue::S(bin_id.to_string()))
+                        .key("createdAt", AttributeValue::S(key.clone()))
+                        .build()
+                        .map_err(|e| AppError::InternalError(e.to_string()))?;
+                    Ok(WriteRequest::builder().delete_request(delete).build())
+                })
+                .collect::<Result<Vec<_>, AppError>>()?;
+
+            let mut attempt = 0;
+            while !requests.is_empty() {
+                if attempt > 0 {
+                    tokio::time::sleep(std::time::Duration::from_millis(50 << attempt.min(6))).await;
+                }
+                let output = self.client
+                    .batch_write_item()
+                    .request_items(&self.reports_table, requests)
+                    .send()
+                    .await
+                    .map_err(db_error)?;
+                requests = output
+                    .unprocessed_items
+                    .and_then(|mut tables| tables.remove(&self.reports_table))
+                    .unwrap_or_default();
+                attempt += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill history events of the bin matching `condition` on `createdAt`,
+    /// which uses `:t` and `:u`, in sort key order. `limit` stops after that
+    /// many.
+    async fn log_entries(
         &self,
         bin_id: &Uuid,
-        status: BinStatus,
-        timestamp: DateTime<Utc>,
-    ) -> Result<(), AppError> {
+        condition: &str,
+        values: &[(&str, DateTime<Utc>)],
+        forward: bool,
+        limit: Option<i32>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        let mut events = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let mut query = self.client
+                .query()
+                .table_name(&self.reports_table)
+                .key_condition_expression(format!("binId = :b AND {}", condition))
+                .expression_attribute_values(":b", AttributeValue::S(bin_id.to_string()))
+                .scan_index_forward(forward)
+                .set_limit(limit)
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key);
+            for (name, value) in values {
+                query = query.expression_attribute_values(*name, AttributeValue::S(value.to_rfc3339()));
+            }
+            let result = query.send().await.map_err(db_error)?;
+
+            for item in result.items() {
+                events.extend(LogItem::parse(item)?.event()?);
+            }
+
+            // Incidents do not count towards the limit, so keep reading past them
+            match result.last_evaluated_key() {
+                Some(key) if limit.is_none_or(|limit| events.len() < limit as usize) => start_key = Some(key.clone()),
+                _ => break,
+            }
+        }
+        if let Some(limit) = limit {
+            events.truncate(limit as usize);
+        }
+        Ok(events)
+    }
+}
+
+/// `#name = :name`, also matching a missing attribute when `value` is
+/// zero, as rollups merged into with `ADD` may lack one.
+fn stat_condition(name: &str, value: i64) -> String {
+    if value == 0 {
+        format!("(attribute_not_exists(#{0}) OR #{0} = :{0})", name)
+    } else {
+        format!("#{0} = :{0}", name)
+    }
+}
+
+#[async_trait]
+impl RollupRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", bin_id = %bin_id))]
+    async fn bin_timeline(&self, bin_id: &Uuid, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Timeline, AppError> {
+        let before = self.log_entries(bin_id, "createdAt < :t", &[(":t", from)], false, Some(1)).await?;
+        // Entries at `to` carry a suffix and sort after it; older reports
+        // keyed by the time alone are dropped here
+        let events = self
+            .log_entries(bin_id, "createdAt BETWEEN :t AND :u", &[(":t", from), (":u", to)], true, None)
+            .await?
+            .into_iter()
+            .filter(|event| event.at() < to)
+            .collect();
+        let after = self.log_entries(bin_id, "createdAt >= :t", &[(":t", to)], true, Some(1)).await?;
+        Ok(Timeline { before: before.into_iter().next(), events, after: after.into_iter().next() })
+    }
+
+    /// The two-table layout keeps no locations, so bins have no district.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", bin_id = %bin_id))]
+    async fn bin_placement(&self, bin_id: &Uuid) -> Result<Placement, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let location_id = result.item().map(BinItem::parse).transpose()?.and_then(|bin| bin.location_id);
+        Ok(Placement { location_id, district: None })
+    }
+
+    async fn rollups(
+        &self,
+        scope: &RollupScope,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Rollup>, AppError> {
+        query_rollups(&self.client, &self.rollups_table, scope, resolution, from, to).await
+    }
+
+    async fn apply_rollup_update(&self, update: &RollupUpdate) -> Result<bool, AppError> {
+        write_rollup_update(&self.client, &self.rollups_table, update).await
+    }
+}
+
+/// Stored rollups of the series with periods starting in `[from, to)`.
+/// Both layouts keep their rollups in a table of their own.
+#[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", resolution = %resolution))]
+pub(crate) async fn query_rollups(
+    client: &Client,
+    table: &str,
+    scope: &RollupScope,
+    resolution: Resolution,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Rollup>, AppError> {
+    let mut rollups = Vec::new();
+    // BETWEEN rejects an empty range
+    if from >= to {
+        return Ok(rollups);
+    }
+    let first = resolution.period_key(resolution.period_start(from));
+    let last = resolution.period_key(resolution.period_start(to - chrono::Duration::nanoseconds(1)));
+    let mut start_key = None;
+
+    loop {
+        let result = client
+            .query()
+            .table_name(table)
+            .key_condition_expression("series = :s AND periodStart BETWEEN :from AND :to")
+            .expression_attribute_values(":s", AttributeValue::S(scope.series(resolution)))
+            .expression_attribute_values(":from", AttributeValue::S(first.clone()))
+            .expression_attribute_values(":to", AttributeValue::S(last.clone()))
+            .consistent_read(true)
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        for item in result.items() {
+            let rollup = RollupItem::parse(item)?.rollup()?;
+            if rollup.period_start >= from {
+                rollups.push(rollup);
+            }
+        }
+
+        match result.last_evaluated_key() {
+            Some(key) => start_key = Some(key.clone()),
+            None => break,
+        }
+    }
+    Ok(rollups)
+}
+
+/// Lowers `min` and raises `max` of the shared rollups first, which
+/// is safe to repeat, then writes the bin rollups and adds to the
+/// shared counts in one `TransactWriteItems` call. Each bin rollup is
+/// conditioned on the stats it was recomputed over.
+#[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "TransactWriteItems", bins = update.bins.len()))]
+pub(crate) async fn write_rollup_update(client: &Client, table: &str, update: &RollupUpdate) -> Result<bool, AppError> {
+    if update.is_empty() {
+        return Ok(true);
+    }
+
+    for rollup in update.shared.iter().filter(|rollup| rollup.stats.reports_count > 0) {
+        let item = RollupItem::from(rollup);
+        for (name, value, comparison) in [("min", item.min, ">"), ("max", item.max, "<")] {
+            let result = client
+                .update_item()
+                .table_name(table)
+                .key("series", AttributeValue::S(item.series.clone()))
+                .key("periodStart", AttributeValue::S(item.period_start.clone()))
+                .update_expression("SET #m = :m")
+                .condition_expression(format!("attribute_not_exists(#m) OR #m {} :m", comparison))
+                .expression_attribute_names("#m", name)
+                .expression_attribute_values(":m", AttributeValue::N(value.to_string()))
+                .send()
+                .await
+                .map_err(db_error);
+            match result {
+                // Already lower or higher
+                Ok(_) | Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    let mut items = Vec::with_capacity(update.bins.len() + update.shared.len());
+    for change in &update.bins {
+        let put = Put::builder()
+            .table_name(table)
+            .set_item(Some(to_item(&RollupItem::from(&change.rollup))?));
+        let put = match &change.stored {
+            None => put.condition_expression("attribute_not_exists(series)"),
+            Some(stored) => put
+                .condition_expression(format!(
+                    "{} AND {} AND {}",
+                    stat_condition("reportsCount", i64::from(stored.reports_count)),
+                    stat_condition("sum", stored.sum),
+                    stat_condition("fullSeconds", stored.full_seconds),
+                ))
+                .expression_attribute_names("#reportsCount", "reportsCount")
+                .expression_attribute_names("#sum", "sum")
+                .expression_attribute_names("#fullSeconds", "fullSeconds")
+                .expression_attribute_values(":reportsCount", AttributeValue::N(stored.reports_count.to_string()))
+                .expression_attribute_values(":sum", AttributeValue::N(stored.sum.to_string()))
+                .expression_attribute_values(":fullSeconds", AttributeValue::N(stored.full_seconds.to_string())),
+        };
+        let put = put.build().map_err(|e| AppError::InternalError(e.to_string()))?;
+        items.push(TransactWriteItem::builder().put(put).build());
+    }
+    for rollup in &update.shared {
+        let item = RollupItem::from(rollup);
+        let add = Update::builder()
+            .table_name(table)
+            .key("series", AttributeValue::S(item.series))
+            .key("periodStart", AttributeValue::S(item.period_start))
+            .update_expression("SET #v = :v ADD #rc :rc, #sum :sum, #full :full")
+            .expression_attribute_names("#v", SCHEMA_VERSION_ATTRIBUTE)
+            .expression_attribute_names("#rc", "reportsCount")
+            .expression_attribute_names("#sum", "sum")
+            .expression_attribute_names("#full", "fullSeconds")
+            .expression_attribute_values(":v", AttributeValue::N(ROLLUP_SCHEMA_VERSION.to_string()))
+            .expression_attribute_values(":rc", AttributeValue::N(item.reports_count.to_string()))
+            .expression_attribute_values(":sum", AttributeValue::N(item.sum.to_string()))
+            .expression_attribute_values(":full", AttributeValue::N(item.full_seconds.to_string()))
+            .build()
+            .map_err(|e| AppError::InternalError(e.to_string()))?;
+        items.push(TransactWriteItem::builder().update(add).build());
+    }
+
+    let result = client
+        .transact_write_items()
+        .set_transact_items(Some(items))
+        .send()
+        .await
+        .map_err(db_error);
+    match result {
+        Ok(_) => Ok(true),
+        Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[async_trait]
+impl BinProfileRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", bin_id = %bin_id))]
+    async fn bin_profile(&self, bin_id: &Uuid) -> Result<Option<BinProfile>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        Ok(result.item().map(BinItem::parse).transpose()?.map(|bin| bin.profile()))
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %bin_id))]
+    async fn set_bin_profile(&self, bin_id: &Uuid, profile: &BinProfile) -> Result<(), AppError> {
+        let (expression, values) = profile_update(profile);
+        let result = self.client
+            .update_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .update_expression(expression)
+            .set_expression_attribute_values(Some(values))
+            .condition_expression("attribute_exists(binId)")
+            .send()
+            .await
+            .map_err(db_error);
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Err(AppError::BinNotFound(bin_id.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// An update expression writing `profile` onto a bin item, removing the
+/// capacity and model when they are unknown, with its values.
+pub(crate) fn profile_update(profile: &BinProfile) -> (String, HashMap<String, AttributeValue>) {
+    let mut set = vec!["wasteType = :w"];
+    let mut remove = Vec::new();
+    let mut values = HashMap::from([(":w".to_string(), AttributeValue::S(profile.waste_type.to_string()))]);
+    match profile.capacity_liters {
+        Some(capacity) => {
+            set.push("capacityLiters = :c");
+            values.insert(":c".to_string(), AttributeValue::N(capacity.to_string()));
+        }
+        None => remove.push("capacityLiters"),
+    }
+    match &profile.container_model {
+        Some(model) => {
+            set.push("containerModel = :m");
+            values.insert(":m".to_string(), AttributeValue::S(model.clone()));
+        }
+        None => remove.push("containerModel"),
+    }
+
+    let mut expression = format!("SET {}", set.join(", "));
+    if !remove.is_empty() {
+        expression.push_str(&format!(" REMOVE {}", remove.join(", ")));
+    }
+    (expression, values)
+}
+
+/// The parts of a conditional update recording a lifecycle transition.
+pub(crate) struct TransitionUpdate {
+    pub expression: String,
+    pub condition: String,
+    pub values: HashMap<String, AttributeValue>,
+}
+
+/// Sets the bin's state and appends the transition to its trail, on the
+/// condition that the bin, keyed by `key_attribute`, exists and is still in
+/// the `from` state. Bins without a stored state are active.
+pub(crate) fn transition_update(transition: &LifecycleTransition, key_attribute: &str) -> Result<TransitionUpdate, AppError> {
+    let entry = AttributeValue::M(to_item(&TransitionItem::from(transition))?);
+    let mut values = HashMap::from([
+        (":from".to_string(), AttributeValue::S(transition.from.to_string())),
+        (":to".to_string(), AttributeValue::S(transition.to.to_string())),
+        (":entry".to_string(), AttributeValue::L(vec![entry])),
+        (":none".to_string(), AttributeValue::L(Vec::new())),
+    ]);
+
+    let mut expression = "SET lifecycleState = :to, \
+        lifecycleHistory = list_append(if_not_exists(lifecycleHistory, :none), :entry)"
+        .to_string();
+    match transition.replaced_by {
+        Some(replaced_by) => {
+            expression.push_str(", replacedBy = :r");
+            values.insert(":r".to_string(), AttributeValue::S(replaced_by.to_string()));
+        }
+        None => expression.push_str(" REMOVE replacedBy"),
+    }
+
+    let in_state = if transition.from == LifecycleState::default() {
+        "(attribute_not_exists(lifecycleState) OR lifecycleState = :from)"
+    } else {
+        "lifecycleState = :from"
+    };
+    let condition = format!("attribute_exists({}) AND {}", key_attribute, in_state);
+    Ok(TransitionUpdate { expression, condition, values })
+}
+
+#[async_trait]
+impl LifecycleRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", bin_id = %bin_id))]
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        Ok(result.item().map(BinItem::parse).transpose()?.map(|bin| bin.lifecycle()))
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %bin_id, to = %transition.to))]
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError> {
+        let update = transition_update(transition, "binId")?;
+        let result = self.client
+            .update_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .update_expression(update.expression)
+            .condition_expression(update.condition)
+            .set_expression_attribute_values(Some(update.values))
+            .send()
+            .await
+            .map_err(db_error);
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl IncidentRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "PutItem", bin_id = %report.bin_id))]
+    async fn add_incident(&self, report: &IncidentReport) -> Result<(), AppError> {
         self.client
             .put_item()
             .table_name(&self.reports_table)
-            .item("binId", AttributeValue::S(bin_id.to_string()))
-            .item("createdAt", AttributeValue::S(timestamp.to_rfc3339()))
-            .item("status", AttributeValue::N(status.value().to_string()))
+            .set_item(Some(to_item(&IncidentItem::from(report))?))
             .send()
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(db_error)?;
         Ok(())
     }
+
+    /// Incidents are found through the report kind index, which only
+    /// holds incidents. Index reads are eventually consistent.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", kind = kind.as_str()))]
+    async fn incidents(&self, kind: ReportKind, since: DateTime<Utc>, limit: usize) -> Result<Vec<IncidentReport>, AppError> {
+        let mut incidents = Vec::new();
+        let mut start_key = None;
+
+        while incidents.len() < limit {
+            let result = self.client
+                .query()
+                .table_name(&self.reports_table)
+                .index_name(REPORT_KIND_INDEX)
+                .key_condition_expression("reportKind = :k AND createdAt >= :t")
+                .expression_attribute_values(":k", AttributeValue::S(kind.as_str().to_string()))
+                .expression_attribute_values(":t", AttributeValue::S(since.to_rfc3339()))
+                .scan_index_forward(false)
+                .limit(i32::try_from(limit - incidents.len()).unwrap_or(i32::MAX))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                incidents.push(IncidentItem::parse(item)?.incident()?);
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(incidents)
+    }
+}
+
+#[async_trait]
+impl LocationRepository for DynamoDbRepository {
+    /// Bins are found through the location index, which only holds bins
+    /// with a location. Index reads are eventually consistent.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", location_id = %location_id))]
+    async fn location_bins(&self, location_id: &Uuid) -> Result<Vec<PlacedBin>, AppError> {
+        let mut bins = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.bins_table)
+                .index_name(LOCATION_INDEX)
+                .key_condition_expression("locationId = :l")
+                .expression_attribute_values(":l", AttributeValue::S(location_id.to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                let bin = BinItem::parse(item)?;
+                bins.push(PlacedBin { bin_id: bin.bin_id, aggregate: bin.aggregate(), profile: bin.profile(), lifecycle: bin.lifecycle_state });
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(bins)
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", device_id = device_id))]
+    async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.devices_table)
+            .key("deviceId", AttributeValue::S(device_id.to_string()))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let Some(item) = result.item() else {
+            return Ok(None);
+        };
+
+        Ok(Some(DeviceItem::parse(item)?.into()))
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", bin_id = %bin_id))]
+    async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.bins_table)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .projection_expression("emptyDistanceMm, fullDistanceMm")
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let Some(item) = result.item() else {
+            return Ok(None);
+        };
+
+        match (number_attr(item, "emptyDistanceMm"), number_attr(item, "fullDistanceMm")) {
+            (Some(empty), Some(full)) => BinCalibration::new(empty, full).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl DeviceHealthRepository for DynamoDbRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", device_id = device_id))]
+    async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.devices_table)
+            .key("deviceId", AttributeValue::S(device_id.to_string()))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        match result.item() {
+            Some(item) => Ok(DeviceHealthItem::parse(item)?.health()?),
+            None => Ok(None),
+        }
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "PutItem", device_id = %health.device_id))]
+    async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+        let flags = serde_json::to_string(&health.flags)
+            .map_err(|e| AppError::InternalError(e.to_string()))?;
+
+        let mut request = self.client
+            .update_item()
+            .table_name(&self.devices_table)
+            .key("deviceId", AttributeValue::S(health.device_id.clone()))
+            .expression_attribute_values(":bin", AttributeValue::S(health.bin_id.to_string()))
+            .expression_attribute_values(":seen", AttributeValue::S(health.last_seen.to_rfc3339()))
+            .expression_attribute_values(":dist", AttributeValue::N(health.last_distance_mm.to_string()))
+            .expression_attribute_values(":changed", AttributeValue::S(health.distance_changed_at.to_rfc3339()))
+            .expression_attribute_values(":flags", AttributeValue::S(flags));
+        let mut assignments = vec![
+            "binId = :bin",
+            "lastSeen = :seen",
+            "lastDistanceMm = :dist",
+            "distanceChangedAt = :changed",
+            "healthFlags = :flags",
+        ];
+        if let Some(battery_mv) = health.battery_mv {
+            assignments.push("batteryMv = :battery");
+            request = request.expression_attribute_values(":battery", AttributeValue::N(battery_mv.to_string()));
+        }
+        if let Some(rssi_dbm) = health.rssi_dbm {
+            assignments.push("rssiDbm = :rssi");
+            request = request.expression_attribute_values(":rssi", AttributeValue::N(rssi_dbm.to_string()));
+        }
+        if let Some(firmware_version) = &health.firmware_version {
+            assignments.push("firmwareVersion = :fw");
+            request = request.expression_attribute_values(":fw", AttributeValue::S(firmware_version.clone()));
+        }
+
+        request
+            .update_expression(format!("SET {}", assignments.join(", ")))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Scan"))]
+    async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+        let mut devices = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self.client
+                .scan()
+                .table_name(&self.devices_table)
+                .filter_expression("attribute_exists(lastSeen)")
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                if let Some(health) = DeviceHealthItem::parse(item)?.health()? {
+                    devices.push(health);
+                }
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(devices)
+    }
+}
+
+/// A client for `endpoint_url`, e.g. LocalStack or DynamoDB Local, or for
+/// the regional endpoint when it is `None`.
+pub async fn connect(endpoint_url: Option<&str>, retry: RetryConfig) -> Client {
+    let region_provider = RegionProviderChain::default_provider().or_else("eu-central-1");
+    let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        .region(region_provider)
+        .load()
+        .await;
+
+    let mut builder = Builder::from(&config).retry_config(retry);
+    if let Some(endpoint_url) = endpoint_url {
+        builder = builder.endpoint_url(endpoint_url);
+    }
+    Client::from_conf(builder.build())
+}
+
+/// Classifies an SDK failure by whether, and how safely, it can be retried.
+pub(crate) fn db_error<E, R>(error: SdkError<E, R>) -> AppError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    R: std::fmt::Debug,
+{
+    let message = aws_sdk_dynamodb::error::DisplayErrorContext(&error).to_string();
+    let classified = match &error {
+        SdkError::TimeoutError(_) => DatabaseError::Timeout(message),
+        SdkError::DispatchFailure(failure) if failure.is_timeout() => DatabaseError::Timeout(message),
+        SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => DatabaseError::Unavailable(message),
+        _ => match error.code() {
+            Some(
+                "ThrottlingException"
+                | "ProvisionedThroughputExceededException"
+                | "RequestLimitExceeded",
+            ) => DatabaseError::Throttled(message),
+            Some(
+                "ConditionalCheckFailedException"
+                | "TransactionConflictException"
+                | "TransactionCanceledException"
+                | "TransactionInProgressException",
+            ) => DatabaseError::Conflict(message),
+            Some("InternalServerError" | "ServiceUnavailable") => DatabaseError::Unavailable(message),
+            _ => DatabaseError::Permanent(message),
+        },
+    };
+    AppError::DatabaseError(classified)
+}
+
+pub(crate) fn string_attr(item: &HashMap<String, AttributeValue>, name: &str) -> Option<String> {
+    item.get(name).and_then(|v| v.as_s().ok()).cloned()
+}
+
+/// A scan's last evaluated key as `value/value`, in the order of `names`,
+/// for resume tokens. Key values never contain a slash.
+pub(crate) fn key_token(key: &HashMap<String, AttributeValue>, names: &[&str]) -> Option<String> {
+    let values = names.iter().map(|name| string_attr(key, name)).collect::<Option<Vec<_>>>()?;
+    Some(values.join("/"))
+}
+
+pub(crate) fn parse_key_token(token: &str, names: &[&str]) -> Result<HashMap<String, AttributeValue>, AppError> {
+    let values: Vec<&str> = token.splitn(names.len(), '/').collect();
+    if values.len() != names.len() {
+        return Err(AppError::InvalidRequest(format!("Invalid resume key: {}", token)));
+    }
+    Ok(names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| (name.to_string(), AttributeValue::S(value.to_string())))
+        .collect())
+}
+
+pub(crate) fn number_attr<T: std::str::FromStr>(item: &HashMap<String, AttributeValue>, name: &str) -> Option<T> {
+    item.get(name)
+        .and_then(|v| v.as_n().ok())
+        .and_then(|n| n.parse::<T>().ok())
+}
+
+/// The estimator with report weights from `CITIZEN_REPORT_WEIGHT` and
+/// `SENSOR_REPORT_WEIGHT`, if set.
+pub fn estimator_from_env() -> Result<FillEstimator, AppError> {
+    let defaults = FillEstimator::default();
+    FillEstimator::new(
+        weight_from_env("CITIZEN_REPORT_WEIGHT", defaults.weight(ReportSource::Citizen))?,
+        weight_from_env("SENSOR_REPORT_WEIGHT", defaults.weight(ReportSource::Sensor))?,
+    )
+}
+
+fn weight_from_env(name: &str, default: f64) -> Result<f64, AppError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse::<f64>()
+            .map_err(|_| AppError::InternalError(format!("{} must be a number, got {}", name, value))),
+        Err(_) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::error::ErrorMetadata;
+
+    fn service_error(code: &str) -> AppError {
+        let error = UpdateItemError::generic(ErrorMetadata::builder().code(code).message("test").build());
+        db_error(SdkError::<UpdateItemError, ()>::service_error(error, ()))
+    }
+
+    #[test]
+    fn test_db_error_classification() {
+        assert!(matches!(service_error("ProvisionedThroughputExceededException"), AppError::DatabaseError(DatabaseError::Throttled(_))));
+        assert!(matches!(service_error("TransactionCanceledException"), AppError::DatabaseError(DatabaseError::Conflict(_))));
+        assert!(matches!(service_error("InternalServerError"), AppError::DatabaseError(DatabaseError::Unavailable(_))));
+        assert!(matches!(service_error("ResourceNotFoundException"), AppError::DatabaseError(DatabaseError::Permanent(_))));
+
+        let timeout = db_error(SdkError::<UpdateItemError, ()>::timeout_error("deadline"));
+        assert!(matches!(timeout, AppError::DatabaseError(DatabaseError::Timeout(_))));
+        assert!(timeout.is_transient());
+    }
+
+    #[test]
+    fn test_key_token_round_trip() {
+        let key = HashMap::from([
+            ("binId".to_string(), AttributeValue::S(Uuid::nil().to_string())),
+            ("createdAt".to_string(), AttributeValue::S("2024-03-20T12:00:00+00:00#collection".to_string())),
+        ]);
+
+        let token = key_token(&key, &["binId", "createdAt"]).unwrap();
+
+        assert_eq!(parse_key_token(&token, &["binId", "createdAt"]).unwrap(), key);
+        assert!(parse_key_token(&Uuid::nil().to_string(), &["binId", "createdAt"]).is_err());
+    }
 }
@@ -0,0 +1,473 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::device_health::{DeviceHealth, DeviceHealthRepository};
+use crate::domain::estimator::BinAggregate;
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleTransition};
+use crate::domain::sensor::{BinCalibration, Device, DeviceRepository};
+use crate::domain::{BinRepository, StatusChange, StatusReport};
+use crate::error::{AppError, DatabaseError};
+
+/// Which faults to inject and how often. Rates are probabilities per call,
+/// or per group for `record_batch`. All decisions come from one RNG seeded
+/// with `seed`, so a failing run can be replayed exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultConfig {
+    pub seed: u64,
+    /// Extra latency drawn uniformly from this range.
+    pub latency: Option<(Duration, Duration)>,
+    /// Calls rejected with `Throttled` before reaching the backend.
+    pub throttle_rate: f64,
+    /// Calls failed with `Unavailable` before reaching the backend.
+    pub error_rate: f64,
+    /// Calls that reach the backend and succeed but report `Timeout`, as
+    /// when a response is lost on the way back.
+    pub lost_response_rate: f64,
+    /// Limits faults to these operations; all operations when empty.
+    pub operations: Vec<&'static str>,
+}
+
+impl FaultConfig {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            latency: None,
+            throttle_rate: 0.0,
+            error_rate: 0.0,
+            lost_response_rate: 0.0,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn with_latency(self, min: Duration, max: Duration) -> Self {
+        Self { latency: Some((min, max.max(min))), ..self }
+    }
+
+    pub fn with_throttle_rate(self, throttle_rate: f64) -> Self {
+        Self { throttle_rate, ..self }
+    }
+
+    pub fn with_error_rate(self, error_rate: f64) -> Self {
+        Self { error_rate, ..self }
+    }
+
+    pub fn with_lost_response_rate(self, lost_response_rate: f64) -> Self {
+        Self { lost_response_rate, ..self }
+    }
+
+    pub fn targeting(self, operations: &[&'static str]) -> Self {
+        Self { operations: operations.to_vec(), ..self }
+    }
+
+    fn applies_to(&self, operation: &str) -> bool {
+        self.operations.is_empty() || self.operations.contains(&operation)
+    }
+}
+
+/// Faults injected so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FaultStats {
+    pub calls: u32,
+    pub throttled: u32,
+    pub errors: u32,
+    pub lost_responses: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fault {
+    None,
+    Throttle,
+    Error,
+    LoseResponse,
+}
+
+/// Wraps any repository and makes it misbehave on purpose, for chaos and
+/// failure-path tests.
+pub struct FaultInjectingRepository<R> {
+    inner: R,
+    config: FaultConfig,
+    rng: Mutex<StdRng>,
+    stats: Mutex<FaultStats>,
+}
+
+impl<R> FaultInjectingRepository<R> {
+    pub fn new(inner: R, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+            config,
+            stats: Mutex::new(FaultStats::default()),
+        }
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        *self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Draws the latency and fault for one call up front, so the RNG is
+    /// never held across an await.
+    fn draw(&self, operation: &str) -> (Duration, Fault) {
+        let mut stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        stats.calls += 1;
+        if !self.config.applies_to(operation) {
+            return (Duration::ZERO, Fault::None);
+        }
+
+        let mut rng = self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let latency = match self.config.latency {
+            Some((min, max)) => min + (max - min).mul_f64(rng.gen_range(0.0..=1.0)),
+            None => Duration::ZERO,
+        };
+        let roll: f64 = rng.gen();
+        let fault = if roll < self.config.throttle_rate {
+            stats.throttled += 1;
+            Fault::Throttle
+        } else if roll < self.config.throttle_rate + self.config.error_rate {
+            stats.errors += 1;
+            Fault::Error
+        } else if roll < self.config.throttle_rate + self.config.error_rate + self.config.lost_response_rate {
+            stats.lost_responses += 1;
+            Fault::LoseResponse
+        } else {
+            Fault::None
+        };
+        if fault != Fault::None {
+            debug!("Injecting {:?} into {}", fault, operation);
+        }
+        (latency, fault)
+    }
+
+    fn fault_error(operation: &str, fault: Fault) -> AppError {
+        match fault {
+            Fault::Throttle => DatabaseError::Throttled(format!("injected into {}", operation)),
+            Fault::Error => DatabaseError::Unavailable(format!("injected into {}", operation)),
+            _ => DatabaseError::Timeout(format!("injected lost response from {}", operation)),
+        }
+        .into()
+    }
+
+    async fn call<T, Fut>(&self, operation: &str, call: Fut) -> Result<T, AppError>
+    where
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let (latency, fault) = self.draw(operation);
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        match fault {
+            Fault::None => call.await,
+            Fault::Throttle | Fault::Error => Err(Self::fault_error(operation, fault)),
+            Fault::LoseResponse => {
+                call.await?;
+                Err(Self::fault_error(operation, fault))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<R: BinRepository> BinRepository for FaultInjectingRepository<R> {
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        self.call("get_bin", self.inner.get_bin(bin_id)).await
+    }
+
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        self.call("update_status", self.inner.update_status(report)).await
+    }
+
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+        self.call("add_report", self.inner.add_report(report)).await
+    }
+
+    async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+        self.call("record_collection", self.inner.record_collection(event)).await
+    }
+
+    /// Draws a fault per group, so one batch can partially fail. Groups
+    /// that pass are handed to the backend together.
+    async fn record_batch(&self, groups: &[ReportGroup]) -> Vec<Result<(), AppError>> {
+        let draws: Vec<(Duration, Fault)> = groups.iter().map(|_| self.draw("record_batch")).collect();
+        if let Some(latency) = draws.iter().map(|(latency, _)| *latency).max().filter(|latency| !latency.is_zero()) {
+            tokio::time::sleep(latency).await;
+        }
+
+        let passed: Vec<ReportGroup> = groups
+            .iter()
+            .zip(&draws)
+            .filter(|(_, (_, fault))| matches!(fault, Fault::None | Fault::LoseResponse))
+            .map(|(group, _)| group.clone())
+            .collect();
+        let mut outcomes = self.inner.record_batch(&passed).await.into_iter();
+
+        draws
+            .into_iter()
+            .map(|(_, fault)| match fault {
+                Fault::Throttle | Fault::Error => Err(Self::fault_error("record_batch", fault)),
+                Fault::None => outcomes.next().unwrap_or(Ok(())),
+                Fault::LoseResponse => {
+                    outcomes.next().unwrap_or(Ok(()))?;
+                    Err(Self::fault_error("record_batch", fault))
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<R: LifecycleRepository> LifecycleRepository for FaultInjectingRepository<R> {
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+        self.call("bin_lifecycle", self.inner.bin_lifecycle(bin_id)).await
+    }
+
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError> {
+        self.call("record_transition", self.inner.record_transition(bin_id, transition)).await
+    }
+}
+
+#[async_trait]
+impl<R: DeviceRepository + Send + Sync> DeviceRepository for FaultInjectingRepository<R> {
+    async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError> {
+        self.call("get_device", self.inner.get_device(device_id)).await
+    }
+
+    async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError> {
+        self.call("get_calibration", self.inner.get_calibration(bin_id)).await
+    }
+}
+
+#[async_trait]
+impl<R: DeviceHealthRepository + Send + Sync> DeviceHealthRepository for FaultInjectingRepository<R> {
+    async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+        self.call("get_health", self.inner.get_health(device_id)).await
+    }
+
+    async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+        self.call("save_health", self.inner.save_health(health)).await
+    }
+
+    async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+        self.call("list_health", self.inner.list_health()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::batch::handle_batch_status_update;
+    use crate::application::collection::handle_collection;
+    use crate::application::context::AppContext;
+    use crate::application::handle_status_update;
+    use crate::domain::batch::BatchStatusRequest;
+    use crate::domain::collection::CollectionRequest;
+    use crate::domain::{BinStatus, StatusUpdateRequest};
+    use crate::infrastructure::memory::InMemoryRepository;
+    use crate::infrastructure::resilience::{BreakerPolicy, ResilientRepository, RetryPolicy};
+    use chrono::Utc;
+    use serde_json::json;
+    use shared::clock::SystemClock;
+    use std::sync::Arc;
+
+    type Stack = ResilientRepository<FaultInjectingRepository<InMemoryRepository>>;
+
+    /// The production decorator over a faulty in-memory backend. The
+    /// breaker is kept out of the way so every call reaches the faults.
+    fn stack(memory: &InMemoryRepository, faults: FaultConfig) -> Stack {
+        let retry = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            call_timeout: Duration::from_millis(500),
+        };
+        let breaker = BreakerPolicy { failure_threshold: u32::MAX, open_for: Duration::from_secs(1) };
+        ResilientRepository::with_policies(
+            FaultInjectingRepository::new(memory.clone(), faults),
+            retry,
+            breaker,
+            Arc::new(SystemClock),
+        )
+        .with_seed(1)
+    }
+
+    fn faults(repo: &Stack) -> FaultStats {
+        repo.inner().stats()
+    }
+
+    #[tokio::test]
+    async fn test_same_seed_injects_same_faults() {
+        let run = || async {
+            let memory = InMemoryRepository::new();
+            let bin_id = Uuid::new_v4();
+            memory.add_bin(bin_id);
+            let repo = FaultInjectingRepository::new(memory, FaultConfig::new(99).with_error_rate(0.5));
+            let mut outcomes = Vec::new();
+            for _ in 0..20 {
+                let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::ok(), Utc::now());
+                outcomes.push(repo.update_status(&report).await.is_ok());
+            }
+            (outcomes, repo.stats())
+        };
+
+        let (first, stats) = run().await;
+        assert_eq!(first, run().await.0);
+        assert_eq!(stats.calls, 20);
+        assert!(stats.errors > 0 && stats.errors < 20);
+    }
+
+    #[tokio::test]
+    async fn test_injected_latency_is_within_range() {
+        let memory = InMemoryRepository::new();
+        let config = FaultConfig::new(3).with_latency(Duration::from_millis(20), Duration::from_millis(30));
+        let repo = FaultInjectingRepository::new(memory, config);
+
+        let started = std::time::Instant::now();
+        repo.get_device("s-1").await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        // Timing the calls would make the upper bound flaky, so check the drawn delays
+        for _ in 0..100 {
+            let (latency, _) = repo.draw("get_device");
+            assert!((Duration::from_millis(20)..=Duration::from_millis(30)).contains(&latency), "{:?}", latency);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_absorb_throttling_without_double_counting() {
+        let memory = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        memory.add_bin(bin_id);
+        let repo = stack(&memory, FaultConfig::new(7).with_throttle_rate(0.3));
+        let ctx = AppContext::default();
+        let start = Utc::now() - chrono::Duration::hours(1);
+
+        for minute in 0..30 {
+            let request = StatusUpdateRequest {
+                bin_id,
+                status: BinStatus::new(minute % 11).unwrap(),
+                observed_at: Some(start + chrono::Duration::minutes(i64::from(minute))),
+            };
+            handle_status_update(&repo, &ctx, request).await.unwrap();
+        }
+
+        assert!(faults(&repo).throttled >= 5);
+        assert_eq!(memory.reports(&bin_id).len(), 30);
+        assert_eq!(memory.aggregate(&bin_id).unwrap().reports_count, 30);
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_failures_never_apply_a_report_twice() {
+        let memory = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        memory.add_bin(bin_id);
+        let config = FaultConfig::new(11).with_lost_response_rate(0.3).targeting(&["update_status"]);
+        let repo = stack(&memory, config);
+        let ctx = AppContext::default();
+        let start = Utc::now() - chrono::Duration::hours(1);
+
+        let mut succeeded = 0;
+        for minute in 0..30 {
+            let request = StatusUpdateRequest {
+                bin_id,
+                status: BinStatus::ok(),
+                observed_at: Some(start + chrono::Duration::minutes(minute)),
+            };
+            if handle_status_update(&repo, &ctx, request).await.is_ok() {
+                succeeded += 1;
+            }
+        }
+
+        // A lost response is reported, not retried, so each report is
+        // counted once. The report is still logged, so the log agrees with
+        // the estimate
+        let lost = faults(&repo).lost_responses;
+        assert!(lost > 0);
+        assert_eq!(succeeded + lost, 30);
+        assert_eq!(memory.aggregate(&bin_id).unwrap().reports_count, 30);
+        assert_eq!(memory.reports(&bin_id).len(), 30);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_writes_are_retried_through_lost_responses() {
+        let memory = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        memory.add_bin(bin_id);
+        let config = FaultConfig::new(5).with_lost_response_rate(0.3).targeting(&["add_report", "record_collection"]);
+        let repo = stack(&memory, config);
+        let ctx = AppContext::default();
+        let start = Utc::now() - chrono::Duration::hours(2);
+
+        for minute in 0..20 {
+            let request = StatusUpdateRequest {
+                bin_id,
+                status: BinStatus::full(),
+                observed_at: Some(start + chrono::Duration::minutes(minute)),
+            };
+            handle_status_update(&repo, &ctx, request).await.unwrap();
+        }
+        let collection = CollectionRequest { bin_id, collected_at: Some(start + chrono::Duration::minutes(10)) };
+        handle_collection(&repo, &ctx, collection).await.unwrap();
+
+        assert!(faults(&repo).lost_responses > 0);
+        assert_eq!(memory.reports(&bin_id).len(), 20);
+        assert_eq!(memory.aggregate(&bin_id).unwrap().reports_count, 10);
+    }
+
+    #[tokio::test]
+    async fn test_batch_groups_are_all_or_nothing_under_partial_failure() {
+        let memory = InMemoryRepository::new();
+        let bins: Vec<Uuid> = (0..10).map(|_| Uuid::new_v4()).collect();
+        for bin_id in &bins {
+            memory.add_bin(*bin_id);
+        }
+        let config = FaultConfig::new(21).with_error_rate(0.4).with_lost_response_rate(0.2);
+        let repo = stack(&memory, config);
+        let start = Utc::now() - chrono::Duration::hours(1);
+
+        let items = bins
+            .iter()
+            .flat_map(|bin_id| {
+                (0..3).map(move |minute| {
+                    json!({
+                        "bin_id": bin_id.to_string(),
+                        "status": {"value": 6},
+                        "client_timestamp": (start + chrono::Duration::minutes(minute)).to_rfc3339(),
+                    })
+                })
+            })
+            .collect();
+        let response = handle_batch_status_update(&repo, &AppContext::default(), BatchStatusRequest { items })
+            .await
+            .unwrap();
+
+        let stats = faults(&repo);
+        assert!(stats.errors > 0 && stats.lost_responses > 0);
+        assert!(response.accepted > 0 && response.rejected > 0);
+        for bin_id in &bins {
+            let stored = memory.reports(bin_id).len();
+            let counted = memory.aggregate(bin_id).unwrap().reports_count as usize;
+            assert!(stored == 0 || stored == 3, "bin {} partially stored", bin_id);
+            assert_eq!(stored, counted);
+
+            let accepted = response
+                .results
+                .iter()
+                .filter(|result| result.bin_id == Some(*bin_id) && result.success)
+                .count();
+            // Acknowledged groups are stored; lost responses may be stored
+            // without being acknowledged
+            assert!(accepted == 0 || accepted == stored);
+        }
+    }
+}
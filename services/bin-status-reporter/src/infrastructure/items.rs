@@ -0,0 +1,1176 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::domain::backup::{BinRecord, Record};
+use crate::domain::collection::CollectionEvent;
+use crate::domain::device_health::DeviceHealth;
+use crate::domain::estimator::BinAggregate;
+use crate::domain::history::BinEvent;
+use crate::domain::incident::{DamagedPart, IncidentReport, ReportDetails, ReportKind};
+use crate::domain::lifecycle::{BinLifecycle, LifecycleState, LifecycleTransition};
+use crate::domain::rollup::{FillStats, Rollup, RollupScope};
+use crate::domain::sensor::{BinCalibration, Device};
+use crate::domain::{BinProfile, BinStatus, Location, QRCode, ReportSource, StatusReport, WasteType};
+use crate::error::{AppError, MalformedItem};
+
+pub type Item = HashMap<String, AttributeValue>;
+
+pub const SCHEMA_VERSION_ATTRIBUTE: &str = "schemaVersion";
+
+/// Bins gained `weightedSum` and `weightTotal` in version 2.
+pub const BIN_SCHEMA_VERSION: u32 = 2;
+/// Reports gained `reportId`, `receivedAt` and `source` in version 2, and
+/// `observedAt` in version 3, when the report id joined the sort key.
+pub const REPORT_SCHEMA_VERSION: u32 = 3;
+pub const COLLECTION_SCHEMA_VERSION: u32 = 1;
+pub const INCIDENT_SCHEMA_VERSION: u32 = 1;
+pub const LOCATION_SCHEMA_VERSION: u32 = 1;
+pub const QR_CODE_SCHEMA_VERSION: u32 = 1;
+/// Rollups gained `fullSeconds` in version 2, and lost `binId`, which the
+/// series holds.
+pub const ROLLUP_SCHEMA_VERSION: u32 = 2;
+
+/// Items written before the attribute existed count as version 1.
+fn unversioned() -> u32 {
+    1
+}
+
+const LEGACY_REPORT_NAMESPACE: Uuid = Uuid::from_u128(0x1404306c_f197_4613_a619_06ce6f8b7730);
+
+/// The id of a report stored before ids were assigned, derived from its bin
+/// and observation time so every read gives the same one. It is name-based
+/// like a version 5 UUID, hashed with SHA-256 as version 8 allows.
+pub fn legacy_report_id(bin_id: Uuid, observed_at: DateTime<Utc>) -> Uuid {
+    let digest = Sha256::new()
+        .chain_update(LEGACY_REPORT_NAMESPACE.as_bytes())
+        .chain_update(bin_id.as_bytes())
+        .chain_update(observed_at.to_rfc3339().as_bytes())
+        .finalize();
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// The estimate, placement and calibration attributes of a bin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinItem {
+    pub bin_id: Uuid,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub status: i32,
+    pub reports_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weighted_sum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_total: Option<f64>,
+    #[serde(default, with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub last_collected_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub empty_distance_mm: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_distance_mm: Option<u32>,
+    #[serde(default)]
+    pub waste_type: WasteType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity_liters: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_model: Option<String>,
+    #[serde(default)]
+    pub lifecycle_state: LifecycleState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lifecycle_history: Vec<TransitionItem>,
+}
+
+impl BinItem {
+    /// A bin without reports.
+    pub fn new(bin_id: Uuid) -> Self {
+        Self {
+            bin_id,
+            schema_version: BIN_SCHEMA_VERSION,
+            location_id: None,
+            status: 0,
+            reports_count: 0,
+            weighted_sum: Some(0.0),
+            weight_total: Some(0.0),
+            last_updated: None,
+            last_collected_at: None,
+            empty_distance_mm: None,
+            full_distance_mm: None,
+            waste_type: WasteType::default(),
+            capacity_liters: None,
+            container_model: None,
+            lifecycle_state: LifecycleState::default(),
+            replaced_by: None,
+            lifecycle_history: Vec::new(),
+        }
+    }
+
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        let parsed: Self = from_item("bin", "binId", item)?;
+        parsed.upgrade()
+    }
+
+    /// Brings an older shape up to the current version in memory. The
+    /// upgraded shape is stored by the next write to the bin.
+    fn upgrade(mut self) -> Result<Self, MalformedItem> {
+        BinStatus::new(self.status).map_err(|e| self.malformed(e.to_string()))?;
+
+        if self.schema_version < 2 && (self.weighted_sum.is_none() || self.weight_total.is_none()) {
+            // Bins written before reports were weighted only carry the plain average
+            let legacy = BinAggregate::from_legacy(self.status, self.reports_count);
+            self.weighted_sum = Some(legacy.weighted_sum);
+            self.weight_total = Some(legacy.weight_total);
+        }
+        self.schema_version = self.schema_version.max(BIN_SCHEMA_VERSION);
+
+        match (self.weighted_sum, self.weight_total) {
+            (Some(sum), Some(total)) if sum >= 0.0 && total >= 0.0 => Ok(self),
+            _ => Err(self.malformed("missing or negative weightedSum and weightTotal".to_string())),
+        }
+    }
+
+    pub fn aggregate(&self) -> BinAggregate {
+        BinAggregate {
+            weighted_sum: self.weighted_sum.unwrap_or_default(),
+            weight_total: self.weight_total.unwrap_or_default(),
+            reports_count: self.reports_count,
+            last_observed_at: self.last_updated,
+            collected_at: self.last_collected_at,
+        }
+    }
+
+    pub fn profile(&self) -> BinProfile {
+        BinProfile {
+            waste_type: self.waste_type,
+            capacity_liters: self.capacity_liters,
+            container_model: self.container_model.clone(),
+        }
+    }
+
+    pub fn lifecycle(&self) -> BinLifecycle {
+        BinLifecycle {
+            state: self.lifecycle_state,
+            replaced_by: self.replaced_by,
+            history: self.lifecycle_history.iter().cloned().map(LifecycleTransition::from).collect(),
+        }
+    }
+
+    pub fn record(&self) -> Result<BinRecord, MalformedItem> {
+        let calibration = match (self.empty_distance_mm, self.full_distance_mm) {
+            (Some(empty), Some(full)) => Some(BinCalibration::new(empty, full).map_err(|e| self.malformed(e.to_string()))?),
+            _ => None,
+        };
+        Ok(BinRecord {
+            bin_id: self.bin_id,
+            location_id: self.location_id,
+            aggregate: self.aggregate(),
+            calibration,
+            profile: self.profile(),
+            lifecycle: self.lifecycle(),
+        })
+    }
+
+    fn malformed(&self, reason: String) -> MalformedItem {
+        MalformedItem { entity: "bin", key: self.bin_id.to_string(), reason }
+    }
+}
+
+impl From<&BinRecord> for BinItem {
+    fn from(record: &BinRecord) -> Self {
+        let aggregate = &record.aggregate;
+        Self {
+            bin_id: record.bin_id,
+            schema_version: BIN_SCHEMA_VERSION,
+            location_id: record.location_id,
+            status: aggregate.status().value(),
+            reports_count: aggregate.reports_count,
+            weighted_sum: Some(aggregate.weighted_sum),
+            weight_total: Some(aggregate.weight_total),
+            last_updated: aggregate.last_observed_at,
+            last_collected_at: aggregate.collected_at,
+            empty_distance_mm: record.calibration.as_ref().map(|calibration| calibration.empty_distance_mm),
+            full_distance_mm: record.calibration.as_ref().map(|calibration| calibration.full_distance_mm),
+            waste_type: record.profile.waste_type,
+            capacity_liters: record.profile.capacity_liters,
+            container_model: record.profile.container_model.clone(),
+            lifecycle_state: record.lifecycle.state,
+            replaced_by: record.lifecycle.replaced_by,
+            lifecycle_history: record.lifecycle.history.iter().map(TransitionItem::from).collect(),
+        }
+    }
+}
+
+/// An entry of a bin's lifecycle trail, stored in a list on the bin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionItem {
+    pub from: LifecycleState,
+    pub to: LifecycleState,
+    #[serde(with = "rfc3339")]
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<Uuid>,
+}
+
+impl From<&LifecycleTransition> for TransitionItem {
+    fn from(transition: &LifecycleTransition) -> Self {
+        Self {
+            from: transition.from,
+            to: transition.to,
+            at: transition.at,
+            actor: transition.actor.clone(),
+            reason: transition.reason.clone(),
+            replaced_by: transition.replaced_by,
+        }
+    }
+}
+
+impl From<TransitionItem> for LifecycleTransition {
+    fn from(item: TransitionItem) -> Self {
+        Self {
+            from: item.from,
+            to: item.to,
+            at: item.at,
+            actor: item.actor,
+            reason: item.reason,
+            replaced_by: item.replaced_by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportItem {
+    pub bin_id: Uuid,
+    /// The sort key orders the log by observation, not by arrival. See
+    /// `sort_key`.
+    pub created_at: String,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    #[serde(default, with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub observed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_id: Option<Uuid>,
+    #[serde(default, with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    pub status: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ReportSource>,
+}
+
+impl ReportItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        let parsed: Self = from_item("report", "binId", item)?;
+        parsed.upgrade()
+    }
+
+    /// The observation time with the report id as a suffix, so reports
+    /// observed at the same instant are kept apart while a redelivered
+    /// report lands on itself.
+    pub fn sort_key(report: &StatusReport) -> String {
+        format!("{}#{}", report.observed_at.to_rfc3339(), report.report_id)
+    }
+
+    /// The key reports were stored under before version 3: the observation
+    /// time alone.
+    pub fn legacy_sort_key(observed_at: DateTime<Utc>) -> String {
+        observed_at.to_rfc3339()
+    }
+
+    fn upgrade(mut self) -> Result<Self, MalformedItem> {
+        if self.schema_version < 3 {
+            let observed_at = DateTime::parse_from_rfc3339(&self.created_at)
+                .map_err(|e| self.malformed(format!("invalid createdAt: {}", e)))?;
+            self.observed_at = Some(observed_at.with_timezone(&Utc));
+        }
+        if self.schema_version < 2 {
+            // Reports stored before ids were assigned get one derived from
+            // their key, and the ones stored before arrival time was tracked
+            // were written on receipt
+            self.report_id = self.report_id.or(self.observed_at.map(|at| legacy_report_id(self.bin_id, at)));
+            self.received_at = self.received_at.or(self.observed_at);
+            self.source = Some(self.source.unwrap_or_default());
+        }
+        self.schema_version = self.schema_version.max(REPORT_SCHEMA_VERSION);
+        Ok(self)
+    }
+
+    pub fn report(self) -> Result<StatusReport, MalformedItem> {
+        Ok(StatusReport {
+            report_id: self.report_id.ok_or_else(|| self.malformed("missing reportId".to_string()))?,
+            bin_id: self.bin_id,
+            status: BinStatus::new(self.status).map_err(|e| self.malformed(e.to_string()))?,
+            source: self.source.ok_or_else(|| self.malformed("missing source".to_string()))?,
+            observed_at: self.observed_at.ok_or_else(|| self.malformed("missing observedAt".to_string()))?,
+            received_at: self.received_at.ok_or_else(|| self.malformed("missing receivedAt".to_string()))?,
+        })
+    }
+
+    fn malformed(&self, reason: String) -> MalformedItem {
+        MalformedItem { entity: "report", key: format!("{}/{}", self.bin_id, self.created_at), reason }
+    }
+}
+
+impl From<&StatusReport> for ReportItem {
+    fn from(report: &StatusReport) -> Self {
+        Self {
+            bin_id: report.bin_id,
+            created_at: Self::sort_key(report),
+            schema_version: REPORT_SCHEMA_VERSION,
+            observed_at: Some(report.observed_at),
+            report_id: Some(report.report_id),
+            received_at: Some(report.received_at),
+            status: report.status.value(),
+            source: Some(report.source),
+        }
+    }
+}
+
+/// A collection kept in the report log next to the reports, so a bin's
+/// history can be replayed from the log alone. Report items carry no
+/// `eventType`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItem {
+    pub bin_id: Uuid,
+    /// The collection time with a suffix, so it cannot replace a report
+    /// observed at the same instant.
+    pub created_at: String,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    pub event_type: String,
+    #[serde(with = "rfc3339")]
+    pub collected_at: DateTime<Utc>,
+    #[serde(with = "rfc3339")]
+    pub received_at: DateTime<Utc>,
+}
+
+pub const EVENT_TYPE_ATTRIBUTE: &str = "eventType";
+const COLLECTION_EVENT_TYPE: &str = "collection";
+const INCIDENT_EVENT_TYPE: &str = "incident";
+
+impl From<&CollectionEvent> for CollectionItem {
+    fn from(event: &CollectionEvent) -> Self {
+        Self {
+            bin_id: event.bin_id,
+            created_at: format!("{}#{}", event.collected_at.to_rfc3339(), COLLECTION_EVENT_TYPE),
+            schema_version: COLLECTION_SCHEMA_VERSION,
+            event_type: COLLECTION_EVENT_TYPE.to_string(),
+            collected_at: event.collected_at,
+            received_at: event.received_at,
+        }
+    }
+}
+
+/// A citizen report other than a fill level, kept in the report log next to
+/// the reports. Only the attributes of its kind are set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncidentItem {
+    pub bin_id: Uuid,
+    /// The observation time with the kind and report id as a suffix, so
+    /// incidents of a kind observed at the same instant are kept apart.
+    pub created_at: String,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    pub event_type: String,
+    /// Hash key of the `reportKind-index`.
+    pub report_kind: ReportKind,
+    pub report_id: Uuid,
+    #[serde(with = "rfc3339")]
+    pub observed_at: DateTime<Utc>,
+    #[serde(with = "rfc3339")]
+    pub received_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<DamagedPart>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waste_on_ground: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub still_burning: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub found: Vec<WasteType>,
+}
+
+impl IncidentItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        from_item("incident", "binId", item)
+    }
+
+    pub fn sort_key(report: &IncidentReport) -> String {
+        format!("{}#{}#{}", report.observed_at.to_rfc3339(), report.kind().as_str(), report.report_id)
+    }
+
+    /// The key incidents were first stored under, without the report id.
+    pub fn legacy_sort_key(report: &IncidentReport) -> String {
+        format!("{}#{}", report.observed_at.to_rfc3339(), report.kind().as_str())
+    }
+
+    pub fn incident(self) -> Result<IncidentReport, MalformedItem> {
+        let malformed = |reason: &str| MalformedItem {
+            entity: "incident",
+            key: format!("{}/{}", self.bin_id, self.created_at),
+            reason: reason.to_string(),
+        };
+        let details = match self.report_kind {
+            ReportKind::Fill => return Err(malformed("fill reports are not incidents")),
+            ReportKind::Damage => ReportDetails::Damage {
+                part: self.part.ok_or_else(|| malformed("missing part"))?,
+                description: self.description.clone(),
+            },
+            ReportKind::Overflow => ReportDetails::Overflow { waste_on_ground: self.waste_on_ground.unwrap_or_default() },
+            ReportKind::Fire => ReportDetails::Fire { still_burning: self.still_burning.unwrap_or_default() },
+            ReportKind::WrongSorting => ReportDetails::WrongSorting {
+                found: self.found.clone(),
+                description: self.description.clone(),
+            },
+        };
+        Ok(IncidentReport {
+            report_id: self.report_id,
+            bin_id: self.bin_id,
+            details,
+            observed_at: self.observed_at,
+            received_at: self.received_at,
+        })
+    }
+}
+
+impl From<&IncidentReport> for IncidentItem {
+    fn from(report: &IncidentReport) -> Self {
+        let kind = report.kind();
+        let mut item = Self {
+            bin_id: report.bin_id,
+            created_at: Self::sort_key(report),
+            schema_version: INCIDENT_SCHEMA_VERSION,
+            event_type: INCIDENT_EVENT_TYPE.to_string(),
+            report_kind: kind,
+            report_id: report.report_id,
+            observed_at: report.observed_at,
+            received_at: report.received_at,
+            part: None,
+            description: None,
+            waste_on_ground: None,
+            still_burning: None,
+            found: Vec::new(),
+        };
+        match &report.details {
+            ReportDetails::Fill { .. } => {}
+            ReportDetails::Damage { part, description } => {
+                item.part = Some(*part);
+                item.description = description.clone();
+            }
+            ReportDetails::Overflow { waste_on_ground } => item.waste_on_ground = Some(*waste_on_ground),
+            ReportDetails::Fire { still_burning } => item.still_burning = Some(*still_burning),
+            ReportDetails::WrongSorting { found, description } => {
+                item.found = found.clone();
+                item.description = description.clone();
+            }
+        }
+        item
+    }
+}
+
+/// An entry of the report log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogItem {
+    Report(ReportItem),
+    Collection(CollectionItem),
+    Incident(IncidentItem),
+}
+
+impl LogItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        match item.get(EVENT_TYPE_ATTRIBUTE) {
+            None => ReportItem::parse(item).map(LogItem::Report),
+            Some(AttributeValue::S(event_type)) if event_type == COLLECTION_EVENT_TYPE => {
+                from_item("collection", "binId", item).map(LogItem::Collection)
+            }
+            Some(AttributeValue::S(event_type)) if event_type == INCIDENT_EVENT_TYPE => {
+                IncidentItem::parse(item).map(LogItem::Incident)
+            }
+            Some(other) => Err(MalformedItem {
+                entity: "report log",
+                key: match item.get("binId") {
+                    Some(AttributeValue::S(key)) => key.clone(),
+                    _ => "without a key".to_string(),
+                },
+                reason: format!("unknown eventType {:?}", other),
+            }),
+        }
+    }
+
+    pub fn bin_id(&self) -> Uuid {
+        match self {
+            LogItem::Report(report) => report.bin_id,
+            LogItem::Collection(collection) => collection.bin_id,
+            LogItem::Incident(incident) => incident.bin_id,
+        }
+    }
+
+    /// The sort key the entry is stored under.
+    pub fn created_at(&self) -> String {
+        match self {
+            LogItem::Report(report) => report.created_at.clone(),
+            LogItem::Collection(collection) => collection.created_at.clone(),
+            LogItem::Incident(incident) => incident.created_at.clone(),
+        }
+    }
+
+    /// The entry as an event of the bin's fill history. Incidents say
+    /// nothing about the fill level and have none.
+    pub fn event(self) -> Result<Option<BinEvent>, MalformedItem> {
+        match self {
+            LogItem::Report(report) => report.report().map(|report| Some(BinEvent::Reported(report))),
+            LogItem::Collection(collection) => Ok(Some(BinEvent::Collected { at: collection.collected_at })),
+            LogItem::Incident(_) => Ok(None),
+        }
+    }
+
+    /// The entry as an export record, keeping the collection's arrival time.
+    pub fn record(self) -> Result<Record, MalformedItem> {
+        match self {
+            LogItem::Report(report) => report.report().map(Record::Report),
+            LogItem::Collection(collection) => Ok(Record::Collection(CollectionEvent {
+                bin_id: collection.bin_id,
+                collected_at: collection.collected_at,
+                received_at: collection.received_at,
+            })),
+            LogItem::Incident(incident) => incident.incident().map(Record::Incident),
+        }
+    }
+
+    pub fn to_item(&self) -> Result<Item, AppError> {
+        match self {
+            LogItem::Report(report) => to_item(report),
+            LogItem::Collection(collection) => to_item(collection),
+            LogItem::Incident(incident) => to_item(incident),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationItem {
+    pub location_id: Uuid,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    pub name: String,
+    pub address: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub district: Option<String>,
+}
+
+impl LocationItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        from_item("location", "locationId", item)
+    }
+}
+
+impl From<&Location> for LocationItem {
+    fn from(location: &Location) -> Self {
+        Self {
+            location_id: location.id,
+            schema_version: LOCATION_SCHEMA_VERSION,
+            name: location.name.clone(),
+            address: location.address.clone(),
+            latitude: location.latitude,
+            longitude: location.longitude,
+            district: location.district.clone(),
+        }
+    }
+}
+
+impl From<LocationItem> for Location {
+    fn from(item: LocationItem) -> Self {
+        Self {
+            id: item.location_id,
+            address: item.address,
+            latitude: item.latitude,
+            longitude: item.longitude,
+            name: item.name,
+            district: item.district,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QrCodeItem {
+    pub qr_code_id: Uuid,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    pub url: String,
+    pub bin_id: Uuid,
+    #[serde(with = "rfc3339")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl QrCodeItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        from_item("QR code", "qrCodeId", item)
+    }
+}
+
+impl From<&QRCode> for QrCodeItem {
+    fn from(qr_code: &QRCode) -> Self {
+        Self {
+            qr_code_id: qr_code.id,
+            schema_version: QR_CODE_SCHEMA_VERSION,
+            url: qr_code.url.clone(),
+            bin_id: qr_code.trash_bin_id,
+            created_at: qr_code.created_at,
+        }
+    }
+}
+
+impl From<QrCodeItem> for QRCode {
+    fn from(item: QrCodeItem) -> Self {
+        Self { id: item.qr_code_id, url: item.url, trash_bin_id: item.bin_id, created_at: item.created_at }
+    }
+}
+
+/// A sensor as provisioned. Its health attributes share the item; see
+/// `DeviceHealthItem`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceItem {
+    pub device_id: String,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    pub bin_id: Uuid,
+    pub key_hash: String,
+    #[serde(default = "default_vendor")]
+    pub vendor: String,
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+/// Devices provisioned before vendors were tracked run the EcoScan codec.
+fn default_vendor() -> String {
+    "ecoscan".to_string()
+}
+
+impl DeviceItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        from_item("device", "deviceId", item)
+    }
+}
+
+impl From<DeviceItem> for Device {
+    fn from(item: DeviceItem) -> Self {
+        Self {
+            device_id: item.device_id,
+            bin_id: item.bin_id,
+            key_hash: item.key_hash,
+            vendor: item.vendor,
+            is_active: item.is_active,
+        }
+    }
+}
+
+/// The health attributes of a device item. They are written together on
+/// each transmission, so a device that never transmitted has none of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceHealthItem {
+    pub device_id: String,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    pub bin_id: Uuid,
+    #[serde(default, with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_mv: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rssi_dbm: Option<i16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_distance_mm: Option<u32>,
+    #[serde(default, with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub distance_changed_at: Option<DateTime<Utc>>,
+    /// The flags as JSON, so they can be replaced in one `SET`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_flags: Option<String>,
+}
+
+impl DeviceHealthItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        from_item("device", "deviceId", item)
+    }
+
+    /// The health of the device, or `None` if it never transmitted.
+    pub fn health(self) -> Result<Option<DeviceHealth>, MalformedItem> {
+        let Some(last_seen) = self.last_seen else {
+            return Ok(None);
+        };
+        let flags = match &self.health_flags {
+            Some(json) => serde_json::from_str(json).map_err(|e| self.malformed(format!("invalid healthFlags: {}", e)))?,
+            None => return Err(self.malformed("missing healthFlags".to_string())),
+        };
+        Ok(Some(DeviceHealth {
+            bin_id: self.bin_id,
+            last_seen,
+            battery_mv: self.battery_mv,
+            rssi_dbm: self.rssi_dbm,
+            last_distance_mm: self.last_distance_mm.ok_or_else(|| self.malformed("missing lastDistanceMm".to_string()))?,
+            distance_changed_at: self
+                .distance_changed_at
+                .ok_or_else(|| self.malformed("missing distanceChangedAt".to_string()))?,
+            flags,
+            firmware_version: self.firmware_version,
+            device_id: self.device_id,
+        }))
+    }
+
+    fn malformed(&self, reason: String) -> MalformedItem {
+        MalformedItem { entity: "device", key: self.device_id.clone(), reason }
+    }
+}
+
+/// A rollup, keyed by its series and period so a range of periods is one
+/// query. Location and district rollups are merged into with `ADD`, so
+/// every stat may be missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollupItem {
+    pub series: String,
+    pub period_start: String,
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub reports_count: u32,
+    #[serde(default)]
+    pub min: i32,
+    #[serde(default)]
+    pub max: i32,
+    #[serde(default)]
+    pub sum: i64,
+    #[serde(default)]
+    pub full_seconds: i64,
+}
+
+impl RollupItem {
+    pub fn parse(item: &Item) -> Result<Self, MalformedItem> {
+        from_item("rollup", "series", item)
+    }
+
+    pub fn rollup(self) -> Result<Rollup, MalformedItem> {
+        let malformed = |reason: &str| MalformedItem {
+            entity: "rollup",
+            key: format!("{}/{}", self.series, self.period_start),
+            reason: reason.to_string(),
+        };
+        let (scope, resolution) = RollupScope::parse_series(&self.series).ok_or_else(|| malformed("unknown series"))?;
+        let period_start = resolution
+            .parse_period_key(&self.period_start)
+            .ok_or_else(|| malformed("invalid periodStart"))?;
+        Ok(Rollup {
+            scope,
+            resolution,
+            period_start,
+            stats: FillStats {
+                reports_count: self.reports_count,
+                min: self.min,
+                max: self.max,
+                sum: self.sum,
+                full_seconds: self.full_seconds,
+            },
+        })
+    }
+}
+
+impl From<&Rollup> for RollupItem {
+    fn from(rollup: &Rollup) -> Self {
+        Self {
+            series: rollup.scope.series(rollup.resolution),
+            period_start: rollup.resolution.period_key(rollup.period_start),
+            schema_version: ROLLUP_SCHEMA_VERSION,
+            reports_count: rollup.stats.reports_count,
+            min: rollup.stats.min,
+            max: rollup.stats.max,
+            sum: rollup.stats.sum,
+            full_seconds: rollup.stats.full_seconds,
+        }
+    }
+}
+
+pub fn to_item<T: Serialize>(value: &T) -> Result<Item, AppError> {
+    serde_dynamo::to_item(value).map_err(|e| AppError::InternalError(format!("Failed to serialize item: {}", e)))
+}
+
+/// Deserializes `item`, naming it by `key_attribute` in the error.
+fn from_item<T: DeserializeOwned>(entity: &'static str, key_attribute: &str, item: &Item) -> Result<T, MalformedItem> {
+    serde_dynamo::from_item(item.clone()).map_err(|e| MalformedItem {
+        entity,
+        key: match item.get(key_attribute) {
+            Some(AttributeValue::S(key)) => key.clone(),
+            _ => "without a key".to_string(),
+        },
+        reason: e.to_string(),
+    })
+}
+
+/// Timestamps are stored as `to_rfc3339` strings. The report sort key is
+/// compared as a string, so the format must not change.
+mod rfc3339 {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&value)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(value: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+            match Option::<String>::deserialize(deserializer)? {
+                Some(value) => DateTime::parse_from_rfc3339(&value)
+                    .map(|time| Some(time.with_timezone(&Utc)))
+                    .map_err(serde::de::Error::custom),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::domain::rollup::Resolution;
+
+    fn s(value: &str) -> AttributeValue {
+        AttributeValue::S(value.to_string())
+    }
+
+    fn n(value: &str) -> AttributeValue {
+        AttributeValue::N(value.to_string())
+    }
+
+    #[test]
+    fn test_unversioned_bin_is_upgraded_on_read() {
+        let bin_id = Uuid::new_v4();
+        let item = Item::from([
+            ("binId".to_string(), s(&bin_id.to_string())),
+            ("status".to_string(), n("6")),
+            ("reportsCount".to_string(), n("4")),
+            ("lastUpdated".to_string(), s("2024-03-20T12:00:00+00:00")),
+            ("emptyDistanceMm".to_string(), n("1200")),
+        ]);
+
+        let bin = BinItem::parse(&item).unwrap();
+
+        assert_eq!(bin.schema_version, BIN_SCHEMA_VERSION);
+        assert_eq!(bin.aggregate(), BinAggregate {
+            last_observed_at: Some(Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap()),
+            ..BinAggregate::from_legacy(6, 4)
+        });
+        assert_eq!(bin.profile(), BinProfile::default());
+        assert_eq!(bin.lifecycle(), BinLifecycle::default());
+    }
+
+    #[test]
+    fn test_current_bin_keeps_its_weights() {
+        let item = Item::from([
+            ("binId".to_string(), s(&Uuid::new_v4().to_string())),
+            ("schemaVersion".to_string(), n("2")),
+            ("status".to_string(), n("3")),
+            ("reportsCount".to_string(), n("2")),
+            ("weightedSum".to_string(), n("7.5")),
+            ("weightTotal".to_string(), n("2.5")),
+        ]);
+
+        let aggregate = BinItem::parse(&item).unwrap().aggregate();
+        assert_eq!((aggregate.weighted_sum, aggregate.weight_total, aggregate.reports_count), (7.5, 2.5, 2));
+    }
+
+    #[test]
+    fn test_malformed_bins_are_rejected() {
+        let bin_id = Uuid::new_v4().to_string();
+        let bin = |attribute: &str, value: Option<AttributeValue>| {
+            let mut item = Item::from([
+                ("binId".to_string(), s(&bin_id)),
+                ("status".to_string(), n("0")),
+                ("reportsCount".to_string(), n("0")),
+            ]);
+            match value {
+                Some(value) => item.insert(attribute.to_string(), value),
+                None => item.remove(attribute),
+            };
+            item
+        };
+        assert!(BinItem::parse(&bin("lastUpdated", None)).is_ok());
+
+        for item in [
+            bin("status", Some(n("11"))),
+            bin("status", Some(s("full"))),
+            bin("status", None),
+            bin("reportsCount", Some(n("-1"))),
+            bin("reportsCount", None),
+            bin("binId", Some(s("not-a-uuid"))),
+            bin("schemaVersion", Some(n("2"))),
+        ] {
+            let error = BinItem::parse(&item).unwrap_err();
+            assert_eq!(error.entity, "bin");
+        }
+        assert_eq!(BinItem::parse(&bin("status", Some(n("11")))).unwrap_err().key, bin_id);
+    }
+
+    #[test]
+    fn test_bin_record_round_trip_keeps_calibration_profile_and_lifecycle() {
+        let record = BinRecord {
+            bin_id: Uuid::new_v4(),
+            location_id: Some(Uuid::new_v4()),
+            aggregate: BinAggregate {
+                weighted_sum: 17.5,
+                weight_total: 2.5,
+                reports_count: 2,
+                last_observed_at: Some(Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap()),
+                collected_at: Some(Utc.with_ymd_and_hms(2024, 3, 20, 6, 0, 0).unwrap()),
+            },
+            calibration: Some(BinCalibration::new(1200, 150).unwrap()),
+            profile: BinProfile::new(WasteType::Glass, Some(1500), Some("Reflex 1500".to_string())).unwrap(),
+            lifecycle: BinLifecycle {
+                state: LifecycleState::UnderMaintenance,
+                replaced_by: None,
+                history: vec![LifecycleTransition {
+                    from: LifecycleState::Active,
+                    to: LifecycleState::UnderMaintenance,
+                    at: Utc.with_ymd_and_hms(2024, 3, 20, 13, 0, 0).unwrap(),
+                    actor: "crew-7".to_string(),
+                    reason: Some("Broken lid".to_string()),
+                    replaced_by: None,
+                }],
+            },
+        };
+
+        let item = to_item(&BinItem::from(&record)).unwrap();
+
+        assert_eq!((&item["status"], &item["wasteType"]), (&n("7"), &s("glass")));
+        assert_eq!(item["lifecycleState"], s("under_maintenance"));
+        assert_eq!(BinItem::parse(&item).unwrap().record().unwrap(), record);
+    }
+
+    #[test]
+    fn test_report_round_trip_keeps_sort_key_format() {
+        let observed_at = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        let report = StatusReport::sensor(Uuid::new_v4(), Uuid::new_v4(), BinStatus::new(7).unwrap(), observed_at)
+            .with_received_at(observed_at + chrono::Duration::minutes(5));
+
+        let item = to_item(&ReportItem::from(&report)).unwrap();
+
+        assert_eq!(item["createdAt"], s(&format!("2024-03-20T12:00:00+00:00#{}", report.report_id)));
+        assert_eq!(item["schemaVersion"], n("3"));
+        assert_eq!(ReportItem::parse(&item).unwrap().report().unwrap(), report);
+    }
+
+    #[test]
+    fn test_report_keyed_by_observation_alone_is_upgraded_on_read() {
+        let report_id = Uuid::new_v4();
+        let item = Item::from([
+            ("binId".to_string(), s(&Uuid::new_v4().to_string())),
+            ("createdAt".to_string(), s("2024-03-20T12:00:00+00:00")),
+            ("schemaVersion".to_string(), n("2")),
+            ("reportId".to_string(), s(&report_id.to_string())),
+            ("receivedAt".to_string(), s("2024-03-20T12:05:00+00:00")),
+            ("status".to_string(), n("4")),
+            ("source".to_string(), s("sensor")),
+        ]);
+
+        let report = ReportItem::parse(&item).unwrap().report().unwrap();
+
+        assert_eq!(report.report_id, report_id);
+        assert_eq!(report.observed_at, Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap());
+        assert_eq!(report.received_at, Utc.with_ymd_and_hms(2024, 3, 20, 12, 5, 0).unwrap());
+    }
+
+    #[test]
+    fn test_unversioned_report_is_upgraded_on_read() {
+        let bin_id = Uuid::new_v4();
+        let item = Item::from([
+            ("binId".to_string(), s(&bin_id.to_string())),
+            ("createdAt".to_string(), s("2024-03-20T12:00:00+00:00")),
+            ("status".to_string(), n("4")),
+        ]);
+
+        let report = ReportItem::parse(&item).unwrap().report().unwrap();
+
+        assert_eq!(report.report_id, legacy_report_id(bin_id, report.observed_at));
+        assert_ne!(report.report_id, legacy_report_id(bin_id, report.observed_at + chrono::Duration::seconds(1)));
+        assert_eq!(ReportItem::parse(&item).unwrap().report().unwrap().report_id, report.report_id);
+        assert_eq!(report.source, ReportSource::Citizen);
+        assert_eq!(report.received_at, report.observed_at);
+
+        let unknown_source = Item::from([("source".to_string(), s("drone"))].into_iter().chain(item).collect::<Item>());
+        assert!(ReportItem::parse(&unknown_source).is_err());
+    }
+
+    #[test]
+    fn test_collections_share_the_report_log() {
+        let collected_at = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        let event = CollectionEvent { bin_id: Uuid::new_v4(), collected_at, received_at: collected_at };
+        let item = to_item(&CollectionItem::from(&event)).unwrap();
+
+        assert_eq!(item["createdAt"], s("2024-03-20T12:00:00+00:00#collection"));
+        let entry = LogItem::parse(&item).unwrap();
+        assert_eq!(entry.event().unwrap(), Some(BinEvent::Collected { at: collected_at }));
+
+        let mut unknown = item;
+        unknown.insert(EVENT_TYPE_ATTRIBUTE.to_string(), s("inspection"));
+        assert_eq!(LogItem::parse(&unknown).unwrap_err().entity, "report log");
+    }
+
+    #[test]
+    fn test_incidents_share_the_report_log_by_kind() {
+        let observed_at = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        let incident = IncidentReport {
+            report_id: Uuid::new_v4(),
+            bin_id: Uuid::new_v4(),
+            details: ReportDetails::Damage { part: DamagedPart::Lid, description: Some("Hinge snapped".to_string()) },
+            observed_at,
+            received_at: observed_at,
+        };
+        let item = to_item(&IncidentItem::from(&incident)).unwrap();
+
+        assert_eq!(item["createdAt"], s(&format!("2024-03-20T12:00:00+00:00#damage#{}", incident.report_id)));
+        assert_eq!((&item["reportKind"], &item["part"]), (&s("damage"), &s("lid")));
+        assert!(!item.contains_key("stillBurning"));
+        let entry = LogItem::parse(&item).unwrap();
+        assert_eq!(entry.clone().event().unwrap(), None);
+        assert_eq!(entry.record().unwrap(), Record::Incident(incident));
+
+        let mut without_part = item;
+        without_part.remove("part");
+        assert_eq!(LogItem::parse(&without_part).unwrap().record().unwrap_err().entity, "incident");
+    }
+
+    #[test]
+    fn test_rollup_written_by_retention_reads_without_full_time() {
+        let bin_id = Uuid::new_v4();
+        let item = Item::from([
+            ("series".to_string(), s(&format!("bin#{}#daily", bin_id))),
+            ("periodStart".to_string(), s("2024-03-19")),
+            ("schemaVersion".to_string(), n("1")),
+            ("binId".to_string(), s(&bin_id.to_string())),
+            ("reportsCount".to_string(), n("2")),
+            ("min".to_string(), n("3")),
+            ("max".to_string(), n("9")),
+            ("sum".to_string(), n("12")),
+        ]);
+
+        let rollup = RollupItem::parse(&item).unwrap().rollup().unwrap();
+
+        assert_eq!((&rollup.scope, rollup.resolution), (&RollupScope::Bin(bin_id), Resolution::Daily));
+        assert_eq!(rollup.period_start, Utc.with_ymd_and_hms(2024, 3, 19, 0, 0, 0).unwrap());
+        assert_eq!((rollup.stats.mean(), rollup.stats.full_seconds), (Some(6.0), 0));
+        let round_trip = RollupItem::parse(&to_item(&RollupItem::from(&rollup)).unwrap()).unwrap();
+        assert_eq!(round_trip.schema_version, ROLLUP_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_location_and_qr_code_round_trip() {
+        let location = Location {
+            id: Uuid::new_v4(),
+            address: "Náměstí Míru 1, Praha".to_string(),
+            latitude: 50.075,
+            longitude: 14.437,
+            name: "Náměstí Míru".to_string(),
+            district: Some("Praha 2".to_string()),
+        };
+        let item = to_item(&LocationItem::from(&location)).unwrap();
+        let read = Location::from(LocationItem::parse(&item).unwrap());
+        assert_eq!((read.id, read.name, read.latitude), (location.id, location.name, location.latitude));
+
+        let qr_code = QRCode {
+            id: Uuid::new_v4(),
+            url: "https://ecoscan.cz/b/abc".to_string(),
+            trash_bin_id: Uuid::new_v4(),
+            created_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        };
+        let item = to_item(&QrCodeItem::from(&qr_code)).unwrap();
+        let read = QRCode::from(QrCodeItem::parse(&item).unwrap());
+        assert_eq!((read.id, read.trash_bin_id, read.created_at), (qr_code.id, qr_code.trash_bin_id, qr_code.created_at));
+
+        let missing_url = Item::from([("qrCodeId".to_string(), s(&qr_code.id.to_string()))]);
+        assert_eq!(QrCodeItem::parse(&missing_url).unwrap_err().key, qr_code.id.to_string());
+    }
+
+    #[test]
+    fn test_device_health_is_read_strictly() {
+        let bin_id = Uuid::new_v4();
+        let device = |attribute: &str, value: Option<AttributeValue>| {
+            let mut item = Item::from([
+                ("deviceId".to_string(), s("sensor-7")),
+                ("binId".to_string(), s(&bin_id.to_string())),
+                ("keyHash".to_string(), s("ab12")),
+                ("lastSeen".to_string(), s("2024-03-20T12:00:00+00:00")),
+                ("batteryMv".to_string(), n("3600")),
+                ("lastDistanceMm".to_string(), n("640")),
+                ("distanceChangedAt".to_string(), s("2024-03-19T08:00:00+00:00")),
+                ("healthFlags".to_string(), s("[]")),
+            ]);
+            match value {
+                Some(value) => item.insert(attribute.to_string(), value),
+                None => item.remove(attribute),
+            };
+            item
+        };
+
+        let read = Device::from(DeviceItem::parse(&device("isActive", Some(AttributeValue::Bool(true)))).unwrap());
+        assert_eq!((read.bin_id, read.vendor.as_str(), read.is_active), (bin_id, "ecoscan", true));
+        let health = DeviceHealthItem::parse(&device("rssiDbm", None)).unwrap().health().unwrap().unwrap();
+        assert_eq!((health.battery_mv, health.rssi_dbm, health.last_distance_mm), (Some(3600), None, 640));
+        assert_eq!(health.distance_changed_at, Utc.with_ymd_and_hms(2024, 3, 19, 8, 0, 0).unwrap());
+        assert_eq!(DeviceHealthItem::parse(&device("lastSeen", None)).unwrap().health(), Ok(None));
+
+        assert_eq!(DeviceItem::parse(&device("binId", Some(s("not-a-uuid")))).unwrap_err().key, "sensor-7");
+        assert!(DeviceItem::parse(&device("keyHash", None)).is_err());
+        for item in [
+            device("batteryMv", Some(n("-1"))),
+            device("lastDistanceMm", Some(s("640"))),
+            device("distanceChangedAt", Some(s("yesterday"))),
+        ] {
+            assert_eq!(DeviceHealthItem::parse(&item).unwrap_err().entity, "device");
+        }
+        for item in [device("lastDistanceMm", None), device("healthFlags", Some(s("{")))] {
+            let error = DeviceHealthItem::parse(&item).unwrap().health().unwrap_err();
+            assert_eq!((error.entity, error.key.as_str()), ("device", "sensor-7"));
+        }
+    }
+}
@@ -0,0 +1,658 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::backup::{BackupRepository, BinRecord, ExportPage, Record, Section};
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::consistency::{BinHistoryRepository, BinPage, ResumeToken, StoredBin};
+use crate::domain::device_health::{DeviceHealth, DeviceHealthRepository};
+use crate::domain::estimator::{BinAggregate, FillEstimator};
+use crate::domain::history::BinEvent;
+use crate::domain::incident::{IncidentReport, IncidentRepository, ReportKind};
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleTransition};
+use crate::domain::location::{LocationRepository, PlacedBin};
+use crate::domain::retention::RetentionRepository;
+use crate::domain::rollup::{
+    DailyRollup, FillStats, Placement, Resolution, Rollup, RollupRepository, RollupScope, RollupUpdate, Timeline,
+};
+use crate::domain::sensor::{BinCalibration, Device, DeviceRepository};
+use crate::domain::{
+    BinProfile, BinProfileRepository, BinRepository, BinStatus, Location, QRCode, StatusChange, StatusReport,
+};
+use crate::error::AppError;
+
+#[derive(Debug, Default)]
+struct State {
+    bins: HashMap<Uuid, BinAggregate>,
+    reports: Vec<StatusReport>,
+    collections: Vec<CollectionEvent>,
+    incidents: Vec<IncidentReport>,
+    devices: HashMap<String, Device>,
+    calibrations: HashMap<Uuid, BinCalibration>,
+    health: HashMap<String, DeviceHealth>,
+    locations: HashMap<Uuid, Location>,
+    /// The location each placed bin stands at.
+    placements: HashMap<Uuid, Uuid>,
+    qr_codes: HashMap<Uuid, QRCode>,
+    profiles: HashMap<Uuid, BinProfile>,
+    lifecycles: HashMap<Uuid, BinLifecycle>,
+    rollups: HashMap<(RollupScope, Resolution, DateTime<Utc>), FillStats>,
+}
+
+/// Process-local repository for tests and for running the services without
+/// DynamoDB. Clones share the same underlying state.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRepository {
+    state: Arc<RwLock<State>>,
+    estimator: FillEstimator,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_estimator(estimator: FillEstimator) -> Self {
+        Self { estimator, ..Self::default() }
+    }
+
+    pub fn add_bin(&self, bin_id: Uuid) {
+        self.write().bins.insert(bin_id, BinAggregate::default());
+    }
+
+    pub fn register_device(&self, device: Device) {
+        self.write().devices.insert(device.device_id.clone(), device);
+    }
+
+    pub fn set_calibration(&self, bin_id: Uuid, calibration: BinCalibration) {
+        self.write().calibrations.insert(bin_id, calibration);
+    }
+
+    pub fn add_location(&self, location: Location) {
+        self.write().locations.insert(location.id, location);
+    }
+
+    pub fn place_bin(&self, bin_id: Uuid, location_id: Uuid) {
+        self.write().placements.insert(bin_id, location_id);
+    }
+
+    pub fn add_qr_code(&self, qr_code: QRCode) {
+        self.write().qr_codes.insert(qr_code.id, qr_code);
+    }
+
+    pub fn bin_status(&self, bin_id: &Uuid) -> Option<BinStatus> {
+        self.read().bins.get(bin_id).map(|aggregate| aggregate.status())
+    }
+
+    pub fn aggregate(&self, bin_id: &Uuid) -> Option<BinAggregate> {
+        self.read().bins.get(bin_id).cloned()
+    }
+
+    pub fn reports(&self, bin_id: &Uuid) -> Vec<StatusReport> {
+        self.read()
+            .reports
+            .iter()
+            .filter(|report| report.bin_id == *bin_id)
+            .cloned()
+            .collect()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.state.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, State> {
+        self.state.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl BinRepository for InMemoryRepository {
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        Ok(self.aggregate(bin_id))
+    }
+
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        let mut state = self.write();
+        let aggregate = state
+            .bins
+            .get_mut(&report.bin_id)
+            .ok_or_else(|| AppError::BinNotFound(report.bin_id.to_string()))?;
+
+        let previous = aggregate.status();
+        aggregate.apply_report(&self.estimator, report);
+        Ok(StatusChange { previous, current: aggregate.status() })
+    }
+
+    /// Like the DynamoDB report table, a report with the same id replaces
+    /// the stored one, while others observed at the same instant are kept.
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+        upsert_report(&mut self.write().reports, report);
+        Ok(())
+    }
+
+    /// Like the DynamoDB report log, every collection is kept, including
+    /// ones older than the last.
+    async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+        let mut state = self.write();
+        if !state.bins.contains_key(&event.bin_id) {
+            return Err(AppError::BinNotFound(event.bin_id.to_string()));
+        }
+        log_collection(&mut state.collections, event);
+        let current = state
+            .bins
+            .get(&event.bin_id)
+            .ok_or_else(|| AppError::BinNotFound(event.bin_id.to_string()))?;
+        if current.collected_at.is_some_and(|collected_at| collected_at >= event.collected_at) {
+            return Ok(());
+        }
+
+        let mut later: Vec<&StatusReport> = state
+            .reports
+            .iter()
+            .filter(|report| report.bin_id == event.bin_id && report.observed_at >= event.collected_at)
+            .collect();
+        later.sort_by_key(|report| report.observed_at);
+
+        let mut aggregate = BinAggregate::collected(event.collected_at);
+        for report in later {
+            aggregate.apply_report(&self.estimator, report);
+        }
+        state.bins.insert(event.bin_id, aggregate);
+        Ok(())
+    }
+
+    /// Applies each group under a single lock, so a group is stored either
+    /// completely or not at all.
+    async fn record_batch(&self, groups: &[ReportGroup]) -> Vec<Result<(), AppError>> {
+        let mut state = self.write();
+        groups
+            .iter()
+            .map(|group| {
+                let mut aggregate = state
+                    .bins
+                    .get(&group.bin_id)
+                    .cloned()
+                    .ok_or_else(|| AppError::BinNotFound(group.bin_id.to_string()))?;
+                for report in &group.reports {
+                    aggregate.apply_report(&self.estimator, report);
+                    upsert_report(&mut state.reports, report);
+                }
+                state.bins.insert(group.bin_id, aggregate);
+                Ok(())
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BinHistoryRepository for InMemoryRepository {
+    /// Scans in bin id order.
+    async fn scan_bins(&self, resume: Option<&ResumeToken>, limit: usize) -> Result<BinPage, AppError> {
+        let state = self.read();
+        let mut bin_ids: Vec<&Uuid> = state
+            .bins
+            .keys()
+            .filter(|bin_id| resume.is_none_or(|token| **bin_id > token.0))
+            .collect();
+        bin_ids.sort();
+
+        let bins: Vec<StoredBin> = bin_ids
+            .iter()
+            .take(limit)
+            .map(|bin_id| {
+                let aggregate = state.bins[*bin_id].clone();
+                StoredBin { bin_id: **bin_id, status: aggregate.status(), aggregate }
+            })
+            .collect();
+        let next = bins.last().filter(|_| bin_ids.len() > limit).map(|bin| ResumeToken(bin.bin_id));
+        Ok(BinPage { bins, next })
+    }
+
+    async fn bin_events(
+        &self,
+        bin_id: &Uuid,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        let collections = self
+            .read()
+            .collections
+            .iter()
+            .filter(|event| event.bin_id == *bin_id)
+            .map(|event| BinEvent::Collected { at: event.collected_at })
+            .collect::<Vec<_>>();
+        Ok(self
+            .reports(bin_id)
+            .into_iter()
+            .map(BinEvent::Reported)
+            .chain(collections)
+            .filter(|event| since.is_none_or(|since| event.at() >= since))
+            .filter(|event| until.is_none_or(|until| event.at() <= until))
+            .collect())
+    }
+
+    async fn repair_bin(&self, stored: &StoredBin, recomputed: &BinAggregate) -> Result<bool, AppError> {
+        let mut state = self.write();
+        match state.bins.get_mut(&stored.bin_id) {
+            Some(current) if *current == stored.aggregate => {
+                *current = recomputed.clone();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl BackupRepository for InMemoryRepository {
+    /// Log entries are keyed by bin and time, like the DynamoDB report log.
+    async fn export_page(&self, section: Section, after: Option<&str>, limit: usize) -> Result<ExportPage, AppError> {
+        let state = self.read();
+        let keyed: Vec<(String, Record)> = match section {
+            Section::Locations => state
+                .locations
+                .values()
+                .map(|location| (location.id.to_string(), Record::Location(location.clone())))
+                .collect(),
+            Section::Bins => state
+                .bins
+                .iter()
+                .map(|(bin_id, aggregate)| {
+                    let bin = BinRecord {
+                        bin_id: *bin_id,
+                        location_id: state.placements.get(bin_id).copied(),
+                        aggregate: aggregate.clone(),
+                        calibration: state.calibrations.get(bin_id).cloned(),
+                        profile: state.profiles.get(bin_id).cloned().unwrap_or_default(),
+                        lifecycle: state.lifecycles.get(bin_id).cloned().unwrap_or_default(),
+                    };
+                    (bin_id.to_string(), Record::Bin(bin))
+                })
+                .collect(),
+            Section::QrCodes => state
+                .qr_codes
+                .values()
+                .map(|qr_code| (qr_code.id.to_string(), Record::QrCode(qr_code.clone())))
+                .collect(),
+            Section::Log => state
+                .reports
+                .iter()
+                .map(|report| {
+                    let key = format!("{}/{}#{}", report.bin_id, report.observed_at.to_rfc3339(), report.report_id);
+                    (key, Record::Report(report.clone()))
+                })
+                .chain(state.collections.iter().map(|event| {
+                    let key = format!("{}/{}#collection", event.bin_id, event.collected_at.to_rfc3339());
+                    (key, Record::Collection(event.clone()))
+                }))
+                .chain(state.incidents.iter().map(|incident| {
+                    let key = format!(
+                        "{}/{}#{}#{}",
+                        incident.bin_id,
+                        incident.observed_at.to_rfc3339(),
+                        incident.kind().as_str(),
+                        incident.report_id
+                    );
+                    (key, Record::Incident(incident.clone()))
+                }))
+                .collect(),
+        };
+        Ok(ExportPage::from_keyed(keyed, after, limit))
+    }
+
+    async fn import_record(&self, record: &Record) -> Result<(), AppError> {
+        let mut state = self.write();
+        match record {
+            Record::Header { .. } => {}
+            Record::Location(location) => {
+                state.locations.insert(location.id, location.clone());
+            }
+            Record::Bin(bin) => {
+                state.bins.insert(bin.bin_id, bin.aggregate.clone());
+                match bin.location_id {
+                    Some(location_id) => state.placements.insert(bin.bin_id, location_id),
+                    None => state.placements.remove(&bin.bin_id),
+                };
+                match &bin.calibration {
+                    Some(calibration) => state.calibrations.insert(bin.bin_id, calibration.clone()),
+                    None => state.calibrations.remove(&bin.bin_id),
+                };
+                state.profiles.insert(bin.bin_id, bin.profile.clone());
+                state.lifecycles.insert(bin.bin_id, bin.lifecycle.clone());
+            }
+            Record::QrCode(qr_code) => {
+                state.qr_codes.insert(qr_code.id, qr_code.clone());
+            }
+            Record::Report(report) => upsert_report(&mut state.reports, report),
+            Record::Collection(event) => log_collection(&mut state.collections, event),
+            Record::Incident(incident) => upsert_incident(&mut state.incidents, incident),
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RetentionRepository for InMemoryRepository {
+    async fn reports_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<StatusReport>, AppError> {
+        Ok(self.reports(bin_id).into_iter().filter(|report| report.observed_at < before).collect())
+    }
+
+    async fn delete_reports(&self, bin_id: &Uuid, reports: &[StatusReport]) -> Result<(), AppError> {
+        self.write()
+            .reports
+            .retain(|report| report.bin_id != *bin_id || !reports.iter().any(|deleted| deleted.report_id == report.report_id));
+        Ok(())
+    }
+
+    async fn incidents_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<IncidentReport>, AppError> {
+        let mut incidents: Vec<IncidentReport> = self
+            .read()
+            .incidents
+            .iter()
+            .filter(|incident| incident.bin_id == *bin_id && incident.observed_at < before)
+            .cloned()
+            .collect();
+        incidents.sort_by_key(|incident| incident.observed_at);
+        Ok(incidents)
+    }
+
+    async fn delete_incidents(&self, bin_id: &Uuid, incidents: &[IncidentReport]) -> Result<(), AppError> {
+        self.write().incidents.retain(|incident| {
+            incident.bin_id != *bin_id || !incidents.iter().any(|deleted| deleted.report_id == incident.report_id)
+        });
+        Ok(())
+    }
+
+    async fn daily_rollups(&self, bin_id: &Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyRollup>, AppError> {
+        let from = from.and_time(NaiveTime::MIN).and_utc();
+        let to = to.and_time(NaiveTime::MIN).and_utc() + Duration::days(1);
+        let rollups = self.rollups(&RollupScope::Bin(*bin_id), Resolution::Daily, from, to).await?;
+        Ok(rollups
+            .into_iter()
+            .map(|rollup| DailyRollup { bin_id: *bin_id, day: rollup.period_start.date_naive(), stats: rollup.stats })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl RollupRepository for InMemoryRepository {
+    async fn bin_timeline(&self, bin_id: &Uuid, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Timeline, AppError> {
+        let mut events = self.bin_events(bin_id, None, None).await?;
+        events.sort_by_key(|event| event.order());
+
+        let mut timeline = Timeline::default();
+        for event in events {
+            if event.at() < from {
+                timeline.before = Some(event);
+            } else if event.at() < to {
+                timeline.events.push(event);
+            } else if timeline.after.is_none() {
+                timeline.after = Some(event);
+            }
+        }
+        Ok(timeline)
+    }
+
+    async fn bin_placement(&self, bin_id: &Uuid) -> Result<Placement, AppError> {
+        let state = self.read();
+        let location_id = state.placements.get(bin_id).copied();
+        let district = location_id
+            .and_then(|location_id| state.locations.get(&location_id))
+            .and_then(|location| location.district.clone());
+        Ok(Placement { location_id, district })
+    }
+
+    async fn rollups(
+        &self,
+        scope: &RollupScope,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Rollup>, AppError> {
+        let mut rollups: Vec<Rollup> = self
+            .read()
+            .rollups
+            .iter()
+            .filter(|((stored_scope, stored_resolution, start), _)| {
+                stored_scope == scope && *stored_resolution == resolution && (from..to).contains(start)
+            })
+            .map(|((scope, resolution, start), stats)| Rollup {
+                scope: scope.clone(),
+                resolution: *resolution,
+                period_start: *start,
+                stats: stats.clone(),
+            })
+            .collect();
+        rollups.sort_by_key(|rollup| rollup.period_start);
+        Ok(rollups)
+    }
+
+    async fn apply_rollup_update(&self, update: &RollupUpdate) -> Result<bool, AppError> {
+        let mut state = self.write();
+        let unchanged = update.bins.iter().all(|change| {
+            let key = (change.rollup.scope.clone(), change.rollup.resolution, change.rollup.period_start);
+            state.rollups.get(&key) == change.stored.as_ref()
+        });
+        if !unchanged {
+            return Ok(false);
+        }
+        for change in &update.bins {
+            let rollup = &change.rollup;
+            state.rollups.insert((rollup.scope.clone(), rollup.resolution, rollup.period_start), rollup.stats.clone());
+        }
+        for rollup in &update.shared {
+            state
+                .rollups
+                .entry((rollup.scope.clone(), rollup.resolution, rollup.period_start))
+                .or_default()
+                .merge(&rollup.stats);
+        }
+        Ok(true)
+    }
+}
+
+fn log_collection(collections: &mut Vec<CollectionEvent>, event: &CollectionEvent) {
+    let logged = collections
+        .iter()
+        .any(|logged| logged.bin_id == event.bin_id && logged.collected_at == event.collected_at);
+    if !logged {
+        collections.push(event.clone());
+    }
+}
+
+fn upsert_report(reports: &mut Vec<StatusReport>, report: &StatusReport) {
+    match reports
+        .iter_mut()
+        .find(|stored| stored.bin_id == report.bin_id && stored.report_id == report.report_id)
+    {
+        Some(stored) => *stored = report.clone(),
+        None => reports.push(report.clone()),
+    }
+}
+
+fn upsert_incident(incidents: &mut Vec<IncidentReport>, incident: &IncidentReport) {
+    match incidents
+        .iter_mut()
+        .find(|stored| stored.bin_id == incident.bin_id && stored.report_id == incident.report_id)
+    {
+        Some(stored) => *stored = incident.clone(),
+        None => incidents.push(incident.clone()),
+    }
+}
+
+#[async_trait]
+impl BinProfileRepository for InMemoryRepository {
+    async fn bin_profile(&self, bin_id: &Uuid) -> Result<Option<BinProfile>, AppError> {
+        let state = self.read();
+        if !state.bins.contains_key(bin_id) {
+            return Ok(None);
+        }
+        Ok(Some(state.profiles.get(bin_id).cloned().unwrap_or_default()))
+    }
+
+    async fn set_bin_profile(&self, bin_id: &Uuid, profile: &BinProfile) -> Result<(), AppError> {
+        let mut state = self.write();
+        if !state.bins.contains_key(bin_id) {
+            return Err(AppError::BinNotFound(bin_id.to_string()));
+        }
+        state.profiles.insert(*bin_id, profile.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LifecycleRepository for InMemoryRepository {
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+        let state = self.read();
+        if !state.bins.contains_key(bin_id) {
+            return Ok(None);
+        }
+        Ok(Some(state.lifecycles.get(bin_id).cloned().unwrap_or_default()))
+    }
+
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError> {
+        let mut state = self.write();
+        if !state.bins.contains_key(bin_id) {
+            return Ok(false);
+        }
+        let lifecycle = state.lifecycles.entry(*bin_id).or_default();
+        if lifecycle.state != transition.from {
+            return Ok(false);
+        }
+        lifecycle.apply(transition.clone());
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl IncidentRepository for InMemoryRepository {
+    async fn add_incident(&self, report: &IncidentReport) -> Result<(), AppError> {
+        upsert_incident(&mut self.write().incidents, report);
+        Ok(())
+    }
+
+    async fn incidents(&self, kind: ReportKind, since: DateTime<Utc>, limit: usize) -> Result<Vec<IncidentReport>, AppError> {
+        let mut incidents: Vec<IncidentReport> = self
+            .read()
+            .incidents
+            .iter()
+            .filter(|incident| incident.kind() == kind && incident.observed_at >= since)
+            .cloned()
+            .collect();
+        incidents.sort_by_key(|incident| std::cmp::Reverse(incident.observed_at));
+        incidents.truncate(limit);
+        Ok(incidents)
+    }
+}
+
+#[async_trait]
+impl LocationRepository for InMemoryRepository {
+    async fn location_bins(&self, location_id: &Uuid) -> Result<Vec<PlacedBin>, AppError> {
+        let state = self.read();
+        let mut bins: Vec<PlacedBin> = state
+            .placements
+            .iter()
+            .filter(|(_, placed_at)| *placed_at == location_id)
+            .filter_map(|(bin_id, _)| {
+                let aggregate = state.bins.get(bin_id)?.clone();
+                let profile = state.profiles.get(bin_id).cloned().unwrap_or_default();
+                let lifecycle = state.lifecycles.get(bin_id).map(|lifecycle| lifecycle.state).unwrap_or_default();
+                Some(PlacedBin { bin_id: *bin_id, aggregate, profile, lifecycle })
+            })
+            .collect();
+        bins.sort_by_key(|bin| bin.bin_id);
+        Ok(bins)
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for InMemoryRepository {
+    async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError> {
+        Ok(self.read().devices.get(device_id).cloned())
+    }
+
+    async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError> {
+        Ok(self.read().calibrations.get(bin_id).cloned())
+    }
+}
+
+#[async_trait]
+impl DeviceHealthRepository for InMemoryRepository {
+    async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+        Ok(self.read().health.get(device_id).cloned())
+    }
+
+    async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+        self.write().health.insert(health.device_id.clone(), health.clone());
+        Ok(())
+    }
+
+    async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+        Ok(self.read().health.values().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_update_status_requires_known_bin() {
+        let repo = InMemoryRepository::new();
+        let report = StatusReport::citizen(Uuid::new_v4(), Uuid::new_v4(), BinStatus::ok(), Utc::now());
+
+        assert!(matches!(repo.update_status(&report).await, Err(AppError::BinNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_update_status_applies_estimator() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+
+        repo.update_status(&StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::empty(), Utc::now())).await.unwrap();
+        repo.update_status(&StatusReport::sensor(Uuid::new_v4(), bin_id, BinStatus::new(8).unwrap(), Utc::now())).await.unwrap();
+
+        assert_eq!(repo.bin_status(&bin_id).unwrap().value(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_state() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        let clone = repo.clone();
+
+        clone.add_report(&StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), Utc::now())).await.unwrap();
+
+        assert_eq!(repo.reports(&bin_id).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_collection_keeps_reports_observed_after_it() {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        let now = Utc::now();
+
+        for (value, hours_ago) in [(9, 3), (2, 1)] {
+            let report = StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::new(value).unwrap(), now - Duration::hours(hours_ago));
+            repo.update_status(&report).await.unwrap();
+            repo.add_report(&report).await.unwrap();
+        }
+
+        let collection = CollectionEvent { bin_id, collected_at: now - Duration::hours(2), received_at: now };
+        repo.record_collection(&collection).await.unwrap();
+        assert_eq!(repo.bin_status(&bin_id).unwrap().value(), 2);
+
+        // A collection arriving after a newer one changes nothing
+        let older = CollectionEvent { collected_at: now - Duration::hours(4), ..collection };
+        repo.record_collection(&older).await.unwrap();
+        assert_eq!(repo.aggregate(&bin_id).unwrap().collected_at, Some(now - Duration::hours(2)));
+    }
+}
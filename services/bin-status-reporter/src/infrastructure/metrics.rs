@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Map, Value};
+use shared::clock::{Clock, SystemClock};
+use shared::metrics::{MetricsRecorder, Unit};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::error::AppError;
+
+/// Writes each sample as a CloudWatch Embedded Metric Format document on
+/// its own line. In Lambda, stdout goes to CloudWatch Logs, which extracts
+/// the metrics without any API calls.
+pub struct EmfRecorder {
+    namespace: String,
+    sink: Mutex<Box<dyn Write + Send>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl EmfRecorder {
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Self::with_sink(namespace, Box::new(std::io::stdout()), Arc::new(SystemClock))
+    }
+
+    /// Reads the namespace from `METRICS_NAMESPACE`, defaulting to `EcoScan`.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("METRICS_NAMESPACE").unwrap_or_else(|_| "EcoScan".to_string()))
+    }
+
+    pub fn with_sink(namespace: impl Into<String>, sink: Box<dyn Write + Send>, clock: Arc<dyn Clock>) -> Self {
+        Self { namespace: namespace.into(), sink: Mutex::new(sink), clock }
+    }
+
+    fn document(&self, name: &str, value: f64, unit: Unit, dimensions: &[(&str, &str)]) -> Value {
+        let mut root = Map::new();
+        root.insert(
+            "_aws".to_string(),
+            json!({
+                "Timestamp": self.clock.now().timestamp_millis(),
+                "CloudWatchMetrics": [{
+                    "Namespace": self.namespace,
+                    "Dimensions": [dimensions.iter().map(|(key, _)| *key).collect::<Vec<_>>()],
+                    "Metrics": [{"Name": name, "Unit": unit.as_str()}],
+                }],
+            }),
+        );
+        for (key, value) in dimensions {
+            root.insert(key.to_string(), Value::from(*value));
+        }
+        root.insert(name.to_string(), Value::from(value));
+        Value::Object(root)
+    }
+}
+
+impl MetricsRecorder for EmfRecorder {
+    fn record(&self, name: &str, value: f64, unit: Unit, dimensions: &[(&str, &str)]) {
+        let line = self.document(name, value, unit, dimensions).to_string();
+        let mut sink = self.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = writeln!(sink, "{}", line) {
+            warn!("Failed to emit metric {}: {}", name, e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Series {
+    unit: Unit,
+    count: u64,
+    sum: f64,
+}
+
+type SeriesKey = (String, Vec<(String, String)>);
+
+/// Aggregates samples in memory and renders them in the Prometheus text
+/// format, for the long-running services. Counts become counters and
+/// timings become summaries with a sum and a count.
+#[derive(Default)]
+pub struct PrometheusRecorder {
+    series: Mutex<BTreeMap<SeriesKey, Series>>,
+}
+
+impl PrometheusRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut output = String::new();
+        let mut last_name = None;
+
+        for ((name, labels), series) in series.iter() {
+            let metric = prometheus_name(name, series.unit);
+            if last_name != Some(name) {
+                let kind = match series.unit {
+                    Unit::Count => "counter",
+                    Unit::Milliseconds => "summary",
+                };
+                output.push_str(&format!("# TYPE {} {}\n", metric, kind));
+                last_name = Some(name);
+            }
+
+            let labels = render_labels(labels);
+            match series.unit {
+                Unit::Count => output.push_str(&format!("{}{} {}\n", metric, labels, series.sum)),
+                Unit::Milliseconds => {
+                    output.push_str(&format!("{}_sum{} {}\n", metric, labels, series.sum));
+                    output.push_str(&format!("{}_count{} {}\n", metric, labels, series.count));
+                }
+            }
+        }
+        output
+    }
+
+    /// Answers every HTTP request on `addr` with the current metrics.
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) -> Result<(), AppError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to bind metrics endpoint {}: {}", addr, e)))?;
+        info!("Serving Prometheus metrics on {}", addr);
+
+        loop {
+            let (mut stream, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Failed to accept metrics connection: {}", e);
+                    continue;
+                }
+            };
+            let body = self.render();
+            tokio::spawn(async move {
+                // The request itself does not matter, every path serves the metrics
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                    warn!("Failed to write metrics response: {}", e);
+                }
+            });
+        }
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn record(&self, name: &str, value: f64, unit: Unit, dimensions: &[(&str, &str)]) {
+        let mut labels: Vec<(String, String)> = dimensions
+            .iter()
+            .map(|(key, value)| (snake_case(key), value.to_string()))
+            .collect();
+        labels.sort();
+
+        let mut series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = series
+            .entry((name.to_string(), labels))
+            .or_insert(Series { unit, count: 0, sum: 0.0 });
+        entry.count += 1;
+        entry.sum += value;
+    }
+}
+
+fn prometheus_name(name: &str, unit: Unit) -> String {
+    match unit {
+        Unit::Count => format!("ecoscan_{}_total", snake_case(name)),
+        Unit::Milliseconds => format!("ecoscan_{}_milliseconds", snake_case(name)),
+    }
+}
+
+fn render_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn snake_case(name: &str) -> String {
+    let mut output = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                output.push('_');
+            }
+            output.push(c.to_ascii_lowercase());
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use shared::clock::FakeClock;
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_emf_document_declares_metric_and_dimensions() {
+        let buffer = Buffer::default();
+        let clock = FakeClock::new(Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap());
+        let recorder = EmfRecorder::with_sink("EcoScan", Box::new(buffer.clone()), Arc::new(clock));
+
+        recorder.timing("DatabaseLatency", Duration::from_millis(42), &[("Operation", "update_status")]);
+        recorder.increment("ColdStarts", &[]);
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+
+        let latency = &lines[0];
+        let directive = &latency["_aws"]["CloudWatchMetrics"][0];
+        assert_eq!(latency["_aws"]["Timestamp"], 1_710_936_000_000i64);
+        assert_eq!(directive["Namespace"], "EcoScan");
+        assert_eq!(directive["Dimensions"], json!([["Operation"]]));
+        assert_eq!(directive["Metrics"], json!([{"Name": "DatabaseLatency", "Unit": "Milliseconds"}]));
+        assert_eq!(latency["Operation"], "update_status");
+        assert_eq!(latency["DatabaseLatency"], 42.0);
+
+        assert_eq!(lines[1]["_aws"]["CloudWatchMetrics"][0]["Dimensions"], json!([[]]));
+        assert_eq!(lines[1]["ColdStarts"], 1.0);
+    }
+
+    #[test]
+    fn test_prometheus_renders_counters_and_summaries() {
+        let recorder = PrometheusRecorder::new();
+        recorder.increment("ReportsRejected", &[("Source", "citizen"), ("Reason", "bin_not_found")]);
+        recorder.increment("ReportsRejected", &[("Reason", "bin_not_found"), ("Source", "citizen")]);
+        recorder.timing("DatabaseLatency", Duration::from_millis(10), &[("Operation", "get_bin")]);
+        recorder.timing("DatabaseLatency", Duration::from_millis(30), &[("Operation", "get_bin")]);
+
+        assert_eq!(
+            recorder.render(),
+            "# TYPE ecoscan_database_latency_milliseconds summary\n\
+             ecoscan_database_latency_milliseconds_sum{operation=\"get_bin\"} 40\n\
+             ecoscan_database_latency_milliseconds_count{operation=\"get_bin\"} 2\n\
+             # TYPE ecoscan_reports_rejected_total counter\n\
+             ecoscan_reports_rejected_total{reason=\"bin_not_found\",source=\"citizen\"} 2\n"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeSet, HashMap};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use chrono::{DateTime, Utc};
+use tracing::{info, instrument};
+
+use crate::error::{AppError, DatabaseError};
+use crate::infrastructure::dynamodb::{db_error, number_attr};
+use crate::infrastructure::items::{BinItem, SCHEMA_VERSION_ATTRIBUTE};
+use crate::infrastructure::schema::{self, SchemaChange, TableNames, TableSchema};
+
+/// A one-off rewrite of stored items. It runs once per environment, but a
+/// run interrupted before it is recorded starts over, so it must be safe
+/// to repeat.
+#[async_trait]
+pub trait DataMigration: Send + Sync {
+    /// Migrations run in ascending version order. Versions are never reused.
+    fn version(&self) -> u32;
+
+    fn description(&self) -> &'static str;
+
+    /// Returns the number of items changed.
+    async fn run(&self, client: &Client, tables: &TableNames) -> Result<usize, AppError>;
+}
+
+/// Every data migration, oldest first.
+pub fn data_migrations() -> Vec<Box<dyn DataMigration>> {
+    vec![Box::new(BackfillBinWeights)]
+}
+
+/// What `apply` would do.
+#[derive(Debug, Default)]
+pub struct MigrationPlan {
+    pub schema_changes: Vec<SchemaChange>,
+    pub pending: Vec<(u32, &'static str)>,
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.schema_changes.is_empty() && self.pending.is_empty()
+    }
+}
+
+/// What `apply` did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub schema_changes: usize,
+    /// Versions applied in this run with the number of items each changed.
+    pub applied: Vec<(u32, usize)>,
+}
+
+/// Brings the tables to the declared schema, then runs the data migrations
+/// not yet recorded in the migrations table.
+pub struct Migrator {
+    client: Client,
+    tables: TableNames,
+    migrations: Vec<Box<dyn DataMigration>>,
+}
+
+impl Migrator {
+    pub fn new(client: Client, tables: TableNames) -> Self {
+        Self::with_migrations(client, tables, data_migrations())
+    }
+
+    pub fn with_migrations(client: Client, tables: TableNames, mut migrations: Vec<Box<dyn DataMigration>>) -> Self {
+        migrations.sort_by_key(|migration| migration.version());
+        Self { client, tables, migrations }
+    }
+
+    pub async fn plan(&self) -> Result<MigrationPlan, AppError> {
+        let mut plan = MigrationPlan::default();
+        for table in schema::tables(&self.tables) {
+            let current = schema::describe(&self.client, &table.name).await?;
+            plan.schema_changes.extend(schema::plan(&table, current.as_ref())?);
+        }
+
+        let applied = self.applied_versions().await?;
+        plan.pending = self
+            .migrations
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version()))
+            .map(|migration| (migration.version(), migration.description()))
+            .collect();
+        Ok(plan)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn apply(&self, now: DateTime<Utc>) -> Result<MigrationReport, AppError> {
+        let mut report = MigrationReport::default();
+        for table in schema::tables(&self.tables) {
+            report.schema_changes += self.apply_schema(&table).await?;
+        }
+
+        let applied = self.applied_versions().await?;
+        for migration in &self.migrations {
+            if applied.contains(&migration.version()) {
+                continue;
+            }
+            info!(version = migration.version(), "Running migration: {}", migration.description());
+            let changed = migration.run(&self.client, &self.tables).await?;
+            self.record(migration.as_ref(), changed, now).await?;
+            report.applied.push((migration.version(), changed));
+        }
+        Ok(report)
+    }
+
+    async fn apply_schema(&self, table: &TableSchema) -> Result<usize, AppError> {
+        let current = schema::describe(&self.client, &table.name).await?;
+        let changes = schema::plan(table, current.as_ref())?;
+        for change in &changes {
+            schema::apply(&self.client, table, change).await?;
+        }
+        Ok(changes.len())
+    }
+
+    /// Versions recorded so far; none if the table does not exist yet.
+    pub async fn applied_versions(&self) -> Result<BTreeSet<u32>, AppError> {
+        if schema::describe(&self.client, &self.tables.migrations).await?.is_none() {
+            return Ok(BTreeSet::new());
+        }
+
+        let mut versions = BTreeSet::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.tables.migrations)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+            versions.extend(output.items().iter().filter_map(|item| number_attr::<u32>(item, "version")));
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(versions);
+            }
+        }
+    }
+
+    async fn record(&self, migration: &dyn DataMigration, changed: usize, applied_at: DateTime<Utc>) -> Result<(), AppError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.tables.migrations)
+            .item("version", AttributeValue::N(migration.version().to_string()))
+            .item("description", AttributeValue::S(migration.description().to_string()))
+            .item("appliedAt", AttributeValue::S(applied_at.to_rfc3339()))
+            .item("itemsChanged", AttributeValue::N(changed.to_string()))
+            .condition_expression("attribute_not_exists(version)")
+            .send()
+            .await;
+
+        match result.map_err(db_error) {
+            Ok(_) => Ok(()),
+            // A concurrent run recorded it first
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Bins written before reports were weighted carry only the average
+/// `status` and `reportsCount`. This stores the equivalent weighted sums so
+/// the next update does not have to reconstruct them.
+struct BackfillBinWeights;
+
+#[async_trait]
+impl DataMigration for BackfillBinWeights {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill weightedSum and weightTotal on legacy bins"
+    }
+
+    async fn run(&self, client: &Client, tables: &TableNames) -> Result<usize, AppError> {
+        let mut changed = 0;
+        let mut start_key = None;
+        loop {
+            let output = client
+                .scan()
+                .table_name(&tables.bins)
+                .filter_expression("attribute_not_exists(weightedSum)")
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in output.items() {
+                if backfill_weights(client, &tables.bins, item).await? {
+                    changed += 1;
+                }
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(changed);
+            }
+        }
+    }
+}
+
+async fn backfill_weights(
+    client: &Client,
+    table: &str,
+    item: &HashMap<String, AttributeValue>,
+) -> Result<bool, AppError> {
+    let bin = BinItem::parse(item)?;
+    let aggregate = bin.aggregate();
+
+    let result = client
+        .update_item()
+        .table_name(table)
+        .key("binId", AttributeValue::S(bin.bin_id.to_string()))
+        .update_expression("SET weightedSum = :ws, weightTotal = :wt, #v = :v")
+        // A report may have been folded in since the scan
+        .condition_expression("attribute_not_exists(weightedSum)")
+        .expression_attribute_names("#v", SCHEMA_VERSION_ATTRIBUTE)
+        .expression_attribute_values(":ws", AttributeValue::N(aggregate.weighted_sum.to_string()))
+        .expression_attribute_values(":wt", AttributeValue::N(aggregate.weight_total.to_string()))
+        .expression_attribute_values(":v", AttributeValue::N(bin.schema_version.to_string()))
+        .send()
+        .await;
+
+    match result.map_err(db_error) {
+        Ok(_) => Ok(true),
+        Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::config::retry::RetryConfig;
+    use aws_sdk_dynamodb::types::{
+        AttributeDefinition, GlobalSecondaryIndex, KeySchemaElement, KeyType, Projection, ProjectionType,
+        ProvisionedThroughput, ScalarAttributeType,
+    };
+    use crate::infrastructure::dynamodb::connect;
+    use uuid::Uuid;
+
+    async fn dynamodb_local() -> Client {
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let endpoint = std::env::var("DYNAMODB_LOCAL_URL").unwrap_or_else(|_| "http://localhost:8000".to_string());
+        connect(Some(&endpoint), RetryConfig::standard()).await
+    }
+
+    /// The bins table as `init-localstack.sh` used to create it.
+    async fn create_legacy_bins_table(client: &Client, table: &str) {
+        let throughput = ProvisionedThroughput::builder()
+            .read_capacity_units(5)
+            .write_capacity_units(5)
+            .build()
+            .unwrap();
+        let key = |name: &str| KeySchemaElement::builder().attribute_name(name).key_type(KeyType::Hash).build().unwrap();
+        let attribute = |name: &str, kind| {
+            AttributeDefinition::builder().attribute_name(name).attribute_type(kind).build().unwrap()
+        };
+
+        client
+            .create_table()
+            .table_name(table)
+            .attribute_definitions(attribute("binId", ScalarAttributeType::S))
+            .attribute_definitions(attribute("status", ScalarAttributeType::N))
+            .key_schema(key("binId"))
+            .provisioned_throughput(throughput.clone())
+            .global_secondary_indexes(
+                GlobalSecondaryIndex::builder()
+                    .index_name("status-index")
+                    .key_schema(key("status"))
+                    .projection(Projection::builder().projection_type(ProjectionType::All).build())
+                    .provisioned_throughput(throughput)
+                    .build()
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .unwrap();
+    }
+
+    /// Runs against DynamoDB Local from docker-compose:
+    /// `docker-compose up -d dynamodb-local && cargo test -- --ignored dynamodb_local`
+    #[tokio::test]
+    #[ignore = "requires DynamoDB Local on localhost:8000"]
+    async fn test_migrate_legacy_layout_against_dynamodb_local() {
+        let client = dynamodb_local().await;
+        let tables = TableNames::with_prefix(&format!("migrate-{}-", Uuid::new_v4()));
+        create_legacy_bins_table(&client, &tables.bins).await;
+
+        let bin_id = Uuid::new_v4();
+        client
+            .put_item()
+            .table_name(&tables.bins)
+            .item("binId", AttributeValue::S(bin_id.to_string()))
+            .item("status", AttributeValue::N("6".to_string()))
+            .item("reportsCount", AttributeValue::N("4".to_string()))
+            .send()
+            .await
+            .unwrap();
+
+        let migrator = Migrator::new(client.clone(), tables.clone());
+        let first = migrator.apply(Utc::now()).await.unwrap();
+        assert_eq!(first.applied, vec![(1, 1)]);
+        assert!(first.schema_changes >= 5);
+
+        let bins = schema::describe(&client, &tables.bins).await.unwrap().unwrap();
+        assert!(bins.global_secondary_indexes().is_empty());
+        for table in [&tables.reports, &tables.devices, &tables.migrations] {
+            assert!(schema::describe(&client, table).await.unwrap().is_some());
+        }
+
+        let item = client
+            .get_item()
+            .table_name(&tables.bins)
+            .key("binId", AttributeValue::S(bin_id.to_string()))
+            .send()
+            .await
+            .unwrap()
+            .item
+            .unwrap();
+        assert_eq!(number_attr::<f64>(&item, "weightedSum"), Some(24.0));
+        assert_eq!(number_attr::<f64>(&item, "weightTotal"), Some(4.0));
+
+        // A second run finds nothing to do
+        assert!(migrator.plan().await.unwrap().is_empty());
+        assert_eq!(migrator.apply(Utc::now()).await.unwrap(), MigrationReport::default());
+    }
+}
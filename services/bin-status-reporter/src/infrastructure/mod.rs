@@ -1,3 +1,19 @@
+pub mod archive;
+pub mod cache;
+#[cfg(test)]
+pub mod conformance;
 pub mod dynamodb;
+pub mod fault_injection;
+pub mod items;
+pub mod memory;
+pub mod metrics;
+pub mod migrations;
+pub mod mqtt;
+pub mod notifications;
+pub mod resilience;
+pub mod schema;
+pub mod single_table;
+pub mod stream;
+pub mod telemetry;
 #[cfg(test)]
 pub mod test_utils;
@@ -0,0 +1,636 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, Publish, QoS};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{error, info, instrument, warn};
+
+use crate::application::context::AppContext;
+use crate::application::handle_device_telemetry;
+use crate::domain::device_health::DeviceHealthRepository;
+use crate::domain::lifecycle::LifecycleRepository;
+use crate::domain::sensor::{DeviceRepository, TelemetryMessage};
+use crate::domain::BinRepository;
+use crate::error::{AppError, DatabaseError};
+
+#[derive(Debug, Clone)]
+pub struct MqttBridgeConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub topics: Vec<String>,
+    pub dead_letter_topic: String,
+    /// Messages buffered between the network loop and the worker. When the
+    /// buffer is full the network loop stops reading from the broker.
+    pub queue_capacity: usize,
+    pub max_reconnect_delay: Duration,
+    /// How often a message failing with a transient error is processed
+    /// before it is dead-lettered.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each further one up to
+    /// `max_reconnect_delay`.
+    pub retry_delay: Duration,
+}
+
+impl MqttBridgeConfig {
+    pub fn from_env() -> Result<Self, AppError> {
+        let topics: Vec<String> = std::env::var("MQTT_TOPICS")
+            .unwrap_or_else(|_| "ecoscan/+/telemetry".to_string())
+            .split(',')
+            .map(|topic| topic.trim().to_string())
+            .filter(|topic| !topic.is_empty())
+            .collect();
+        if topics.is_empty() {
+            return Err(AppError::InternalError("MQTT_TOPICS must name at least one topic".to_string()));
+        }
+
+        Ok(Self {
+            host: std::env::var("MQTT_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            port: parse_env("MQTT_PORT", 1883)?,
+            client_id: std::env::var("MQTT_CLIENT_ID")
+                .unwrap_or_else(|_| "ecoscan-mqtt-bridge".to_string()),
+            username: std::env::var("MQTT_USERNAME").ok(),
+            password: std::env::var("MQTT_PASSWORD").ok(),
+            topics,
+            dead_letter_topic: std::env::var("MQTT_DEAD_LETTER_TOPIC")
+                .unwrap_or_else(|_| "ecoscan/dead-letter".to_string()),
+            queue_capacity: parse_env("MQTT_QUEUE_CAPACITY", 64)?,
+            max_reconnect_delay: Duration::from_secs(parse_env("MQTT_MAX_RECONNECT_DELAY_SECS", 60)?),
+            max_attempts: parse_env("MQTT_MAX_ATTEMPTS", 5)?,
+            retry_delay: Duration::from_millis(parse_env("MQTT_RETRY_DELAY_MS", 500)?),
+        })
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts.max(1),
+            initial_delay: self.retry_delay,
+            max_delay: self.max_reconnect_delay.max(self.retry_delay),
+        }
+    }
+
+    fn mqtt_options(&self) -> MqttOptions {
+        let mut options = MqttOptions::new(&self.client_id, &self.host, self.port);
+        options
+            .set_keep_alive(Duration::from_secs(30))
+            // A persistent session lets the broker hold QoS 1 messages while we reconnect
+            .set_clean_session(false)
+            // Acknowledge only once a message is stored or dead-lettered
+            .set_manual_acks(true)
+            .set_inflight(self.queue_capacity.clamp(1, u16::MAX as usize) as u16);
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            options.set_credentials(username, password);
+        }
+        options
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, default: T) -> Result<T, AppError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| AppError::InternalError(format!("{} has an invalid value: {}", name, value))),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Telemetry is accepted as JSON or CBOR. JSON objects always start with
+/// `{`, which as a CBOR initial byte would be an array header, so the first
+/// non-whitespace byte tells the encodings apart.
+pub fn parse_telemetry(payload: &[u8]) -> Result<TelemetryMessage, AppError> {
+    let is_json = payload
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{');
+
+    if is_json {
+        serde_json::from_slice(payload)
+            .map_err(|e| AppError::InvalidRequest(format!("Invalid JSON telemetry: {}", e)))
+    } else {
+        ciborium::from_reader(payload)
+            .map_err(|e| AppError::InvalidRequest(format!("Invalid CBOR telemetry: {}", e)))
+    }
+}
+
+/// Where messages that can never be processed are parked for inspection.
+#[async_trait]
+pub trait DeadLetterSink {
+    async fn dead_letter(&self, topic: &str, payload: &[u8], reason: &str) -> Result<(), AppError>;
+}
+
+#[derive(Serialize)]
+struct DeadLetter<'a> {
+    source_topic: &'a str,
+    reason: &'a str,
+    payload_hex: String,
+}
+
+/// Republishes dead letters to a broker topic as JSON envelopes.
+pub struct MqttDeadLetterSink {
+    client: AsyncClient,
+    topic: String,
+}
+
+#[async_trait]
+impl DeadLetterSink for MqttDeadLetterSink {
+    async fn dead_letter(&self, topic: &str, payload: &[u8], reason: &str) -> Result<(), AppError> {
+        let envelope = DeadLetter { source_topic: topic, reason, payload_hex: hex::encode(payload) };
+        let body = serde_json::to_vec(&envelope).map_err(|e| AppError::InternalError(e.to_string()))?;
+        self.client
+            .publish(&self.topic, QoS::AtLeastOnce, false, body)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to publish dead letter: {}", e)))
+    }
+}
+
+/// Outcome of processing a single message, returned for logging and tests.
+#[derive(Debug, PartialEq)]
+pub enum Disposition {
+    Stored,
+    DeadLettered,
+    /// A transient failure such as throttling or an open circuit. The
+    /// message must not be acknowledged until a retry stores it.
+    Retry(String),
+    /// The message could not be stored and publishing it as a dead letter
+    /// failed too. It is left unacknowledged so the broker redelivers it.
+    Unacknowledged(String),
+}
+
+/// Parks a message as a dead letter, or leaves it to be redelivered when
+/// the dead letter cannot be published.
+async fn dead_letter<D: DeadLetterSink>(dead_letters: &D, topic: &str, payload: &[u8], reason: &str) -> Disposition {
+    match dead_letters.dead_letter(topic, payload, reason).await {
+        Ok(()) => Disposition::DeadLettered,
+        Err(e) => {
+            error!("Failed to dead-letter message from {}, leaving it unacknowledged: {}", topic, e);
+            Disposition::Unacknowledged(e.to_string())
+        }
+    }
+}
+
+/// Backoff for messages that fail with a transient error.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+fn is_retryable(error: &AppError) -> bool {
+    error.is_transient() || matches!(error, AppError::DatabaseError(DatabaseError::CircuitOpen(_)))
+}
+
+#[instrument(skip_all, fields(topic = topic, payload_bytes = payload.len()))]
+pub async fn process_message<R, D>(
+    repo: &R,
+    ctx: &AppContext,
+    dead_letters: &D,
+    topic: &str,
+    payload: &[u8],
+) -> Disposition
+where
+    R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository,
+    D: DeadLetterSink,
+{
+    let result = match parse_telemetry(payload) {
+        Ok(message) => handle_device_telemetry(repo, ctx, message).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(response) => {
+            info!("Stored telemetry from {}: {}", topic, response.message);
+            Disposition::Stored
+        }
+        Err(
+            e @ (AppError::InvalidRequest(_)
+            | AppError::Unauthorized(_)
+            | AppError::BinNotFound(_)
+            | AppError::BinOutOfService(_)),
+        ) => {
+            warn!("Dead-lettering message from {}: {}", topic, e);
+            dead_letter(dead_letters, topic, payload, &e.to_string()).await
+        }
+        Err(e) if is_retryable(&e) => {
+            warn!("Transient failure processing message from {}: {}", topic, e);
+            Disposition::Retry(e.to_string())
+        }
+        Err(e) => {
+            error!("Dead-lettering message from {} after processing failure: {}", topic, e);
+            dead_letter(dead_letters, topic, payload, &e.to_string()).await
+        }
+    }
+}
+
+/// Processes a message until it is stored or dead-lettered, retrying
+/// transient failures with exponential backoff. A message still failing
+/// after `max_attempts` is dead-lettered so the worker can move on, or
+/// left unacknowledged if that fails too.
+pub async fn deliver_message<R, D>(
+    repo: &R,
+    ctx: &AppContext,
+    dead_letters: &D,
+    policy: RetryPolicy,
+    topic: &str,
+    payload: &[u8],
+) -> Disposition
+where
+    R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository,
+    D: DeadLetterSink,
+{
+    let mut delay = policy.initial_delay;
+    let mut attempt = 1;
+
+    loop {
+        let reason = match process_message(repo, ctx, dead_letters, topic, payload).await {
+            Disposition::Retry(reason) => reason,
+            disposition => return disposition,
+        };
+        if attempt >= policy.max_attempts {
+            let reason = format!("Gave up after {} attempts: {}", attempt, reason);
+            error!("Dead-lettering message from {}: {}", topic, reason);
+            return dead_letter(dead_letters, topic, payload, &reason).await;
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(policy.max_delay);
+        attempt += 1;
+    }
+}
+
+/// Long-running bridge from an MQTT broker into the repository layer.
+pub struct MqttBridge<R> {
+    config: MqttBridgeConfig,
+    repo: Arc<R>,
+    ctx: AppContext,
+}
+
+impl<R> MqttBridge<R>
+where
+    R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository + Send + Sync + 'static,
+{
+    pub fn new(config: MqttBridgeConfig, repo: R) -> Self {
+        Self { config, repo: Arc::new(repo), ctx: AppContext::default() }
+    }
+
+    pub fn with_context(mut self, ctx: AppContext) -> Self {
+        self.ctx = ctx;
+        self
+    }
+
+    /// Runs until the worker stops. Connection failures never end the loop;
+    /// they are retried with exponential backoff capped at
+    /// `max_reconnect_delay`.
+    pub async fn run(self) -> Result<(), AppError> {
+        let (client, eventloop) = AsyncClient::new(self.config.mqtt_options(), self.config.queue_capacity.max(10));
+        let (sender, receiver) = mpsc::channel::<Publish>(self.config.queue_capacity.max(1));
+
+        let worker = tokio::spawn(run_worker(
+            self.repo.clone(),
+            self.ctx.clone(),
+            MqttDeadLetterSink { client: client.clone(), topic: self.config.dead_letter_topic.clone() },
+            self.config.retry_policy(),
+            client.clone(),
+            receiver,
+        ));
+
+        poll_broker(&self.config, client, eventloop, sender).await;
+        worker
+            .await
+            .map_err(|e| AppError::InternalError(format!("Bridge worker panicked: {}", e)))
+    }
+}
+
+async fn poll_broker(
+    config: &MqttBridgeConfig,
+    client: AsyncClient,
+    mut eventloop: EventLoop,
+    sender: mpsc::Sender<Publish>,
+) {
+    let mut reconnect_delay = Duration::from_millis(500);
+
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker {}:{}", config.host, config.port);
+                reconnect_delay = Duration::from_millis(500);
+                for topic in &config.topics {
+                    if let Err(e) = client.try_subscribe(topic.as_str(), QoS::AtLeastOnce) {
+                        error!("Failed to subscribe to {}: {}", topic, e);
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                // Blocks while the worker is behind, which stops us reading
+                // from the socket and lets the broker's inflight window apply
+                if sender.send(publish).await.is_err() {
+                    warn!("Worker stopped, shutting down MQTT bridge");
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("MQTT connection error: {}, reconnecting in {:?}", e, reconnect_delay);
+                tokio::time::sleep(reconnect_delay).await;
+                reconnect_delay = (reconnect_delay * 2).min(config.max_reconnect_delay);
+            }
+        }
+    }
+}
+
+async fn run_worker<R, D>(
+    repo: Arc<R>,
+    ctx: AppContext,
+    dead_letters: D,
+    policy: RetryPolicy,
+    client: AsyncClient,
+    mut receiver: mpsc::Receiver<Publish>,
+)
+where
+    R: BinRepository + DeviceRepository + DeviceHealthRepository + LifecycleRepository,
+    D: DeadLetterSink,
+{
+    // Retrying in place holds up the queue, which keeps the backpressure on
+    // the broker while the database recovers
+    while let Some(publish) = receiver.recv().await {
+        let disposition = deliver_message(repo.as_ref(), &ctx, &dead_letters, policy, &publish.topic, &publish.payload).await;
+        // Without an ack the persistent session redelivers the message
+        if matches!(disposition, Disposition::Unacknowledged(_)) {
+            continue;
+        }
+        if let Err(e) = client.ack(&publish).await {
+            error!("Failed to acknowledge message from {}: {}", publish.topic, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::lifecycle::handle_lifecycle_change;
+    use crate::domain::lifecycle::{LifecycleRequest, LifecycleState};
+    use crate::domain::sensor::{BinCalibration, Device};
+    use crate::infrastructure::fault_injection::{FaultConfig, FaultInjectingRepository};
+    use crate::infrastructure::memory::InMemoryRepository;
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct CapturingSink {
+        letters: Mutex<Vec<(String, Vec<u8>, String)>>,
+    }
+
+    #[async_trait]
+    impl DeadLetterSink for CapturingSink {
+        async fn dead_letter(&self, topic: &str, payload: &[u8], reason: &str) -> Result<(), AppError> {
+            self.letters.lock().await.push((topic.to_string(), payload.to_vec(), reason.to_string()));
+            Ok(())
+        }
+    }
+
+    fn repo_with_device(device_id: &str) -> (InMemoryRepository, Uuid) {
+        let repo = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        repo.add_bin(bin_id);
+        repo.set_calibration(bin_id, BinCalibration::new(1000, 200).unwrap());
+        repo.register_device(Device {
+            device_id: device_id.to_string(),
+            bin_id,
+            key_hash: String::new(),
+            vendor: "ecoscan".to_string(),
+            is_active: true,
+        });
+        (repo, bin_id)
+    }
+
+    fn cbor(value: &serde_json::Value) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(value, &mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_parse_json_telemetry() {
+        let message = parse_telemetry(br#" {"device_id":"s-1","distance_mm":440,"battery_mv":3600}"#).unwrap();
+        assert_eq!(message.device_id, "s-1");
+        assert_eq!(message.frame.distance_mm, 440);
+        assert_eq!(message.frame.tilt_deg, None);
+    }
+
+    #[test]
+    fn test_parse_cbor_telemetry() {
+        let payload = cbor(&serde_json::json!({
+            "device_id": "s-1", "distance_mm": 600, "battery_mv": 3300, "tilt_deg": 4
+        }));
+        let message = parse_telemetry(&payload).unwrap();
+        assert_eq!(message.frame.distance_mm, 600);
+        assert_eq!(message.frame.tilt_deg, Some(4));
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(matches!(parse_telemetry(b"{not json"), Err(AppError::InvalidRequest(_))));
+        assert!(matches!(parse_telemetry(&[0xff, 0x00, 0x13]), Err(AppError::InvalidRequest(_))));
+        assert!(matches!(parse_telemetry(b""), Err(AppError::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_process_message_stores_reading() {
+        let (repo, bin_id) = repo_with_device("s-1");
+        let sink = CapturingSink::default();
+
+        let disposition = process_message(
+            &repo,
+            &AppContext::default(),
+            &sink,
+            "ecoscan/s-1/telemetry",
+            br#"{"device_id":"s-1","distance_mm":440,"battery_mv":3600}"#,
+        )
+        .await;
+
+        assert_eq!(disposition, Disposition::Stored);
+        assert_eq!(repo.bin_status(&bin_id).unwrap().value(), 7);
+        assert!(sink.letters.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_message_dead_letters_unparseable_and_unknown() {
+        let (repo, _) = repo_with_device("s-1");
+        let sink = CapturingSink::default();
+
+        let garbage = process_message(&repo, &AppContext::default(), &sink, "ecoscan/s-1/telemetry", b"\x01\x02garbage").await;
+        let unknown = process_message(
+            &repo,
+            &AppContext::default(),
+            &sink,
+            "ecoscan/s-9/telemetry",
+            br#"{"device_id":"s-9","distance_mm":440,"battery_mv":3600}"#,
+        )
+        .await;
+
+        assert_eq!(garbage, Disposition::DeadLettered);
+        assert_eq!(unknown, Disposition::DeadLettered);
+        let letters = sink.letters.lock().await;
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].1, b"\x01\x02garbage".to_vec());
+        assert!(letters[1].2.contains("s-9"));
+    }
+
+    #[tokio::test]
+    async fn test_process_message_follows_bin_lifecycle() {
+        let (repo, bin_id) = repo_with_device("s-1");
+        let replacement = Uuid::new_v4();
+        repo.add_bin(replacement);
+        let ctx = AppContext::default();
+        let sink = CapturingSink::default();
+        let payload = br#"{"device_id":"s-1","distance_mm":440,"battery_mv":3600}"#;
+        let change = |bin_id, state, replaced_by| {
+            let request = LifecycleRequest { bin_id, state, actor: "crew-7".to_string(), reason: None, replaced_by };
+            handle_lifecycle_change(&repo, &ctx, request)
+        };
+
+        change(bin_id, LifecycleState::Replaced, Some(replacement)).await.unwrap();
+        let followed = process_message(&repo, &ctx, &sink, "ecoscan/s-1/telemetry", payload).await;
+        change(replacement, LifecycleState::Removed, None).await.unwrap();
+        let refused = process_message(&repo, &ctx, &sink, "ecoscan/s-1/telemetry", payload).await;
+
+        assert_eq!((followed, refused), (Disposition::Stored, Disposition::DeadLettered));
+        assert_eq!(repo.bin_status(&replacement).unwrap().value(), 7);
+        assert!(repo.reports(&bin_id).is_empty());
+        assert!(sink.letters.lock().await[0].2.contains("removed"));
+    }
+
+    struct FailingSink;
+
+    #[async_trait]
+    impl DeadLetterSink for FailingSink {
+        async fn dead_letter(&self, _topic: &str, _payload: &[u8], _reason: &str) -> Result<(), AppError> {
+            Err(AppError::InternalError("Failed to publish dead letter: disconnected".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_message_is_left_unacknowledged_when_dead_lettering_fails() {
+        let (repo, _) = repo_with_device("s-1");
+        let repo = FaultInjectingRepository::new(
+            repo,
+            FaultConfig::new(7).with_throttle_rate(1.0).targeting(&["get_device"]),
+        );
+        let payload = br#"{"device_id":"s-1","distance_mm":440,"battery_mv":3600}"#;
+
+        let garbage = process_message(&repo, &AppContext::default(), &FailingSink, "ecoscan/s-1/telemetry", b"garbage").await;
+        let gave_up =
+            deliver_message(&repo, &AppContext::default(), &FailingSink, quick_retries(2), "ecoscan/s-1/telemetry", payload).await;
+
+        assert!(matches!(garbage, Disposition::Unacknowledged(_)));
+        assert!(matches!(gave_up, Disposition::Unacknowledged(_)));
+    }
+
+    fn quick_retries(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy { max_attempts, initial_delay: Duration::from_millis(1), max_delay: Duration::from_millis(4) }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried_until_stored() {
+        let (repo, bin_id) = repo_with_device("s-1");
+        let repo = FaultInjectingRepository::new(
+            repo,
+            FaultConfig::new(7).with_throttle_rate(0.5).targeting(&["get_device"]),
+        );
+        let sink = CapturingSink::default();
+        let payload = br#"{"device_id":"s-1","distance_mm":440,"battery_mv":3600}"#;
+
+        let transient = process_message(&repo, &AppContext::default(), &sink, "ecoscan/s-1/telemetry", payload).await;
+        let delivered =
+            deliver_message(&repo, &AppContext::default(), &sink, quick_retries(10), "ecoscan/s-1/telemetry", payload).await;
+
+        assert!(repo.stats().throttled > 0);
+        assert!(matches!(transient, Disposition::Retry(_)));
+        assert_eq!(delivered, Disposition::Stored);
+        assert_eq!(repo.inner().bin_status(&bin_id).unwrap().value(), 7);
+        assert!(sink.letters.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_persistent_transient_failures_are_dead_lettered_after_retries() {
+        let (repo, _) = repo_with_device("s-1");
+        let repo = FaultInjectingRepository::new(
+            repo,
+            FaultConfig::new(7).with_throttle_rate(1.0).targeting(&["get_device"]),
+        );
+        let sink = CapturingSink::default();
+        let payload = br#"{"device_id":"s-1","distance_mm":440,"battery_mv":3600}"#;
+
+        let transient = process_message(&repo, &AppContext::default(), &sink, "ecoscan/s-1/telemetry", payload).await;
+        assert!(matches!(transient, Disposition::Retry(_)));
+        assert!(sink.letters.lock().await.is_empty());
+
+        let delivered =
+            deliver_message(&repo, &AppContext::default(), &sink, quick_retries(3), "ecoscan/s-1/telemetry", payload).await;
+
+        assert_eq!(delivered, Disposition::DeadLettered);
+        assert_eq!(repo.stats().throttled, 4);
+        let letters = sink.letters.lock().await;
+        assert_eq!(letters.len(), 1);
+        assert!(letters[0].2.starts_with("Gave up after 3 attempts"));
+    }
+
+    /// Runs against the mosquitto container from docker-compose:
+    /// `docker-compose up -d mosquitto && cargo test -- --ignored mqtt_broker`
+    #[tokio::test]
+    #[ignore = "requires a local MQTT broker on localhost:1883"]
+    async fn test_bridge_against_mqtt_broker() {
+        let (repo, bin_id) = repo_with_device("broker-test");
+        let config = MqttBridgeConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: format!("ecoscan-test-{}", Uuid::new_v4()),
+            username: None,
+            password: None,
+            topics: vec!["ecoscan-test/+/telemetry".to_string()],
+            dead_letter_topic: "ecoscan-test/dead-letter".to_string(),
+            queue_capacity: 4,
+            max_reconnect_delay: Duration::from_secs(1),
+            max_attempts: 3,
+            retry_delay: Duration::from_millis(100),
+        };
+        let bridge = tokio::spawn(MqttBridge::new(config, repo.clone()).run());
+
+        let (publisher, mut publisher_loop) =
+            AsyncClient::new(MqttOptions::new(format!("ecoscan-pub-{}", Uuid::new_v4()), "localhost", 1883), 10);
+        publisher.subscribe("ecoscan-test/dead-letter", QoS::AtLeastOnce).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        publisher
+            .publish(
+                "ecoscan-test/broker-test/telemetry",
+                QoS::AtLeastOnce,
+                false,
+                br#"{"device_id":"broker-test","distance_mm":200,"battery_mv":3600}"#.to_vec(),
+            )
+            .await
+            .unwrap();
+        publisher
+            .publish("ecoscan-test/broker-test/telemetry", QoS::AtLeastOnce, false, b"garbage".to_vec())
+            .await
+            .unwrap();
+
+        let dead_letter = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(Event::Incoming(Packet::Publish(publish))) = publisher_loop.poll().await {
+                    if publish.topic == "ecoscan-test/dead-letter" {
+                        return publish;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("dead letter was not published");
+
+        assert!(String::from_utf8_lossy(&dead_letter.payload).contains(&hex::encode("garbage")));
+        assert_eq!(repo.bin_status(&bin_id).unwrap().value(), 10);
+        bridge.abort();
+    }
+}
@@ -0,0 +1,216 @@
+use async_trait::async_trait;
+use tracing::{error, warn};
+
+use crate::domain::device_health::{AlertNotifier, DeviceAlert};
+use crate::domain::incident::{IncidentAction, IncidentDispatcher};
+use crate::error::AppError;
+
+/// A channel that carries both device alerts and incident actions.
+pub trait Channel: AlertNotifier + IncidentDispatcher + Send + Sync {}
+
+impl<T: AlertNotifier + IncidentDispatcher + Send + Sync> Channel for T {}
+
+/// Writes alerts as structured log events, which a CloudWatch metric filter
+/// can turn into an alarm without any extra infrastructure.
+pub struct LogNotifier;
+
+#[async_trait]
+impl AlertNotifier for LogNotifier {
+    async fn notify(&self, alert: &DeviceAlert) -> Result<(), AppError> {
+        warn!(
+            device_id = %alert.device_id,
+            bin_id = %alert.bin_id,
+            flag = alert.flag.kind(),
+            "Device health alert"
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IncidentDispatcher for LogNotifier {
+    async fn dispatch(&self, action: &IncidentAction) -> Result<(), AppError> {
+        match action {
+            IncidentAction::OpenTicket(ticket) => warn!(
+                bin_id = %ticket.bin_id,
+                report_id = %ticket.report_id,
+                part = ?ticket.part,
+                "Maintenance ticket opened"
+            ),
+            IncidentAction::Escalate(escalation) => error!(
+                bin_id = %escalation.bin_id,
+                report_id = %escalation.report_id,
+                kind = escalation.kind.as_str(),
+                "Incident escalated: {}",
+                escalation.summary
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// POSTs each alert and incident action as JSON to a chat or paging
+/// webhook.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        Self { client: reqwest::Client::new(), url }
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for WebhookNotifier {
+    async fn notify(&self, alert: &DeviceAlert) -> Result<(), AppError> {
+        self.client
+            .post(&self.url)
+            .json(alert)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AppError::InternalError(format!("Alert webhook failed: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IncidentDispatcher for WebhookNotifier {
+    async fn dispatch(&self, action: &IncidentAction) -> Result<(), AppError> {
+        self.client
+            .post(&self.url)
+            .json(action)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AppError::InternalError(format!("Incident webhook failed: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Delivers every alert and incident action to all configured channels.
+/// Delivery counts as successful if at least one channel accepted it.
+pub struct FanoutNotifier {
+    channels: Vec<Box<dyn Channel>>,
+}
+
+impl FanoutNotifier {
+    pub fn new(channels: Vec<Box<dyn Channel>>) -> Self {
+        Self { channels }
+    }
+
+    /// Always logs; also posts to `ALERT_WEBHOOK_URL` when it is set.
+    pub fn from_env() -> Self {
+        let mut channels: Vec<Box<dyn Channel>> = vec![Box::new(LogNotifier)];
+        if let Ok(url) = std::env::var("ALERT_WEBHOOK_URL") {
+            channels.push(Box::new(WebhookNotifier::new(url)));
+        }
+        Self::new(channels)
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for FanoutNotifier {
+    async fn notify(&self, alert: &DeviceAlert) -> Result<(), AppError> {
+        let mut last_error = None;
+        let mut delivered = false;
+        for channel in &self.channels {
+            match channel.notify(alert).await {
+                Ok(()) => delivered = true,
+                Err(e) => {
+                    error!("Alert channel failed for device {}: {}", alert.device_id, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match (delivered, last_error) {
+            (false, Some(e)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl IncidentDispatcher for FanoutNotifier {
+    async fn dispatch(&self, action: &IncidentAction) -> Result<(), AppError> {
+        let mut last_error = None;
+        let mut delivered = false;
+        for channel in &self.channels {
+            match channel.dispatch(action).await {
+                Ok(()) => delivered = true,
+                Err(e) => {
+                    error!("Incident channel failed: {}", e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match (delivered, last_error) {
+            (false, Some(e)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::device_health::HealthFlag;
+    use crate::domain::incident::{Escalation, ReportKind};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    struct FailingNotifier;
+
+    #[async_trait]
+    impl AlertNotifier for FailingNotifier {
+        async fn notify(&self, _alert: &DeviceAlert) -> Result<(), AppError> {
+            Err(AppError::InternalError("channel down".to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl IncidentDispatcher for FailingNotifier {
+        async fn dispatch(&self, _action: &IncidentAction) -> Result<(), AppError> {
+            Err(AppError::InternalError("channel down".to_string()))
+        }
+    }
+
+    fn alert() -> DeviceAlert {
+        DeviceAlert {
+            device_id: "s-1".to_string(),
+            bin_id: Uuid::new_v4(),
+            flag: HealthFlag::LowBattery { battery_mv: 3100 },
+            raised_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fanout_succeeds_if_any_channel_delivers() {
+        let notifier = FanoutNotifier::new(vec![Box::new(FailingNotifier), Box::new(LogNotifier)]);
+        assert!(notifier.notify(&alert()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fanout_fails_if_all_channels_fail() {
+        let notifier = FanoutNotifier::new(vec![Box::new(FailingNotifier)]);
+        assert!(notifier.notify(&alert()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_incident_actions_use_the_same_channels() {
+        let escalation = IncidentAction::Escalate(Escalation {
+            bin_id: Uuid::new_v4(),
+            report_id: Uuid::new_v4(),
+            kind: ReportKind::Fire,
+            summary: "Bin on fire".to_string(),
+            raised_at: Utc::now(),
+        });
+
+        let notifier = FanoutNotifier::new(vec![Box::new(FailingNotifier), Box::new(LogNotifier)]);
+        assert!(notifier.dispatch(&escalation).await.is_ok());
+        let notifier = FanoutNotifier::new(vec![Box::new(FailingNotifier)]);
+        assert!(notifier.dispatch(&escalation).await.is_err());
+    }
+}
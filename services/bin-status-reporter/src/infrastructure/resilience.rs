@@ -0,0 +1,672 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use shared::clock::{Clock, SystemClock};
+use shared::metrics::{MetricsRecorder, NoopRecorder, Unit, DATABASE_LATENCY, DATABASE_RETRIES};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::consistency::{BinHistoryRepository, BinPage, ResumeToken, StoredBin};
+use crate::domain::retention::RetentionRepository;
+use crate::domain::rollup::{DailyRollup, Placement, Resolution, Rollup, RollupRepository, RollupScope, RollupUpdate, Timeline};
+use crate::domain::device_health::{DeviceHealth, DeviceHealthRepository};
+use crate::domain::estimator::BinAggregate;
+use crate::domain::history::BinEvent;
+use crate::domain::incident::{IncidentReport, IncidentRepository, ReportKind};
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleTransition};
+use crate::domain::location::{LocationRepository, PlacedBin};
+use crate::domain::sensor::{BinCalibration, Device, DeviceRepository};
+use crate::domain::{BinProfile, BinProfileRepository, BinRepository, StatusChange, StatusReport};
+use crate::error::{AppError, DatabaseError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts per call, including the first.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Deadline for a single attempt.
+    pub call_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(1),
+            call_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakerPolicy {
+    /// Consecutive failed calls after which the breaker opens.
+    pub failure_threshold: u32,
+    /// How long calls are short-circuited before a trial call is let through.
+    pub open_for: Duration,
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        Self { failure_threshold: 5, open_for: Duration::from_secs(30) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: DateTime<Utc> },
+    /// One trial call is in flight; everything else is still rejected.
+    HalfOpen,
+}
+
+/// Stops calling a backend that keeps throttling, timing out or failing,
+/// so that a struggling table is not hammered by retries.
+pub struct CircuitBreaker {
+    policy: BreakerPolicy,
+    clock: Arc<dyn Clock>,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(policy: BreakerPolicy, clock: Arc<dyn Clock>) -> Self {
+        Self { policy, clock, state: Mutex::new(BreakerState::Closed { failures: 0 }) }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.lock(), BreakerState::Closed { .. })
+    }
+
+    fn allow(&self) -> Result<(), AppError> {
+        let mut state = self.lock();
+        match *state {
+            BreakerState::Closed { .. } => Ok(()),
+            BreakerState::Open { until } if self.clock.now() >= until => {
+                *state = BreakerState::HalfOpen;
+                Ok(())
+            }
+            BreakerState::Open { until } => Err(DatabaseError::CircuitOpen(format!(
+                "storage calls suspended until {}",
+                until.to_rfc3339()
+            ))
+            .into()),
+            BreakerState::HalfOpen => {
+                Err(DatabaseError::CircuitOpen("waiting for trial call".to_string()).into())
+            }
+        }
+    }
+
+    fn record(&self, failed: bool) {
+        let mut state = self.lock();
+        *state = match (*state, failed) {
+            (_, false) => BreakerState::Closed { failures: 0 },
+            (BreakerState::Closed { failures }, true) if failures + 1 < self.policy.failure_threshold => {
+                BreakerState::Closed { failures: failures + 1 }
+            }
+            (_, true) => {
+                let open_for = chrono::Duration::from_std(self.policy.open_for).unwrap_or(chrono::Duration::MAX);
+                warn!("Opening storage circuit breaker for {:?}", self.policy.open_for);
+                BreakerState::Open { until: self.clock.now() + open_for }
+            }
+        };
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Failures that say the backend is struggling, as opposed to a conflict or
+/// a bad request that a healthy backend also returns.
+fn is_outage(error: &AppError) -> bool {
+    matches!(
+        error,
+        AppError::DatabaseError(
+            DatabaseError::Throttled(_) | DatabaseError::Timeout(_) | DatabaseError::Unavailable(_)
+        )
+    )
+}
+
+/// Wraps any repository with per-call deadlines, jittered exponential
+/// backoff and a circuit breaker.
+///
+/// Reads and idempotent writes are retried on any transient error. Writes
+/// that fold into an aggregate are only retried when the error guarantees
+/// nothing was applied, since repeating them after a timeout could count a
+/// report twice.
+pub struct ResilientRepository<R> {
+    inner: R,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
+    jitter: Mutex<StdRng>,
+    metrics: Arc<dyn MetricsRecorder>,
+}
+
+impl<R> ResilientRepository<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_policies(inner, RetryPolicy::default(), BreakerPolicy::default(), Arc::new(SystemClock))
+    }
+
+    pub fn with_policies(inner: R, retry: RetryPolicy, breaker: BreakerPolicy, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner,
+            retry,
+            breaker: CircuitBreaker::new(breaker, clock),
+            jitter: Mutex::new(StdRng::from_entropy()),
+            metrics: Arc::new(NoopRecorder),
+        }
+    }
+
+    /// Records the latency of every attempt and the number of retries.
+    pub fn with_metrics(self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        Self { metrics, ..self }
+    }
+
+    /// Makes the backoff delays reproducible.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { jitter: Mutex::new(StdRng::seed_from_u64(seed)), ..self }
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Full jitter: a uniform delay up to the capped exponential backoff.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let cap = self
+            .retry
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.retry.max_delay);
+        let mut rng = self.jitter.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        cap.mul_f64(rng.gen_range(0.0..=1.0))
+    }
+
+    async fn attempt<T, Fut>(&self, operation: &str, call: Fut) -> Result<T, AppError>
+    where
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        self.breaker.allow()?;
+        let started = Instant::now();
+        let result = match tokio::time::timeout(self.retry.call_timeout, call).await {
+            Ok(result) => result,
+            Err(_) => Err(DatabaseError::Timeout(format!(
+                "{} exceeded {:?}",
+                operation, self.retry.call_timeout
+            ))
+            .into()),
+        };
+        self.breaker.record(matches!(&result, Err(e) if is_outage(e)));
+        self.metrics.timing(DATABASE_LATENCY, started.elapsed(), &[("Operation", operation)]);
+        result
+    }
+
+    async fn call<T, F, Fut>(&self, operation: &str, idempotent: bool, mut call: F) -> Result<T, AppError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.attempt(operation, call()).await {
+                Err(AppError::DatabaseError(e))
+                    if attempt < self.retry.max_attempts
+                        && e.is_transient()
+                        && (idempotent || e.was_rejected()) =>
+                {
+                    let delay = self.backoff(attempt);
+                    warn!("{} failed on attempt {}: {}, retrying in {:?}", operation, attempt, e, delay);
+                    self.metrics.increment(DATABASE_RETRIES, &[("Operation", operation)]);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<R: BinRepository> BinRepository for ResilientRepository<R> {
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        self.call("get_bin", true, || self.inner.get_bin(bin_id)).await
+    }
+
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        self.call("update_status", false, || self.inner.update_status(report)).await
+    }
+
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+        // Reports are keyed by bin, observation time and report id, so a
+        // repeat overwrites the same item
+        self.call("add_report", true, || self.inner.add_report(report)).await
+    }
+
+    async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+        // Conditional on the stored collection time, so a repeat is a no-op
+        self.call("record_collection", true, || self.inner.record_collection(event)).await
+    }
+
+    /// Retries only the groups whose write was rejected outright; the
+    /// others keep their first outcome.
+    async fn record_batch(&self, groups: &[ReportGroup]) -> Vec<Result<(), AppError>> {
+        let mut results: Vec<Option<Result<(), AppError>>> = groups.iter().map(|_| None).collect();
+        let mut pending: Vec<usize> = (0..groups.len()).collect();
+        let mut attempt = 1;
+
+        while !pending.is_empty() {
+            let batch: Vec<ReportGroup> = pending.iter().map(|&index| groups[index].clone()).collect();
+            let started = Instant::now();
+            let outcomes = match self.breaker.allow() {
+                Ok(()) => match tokio::time::timeout(self.retry.call_timeout, self.inner.record_batch(&batch)).await {
+                    Ok(outcomes) => outcomes,
+                    Err(_) => batch
+                        .iter()
+                        .map(|_| Err(DatabaseError::Timeout(format!("record_batch exceeded {:?}", self.retry.call_timeout)).into()))
+                        .collect(),
+                },
+                Err(e) => {
+                    let message = e.to_string();
+                    batch.iter().map(|_| Err(DatabaseError::CircuitOpen(message.clone()).into())).collect()
+                }
+            };
+            self.breaker.record(outcomes.iter().any(|outcome| matches!(outcome, Err(e) if is_outage(e))));
+            self.metrics.timing(DATABASE_LATENCY, started.elapsed(), &[("Operation", "record_batch")]);
+
+            let mut retry = Vec::new();
+            for (index, outcome) in pending.into_iter().zip(outcomes) {
+                match outcome {
+                    Err(AppError::DatabaseError(e))
+                        if attempt < self.retry.max_attempts && e.is_transient() && e.was_rejected() =>
+                    {
+                        retry.push(index);
+                    }
+                    outcome => results[index] = Some(outcome),
+                }
+            }
+
+            if !retry.is_empty() {
+                let delay = self.backoff(attempt);
+                warn!("Retrying {} rejected report groups in {:?}", retry.len(), delay);
+                self.metrics.record(DATABASE_RETRIES, retry.len() as f64, Unit::Count, &[("Operation", "record_batch")]);
+                tokio::time::sleep(delay).await;
+            }
+            pending = retry;
+            attempt += 1;
+        }
+
+        results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| Err(AppError::InternalError("Backend returned no outcome for group".to_string())))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<R: BinHistoryRepository> BinHistoryRepository for ResilientRepository<R> {
+    async fn scan_bins(&self, resume: Option<&ResumeToken>, limit: usize) -> Result<BinPage, AppError> {
+        self.call("scan_bins", true, || self.inner.scan_bins(resume, limit)).await
+    }
+
+    async fn bin_events(
+        &self,
+        bin_id: &Uuid,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        self.call("bin_events", true, || self.inner.bin_events(bin_id, since, until)).await
+    }
+
+    async fn repair_bin(&self, stored: &StoredBin, recomputed: &BinAggregate) -> Result<bool, AppError> {
+        // A repeat after a lost response would find the bin changed
+        self.call("repair_bin", false, || self.inner.repair_bin(stored, recomputed)).await
+    }
+}
+
+#[async_trait]
+impl<R: RetentionRepository> RetentionRepository for ResilientRepository<R> {
+    async fn reports_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<StatusReport>, AppError> {
+        self.call("reports_before", true, || self.inner.reports_before(bin_id, before)).await
+    }
+
+    async fn delete_reports(&self, bin_id: &Uuid, reports: &[StatusReport]) -> Result<(), AppError> {
+        self.call("delete_reports", true, || self.inner.delete_reports(bin_id, reports)).await
+    }
+
+    async fn incidents_before(&self, bin_id: &Uuid, before: DateTime<Utc>) -> Result<Vec<IncidentReport>, AppError> {
+        self.call("incidents_before", true, || self.inner.incidents_before(bin_id, before)).await
+    }
+
+    async fn delete_incidents(&self, bin_id: &Uuid, incidents: &[IncidentReport]) -> Result<(), AppError> {
+        self.call("delete_incidents", true, || self.inner.delete_incidents(bin_id, incidents)).await
+    }
+
+    async fn daily_rollups(&self, bin_id: &Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyRollup>, AppError> {
+        self.call("daily_rollups", true, || self.inner.daily_rollups(bin_id, from, to)).await
+    }
+}
+
+#[async_trait]
+impl<R: RollupRepository> RollupRepository for ResilientRepository<R> {
+    async fn bin_timeline(&self, bin_id: &Uuid, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Timeline, AppError> {
+        self.call("bin_timeline", true, || self.inner.bin_timeline(bin_id, from, to)).await
+    }
+
+    async fn bin_placement(&self, bin_id: &Uuid) -> Result<Placement, AppError> {
+        self.call("bin_placement", true, || self.inner.bin_placement(bin_id)).await
+    }
+
+    async fn rollups(
+        &self,
+        scope: &RollupScope,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Rollup>, AppError> {
+        self.call("rollups", true, || self.inner.rollups(scope, resolution, from, to)).await
+    }
+
+    /// A retry after an update that did apply fails its conditions and
+    /// returns `false`, and recomputing then finds nothing to change.
+    async fn apply_rollup_update(&self, update: &RollupUpdate) -> Result<bool, AppError> {
+        self.call("apply_rollup_update", true, || self.inner.apply_rollup_update(update)).await
+    }
+}
+
+#[async_trait]
+impl<R: BinProfileRepository> BinProfileRepository for ResilientRepository<R> {
+    async fn bin_profile(&self, bin_id: &Uuid) -> Result<Option<BinProfile>, AppError> {
+        self.call("bin_profile", true, || self.inner.bin_profile(bin_id)).await
+    }
+
+    async fn set_bin_profile(&self, bin_id: &Uuid, profile: &BinProfile) -> Result<(), AppError> {
+        self.call("set_bin_profile", true, || self.inner.set_bin_profile(bin_id, profile)).await
+    }
+}
+
+#[async_trait]
+impl<R: LifecycleRepository> LifecycleRepository for ResilientRepository<R> {
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+        self.call("bin_lifecycle", true, || self.inner.bin_lifecycle(bin_id)).await
+    }
+
+    /// Conditional on the state it moves from, so a repeat after a lost
+    /// response is refused rather than recorded twice.
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError> {
+        self.call("record_transition", true, || self.inner.record_transition(bin_id, transition)).await
+    }
+}
+
+#[async_trait]
+impl<R: IncidentRepository> IncidentRepository for ResilientRepository<R> {
+    /// A put keyed by the observation time, kind and report id, so a repeat
+    /// replaces the same item.
+    async fn add_incident(&self, report: &IncidentReport) -> Result<(), AppError> {
+        self.call("add_incident", true, || self.inner.add_incident(report)).await
+    }
+
+    async fn incidents(&self, kind: ReportKind, since: DateTime<Utc>, limit: usize) -> Result<Vec<IncidentReport>, AppError> {
+        self.call("incidents", true, || self.inner.incidents(kind, since, limit)).await
+    }
+}
+
+#[async_trait]
+impl<R: LocationRepository> LocationRepository for ResilientRepository<R> {
+    async fn location_bins(&self, location_id: &Uuid) -> Result<Vec<PlacedBin>, AppError> {
+        self.call("location_bins", true, || self.inner.location_bins(location_id)).await
+    }
+}
+
+#[async_trait]
+impl<R: DeviceRepository + Send + Sync> DeviceRepository for ResilientRepository<R> {
+    async fn get_device(&self, device_id: &str) -> Result<Option<Device>, AppError> {
+        self.call("get_device", true, || self.inner.get_device(device_id)).await
+    }
+
+    async fn get_calibration(&self, bin_id: &Uuid) -> Result<Option<BinCalibration>, AppError> {
+        self.call("get_calibration", true, || self.inner.get_calibration(bin_id)).await
+    }
+}
+
+#[async_trait]
+impl<R: DeviceHealthRepository + Send + Sync> DeviceHealthRepository for ResilientRepository<R> {
+    async fn get_health(&self, device_id: &str) -> Result<Option<DeviceHealth>, AppError> {
+        self.call("get_health", true, || self.inner.get_health(device_id)).await
+    }
+
+    async fn save_health(&self, health: &DeviceHealth) -> Result<(), AppError> {
+        self.call("save_health", true, || self.inner.save_health(health)).await
+    }
+
+    async fn list_health(&self) -> Result<Vec<DeviceHealth>, AppError> {
+        self.call("list_health", true, || self.inner.list_health()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::BinStatus;
+    use crate::infrastructure::memory::InMemoryRepository;
+    use shared::clock::FakeClock;
+    use shared::metrics::{CapturingRecorder, Sample};
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails calls with a scripted sequence of errors, then delegates.
+    struct ScriptedRepository {
+        inner: InMemoryRepository,
+        failures: Mutex<VecDeque<DatabaseError>>,
+        calls: AtomicU32,
+        delay: Duration,
+    }
+
+    impl ScriptedRepository {
+        fn new(inner: InMemoryRepository, failures: Vec<DatabaseError>) -> Self {
+            Self { inner, failures: Mutex::new(failures.into()), calls: AtomicU32::new(0), delay: Duration::ZERO }
+        }
+
+        async fn next(&self) -> Result<(), AppError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            match self.failures.lock().unwrap().pop_front() {
+                Some(e) => Err(e.into()),
+                None => Ok(()),
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl BinRepository for ScriptedRepository {
+        async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+            self.next().await?;
+            self.inner.get_bin(bin_id).await
+        }
+
+        async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+            self.next().await?;
+            self.inner.update_status(report).await
+        }
+
+        async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+            self.next().await?;
+            self.inner.add_report(report).await
+        }
+
+        async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+            self.next().await?;
+            self.inner.record_collection(event).await
+        }
+    }
+
+    fn fast_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            call_timeout: Duration::from_millis(200),
+        }
+    }
+
+    fn resilient(failures: Vec<DatabaseError>) -> (ResilientRepository<ScriptedRepository>, Uuid, FakeClock) {
+        let memory = InMemoryRepository::new();
+        let bin_id = Uuid::new_v4();
+        memory.add_bin(bin_id);
+        let clock = FakeClock::new(Utc::now());
+        let breaker = BreakerPolicy { failure_threshold: 3, open_for: Duration::from_secs(30) };
+        let repo = ResilientRepository::with_policies(
+            ScriptedRepository::new(memory, failures),
+            fast_retries(),
+            breaker,
+            Arc::new(clock.clone()),
+        )
+        .with_seed(7);
+        (repo, bin_id, clock)
+    }
+
+    fn report(bin_id: Uuid) -> StatusReport {
+        StatusReport::citizen(Uuid::new_v4(), bin_id, BinStatus::full(), Utc::now())
+    }
+
+    fn throttled() -> DatabaseError {
+        DatabaseError::Throttled("slow down".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_retries_throttling_until_success() {
+        let (repo, bin_id, _) = resilient(vec![throttled(), throttled()]);
+
+        repo.update_status(&report(bin_id)).await.unwrap();
+
+        assert_eq!(repo.inner().calls(), 3);
+        assert_eq!(repo.inner().inner.bin_status(&bin_id), Some(BinStatus::full()));
+    }
+
+    #[tokio::test]
+    async fn test_records_attempt_latency_and_retries() {
+        let (repo, bin_id, _) = resilient(vec![throttled()]);
+        let recorder = CapturingRecorder::new();
+        let repo = repo.with_metrics(Arc::new(recorder.clone()));
+
+        repo.update_status(&report(bin_id)).await.unwrap();
+
+        let latencies: Vec<Sample> = recorder
+            .samples()
+            .into_iter()
+            .filter(|sample| sample.name == DATABASE_LATENCY)
+            .collect();
+        assert_eq!(latencies.len(), 2);
+        assert!(latencies.iter().all(|sample| sample.unit == Unit::Milliseconds));
+        assert_eq!(latencies[0].dimension("Operation"), Some("update_status"));
+        assert_eq!(recorder.total(DATABASE_RETRIES, &[("Operation", "update_status")]), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let (repo, bin_id, _) = resilient(vec![throttled(), throttled(), throttled()]);
+
+        let result = repo.add_report(&report(bin_id)).await;
+
+        assert!(matches!(result, Err(AppError::DatabaseError(DatabaseError::Throttled(_)))));
+        assert_eq!(repo.inner().calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_failures_only_retried_for_idempotent_calls() {
+        let unavailable = || DatabaseError::Unavailable("connection reset".to_string());
+
+        let (repo, bin_id, _) = resilient(vec![unavailable()]);
+        assert!(repo.update_status(&report(bin_id)).await.is_err());
+        assert_eq!(repo.inner().calls(), 1);
+
+        let (repo, bin_id, _) = resilient(vec![unavailable()]);
+        assert!(repo.add_report(&report(bin_id)).await.is_ok());
+        assert_eq!(repo.inner().calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_errors_are_not_retried() {
+        let (repo, bin_id, _) = resilient(vec![DatabaseError::Permanent("no such table".to_string())]);
+
+        assert!(repo.add_report(&report(bin_id)).await.is_err());
+        assert_eq!(repo.inner().calls(), 1);
+        assert!(!repo.breaker().is_open());
+    }
+
+    #[tokio::test]
+    async fn test_slow_calls_hit_the_deadline() {
+        let (mut repo, bin_id, _) = resilient(vec![]);
+        repo.inner.delay = Duration::from_secs(5);
+
+        let result = repo.update_status(&report(bin_id)).await;
+
+        assert!(matches!(result, Err(AppError::DatabaseError(DatabaseError::Timeout(_)))));
+        assert_eq!(repo.inner().calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_and_recovers_after_trial_call() {
+        let (repo, bin_id, clock) = resilient(vec![throttled(), throttled(), throttled()]);
+
+        assert!(repo.add_report(&report(bin_id)).await.is_err());
+        assert!(repo.breaker().is_open());
+
+        // Short-circuited without reaching the backend
+        let result = repo.add_report(&report(bin_id)).await;
+        assert!(matches!(result, Err(AppError::DatabaseError(DatabaseError::CircuitOpen(_)))));
+        assert_eq!(repo.inner().calls(), 3);
+
+        clock.advance(chrono::Duration::seconds(31));
+        repo.add_report(&report(bin_id)).await.unwrap();
+        assert!(!repo.breaker().is_open());
+        assert_eq!(repo.inner().calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_batch_retries_only_rejected_groups() {
+        let (repo, bin_id, _) = resilient(vec![throttled()]);
+        let other = Uuid::new_v4();
+        repo.inner().inner.add_bin(other);
+        let groups = vec![
+            ReportGroup { bin_id, reports: vec![report(bin_id)] },
+            ReportGroup { bin_id: other, reports: vec![report(other)] },
+        ];
+
+        let results = repo.record_batch(&groups).await;
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(repo.inner().inner.reports(&bin_id).len(), 1);
+        assert_eq!(repo.inner().inner.reports(&other).len(), 1);
+    }
+
+    #[test]
+    fn test_backoff_is_capped_and_reproducible() {
+        let delays = |seed| {
+            let (repo, _, _) = resilient(vec![]);
+            let repo = repo.with_seed(seed);
+            (1..=6).map(|attempt| repo.backoff(attempt)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(delays(42), delays(42));
+        assert!(delays(42).iter().all(|delay| *delay <= Duration::from_millis(5)));
+    }
+}
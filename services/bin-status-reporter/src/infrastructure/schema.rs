@@ -0,0 +1,454 @@
+use std::fmt;
+use std::time::Duration;
+
+use aws_sdk_dynamodb::error::BuildError;
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, BillingMode, CreateGlobalSecondaryIndexAction, DeleteGlobalSecondaryIndexAction,
+    GlobalSecondaryIndex, GlobalSecondaryIndexUpdate, IndexStatus, KeySchemaElement, KeyType, Projection,
+    ProjectionType, ScalarAttributeType, TableDescription, TableStatus,
+};
+use aws_sdk_dynamodb::Client;
+use tracing::info;
+
+use crate::error::AppError;
+use crate::infrastructure::dynamodb::db_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    String,
+    Number,
+}
+
+impl AttributeKind {
+    fn scalar_type(&self) -> ScalarAttributeType {
+        match self {
+            AttributeKind::String => ScalarAttributeType::S,
+            AttributeKind::Number => ScalarAttributeType::N,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyAttribute {
+    pub name: &'static str,
+    pub kind: AttributeKind,
+}
+
+impl KeyAttribute {
+    pub const fn string(name: &'static str) -> Self {
+        Self { name, kind: AttributeKind::String }
+    }
+
+    pub const fn number(name: &'static str) -> Self {
+        Self { name, kind: AttributeKind::Number }
+    }
+}
+
+/// A global secondary index projecting every attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexSchema {
+    pub name: &'static str,
+    pub hash_key: KeyAttribute,
+    pub range_key: Option<KeyAttribute>,
+}
+
+/// The declared shape of one on-demand table. Only key attributes are
+/// declared; DynamoDB does not fix the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSchema {
+    pub name: String,
+    pub hash_key: KeyAttribute,
+    pub range_key: Option<KeyAttribute>,
+    pub indexes: Vec<IndexSchema>,
+}
+
+impl TableSchema {
+    pub fn new(name: impl Into<String>, hash_key: KeyAttribute) -> Self {
+        Self { name: name.into(), hash_key, range_key: None, indexes: Vec::new() }
+    }
+
+    pub fn with_range_key(mut self, range_key: KeyAttribute) -> Self {
+        self.range_key = Some(range_key);
+        self
+    }
+
+    pub fn with_index(mut self, index: IndexSchema) -> Self {
+        self.indexes.push(index);
+        self
+    }
+
+    /// Definitions for the table and index keys, without duplicates.
+    fn attribute_definitions(&self) -> Result<Vec<AttributeDefinition>, AppError> {
+        let mut keys = vec![self.hash_key];
+        keys.extend(self.range_key);
+        for index in &self.indexes {
+            keys.push(index.hash_key);
+            keys.extend(index.range_key);
+        }
+
+        let mut definitions: Vec<AttributeDefinition> = Vec::new();
+        for key in keys {
+            if definitions.iter().any(|definition| definition.attribute_name() == key.name) {
+                continue;
+            }
+            definitions.push(
+                AttributeDefinition::builder()
+                    .attribute_name(key.name)
+                    .attribute_type(key.kind.scalar_type())
+                    .build()
+                    .map_err(build_error)?,
+            );
+        }
+        Ok(definitions)
+    }
+}
+
+/// Table names as configured for the functions, so the tool provisions
+/// exactly what they read and write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableNames {
+    pub bins: String,
+    pub reports: String,
+    pub devices: String,
+    pub migrations: String,
+    pub rollups: String,
+}
+
+impl TableNames {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        Self {
+            bins: var("TRASH_BINS_TABLE", "trash-bins"),
+            reports: var("STATUS_REPORTS_TABLE", "status-reports"),
+            devices: var("DEVICES_TABLE", "devices"),
+            migrations: var("SCHEMA_MIGRATIONS_TABLE", "schema-migrations"),
+            rollups: var("FILL_ROLLUPS_TABLE", "fill-rollups"),
+        }
+    }
+
+    /// Default names behind `prefix`, to keep test runs apart.
+    pub fn with_prefix(prefix: &str) -> Self {
+        Self {
+            bins: format!("{}trash-bins", prefix),
+            reports: format!("{}status-reports", prefix),
+            devices: format!("{}devices", prefix),
+            migrations: format!("{}schema-migrations", prefix),
+            rollups: format!("{}fill-rollups", prefix),
+        }
+    }
+}
+
+pub const LOCATION_INDEX: &str = "locationId-index";
+/// Sparse index on the report log, holding only incidents, which carry a
+/// `reportKind`.
+pub const REPORT_KIND_INDEX: &str = "reportKind-index";
+
+/// Every table the service uses. Bins carry no index on their fill status,
+/// which changes with every report, only one grouping them by location.
+pub fn tables(names: &TableNames) -> Vec<TableSchema> {
+    vec![
+        TableSchema::new(&names.bins, KeyAttribute::string("binId")).with_index(IndexSchema {
+            name: LOCATION_INDEX,
+            hash_key: KeyAttribute::string("locationId"),
+            range_key: Some(KeyAttribute::string("binId")),
+        }),
+        TableSchema::new(&names.reports, KeyAttribute::string("binId"))
+            .with_range_key(KeyAttribute::string("createdAt"))
+            .with_index(IndexSchema {
+                name: REPORT_KIND_INDEX,
+                hash_key: KeyAttribute::string("reportKind"),
+                range_key: Some(KeyAttribute::string("createdAt")),
+            }),
+        TableSchema::new(&names.devices, KeyAttribute::string("deviceId")),
+        TableSchema::new(&names.migrations, KeyAttribute::number("version")),
+        rollups_table(&names.rollups),
+    ]
+}
+
+/// Fill level rollups, one series per scope and resolution. Both layouts
+/// keep them here.
+pub fn rollups_table(name: &str) -> TableSchema {
+    TableSchema::new(name, KeyAttribute::string("series")).with_range_key(KeyAttribute::string("periodStart"))
+}
+
+pub const SINGLE_TABLE_INDEX: &str = "GSI1";
+
+/// The single-table layout, where every entity is keyed by `PK` and `SK`
+/// and a location shares a `GSI1` partition with its bins.
+pub fn single_table(name: &str) -> TableSchema {
+    TableSchema::new(name, KeyAttribute::string("PK"))
+        .with_range_key(KeyAttribute::string("SK"))
+        .with_index(IndexSchema {
+            name: SINGLE_TABLE_INDEX,
+            hash_key: KeyAttribute::string("GSI1PK"),
+            range_key: Some(KeyAttribute::string("GSI1SK")),
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    CreateTable(TableSchema),
+    /// Tables created by older scripts used provisioned capacity.
+    SetOnDemand { table: String },
+    CreateIndex { table: String, index: IndexSchema },
+    /// Indexes that are no longer declared, such as the old one on `status`.
+    DeleteIndex { table: String, index: String },
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::CreateTable(schema) => write!(f, "create table {}", schema.name),
+            SchemaChange::SetOnDemand { table } => write!(f, "switch {} to on-demand capacity", table),
+            SchemaChange::CreateIndex { table, index } => write!(f, "create index {} on {}", index.name, table),
+            SchemaChange::DeleteIndex { table, index } => write!(f, "delete index {} from {}", index, table),
+        }
+    }
+}
+
+/// The changes that bring `current` to `desired`, in the order they must
+/// run. Key schemas cannot change in place, so a mismatch is an error.
+pub fn plan(desired: &TableSchema, current: Option<&TableDescription>) -> Result<Vec<SchemaChange>, AppError> {
+    let Some(current) = current else {
+        return Ok(vec![SchemaChange::CreateTable(desired.clone())]);
+    };
+
+    if !keys_match(current.key_schema(), desired.hash_key, desired.range_key) {
+        return Err(AppError::InternalError(format!(
+            "Table {} has a different key schema than declared and must be recreated",
+            desired.name
+        )));
+    }
+
+    let mut changes = Vec::new();
+    let on_demand = current
+        .billing_mode_summary()
+        .and_then(|summary| summary.billing_mode())
+        .is_some_and(|mode| *mode == BillingMode::PayPerRequest);
+    if !on_demand {
+        changes.push(SchemaChange::SetOnDemand { table: desired.name.clone() });
+    }
+
+    let existing = current.global_secondary_indexes();
+    for index in existing {
+        let name = index.index_name().unwrap_or_default();
+        let declared = desired
+            .indexes
+            .iter()
+            .find(|declared| declared.name == name)
+            .is_some_and(|declared| keys_match(index.key_schema(), declared.hash_key, declared.range_key));
+        if !declared {
+            changes.push(SchemaChange::DeleteIndex { table: desired.name.clone(), index: name.to_string() });
+        }
+    }
+    for index in &desired.indexes {
+        let exists = existing.iter().any(|existing| {
+            existing.index_name() == Some(index.name)
+                && keys_match(existing.key_schema(), index.hash_key, index.range_key)
+        });
+        if !exists {
+            changes.push(SchemaChange::CreateIndex { table: desired.name.clone(), index: index.clone() });
+        }
+    }
+    Ok(changes)
+}
+
+fn keys_match(current: &[KeySchemaElement], hash_key: KeyAttribute, range_key: Option<KeyAttribute>) -> bool {
+    let find = |key_type: KeyType| {
+        current
+            .iter()
+            .find(|element| *element.key_type() == key_type)
+            .map(|element| element.attribute_name())
+    };
+    find(KeyType::Hash) == Some(hash_key.name) && find(KeyType::Range) == range_key.map(|key| key.name)
+}
+
+/// The table's description, or `None` if it does not exist.
+pub async fn describe(client: &Client, table: &str) -> Result<Option<TableDescription>, AppError> {
+    match client.describe_table().table_name(table).send().await {
+        Ok(output) => Ok(output.table),
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_resource_not_found_exception()) => Ok(None),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+/// Applies one change and waits for the table and its indexes to be active
+/// again, as DynamoDB rejects further updates until then.
+pub async fn apply(client: &Client, desired: &TableSchema, change: &SchemaChange) -> Result<(), AppError> {
+    info!(change = %change, "Applying schema change");
+    match change {
+        SchemaChange::CreateTable(schema) => {
+            let mut request = client
+                .create_table()
+                .table_name(&schema.name)
+                .billing_mode(BillingMode::PayPerRequest)
+                .set_attribute_definitions(Some(schema.attribute_definitions()?))
+                .set_key_schema(Some(key_schema(schema.hash_key, schema.range_key)?));
+            for index in &schema.indexes {
+                request = request.global_secondary_indexes(
+                    GlobalSecondaryIndex::builder()
+                        .index_name(index.name)
+                        .set_key_schema(Some(key_schema(index.hash_key, index.range_key)?))
+                        .projection(Projection::builder().projection_type(ProjectionType::All).build())
+                        .build()
+                        .map_err(build_error)?,
+                );
+            }
+            request.send().await.map_err(db_error)?;
+        }
+        SchemaChange::SetOnDemand { table } => {
+            client
+                .update_table()
+                .table_name(table)
+                .billing_mode(BillingMode::PayPerRequest)
+                .send()
+                .await
+                .map_err(db_error)?;
+        }
+        SchemaChange::CreateIndex { table, index } => {
+            let action = CreateGlobalSecondaryIndexAction::builder()
+                .index_name(index.name)
+                .set_key_schema(Some(key_schema(index.hash_key, index.range_key)?))
+                .projection(Projection::builder().projection_type(ProjectionType::All).build())
+                .build()
+                .map_err(build_error)?;
+            client
+                .update_table()
+                .table_name(table)
+                .set_attribute_definitions(Some(desired.attribute_definitions()?))
+                .global_secondary_index_updates(GlobalSecondaryIndexUpdate::builder().create(action).build())
+                .send()
+                .await
+                .map_err(db_error)?;
+        }
+        SchemaChange::DeleteIndex { table, index } => {
+            let action = DeleteGlobalSecondaryIndexAction::builder()
+                .index_name(index)
+                .build()
+                .map_err(build_error)?;
+            client
+                .update_table()
+                .table_name(table)
+                .global_secondary_index_updates(GlobalSecondaryIndexUpdate::builder().delete(action).build())
+                .send()
+                .await
+                .map_err(db_error)?;
+        }
+    }
+    wait_until_active(client, &desired.name).await
+}
+
+async fn wait_until_active(client: &Client, table: &str) -> Result<(), AppError> {
+    const ATTEMPTS: u32 = 120;
+
+    for _ in 0..ATTEMPTS {
+        if let Some(description) = describe(client, table).await? {
+            let table_active = description.table_status() == Some(&TableStatus::Active);
+            let indexes_active = description
+                .global_secondary_indexes()
+                .iter()
+                .all(|index| index.index_status() == Some(&IndexStatus::Active));
+            if table_active && indexes_active {
+                return Ok(());
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    Err(AppError::InternalError(format!("Table {} did not become active in time", table)))
+}
+
+fn key_schema(hash_key: KeyAttribute, range_key: Option<KeyAttribute>) -> Result<Vec<KeySchemaElement>, AppError> {
+    let mut elements = vec![KeySchemaElement::builder()
+        .attribute_name(hash_key.name)
+        .key_type(KeyType::Hash)
+        .build()
+        .map_err(build_error)?];
+    if let Some(range_key) = range_key {
+        elements.push(
+            KeySchemaElement::builder()
+                .attribute_name(range_key.name)
+                .key_type(KeyType::Range)
+                .build()
+                .map_err(build_error)?,
+        );
+    }
+    Ok(elements)
+}
+
+fn build_error(error: BuildError) -> AppError {
+    AppError::InternalError(format!("Invalid table definition: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::{BillingModeSummary, GlobalSecondaryIndexDescription};
+
+    fn description(keys: &[(&str, KeyType)], billing_mode: Option<BillingMode>) -> TableDescription {
+        let mut builder = TableDescription::builder().table_status(TableStatus::Active);
+        for (name, key_type) in keys {
+            builder = builder.key_schema(
+                KeySchemaElement::builder().attribute_name(*name).key_type(key_type.clone()).build().unwrap(),
+            );
+        }
+        if let Some(mode) = billing_mode {
+            builder = builder.billing_mode_summary(BillingModeSummary::builder().billing_mode(mode).build());
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_plan_creates_missing_table() {
+        let schema = TableSchema::new("bins", KeyAttribute::string("binId"));
+        assert_eq!(plan(&schema, None).unwrap(), vec![SchemaChange::CreateTable(schema)]);
+    }
+
+    #[test]
+    fn test_plan_is_empty_for_matching_table() {
+        let schema = TableSchema::new("reports", KeyAttribute::string("binId"))
+            .with_range_key(KeyAttribute::string("createdAt"));
+        let current = description(
+            &[("binId", KeyType::Hash), ("createdAt", KeyType::Range)],
+            Some(BillingMode::PayPerRequest),
+        );
+
+        assert!(plan(&schema, Some(&current)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_replaces_legacy_status_index() {
+        let schema = TableSchema::new("bins", KeyAttribute::string("binId")).with_index(IndexSchema {
+            name: "location-index",
+            hash_key: KeyAttribute::string("locationId"),
+            range_key: None,
+        });
+        let mut current = description(&[("binId", KeyType::Hash)], None);
+        current.global_secondary_indexes = Some(vec![GlobalSecondaryIndexDescription::builder()
+            .index_name("status-index")
+            .key_schema(KeySchemaElement::builder().attribute_name("status").key_type(KeyType::Hash).build().unwrap())
+            .build()]);
+
+        let changes = plan(&schema, Some(&current)).unwrap();
+
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "switch bins to on-demand capacity",
+                "delete index status-index from bins",
+                "create index location-index on bins",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_rejects_changed_key_schema() {
+        let schema = TableSchema::new("reports", KeyAttribute::string("binId"))
+            .with_range_key(KeyAttribute::string("observedAt"));
+        let current = description(
+            &[("binId", KeyType::Hash), ("createdAt", KeyType::Range)],
+            Some(BillingMode::PayPerRequest),
+        );
+
+        assert!(matches!(plan(&schema, Some(&current)), Err(AppError::InternalError(_))));
+    }
+}
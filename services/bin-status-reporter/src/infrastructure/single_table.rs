@@ -0,0 +1,890 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::config::retry::RetryConfig;
+use aws_sdk_dynamodb::types::{AttributeValue, Put, PutRequest, TransactWriteItem, Update, WriteRequest};
+use aws_sdk_dynamodb::Client;
+use chrono::{DateTime, Utc};
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use crate::domain::backup::{BackupRepository, ExportPage, Record, Section};
+use crate::domain::batch::ReportGroup;
+use crate::domain::collection::CollectionEvent;
+use crate::domain::estimator::{BinAggregate, FillEstimator};
+use crate::domain::history::BinEvent;
+use crate::domain::incident::{IncidentReport, IncidentRepository, ReportKind};
+use crate::domain::lifecycle::{BinLifecycle, LifecycleRepository, LifecycleTransition};
+use crate::domain::location::{LocationRepository, PlacedBin};
+use crate::domain::rollup::{Placement, Resolution, Rollup, RollupRepository, RollupScope, RollupUpdate, Timeline};
+use crate::domain::{BinProfile, BinProfileRepository, BinRepository, Location, QRCode, StatusChange, StatusReport};
+use crate::error::{AppError, DatabaseError};
+use crate::infrastructure::dynamodb::{
+    connect, db_error, estimator_from_env, key_token, parse_key_token, profile_update, query_rollups, string_attr,
+    transition_update, write_rollup_update,
+};
+use crate::infrastructure::items::{
+    to_item, BinItem, CollectionItem, IncidentItem, Item, LocationItem, LogItem, QrCodeItem, ReportItem, BIN_SCHEMA_VERSION,
+    SCHEMA_VERSION_ATTRIBUTE,
+};
+use crate::infrastructure::schema::{TableNames, SINGLE_TABLE_INDEX};
+
+const META: &str = "META";
+const TABLE_KEY: &[&str] = &["PK", "SK"];
+const REPORT_PREFIX: &str = "REPORT#";
+/// Sorts after every report log key, which only use RFC 3339 characters
+/// and a snake_case suffix.
+const REPORT_UPPER_BOUND: &str = "REPORT#~";
+
+fn bin_key(bin_id: &Uuid) -> String {
+    format!("BIN#{}", bin_id)
+}
+
+fn location_key(location_id: &Uuid) -> String {
+    format!("LOC#{}", location_id)
+}
+
+fn qr_code_key(qr_code_id: &Uuid) -> String {
+    format!("QR#{}", qr_code_id)
+}
+
+/// Reports sort by observation time within their bin's partition.
+fn report_sort_key(observed_at: DateTime<Utc>) -> String {
+    format!("{}{}", REPORT_PREFIX, observed_at.to_rfc3339())
+}
+
+/// Adds the table keys to an entity item.
+fn keyed(mut item: Item, pk: String, sk: &str) -> Item {
+    item.insert("PK".to_string(), AttributeValue::S(pk));
+    item.insert("SK".to_string(), AttributeValue::S(sk.to_string()));
+    item
+}
+
+/// Bins with a location join their location's `GSI1` partition, so a
+/// location and its bins are read with one query.
+fn bin_item(bin: &BinItem) -> Result<Item, AppError> {
+    let mut item = keyed(to_item(bin)?, bin_key(&bin.bin_id), META);
+    if let Some(location_id) = bin.location_id {
+        item.insert("GSI1PK".to_string(), AttributeValue::S(location_key(&location_id)));
+        item.insert("GSI1SK".to_string(), AttributeValue::S(bin_key(&bin.bin_id)));
+    }
+    Ok(item)
+}
+
+fn kind_key(kind: ReportKind) -> String {
+    format!("KIND#{}", kind.as_str())
+}
+
+/// Report log entries, reports, collections and incidents alike, follow
+/// their bin. Incidents also join their kind's `GSI1` partition, newest
+/// last.
+fn log_item(entry: &LogItem) -> Result<Item, AppError> {
+    let mut item = keyed(entry.to_item()?, bin_key(&entry.bin_id()), &format!("{}{}", REPORT_PREFIX, entry.created_at()));
+    if let LogItem::Incident(incident) = entry {
+        item.insert("GSI1PK".to_string(), AttributeValue::S(kind_key(incident.report_kind)));
+        item.insert("GSI1SK".to_string(), AttributeValue::S(incident.created_at.clone()));
+    }
+    Ok(item)
+}
+
+fn report_item(report: &StatusReport) -> Result<Item, AppError> {
+    log_item(&LogItem::Report(ReportItem::from(report)))
+}
+
+/// A location with the bins placed at it.
+#[derive(Debug)]
+pub struct LocationWithBins {
+    pub location: Location,
+    pub bins: Vec<BinItem>,
+}
+
+/// Counts of items copied by `copy_two_table_layout`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CopyReport {
+    pub bins: usize,
+    /// Report log entries, including logged collections.
+    pub reports: usize,
+}
+
+/// Keeps bins, reports, locations and QR codes in one table keyed by `PK`
+/// and `SK`:
+///
+/// - a bin is `BIN#<id>` / `META`, and its reports follow it in the same
+///   partition as `REPORT#<observed at>`, so a bin and its recent reports
+///   are read with one query;
+/// - a location is `LOC#<id>` / `META`, and it shares a `GSI1` partition
+///   with its bins;
+/// - incidents are in their bin's report log, and in a `KIND#<kind>`
+///   partition of `GSI1` sorted by their log key, observation time first;
+/// - a QR code is `QR#<id>` / `META` and names its bin.
+///
+/// Devices stay in their own table, and fill level rollups in the rollups
+/// table the two-table layout uses, so moving over keeps them.
+pub struct SingleTableRepository {
+    client: Client,
+    table: String,
+    rollups_table: String,
+    estimator: FillEstimator,
+}
+
+impl SingleTableRepository {
+    /// Uses the table named by `ECOSCAN_TABLE`, and `FILL_ROLLUPS_TABLE`
+    /// for rollups.
+    pub async fn from_env() -> Result<Self, AppError> {
+        // Retries happen in ResilientRepository, where the circuit breaker sees them
+        let endpoint_url = std::env::var("DYNAMODB_ENDPOINT_URL").ok();
+        let client = connect(endpoint_url.as_deref(), RetryConfig::disabled()).await;
+        let table = std::env::var("ECOSCAN_TABLE").unwrap_or_else(|_| "ecoscan".to_string());
+
+        Ok(Self::new(client, table, estimator_from_env()?).with_rollups_table(TableNames::from_env().rollups))
+    }
+
+    /// Keeps rollups in the default `fill-rollups` table.
+    pub fn new(client: Client, table: impl Into<String>, estimator: FillEstimator) -> Self {
+        Self { client, table: table.into(), rollups_table: TableNames::with_prefix("").rollups, estimator }
+    }
+
+    pub fn with_rollups_table(mut self, rollups_table: impl Into<String>) -> Self {
+        self.rollups_table = rollups_table.into();
+        self
+    }
+
+    /// Registers a bin with an empty estimate. An existing bin is left as is.
+    pub async fn create_bin(&self, bin_id: Uuid, location_id: Option<Uuid>) -> Result<(), AppError> {
+        let bin = BinItem { location_id, ..BinItem::new(bin_id) };
+        let result = self.client
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(bin_item(&bin)?))
+            .condition_expression("attribute_not_exists(PK)")
+            .send()
+            .await
+            .map_err(db_error);
+
+        match result {
+            Ok(_) | Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn put_location(&self, location: &Location) -> Result<(), AppError> {
+        let mut item = keyed(to_item(&LocationItem::from(location))?, location_key(&location.id), META);
+        item.insert("GSI1PK".to_string(), AttributeValue::S(location_key(&location.id)));
+        item.insert("GSI1SK".to_string(), AttributeValue::S(META.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// The location and its bins, or `None` if the location does not exist.
+    pub async fn location_with_bins(&self, location_id: &Uuid) -> Result<Option<LocationWithBins>, AppError> {
+        let (location, bins) = self.location_partition(location_id).await?;
+        Ok(location.map(|location| LocationWithBins { location, bins }))
+    }
+
+    /// The location's `GSI1` partition: the location, if stored, and the
+    /// bins placed at it.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", location_id = %location_id))]
+    async fn location_partition(&self, location_id: &Uuid) -> Result<(Option<Location>, Vec<BinItem>), AppError> {
+        let mut location = None;
+        let mut bins = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.table)
+                .index_name(SINGLE_TABLE_INDEX)
+                .key_condition_expression("GSI1PK = :pk")
+                .expression_attribute_values(":pk", AttributeValue::S(location_key(location_id)))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                if string_attr(item, "GSI1SK").as_deref() == Some(META) {
+                    location = Some(Location::from(LocationItem::parse(item)?));
+                } else {
+                    bins.push(BinItem::parse(item)?);
+                }
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok((location, bins))
+    }
+
+    async fn get_location(&self, location_id: &Uuid) -> Result<Option<Location>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(location_key(location_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        Ok(result.item().map(LocationItem::parse).transpose()?.map(Location::from))
+    }
+
+    pub async fn put_qr_code(&self, qr_code: &QRCode) -> Result<(), AppError> {
+        let item = keyed(to_item(&QrCodeItem::from(qr_code))?, qr_code_key(&qr_code.id), META);
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// The bin a scanned QR code points to.
+    pub async fn bin_for_qr_code(&self, qr_code_id: &Uuid) -> Result<Option<Uuid>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(qr_code_key(qr_code_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        Ok(result.item().map(QrCodeItem::parse).transpose()?.map(|qr_code| qr_code.bin_id))
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "PutItem"))]
+    async fn put(&self, item: Item) -> Result<(), AppError> {
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn get_bin_item(&self, bin_id: &Uuid, consistent: bool) -> Result<Option<BinItem>, AppError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(bin_key(bin_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .consistent_read(consistent)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        Ok(result.item().map(BinItem::parse).transpose()?)
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", bin_id = %bin_id))]
+    async fn reports_since(&self, bin_id: &Uuid, since: DateTime<Utc>) -> Result<Vec<StatusReport>, AppError> {
+        let mut reports = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.table)
+                .key_condition_expression("PK = :pk AND SK BETWEEN :from AND :to")
+                .expression_attribute_values(":pk", AttributeValue::S(bin_key(bin_id)))
+                .expression_attribute_values(":from", AttributeValue::S(report_sort_key(since)))
+                .expression_attribute_values(":to", AttributeValue::S(REPORT_UPPER_BOUND.to_string()))
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                if let LogItem::Report(report) = LogItem::parse(item)? {
+                    reports.push(report.report()?);
+                }
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Events of the bin's report log with sort keys from `from` to `to`,
+    /// in sort key order. `limit` stops after that many.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", bin_id = %bin_id))]
+    async fn log_entries(
+        &self,
+        bin_id: &Uuid,
+        from: String,
+        to: String,
+        forward: bool,
+        limit: Option<i32>,
+    ) -> Result<Vec<BinEvent>, AppError> {
+        let mut events = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.table)
+                .key_condition_expression("PK = :pk AND SK BETWEEN :from AND :to")
+                .expression_attribute_values(":pk", AttributeValue::S(bin_key(bin_id)))
+                .expression_attribute_values(":from", AttributeValue::S(from.clone()))
+                .expression_attribute_values(":to", AttributeValue::S(to.clone()))
+                .scan_index_forward(forward)
+                .set_limit(limit)
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                events.extend(LogItem::parse(item)?.event()?);
+            }
+
+            // Incidents do not count towards the limit, so keep reading past them
+            match result.last_evaluated_key() {
+                Some(key) if limit.is_none_or(|limit| events.len() < limit as usize) => start_key = Some(key.clone()),
+                _ => break,
+            }
+        }
+        if let Some(limit) = limit {
+            events.truncate(limit as usize);
+        }
+        Ok(events)
+    }
+
+    async fn write_group(&self, group: &ReportGroup, mut aggregate: BinAggregate) -> Result<(), AppError> {
+        let expected_count = aggregate.reports_count;
+        for report in &group.reports {
+            aggregate.apply_report(&self.estimator, report);
+        }
+        let last_updated = aggregate
+            .last_observed_at
+            .or(aggregate.collected_at)
+            .ok_or_else(|| AppError::InvalidRequest("Empty report group".to_string()))?;
+
+        let (names, mut values) = estimate_attributes(&aggregate, last_updated);
+        values.insert(":expected".to_string(), AttributeValue::N(expected_count.to_string()));
+        let bin_update = Update::builder()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(bin_key(&group.bin_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .update_expression(ESTIMATE_UPDATE)
+            .condition_expression("attribute_exists(PK) AND #rc = :expected")
+            .set_expression_attribute_names(Some(names))
+            .set_expression_attribute_values(Some(values))
+            .build()
+            .map_err(|e| AppError::InternalError(e.to_string()))?;
+
+        let mut items = vec![TransactWriteItem::builder().update(bin_update).build()];
+        for report in &group.reports {
+            let put = Put::builder()
+                .table_name(&self.table)
+                .set_item(Some(report_item(report)?))
+                .build()
+                .map_err(|e| AppError::InternalError(e.to_string()))?;
+            items.push(TransactWriteItem::builder().put(put).build());
+        }
+
+        self.client
+            .transact_write_items()
+            .set_transact_items(Some(items))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+const ESTIMATE_UPDATE: &str = "SET #s = :s, #u = :u, #ws = :ws, #wt = :wt, #rc = :rc, #v = :v";
+
+/// Names and values for `ESTIMATE_UPDATE`.
+fn estimate_attributes(
+    aggregate: &BinAggregate,
+    last_updated: DateTime<Utc>,
+) -> (HashMap<String, String>, HashMap<String, AttributeValue>) {
+    let names = [
+        ("#s", "status"),
+        ("#u", "lastUpdated"),
+        ("#ws", "weightedSum"),
+        ("#wt", "weightTotal"),
+        ("#rc", "reportsCount"),
+        ("#v", SCHEMA_VERSION_ATTRIBUTE),
+    ];
+    let values = [
+        (":s", AttributeValue::N(aggregate.status().value().to_string())),
+        (":u", AttributeValue::S(last_updated.to_rfc3339())),
+        (":ws", AttributeValue::N(aggregate.weighted_sum.to_string())),
+        (":wt", AttributeValue::N(aggregate.weight_total.to_string())),
+        (":rc", AttributeValue::N(aggregate.reports_count.to_string())),
+        (":v", AttributeValue::N(BIN_SCHEMA_VERSION.to_string())),
+    ];
+    (
+        names.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        values.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+    )
+}
+
+#[async_trait]
+impl BinRepository for SingleTableRepository {
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "GetItem", bin_id = %bin_id))]
+    async fn get_bin(&self, bin_id: &Uuid) -> Result<Option<BinAggregate>, AppError> {
+        Ok(self.get_bin_item(bin_id, false).await?.map(|bin| bin.aggregate()))
+    }
+
+    /// The write is conditioned on the report count read, so concurrent
+    /// updates to one bin conflict instead of losing a report.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %report.bin_id))]
+    async fn update_status(&self, report: &StatusReport) -> Result<StatusChange, AppError> {
+        let mut aggregate = self
+            .get_bin_item(&report.bin_id, true)
+            .await?
+            .ok_or_else(|| AppError::BinNotFound(report.bin_id.to_string()))?
+            .aggregate();
+        let previous = aggregate.status();
+        let expected_count = aggregate.reports_count;
+
+        if !aggregate.apply_report(&self.estimator, report) {
+            info!(
+                "Report for bin {} observed at {} predates the last collection, keeping estimate",
+                report.bin_id,
+                report.observed_at.to_rfc3339()
+            );
+            return Ok(StatusChange::unchanged(previous));
+        }
+
+        let (names, mut values) = estimate_attributes(&aggregate, aggregate.last_observed_at.unwrap_or(report.observed_at));
+        values.insert(":expected".to_string(), AttributeValue::N(expected_count.to_string()));
+        self.client
+            .update_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(bin_key(&report.bin_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .update_expression(ESTIMATE_UPDATE)
+            .condition_expression("attribute_exists(PK) AND #rc = :expected")
+            .set_expression_attribute_names(Some(names))
+            .set_expression_attribute_values(Some(values))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(StatusChange { previous, current: aggregate.status() })
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "PutItem", bin_id = %report.bin_id))]
+    async fn add_report(&self, report: &StatusReport) -> Result<(), AppError> {
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(report_item(report)?))
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Rebuilds the estimate from the reports in the bin's partition
+    /// observed since the collection. A newer collection recorded
+    /// concurrently wins.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %event.bin_id))]
+    async fn record_collection(&self, event: &CollectionEvent) -> Result<(), AppError> {
+        let bin = self
+            .get_bin_item(&event.bin_id, true)
+            .await?
+            .ok_or_else(|| AppError::BinNotFound(event.bin_id.to_string()))?;
+
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(log_item(&LogItem::Collection(CollectionItem::from(event)))?))
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        if bin.last_collected_at.is_some_and(|collected_at| collected_at >= event.collected_at) {
+            info!("Ignoring collection of bin {} older than the last one", event.bin_id);
+            return Ok(());
+        }
+
+        let mut aggregate = BinAggregate::collected(event.collected_at);
+        for report in self.reports_since(&event.bin_id, event.collected_at).await? {
+            aggregate.apply_report(&self.estimator, &report);
+        }
+
+        let (mut names, mut values) =
+            estimate_attributes(&aggregate, aggregate.last_observed_at.unwrap_or(event.collected_at));
+        names.insert("#c".to_string(), "lastCollectedAt".to_string());
+        values.insert(":c".to_string(), AttributeValue::S(event.collected_at.to_rfc3339()));
+        let result = self.client
+            .update_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(bin_key(&event.bin_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .update_expression(format!("{}, #c = :c", ESTIMATE_UPDATE))
+            .condition_expression("attribute_not_exists(#c) OR #c < :c")
+            .set_expression_attribute_names(Some(names))
+            .set_expression_attribute_values(Some(values))
+            .send()
+            .await
+            .map_err(db_error);
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => {
+                info!("A newer collection of bin {} was recorded concurrently", event.bin_id);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes each bin's update and its reports in one `TransactWriteItems`
+    /// call, conditioned on the report count read.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "TransactWriteItems", groups = groups.len()))]
+    async fn record_batch(&self, groups: &[ReportGroup]) -> Vec<Result<(), AppError>> {
+        let mut results = Vec::with_capacity(groups.len());
+        for group in groups {
+            results.push(match self.get_bin_item(&group.bin_id, true).await {
+                Ok(Some(bin)) => self.write_group(group, bin.aggregate()).await,
+                Ok(None) => Err(AppError::BinNotFound(group.bin_id.to_string())),
+                Err(e) => Err(e),
+            });
+        }
+        results
+    }
+}
+
+#[async_trait]
+impl BinProfileRepository for SingleTableRepository {
+    async fn bin_profile(&self, bin_id: &Uuid) -> Result<Option<BinProfile>, AppError> {
+        Ok(self.get_bin_item(bin_id, false).await?.map(|bin| bin.profile()))
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %bin_id))]
+    async fn set_bin_profile(&self, bin_id: &Uuid, profile: &BinProfile) -> Result<(), AppError> {
+        let (expression, values) = profile_update(profile);
+        let result = self.client
+            .update_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(bin_key(bin_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .update_expression(expression)
+            .set_expression_attribute_values(Some(values))
+            .condition_expression("attribute_exists(PK)")
+            .send()
+            .await
+            .map_err(db_error);
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Err(AppError::BinNotFound(bin_id.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl LifecycleRepository for SingleTableRepository {
+    async fn bin_lifecycle(&self, bin_id: &Uuid) -> Result<Option<BinLifecycle>, AppError> {
+        Ok(self.get_bin_item(bin_id, true).await?.map(|bin| bin.lifecycle()))
+    }
+
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "UpdateItem", bin_id = %bin_id, to = %transition.to))]
+    async fn record_transition(&self, bin_id: &Uuid, transition: &LifecycleTransition) -> Result<bool, AppError> {
+        let update = transition_update(transition, "PK")?;
+        let result = self.client
+            .update_item()
+            .table_name(&self.table)
+            .key("PK", AttributeValue::S(bin_key(bin_id)))
+            .key("SK", AttributeValue::S(META.to_string()))
+            .update_expression(update.expression)
+            .condition_expression(update.condition)
+            .set_expression_attribute_values(Some(update.values))
+            .send()
+            .await
+            .map_err(db_error);
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl IncidentRepository for SingleTableRepository {
+    async fn add_incident(&self, report: &IncidentReport) -> Result<(), AppError> {
+        self.put(log_item(&LogItem::Incident(IncidentItem::from(report)))?).await
+    }
+
+    /// Reads the kind's `GSI1` partition, which is eventually consistent.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Query", kind = kind.as_str()))]
+    async fn incidents(&self, kind: ReportKind, since: DateTime<Utc>, limit: usize) -> Result<Vec<IncidentReport>, AppError> {
+        let mut incidents = Vec::new();
+        let mut start_key = None;
+
+        while incidents.len() < limit {
+            let result = self.client
+                .query()
+                .table_name(&self.table)
+                .index_name(SINGLE_TABLE_INDEX)
+                .key_condition_expression("GSI1PK = :pk AND GSI1SK >= :t")
+                .expression_attribute_values(":pk", AttributeValue::S(kind_key(kind)))
+                .expression_attribute_values(":t", AttributeValue::S(since.to_rfc3339()))
+                .scan_index_forward(false)
+                .limit(i32::try_from(limit - incidents.len()).unwrap_or(i32::MAX))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(db_error)?;
+
+            for item in result.items() {
+                incidents.push(IncidentItem::parse(item)?.incident()?);
+            }
+
+            match result.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        Ok(incidents)
+    }
+}
+
+#[async_trait]
+impl LocationRepository for SingleTableRepository {
+    async fn location_bins(&self, location_id: &Uuid) -> Result<Vec<PlacedBin>, AppError> {
+        let (_, bins) = self.location_partition(location_id).await?;
+        Ok(bins
+            .iter()
+            .map(|bin| PlacedBin { bin_id: bin.bin_id, aggregate: bin.aggregate(), profile: bin.profile(), lifecycle: bin.lifecycle_state })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl RollupRepository for SingleTableRepository {
+    async fn bin_timeline(&self, bin_id: &Uuid, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Timeline, AppError> {
+        // Entries at an instant carry a suffix and sort after its bare key
+        let before = self.log_entries(bin_id, REPORT_PREFIX.to_string(), report_sort_key(from), false, Some(1)).await?;
+        let events = self
+            .log_entries(bin_id, report_sort_key(from), report_sort_key(to), true, None)
+            .await?
+            .into_iter()
+            .filter(|event| event.at() < to)
+            .collect();
+        let after = self.log_entries(bin_id, report_sort_key(to), REPORT_UPPER_BOUND.to_string(), true, Some(1)).await?;
+        Ok(Timeline { before: before.into_iter().next(), events, after: after.into_iter().next() })
+    }
+
+    /// The district is the one of the bin's location, if the location is
+    /// stored.
+    async fn bin_placement(&self, bin_id: &Uuid) -> Result<Placement, AppError> {
+        let location_id = self.get_bin_item(bin_id, false).await?.and_then(|bin| bin.location_id);
+        let district = match location_id {
+            Some(location_id) => self.get_location(&location_id).await?.and_then(|location| location.district),
+            None => None,
+        };
+        Ok(Placement { location_id, district })
+    }
+
+    async fn rollups(
+        &self,
+        scope: &RollupScope,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Rollup>, AppError> {
+        query_rollups(&self.client, &self.rollups_table, scope, resolution, from, to).await
+    }
+
+    async fn apply_rollup_update(&self, update: &RollupUpdate) -> Result<bool, AppError> {
+        write_rollup_update(&self.client, &self.rollups_table, update).await
+    }
+}
+
+#[async_trait]
+impl BackupRepository for SingleTableRepository {
+    /// Scans the whole table for each section, keeping the section's items.
+    #[instrument(skip_all, fields(db.system = "dynamodb", db.operation = "Scan", section = section.as_str()))]
+    async fn export_page(&self, section: Section, after: Option<&str>, limit: usize) -> Result<ExportPage, AppError> {
+        let (prefix, filter) = match section {
+            Section::Locations => ("LOC#", "begins_with(PK, :p) AND SK = :sk"),
+            Section::Bins => ("BIN#", "begins_with(PK, :p) AND SK = :sk"),
+            Section::QrCodes => ("QR#", "begins_with(PK, :p) AND SK = :sk"),
+            Section::Log => ("BIN#", "begins_with(PK, :p) AND begins_with(SK, :sk)"),
+        };
+        let sort_key = if section == Section::Log { REPORT_PREFIX } else { META };
+        let result = self.client
+            .scan()
+            .table_name(&self.table)
+            .filter_expression(filter)
+            .expression_attribute_values(":p", AttributeValue::S(prefix.to_string()))
+            .expression_attribute_values(":sk", AttributeValue::S(sort_key.to_string()))
+            .limit(i32::try_from(limit).unwrap_or(i32::MAX))
+            .set_exclusive_start_key(after.map(|token| parse_key_token(token, TABLE_KEY)).transpose()?)
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+
+        let mut records = Vec::with_capacity(result.items().len());
+        for item in result.items() {
+            records.push(match section {
+                Section::Locations => Record::Location(Location::from(LocationItem::parse(item)?)),
+                Section::Bins => Record::Bin(BinItem::parse(item)?.record()?),
+                Section::QrCodes => Record::QrCode(QRCode::from(QrCodeItem::parse(item)?)),
+                Section::Log => LogItem::parse(item)?.record()?,
+            });
+        }
+        let next = result.last_evaluated_key().and_then(|last| key_token(last, TABLE_KEY));
+        Ok(ExportPage { records, next })
+    }
+
+    async fn import_record(&self, record: &Record) -> Result<(), AppError> {
+        match record {
+            Record::Header { .. } => Ok(()),
+            Record::Location(location) => self.put_location(location).await,
+            Record::QrCode(qr_code) => self.put_qr_code(qr_code).await,
+            Record::Report(report) => self.add_report(report).await,
+            Record::Bin(bin) => self.put(bin_item(&BinItem::from(bin))?).await,
+            Record::Collection(event) => self.put(log_item(&LogItem::Collection(CollectionItem::from(event)))?).await,
+            Record::Incident(incident) => self.add_incident(incident).await,
+        }
+    }
+}
+
+/// Copies bins and reports from the two-table layout into `target`,
+/// upgrading each item to its current shape on the way. Copying again
+/// writes the same items, so an interrupted copy can simply be rerun.
+/// Run it before the functions switch to the single table; a copy over a
+/// live table would put back older estimates.
+#[instrument(skip(client, tables))]
+pub async fn copy_two_table_layout(client: &Client, tables: &TableNames, target: &str) -> Result<CopyReport, AppError> {
+    let mut report = CopyReport::default();
+
+    for item in scan_all(client, &tables.bins).await? {
+        let bin = BinItem::parse(&item)?;
+        write_all(client, target, vec![bin_item(&bin)?]).await?;
+        report.bins += 1;
+    }
+
+    let mut pending = Vec::new();
+    for item in scan_all(client, &tables.reports).await? {
+        pending.push(log_item(&LogItem::parse(&item)?)?);
+        if pending.len() == BATCH_WRITE_LIMIT {
+            report.reports += pending.len();
+            write_all(client, target, std::mem::take(&mut pending)).await?;
+        }
+    }
+    report.reports += pending.len();
+    write_all(client, target, pending).await?;
+
+    info!(bins = report.bins, reports = report.reports, "Copied two-table layout into {}", target);
+    Ok(report)
+}
+
+/// `BatchWriteItem` accepts at most 25 items per call.
+const BATCH_WRITE_LIMIT: usize = 25;
+
+async fn scan_all(client: &Client, table: &str) -> Result<Vec<Item>, AppError> {
+    let mut items = Vec::new();
+    let mut start_key = None;
+    loop {
+        let output = client
+            .scan()
+            .table_name(table)
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await
+            .map_err(db_error)?;
+        items.extend(output.items().iter().cloned());
+        start_key = output.last_evaluated_key;
+        if start_key.is_none() {
+            return Ok(items);
+        }
+    }
+}
+
+/// Writes `items`, retrying the ones DynamoDB leaves unprocessed.
+async fn write_all(client: &Client, table: &str, items: Vec<Item>) -> Result<(), AppError> {
+    for chunk in items.chunks(BATCH_WRITE_LIMIT) {
+        let mut requests: Vec<WriteRequest> = chunk
+            .iter()
+            .map(|item| {
+                let put = PutRequest::builder()
+                    .set_item(Some(item.clone()))
+                    .build()
+                    .map_err(|e| AppError::InternalError(e.to_string()))?;
+                Ok(WriteRequest::builder().put_request(put).build())
+            })
+            .collect::<Result<_, AppError>>()?;
+
+        let mut attempt = 0;
+        while !requests.is_empty() {
+            if attempt > 0 {
+                tokio::time::sleep(Duration::from_millis(50 << attempt.min(6))).await;
+            }
+            let output = client
+                .batch_write_item()
+                .request_items(table, requests)
+                .send()
+                .await
+                .map_err(db_error)?;
+            requests = output
+                .unprocessed_items
+                .and_then(|mut tables| tables.remove(table))
+                .unwrap_or_default();
+            attempt += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_report_keys_sort_by_observation_within_bounds() {
+        let bin_id = Uuid::new_v4();
+        let earlier = report_sort_key(Utc.with_ymd_and_hms(2024, 3, 20, 9, 0, 0).unwrap());
+        let later = report_sort_key(Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap());
+
+        assert!(earlier < later);
+        assert!(later.as_str() < REPORT_UPPER_BOUND);
+        // The bin's own item sorts before its reports
+        assert!(META < earlier.as_str());
+        assert_eq!(bin_key(&bin_id), format!("BIN#{}", bin_id));
+    }
+
+    #[test]
+    fn test_only_placed_bins_join_the_location_index() {
+        let location_id = Uuid::new_v4();
+        let unplaced = bin_item(&BinItem::new(Uuid::new_v4())).unwrap();
+        let placed = bin_item(&BinItem { location_id: Some(location_id), ..BinItem::new(Uuid::new_v4()) }).unwrap();
+
+        assert!(!unplaced.contains_key("GSI1PK"));
+        assert_eq!(placed["GSI1PK"], AttributeValue::S(location_key(&location_id)));
+        assert_eq!(placed["SK"], AttributeValue::S(META.to_string()));
+        // The keys do not get in the way of reading the entity back
+        assert_eq!(BinItem::parse(&placed).unwrap().location_id, Some(location_id));
+    }
+}
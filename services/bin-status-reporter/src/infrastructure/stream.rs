@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::domain::history::BinEvent;
+use crate::error::MalformedItem;
+use crate::infrastructure::items::{Item, LogItem};
+
+/// A batch of DynamoDB Streams records from the report log, as Lambda
+/// delivers it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReportStreamEvent {
+    #[serde(rename = "Records", default)]
+    pub records: Vec<StreamRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamRecord {
+    #[serde(rename = "eventName")]
+    pub event_name: String,
+    pub dynamodb: StreamImages,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamImages {
+    #[serde(rename = "NewImage", default)]
+    pub new_image: Option<HashMap<String, serde_dynamo::AttributeValue>>,
+}
+
+impl ReportStreamEvent {
+    /// The reports and collections written or replaced, by bin. Removals,
+    /// such as those of archived reports, leave the rollups as they are, and
+    /// so do incidents.
+    pub fn log_entries(&self) -> Result<Vec<(Uuid, BinEvent)>, MalformedItem> {
+        let mut entries = Vec::with_capacity(self.records.len());
+        for record in &self.records {
+            let Some(image) = record.dynamodb.new_image.as_ref().filter(|_| record.event_name != "REMOVE") else {
+                continue;
+            };
+            let item: Item = image.iter().map(|(name, value)| (name.clone(), value.clone().into())).collect();
+            let entry = LogItem::parse(&item)?;
+            let bin_id = entry.bin_id();
+            if let Some(event) = entry.event()? {
+                entries.push((bin_id, event));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reads_inserted_reports_and_collections_but_not_incidents() {
+        let bin_id = Uuid::new_v4();
+        let event: ReportStreamEvent = serde_json::from_value(json!({
+            "Records": [
+                {
+                    "eventName": "INSERT",
+                    "dynamodb": {"NewImage": {
+                        "binId": {"S": bin_id.to_string()},
+                        "createdAt": {"S": "2024-03-19T08:00:00+00:00"},
+                        "schemaVersion": {"N": "2"},
+                        "reportId": {"S": Uuid::new_v4().to_string()},
+                        "receivedAt": {"S": "2024-03-19T08:00:02+00:00"},
+                        "status": {"N": "9"},
+                        "source": {"S": "sensor"}
+                    }}
+                },
+                {
+                    "eventName": "INSERT",
+                    "dynamodb": {"NewImage": {
+                        "binId": {"S": bin_id.to_string()},
+                        "createdAt": {"S": "2024-03-19T11:00:00+00:00#collection"},
+                        "eventType": {"S": "collection"},
+                        "collectedAt": {"S": "2024-03-19T11:00:00+00:00"},
+                        "receivedAt": {"S": "2024-03-19T11:00:05+00:00"}
+                    }}
+                },
+                {
+                    "eventName": "INSERT",
+                    "dynamodb": {"NewImage": {
+                        "binId": {"S": bin_id.to_string()},
+                        "createdAt": {"S": "2024-03-19T12:00:00+00:00#fire"},
+                        "eventType": {"S": "incident"},
+                        "reportKind": {"S": "fire"},
+                        "reportId": {"S": Uuid::new_v4().to_string()},
+                        "observedAt": {"S": "2024-03-19T12:00:00+00:00"},
+                        "receivedAt": {"S": "2024-03-19T12:00:01+00:00"},
+                        "stillBurning": {"BOOL": true}
+                    }}
+                },
+                {"eventName": "REMOVE", "dynamodb": {}}
+            ]
+        }))
+        .unwrap();
+
+        let entries = event.log_entries().unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(&entries[0], (id, BinEvent::Reported(report)) if *id == bin_id && report.status.value() == 9));
+        assert!(matches!(entries[1], (id, BinEvent::Collected { .. }) if id == bin_id));
+    }
+}
@@ -0,0 +1,212 @@
+use std::sync::OnceLock;
+
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::{field, info_span, warn, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::Config;
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const XRAY_HEADER: &str = "X-Amzn-Trace-Id";
+
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// The caller's span that an incoming request continues, from a W3C
+/// `traceparent` or an AWS X-Ray trace header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemoteParent {
+    pub trace_id: TraceId,
+    pub span_id: SpanId,
+    pub sampled: bool,
+}
+
+impl RemoteParent {
+    /// Parses `00-<trace id>-<parent id>-<flags>`.
+    pub fn from_w3c(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let (version, trace_id, span_id, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version.len() != 2 || version == "ff" || trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Self::valid(trace_id, span_id, flags & 1 == 1)
+    }
+
+    /// Parses `Root=1-<epoch>-<unique id>;Parent=<span id>;Sampled=<0|1>`.
+    /// The X-Ray root maps onto a W3C trace id by dropping the version and
+    /// dashes.
+    pub fn from_xray(header: &str) -> Option<Self> {
+        let (mut root, mut parent, mut sampled) = (None, None, false);
+        for part in header.split(';') {
+            match part.trim().split_once('=') {
+                Some(("Root", value)) => root = Some(value),
+                Some(("Parent", value)) => parent = Some(value),
+                Some(("Sampled", value)) => sampled = value == "1",
+                _ => {}
+            }
+        }
+
+        let mut root_parts = root?.split('-');
+        if root_parts.next()? != "1" {
+            return None;
+        }
+        let (epoch, unique) = (root_parts.next()?, root_parts.next()?);
+        if epoch.len() != 8 || unique.len() != 24 {
+            return None;
+        }
+        Self::valid(&format!("{}{}", epoch, unique), parent?, sampled)
+    }
+
+    fn valid(trace_id: &str, span_id: &str, sampled: bool) -> Option<Self> {
+        let trace_id = TraceId::from_hex(trace_id).ok().filter(|id| *id != TraceId::INVALID)?;
+        let span_id = SpanId::from_hex(span_id).ok().filter(|id| *id != SpanId::INVALID)?;
+        Some(Self { trace_id, span_id, sampled })
+    }
+
+    pub fn context(&self) -> opentelemetry::Context {
+        let flags = if self.sampled { TraceFlags::SAMPLED } else { TraceFlags::default() };
+        let span_context = SpanContext::new(self.trace_id, self.span_id, flags, true, TraceState::default());
+        opentelemetry::Context::new().with_remote_span_context(span_context)
+    }
+}
+
+/// Root span for one Lambda invocation. It continues the W3C trace in
+/// `traceparent` if one was passed, else the X-Ray trace Lambda received.
+/// `request_id` and `trace_id` are recorded on it so every event logged
+/// inside carries them.
+pub fn invocation_span(handler: &'static str, context: &lambda_runtime::Context, traceparent: Option<&str>) -> Span {
+    let span = info_span!(
+        "invocation",
+        otel.name = handler,
+        otel.kind = "server",
+        faas.invocation_id = %context.request_id,
+        request_id = %context.request_id,
+        trace_id = field::Empty,
+    );
+
+    let parent = traceparent
+        .and_then(RemoteParent::from_w3c)
+        .or_else(|| context.xray_trace_id.as_deref().and_then(RemoteParent::from_xray));
+    if let Some(parent) = parent {
+        if let Err(e) = span.set_parent(parent.context()) {
+            warn!("Failed to continue trace {}: {}", parent.trace_id, e);
+        }
+    }
+
+    let trace_id = span.context().span().span_context().trace_id();
+    if trace_id != TraceId::INVALID {
+        span.record("trace_id", field::display(trace_id));
+    }
+    span
+}
+
+/// Installs the shared logging layer, configured from `LOG_LEVEL` and
+/// `LOG_FORMAT`, and OpenTelemetry tracing. Spans are exported over OTLP
+/// when the `otlp` feature is on and `OTEL_EXPORTER_OTLP_ENDPOINT` is set;
+/// without an exporter they still carry trace ids into the logs.
+pub fn init() {
+    let provider = PROVIDER.get_or_init(build_provider);
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    tracing_subscriber::registry()
+        .with(shared::logging::layer(&Config::from_env().logging()))
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .init();
+}
+
+/// Exports buffered spans. Lambda freezes the container between
+/// invocations, so handlers flush before they return.
+pub fn flush() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.force_flush() {
+            warn!("Failed to flush spans: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn build_provider() -> SdkTracerProvider {
+    let builder = SdkTracerProvider::builder();
+    if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err() {
+        return builder.build();
+    }
+
+    // The exporter reads the endpoint and headers from the OTEL_* variables
+    match opentelemetry_otlp::SpanExporter::builder().with_http().build() {
+        Ok(exporter) => builder.with_batch_exporter(exporter).build(),
+        Err(e) => {
+            eprintln!("Failed to create OTLP exporter, spans will not be exported: {}", e);
+            builder.build()
+        }
+    }
+}
+
+#[cfg(not(feature = "otlp"))]
+fn build_provider() -> SdkTracerProvider {
+    SdkTracerProvider::builder().build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_w3c_traceparent() {
+        let parent = RemoteParent::from_w3c("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+
+        assert_eq!(parent.trace_id.to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.span_id.to_string(), "00f067aa0ba902b7");
+        assert!(parent.sampled);
+        assert!(!RemoteParent::from_w3c("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00").unwrap().sampled);
+    }
+
+    #[test]
+    fn test_parses_xray_header() {
+        let parent =
+            RemoteParent::from_xray("Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1").unwrap();
+
+        assert_eq!(parent.trace_id.to_string(), "5759e988bd862e3fe1be46a994272793");
+        assert_eq!(parent.span_id.to_string(), "53995c3f42cd8ad8");
+        assert!(parent.sampled);
+    }
+
+    #[test]
+    fn test_rejects_malformed_trace_headers() {
+        assert!(RemoteParent::from_w3c("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(RemoteParent::from_w3c("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(RemoteParent::from_w3c("garbage").is_none());
+        // Lambda passes a root without a parent when tracing is not active
+        assert!(RemoteParent::from_xray("Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=0").is_none());
+        assert!(RemoteParent::from_xray("Root=2-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8").is_none());
+    }
+
+    #[test]
+    fn test_invocation_span_continues_remote_trace() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut context = lambda_runtime::Context::default();
+            context.request_id = "req-1".to_string();
+            context.xray_trace_id =
+                Some("Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1".to_string());
+
+            let from_xray = invocation_span("update_bin_status", &context, None);
+            assert_eq!(
+                from_xray.context().span().span_context().trace_id().to_string(),
+                "5759e988bd862e3fe1be46a994272793"
+            );
+
+            let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+            let from_w3c = invocation_span("receive_lorawan_uplink", &context, Some(traceparent));
+            assert_eq!(
+                from_w3c.context().span().span_context().trace_id().to_string(),
+                "4bf92f3577b34da6a3ce929d0e0e4736"
+            );
+        });
+    }
+}
@@ -1,51 +1,722 @@
+pub mod config;
 pub mod error;
 pub mod domain;
 pub mod application;
 pub mod infrastructure;
 
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use lambda_runtime::{Error, LambdaEvent};
-use tracing::{info, error};
+use shared::metrics::MetricsRecorder;
+use tracing::{info, error, Instrument, Span};
 
-use crate::application::handle_status_update;
-use crate::domain::{StatusUpdateRequest, StatusUpdateResponse};
-use crate::infrastructure::dynamodb::DynamoDbRepository;
+use crate::application::batch::handle_batch_status_update;
+use crate::application::collection::handle_collection;
+use crate::application::device_health::{list_flagged_devices, run_device_health_check, HealthCheckSummary};
+use crate::application::incident::{handle_bin_report, handle_list_incidents};
+use crate::application::lifecycle::{handle_get_lifecycle, handle_lifecycle_change};
+use crate::application::retention::{run_scheduled_retention, RetentionOptions, RetentionSummary};
+use crate::application::rollup::{apply_log_entries, fill_history, FillHistory, RollupSummary};
+use crate::application::{
+    handle_get_bin_status, handle_get_bin_status_at, handle_get_location_status, handle_lorawan_webhook, handle_sensor_reading,
+    handle_set_bin_profile, handle_status_update,
+};
+use crate::domain::batch::{BatchStatusRequest, BatchStatusResponse};
+use crate::domain::collection::CollectionRequest;
+use crate::domain::consistency::ResumeToken;
+use crate::domain::device_health::{DeviceHealth, HealthPolicy};
+use crate::domain::incident::{IncidentQuery, IncidentReport, ReportRequest, ReportResponse};
+use crate::domain::lifecycle::{BinLifecycle, BinLifecycleQuery, LifecycleRequest};
+use crate::domain::location::{LocationStatus, LocationStatusQuery};
+use crate::domain::lorawan::{DecoderRegistry, WebhookRequest};
+use crate::domain::rollup::{FillHistoryQuery, RollupScope};
+use crate::domain::sensor::SensorReadingRequest;
+use crate::application::context::AppContext;
+use crate::application::metrics::COLD_STARTS;
+use crate::domain::timing::TimestampPolicy;
+use crate::domain::{
+    BinProfile, BinProfileRequest, BinStatusQuery, BinStatusView, StatusUpdateRequest, StatusUpdateResponse,
+};
+use crate::infrastructure::archive::{retention_from_env, S3Archive};
+use crate::infrastructure::cache::{BinCache, CachePolicy, CachedRepository, LruBinCache};
+use crate::infrastructure::dynamodb::{estimator_from_env, DynamoDbRepository};
+use crate::infrastructure::metrics::EmfRecorder;
+use crate::infrastructure::resilience::ResilientRepository;
+use crate::infrastructure::stream::ReportStreamEvent;
+use crate::infrastructure::telemetry::{self, invocation_span, TRACEPARENT_HEADER};
+use crate::infrastructure::notifications::FanoutNotifier;
 
 pub use error::AppError;
 
+type Repository = CachedRepository<ResilientRepository<DynamoDbRepository>>;
+
+static REPOSITORY: tokio::sync::OnceCell<Repository> = tokio::sync::OnceCell::const_new();
+
+/// DynamoDB behind retries, call deadlines and a circuit breaker, with bin
+/// reads cached. Built once per Lambda container so the client, the breaker
+/// state and the cache outlive a single invocation.
+async fn repository() -> Result<&'static Repository, AppError> {
+    record_cold_start();
+    REPOSITORY
+        .get_or_try_init(|| async {
+            let inner = ResilientRepository::new(DynamoDbRepository::new().await?).with_metrics(metrics());
+            Ok(CachedRepository::new(inner, bin_cache_from_env().await?))
+        })
+        .await
+}
+
+static METRICS: OnceLock<Arc<EmfRecorder>> = OnceLock::new();
+
+static COLD_START: AtomicBool = AtomicBool::new(true);
+
+/// CloudWatch EMF on stdout, which Lambda ships to CloudWatch Logs.
+fn metrics() -> Arc<dyn MetricsRecorder> {
+    METRICS.get_or_init(|| Arc::new(EmfRecorder::from_env())).clone()
+}
+
+/// Counts the first invocation in each container.
+fn record_cold_start() {
+    if COLD_START.swap(false, Ordering::Relaxed) {
+        let function = std::env::var("AWS_LAMBDA_FUNCTION_NAME").unwrap_or_else(|_| "unknown".to_string());
+        metrics().increment(COLD_STARTS, &[("Function", &function)]);
+    }
+}
+
+/// Runs a handler inside its invocation span, then exports the spans
+/// before Lambda freezes the container. The handler is boxed to keep the
+/// nested future types within the compiler's layout depth.
+async fn traced<T, Fut>(span: Span, handler: Fut) -> Result<T, Error>
+where
+    Fut: Future<Output = Result<T, Error>> + Send,
+{
+    let result = Box::pin(handler.instrument(span)).await;
+    telemetry::flush();
+    result
+}
+
+/// Reads `BIN_CACHE_CAPACITY` and `BIN_CACHE_TTL_SECS`. With the
+/// `redis-cache` feature and `REDIS_URL` set, the cache is shared through
+/// Redis instead of kept in process.
+async fn bin_cache_from_env() -> Result<Arc<dyn BinCache>, AppError> {
+    let defaults = CachePolicy::default();
+    let policy = CachePolicy {
+        capacity: match usize_from_env("BIN_CACHE_CAPACITY")? {
+            Some(capacity) => NonZeroUsize::new(capacity)
+                .ok_or_else(|| AppError::InternalError("BIN_CACHE_CAPACITY must be positive".to_string()))?,
+            None => defaults.capacity,
+        },
+        ttl: match duration_from_env("BIN_CACHE_TTL_SECS")? {
+            Some(secs) => std::time::Duration::from_secs(secs as u64),
+            None => defaults.ttl,
+        },
+    };
+
+    #[cfg(feature = "redis-cache")]
+    if let Ok(url) = std::env::var("REDIS_URL") {
+        info!("Caching bins in Redis");
+        return Ok(Arc::new(infrastructure::cache::RedisBinCache::connect(&url, policy.ttl).await?));
+    }
+
+    Ok(Arc::new(LruBinCache::new(policy)))
+}
+
 pub async fn update_bin_status(
     event: LambdaEvent<StatusUpdateRequest>,
 ) -> Result<StatusUpdateResponse, Error> {
-    info!(
-        "Lambda invocation started - RequestId: {:?}, BinId: {}, Status: {}", 
-        event.context.request_id, 
-        event.payload.bin_id, 
-        event.payload.status
-    );
-
-    let repo = match DynamoDbRepository::new().await {
-        Ok(repo) => {
-            info!("Successfully initialized DynamoDB repository");
-            repo
-        }
-        Err(e) => {
-            error!("Failed to initialize DynamoDB repository: {}", e);
-            return Err(Box::new(e));
+    let span = invocation_span("update_bin_status", &event.context, None);
+    traced(span, async move {
+        info!(bin_id = %event.payload.bin_id, status = event.payload.status.value(), "Status update received");
+
+        let repo = match repository().await {
+            Ok(repo) => {
+                info!("Successfully initialized DynamoDB repository");
+                repo
+            }
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::system(timestamp_policy_from_env()?).with_metrics(metrics());
+        match handle_status_update(repo, &ctx, event.payload).await {
+            Ok(response) => {
+                info!(updated_at = %response.updated_at, "Status update completed: {}", response.message);
+                Ok(response)
+            }
+            Err(e) => {
+                error!(error = %e, "Status update failed");
+                Err(e.into())
+            }
         }
-    };
+    })
+    .await
+}
+
+/// Public read for the QR landing page and the map.
+pub async fn get_bin_status(
+    event: LambdaEvent<BinStatusQuery>,
+) -> Result<BinStatusView, Error> {
+    let span = invocation_span("get_bin_status", &event.context, None);
+    traced(span, async move {
+        info!(bin_id = %event.payload.bin_id, "Bin status query received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let result = match event.payload.as_of {
+            Some(as_of) => match estimator_from_env() {
+                Ok(estimator) => handle_get_bin_status_at(repo, &estimator, &event.payload.bin_id, as_of).await,
+                Err(e) => Err(e),
+            },
+            None => handle_get_bin_status(repo, &event.payload.bin_id).await,
+        };
+        match result {
+            Ok(view) => Ok(view),
+            Err(e) => {
+                error!("Bin status query failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Public read for the map, which shows one marker per site.
+pub async fn get_location_status(
+    event: LambdaEvent<LocationStatusQuery>,
+) -> Result<LocationStatus, Error> {
+    let span = invocation_span("get_location_status", &event.context, None);
+    traced(span, async move {
+        let query = event.payload;
+        info!(location_id = %query.location_id, waste_type = ?query.waste_type, "Location status query received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        match handle_get_location_status(repo, &query.location_id, query.waste_type).await {
+            Ok(status) => Ok(status),
+            Err(e) => {
+                error!("Location status query failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Fill level history of a bin, location or district for charts, read
+/// from the rollups the report stream keeps.
+pub async fn get_fill_history(
+    event: LambdaEvent<FillHistoryQuery>,
+) -> Result<FillHistory, Error> {
+    let span = invocation_span("get_fill_history", &event.context, None);
+    traced(span, async move {
+        let query = event.payload;
+        info!(scope = %query.scope, id = %query.id, from = %query.from, to = %query.to, "Fill history query received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let result = match RollupScope::new(&query.scope, &query.id) {
+            Ok(scope) => fill_history(repo, &scope, query.from, query.to, query.resolution).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(history) => Ok(history),
+            Err(e) => {
+                error!("Fill history query failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Crew and operator changes to a bin's lifecycle state.
+pub async fn change_bin_lifecycle(
+    event: LambdaEvent<LifecycleRequest>,
+) -> Result<BinLifecycle, Error> {
+    let span = invocation_span("change_bin_lifecycle", &event.context, None);
+    traced(span, async move {
+        let request = event.payload;
+        info!(bin_id = %request.bin_id, state = %request.state, actor = %request.actor, "Lifecycle change received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::system(TimestampPolicy::default());
+        match handle_lifecycle_change(repo, &ctx, request).await {
+            Ok(lifecycle) => Ok(lifecycle),
+            Err(e) => {
+                error!("Lifecycle change failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// The bin's lifecycle state and the audit trail of its changes.
+pub async fn get_bin_lifecycle(
+    event: LambdaEvent<BinLifecycleQuery>,
+) -> Result<BinLifecycle, Error> {
+    let span = invocation_span("get_bin_lifecycle", &event.context, None);
+    traced(span, async move {
+        info!(bin_id = %event.payload.bin_id, "Lifecycle query received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        match handle_get_lifecycle(repo, &event.payload.bin_id).await {
+            Ok(lifecycle) => Ok(lifecycle),
+            Err(e) => {
+                error!("Lifecycle query failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Citizen reports of any kind: fill level, damage, overflow, fire or
+/// wrong sorting. Damage tickets and escalations go out through the alert
+/// channels.
+pub async fn submit_bin_report(
+    event: LambdaEvent<ReportRequest>,
+) -> Result<ReportResponse, Error> {
+    let span = invocation_span("submit_bin_report", &event.context, None);
+    traced(span, async move {
+        info!(bin_id = %event.payload.bin_id, kind = event.payload.details.kind().as_str(), "Bin report received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::system(timestamp_policy_from_env()?).with_metrics(metrics());
+        let dispatcher = FanoutNotifier::from_env();
+        match handle_bin_report(repo, &ctx, &dispatcher, event.payload).await {
+            Ok(response) => {
+                info!(report_id = %response.report_id, "Bin report completed: {}", response.message);
+                Ok(response)
+            }
+            Err(e) => {
+                error!(error = %e, "Bin report failed");
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Operator query: recent incidents of one kind across all bins.
+pub async fn list_incidents(
+    event: LambdaEvent<IncidentQuery>,
+) -> Result<Vec<IncidentReport>, Error> {
+    let span = invocation_span("list_incidents", &event.context, None);
+    traced(span, async move {
+        info!(kind = event.payload.kind.as_str(), "Incident query received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        match handle_list_incidents(repo, &AppContext::default(), event.payload).await {
+            Ok(incidents) => Ok(incidents),
+            Err(e) => {
+                error!("Incident query failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+pub async fn update_bin_status_batch(
+    event: LambdaEvent<BatchStatusRequest>,
+) -> Result<BatchStatusResponse, Error> {
+    let span = invocation_span("update_bin_status_batch", &event.context, None);
+    traced(span, async move {
+        info!(items = event.payload.items.len(), "Batch status update received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::system(timestamp_policy_from_env()?).with_metrics(metrics());
+        match handle_batch_status_update(repo, &ctx, event.payload).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                error!("Batch status update failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+pub async fn record_bin_collection(
+    event: LambdaEvent<CollectionRequest>,
+) -> Result<StatusUpdateResponse, Error> {
+    let span = invocation_span("record_bin_collection", &event.context, None);
+    traced(span, async move {
+        info!(bin_id = %event.payload.bin_id, "Collection event received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::system(timestamp_policy_from_env()?).with_metrics(metrics());
+        match handle_collection(repo, &ctx, event.payload).await {
+            Ok(response) => {
+                info!("Collection recorded - Message: {}", response.message);
+                Ok(response)
+            }
+            Err(e) => {
+                error!("Collection event failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+pub async fn ingest_sensor_reading(
+    event: LambdaEvent<SensorReadingRequest>,
+) -> Result<StatusUpdateResponse, Error> {
+    let span = invocation_span("ingest_sensor_reading", &event.context, None);
+    traced(span, async move {
+        info!(
+            device_id = %event.payload.device_id,
+            distance_mm = event.payload.distance_mm,
+            "Sensor reading received"
+        );
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::default().with_metrics(metrics()).with_health_policy(health_policy_from_env()?);
+
+        match handle_sensor_reading(repo, &ctx, event.payload).await {
+            Ok(response) => {
+                info!("Sensor reading stored - Message: {}", response.message);
+                Ok(response)
+            }
+            Err(e) => {
+                error!("Sensor reading failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+pub async fn receive_lorawan_uplink(
+    event: LambdaEvent<WebhookRequest>,
+) -> Result<StatusUpdateResponse, Error> {
+    let span = invocation_span("receive_lorawan_uplink", &event.context, event.payload.header(TRACEPARENT_HEADER));
+    traced(span, async move {
+        info!("LoRaWAN webhook invoked");
+
+        let secret = match std::env::var("LORAWAN_WEBHOOK_SECRET") {
+            Ok(secret) => secret,
+            Err(_) => {
+                error!("LORAWAN_WEBHOOK_SECRET is not configured");
+                return Err(Error::from(AppError::InternalError(
+                    "Webhook secret not configured".to_string(),
+                )));
+            }
+        };
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = AppContext::default().with_metrics(metrics()).with_health_policy(health_policy_from_env()?);
+
+        match handle_lorawan_webhook(repo, &ctx, &DecoderRegistry::default(), secret.as_bytes(), event.payload).await {
+            Ok(response) => {
+                info!("LoRaWAN uplink stored - Message: {}", response.message);
+                Ok(response)
+            }
+            Err(e) => {
+                error!("LoRaWAN uplink failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Scheduled (EventBridge) entry point for the device health check.
+pub async fn check_device_health(
+    event: LambdaEvent<serde_json::Value>,
+) -> Result<HealthCheckSummary, Error> {
+    let span = invocation_span("check_device_health", &event.context, None);
+    traced(span, async move {
+        info!("Device health check started");
+
+        let repo = repository().await?;
+        let notifier = FanoutNotifier::from_env();
+        let policy = health_policy_from_env()?;
 
-    match handle_status_update(&repo, event.payload).await {
-        Ok(response) => {
-            info!(
-                "Status update completed successfully - Message: {}, Timestamp: {}", 
-                response.message, 
-                response.updated_at
-            );
-            Ok(response)
+        match run_device_health_check(repo, &notifier, &policy, AppContext::default().now()).await {
+            Ok(summary) => {
+                info!(
+                    devices_checked = summary.devices_checked,
+                    devices_flagged = summary.devices_flagged,
+                    alerts_sent = summary.alerts_sent,
+                    "Device health check finished"
+                );
+                Ok(summary)
+            }
+            Err(e) => {
+                error!("Device health check failed: {}", e);
+                Err(e.into())
+            }
         }
-        Err(e) => {
-            error!("Status update failed: {}", e);
-            Err(Box::new(e))
+    })
+    .await
+}
+
+/// Time left for the retention run to finish the bin it is on once it
+/// stops starting new pages.
+const RETENTION_SAFETY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Scheduled (EventBridge) entry point for report retention. A run that
+/// reaches the Lambda timeout saves where it stopped next to the archive,
+/// and the next run continues from there. `{"resume": "<token>"}` starts
+/// from a given bin instead.
+pub async fn run_report_retention(
+    event: LambdaEvent<serde_json::Value>,
+) -> Result<RetentionSummary, Error> {
+    let span = invocation_span("run_report_retention", &event.context, None);
+    traced(span, async move {
+        info!("Report retention started");
+
+        let resume = match event.payload.get("resume").and_then(|resume| resume.as_str()) {
+            Some(token) => Some(token.parse::<ResumeToken>()?),
+            None => None,
+        };
+        let remaining = UNIX_EPOCH + Duration::from_millis(event.context.deadline);
+        let remaining = remaining.duration_since(SystemTime::now()).unwrap_or_default();
+        let options = RetentionOptions {
+            deadline: Some(tokio::time::Instant::now() + remaining.saturating_sub(RETENTION_SAFETY_MARGIN)),
+            resume,
+            ..RetentionOptions::default()
+        };
+
+        let repo = repository().await?;
+        let archive = S3Archive::from_env().await?;
+        let policy = retention_from_env()?;
+
+        match run_scheduled_retention(repo, &archive, &policy, AppContext::default().now(), &options).await {
+            Ok(summary) => {
+                info!(
+                    bins_checked = summary.bins_checked,
+                    days_archived = summary.days_archived,
+                    reports_archived = summary.reports_archived,
+                    incidents_archived = summary.incidents_archived,
+                    resume = summary.resume.as_deref(),
+                    "Report retention finished"
+                );
+                Ok(summary)
+            }
+            Err(e) => {
+                error!("Report retention failed: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// DynamoDB Streams entry point that keeps the fill level rollups up to
+/// date with the report log. A failed batch is retried as a whole, which
+/// the rollups absorb.
+pub async fn handle_report_stream(
+    event: LambdaEvent<ReportStreamEvent>,
+) -> Result<RollupSummary, Error> {
+    let span = invocation_span("handle_report_stream", &event.context, None);
+    traced(span, async move {
+        let entries = event.payload.log_entries().map_err(AppError::from)?;
+        let repo = repository().await?;
+
+        match apply_log_entries(repo, &entries).await {
+            Ok(summary) => {
+                info!(bins = summary.bins, periods_updated = summary.periods_updated, "Rollups updated");
+                Ok(summary)
+            }
+            Err(e) => {
+                error!("Failed to update rollups: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Admin API: devices with open health flags.
+pub async fn get_flagged_devices(
+    event: LambdaEvent<serde_json::Value>,
+) -> Result<Vec<DeviceHealth>, Error> {
+    let span = invocation_span("get_flagged_devices", &event.context, None);
+    traced(span, async move {
+        info!("Listing flagged devices");
+
+        let repo = repository().await?;
+        match list_flagged_devices(repo).await {
+            Ok(devices) => Ok(devices),
+            Err(e) => {
+                error!("Failed to list flagged devices: {}", e);
+                Err(e.into())
+            }
+        }
+    })
+    .await
+}
+
+/// Admin API: records a bin's waste stream, capacity and container model.
+pub async fn set_bin_profile(
+    event: LambdaEvent<BinProfileRequest>,
+) -> Result<BinProfile, Error> {
+    let span = invocation_span("set_bin_profile", &event.context, None);
+    traced(span, async move {
+        info!(bin_id = %event.payload.bin_id, "Bin profile received");
+
+        let repo = match repository().await {
+            Ok(repo) => repo,
+            Err(e) => {
+                error!("Failed to initialize DynamoDB repository: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        match handle_set_bin_profile(repo, event.payload).await {
+            Ok(profile) => Ok(profile),
+            Err(e) => {
+                error!("Failed to record bin profile: {}", e);
+                Err(e.into())
+            }
         }
+    })
+    .await
+}
+
+/// Reads `MAX_CLOCK_SKEW_SECS` and `MAX_REPORT_AGE_HOURS`, falling back to
+/// the policy defaults.
+fn timestamp_policy_from_env() -> Result<TimestampPolicy, AppError> {
+    let defaults = TimestampPolicy::default();
+    Ok(TimestampPolicy {
+        max_clock_skew: match duration_from_env("MAX_CLOCK_SKEW_SECS")? {
+            Some(secs) => chrono::Duration::seconds(secs),
+            None => defaults.max_clock_skew,
+        },
+        max_report_age: match duration_from_env("MAX_REPORT_AGE_HOURS")? {
+            Some(hours) => chrono::Duration::hours(hours),
+            None => defaults.max_report_age,
+        },
+    })
+}
+
+/// Reads `DEVICE_SILENT_AFTER_HOURS`, `DEVICE_LOW_BATTERY_MV`,
+/// `DEVICE_STUCK_AFTER_DAYS` and `DEVICE_STUCK_TOLERANCE_MM`, falling back
+/// to the policy defaults.
+pub fn health_policy_from_env() -> Result<HealthPolicy, AppError> {
+    let defaults = HealthPolicy::default();
+    Ok(HealthPolicy {
+        silent_after: match duration_from_env("DEVICE_SILENT_AFTER_HOURS")? {
+            Some(hours) => chrono::Duration::hours(hours),
+            None => defaults.silent_after,
+        },
+        low_battery_mv: match number_from_env("DEVICE_LOW_BATTERY_MV")? {
+            Some(mv) => mv,
+            None => defaults.low_battery_mv,
+        },
+        stuck_after: match duration_from_env("DEVICE_STUCK_AFTER_DAYS")? {
+            Some(days) => chrono::Duration::days(days),
+            None => defaults.stuck_after,
+        },
+        stuck_tolerance_mm: match u32_from_env("DEVICE_STUCK_TOLERANCE_MM")? {
+            Some(mm) => mm,
+            None => defaults.stuck_tolerance_mm,
+        },
+    })
+}
+
+fn duration_from_env(name: &str) -> Result<Option<i64>, AppError> {
+    Ok(u32_from_env(name)?.map(i64::from))
+}
+
+fn u32_from_env(name: &str) -> Result<Option<u32>, AppError> {
+    number_from_env(name)
+}
+
+fn usize_from_env(name: &str) -> Result<Option<usize>, AppError> {
+    number_from_env(name)
+}
+
+fn number_from_env<T: std::str::FromStr>(name: &str) -> Result<Option<T>, AppError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse::<T>()
+            .map(Some)
+            .map_err(|_| AppError::InternalError(format!("{} must be a whole number, got {}", name, value))),
+        Err(_) => Ok(None),
     }
 }
 
@@ -53,7 +724,6 @@ pub async fn update_bin_status(
 mod tests {
     use super::*;
     use lambda_runtime::LambdaEvent;
-    use uuid::Uuid;
     use crate::domain::{BinStatus, StatusUpdateRequest};
     use crate::infrastructure::test_utils;
 
@@ -67,6 +737,7 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: BinStatus::full(),
+            observed_at: None,
         };
 
         let event = LambdaEvent::new(request, Default::default());
@@ -86,6 +757,7 @@ mod tests {
         let request = StatusUpdateRequest {
             bin_id,
             status: BinStatus::new(7).unwrap(),
+            observed_at: None,
         };
 
         let event = LambdaEvent::new(request, Default::default());
@@ -1,13 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+/// The waste stream a bin collects, as on the colour-coded bins at Czech
+/// sorting sites. Bins registered before streams were recorded are mixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WasteType {
+    Plastic,
+    Paper,
+    Glass,
+    Bio,
+    #[default]
+    Mixed,
+}
+
+impl WasteType {
+    pub const ALL: [WasteType; 5] =
+        [WasteType::Plastic, WasteType::Paper, WasteType::Glass, WasteType::Bio, WasteType::Mixed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WasteType::Plastic => "plastic",
+            WasteType::Paper => "paper",
+            WasteType::Glass => "glass",
+            WasteType::Bio => "bio",
+            WasteType::Mixed => "mixed",
+        }
+    }
+}
+
+impl fmt::Display for WasteType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WasteType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WasteType::ALL
+            .into_iter()
+            .find(|waste_type| waste_type.as_str() == s.trim().to_ascii_lowercase())
+            .ok_or_else(|| format!("Unknown waste type: {}", s))
+    }
+}
+
+/// Where a bin is in its service life. Bins registered before states were
+/// recorded are active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleState {
+    /// Registered ahead of being placed.
+    Planned,
+    #[default]
+    Active,
+    /// Still standing and in use, awaiting repair.
+    Damaged,
+    /// Taken out of use while it is repaired.
+    UnderMaintenance,
+    Removed,
+    /// Removed in favour of another bin, which takes over its reports.
+    Replaced,
+}
+
+impl LifecycleState {
+    pub const ALL: [LifecycleState; 6] = [
+        LifecycleState::Planned,
+        LifecycleState::Active,
+        LifecycleState::Damaged,
+        LifecycleState::UnderMaintenance,
+        LifecycleState::Removed,
+        LifecycleState::Replaced,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleState::Planned => "planned",
+            LifecycleState::Active => "active",
+            LifecycleState::Damaged => "damaged",
+            LifecycleState::UnderMaintenance => "under_maintenance",
+            LifecycleState::Removed => "removed",
+            LifecycleState::Replaced => "replaced",
+        }
+    }
+
+    /// Whether the bin is out on the street taking waste, and so takes
+    /// reports and shows on the map.
+    pub fn is_in_service(&self) -> bool {
+        matches!(self, LifecycleState::Active | LifecycleState::Damaged)
+    }
+
+    /// Whether a bin in this state may move to `next`. Removed and replaced
+    /// bins are final; a bin put back out is registered anew.
+    pub fn can_become(&self, next: LifecycleState) -> bool {
+        use LifecycleState::*;
+        match self {
+            Planned => matches!(next, Active | Removed),
+            Active => matches!(next, Damaged | UnderMaintenance | Removed | Replaced),
+            Damaged => matches!(next, Active | UnderMaintenance | Removed | Replaced),
+            UnderMaintenance => matches!(next, Active | Damaged | Removed | Replaced),
+            Removed | Replaced => false,
+        }
+    }
+
+    /// The state of a record that may predate lifecycle states, when bins
+    /// only carried `is_active`: inactive bins read as removed.
+    pub fn or_legacy(state: Option<LifecycleState>, is_active: Option<bool>) -> LifecycleState {
+        match (state, is_active) {
+            (Some(state), _) => state,
+            (None, Some(false)) => LifecycleState::Removed,
+            (None, _) => LifecycleState::Active,
+        }
+    }
+}
+
+impl fmt::Display for LifecycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LifecycleState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LifecycleState::ALL
+            .into_iter()
+            .find(|state| state.as_str() == s.trim().to_ascii_lowercase())
+            .ok_or_else(|| format!("Unknown lifecycle state: {}", s))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TrashBinRecord")]
 pub struct TrashBin {
     pub bin_id: String,
     pub location_id: String,
     pub status: i32,  // 0-100 percentage full
     pub last_updated: DateTime<Utc>,
-    pub is_active: bool,
+    pub lifecycle: LifecycleState,
+    #[serde(default)]
+    pub waste_type: WasteType,
+    #[serde(default)]
+    pub capacity_liters: Option<u32>,
+    #[serde(default)]
+    pub container_model: Option<String>,
+}
+
+/// A stored bin as written before or after lifecycle states replaced `is_active`.
+#[derive(Deserialize)]
+struct TrashBinRecord {
+    bin_id: String,
+    location_id: String,
+    status: i32,
+    last_updated: DateTime<Utc>,
+    #[serde(default)]
+    lifecycle: Option<LifecycleState>,
+    #[serde(default)]
+    is_active: Option<bool>,
+    #[serde(default)]
+    waste_type: WasteType,
+    #[serde(default)]
+    capacity_liters: Option<u32>,
+    #[serde(default)]
+    container_model: Option<String>,
+}
+
+impl From<TrashBinRecord> for TrashBin {
+    fn from(record: TrashBinRecord) -> Self {
+        TrashBin {
+            bin_id: record.bin_id,
+            location_id: record.location_id,
+            status: record.status,
+            last_updated: record.last_updated,
+            lifecycle: LifecycleState::or_legacy(record.lifecycle, record.is_active),
+            waste_type: record.waste_type,
+            capacity_liters: record.capacity_liters,
+            container_model: record.container_model,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,3 +201,60 @@ pub struct StatusUpdate {
     pub status: i32,
     pub timestamp: DateTime<Utc>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waste_type_round_trip() {
+        for waste_type in WasteType::ALL {
+            assert_eq!(waste_type.as_str().parse::<WasteType>().unwrap(), waste_type);
+            assert_eq!(serde_json::to_string(&waste_type).unwrap(), format!("\"{}\"", waste_type));
+        }
+        assert_eq!(" Glass".parse::<WasteType>().unwrap(), WasteType::Glass);
+        assert!("textile".parse::<WasteType>().is_err());
+    }
+
+    #[test]
+    fn test_lifecycle_states_round_trip() {
+        for state in LifecycleState::ALL {
+            assert_eq!(state.as_str().parse::<LifecycleState>().unwrap(), state);
+            assert_eq!(serde_json::to_string(&state).unwrap(), format!("\"{}\"", state));
+        }
+        assert!("scrapped".parse::<LifecycleState>().is_err());
+    }
+
+    #[test]
+    fn test_removed_and_replaced_bins_are_final() {
+        for state in LifecycleState::ALL {
+            assert!(!LifecycleState::Removed.can_become(state));
+            assert!(!LifecycleState::Replaced.can_become(state));
+            assert!(!state.can_become(state));
+        }
+        assert!(LifecycleState::Planned.can_become(LifecycleState::Active));
+        assert!(!LifecycleState::Planned.can_become(LifecycleState::Damaged));
+        assert!(LifecycleState::UnderMaintenance.can_become(LifecycleState::Active));
+    }
+
+    #[test]
+    fn test_inactive_legacy_bins_read_as_removed() {
+        let bin = |fields: &str| {
+            let json = format!(
+                r#"{{"bin_id":"b-1","location_id":"l-1","status":40,"last_updated":"2024-03-20T10:00:00Z"{}}}"#,
+                fields
+            );
+            serde_json::from_str::<TrashBin>(&json).unwrap().lifecycle
+        };
+
+        assert_eq!(bin(r#","is_active":false"#), LifecycleState::Removed);
+        assert_eq!(bin(r#","is_active":true"#), LifecycleState::Active);
+        assert_eq!(bin(""), LifecycleState::Active);
+        assert_eq!(bin(r#","is_active":false,"lifecycle":"damaged""#), LifecycleState::Damaged);
+
+        let stored = serde_json::to_value(serde_json::from_str::<TrashBin>(
+            r#"{"bin_id":"b-1","location_id":"l-1","status":40,"last_updated":"2024-03-20T10:00:00Z","is_active":false}"#,
+        ).unwrap()).unwrap();
+        assert_eq!(stored["lifecycle"], "removed");
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::domain::{LifecycleState, WasteType};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusUpdateRequest {
     pub bin_id: String,
@@ -21,12 +23,51 @@ pub struct ApiResponse<T> {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "BinStatusRecord")]
 pub struct BinStatusDto {
     pub bin_id: String,
     pub location_id: String,
     pub status: i32,
     pub last_updated: String,
-    pub is_active: bool,
+    pub lifecycle: LifecycleState,
+    pub waste_type: WasteType,
+    pub capacity_liters: Option<u32>,
+    pub container_model: Option<String>,
+}
+
+/// A bin status as sent before or after lifecycle states replaced `is_active`,
+/// with or without the bin profile.
+#[derive(Deserialize)]
+struct BinStatusRecord {
+    bin_id: String,
+    location_id: String,
+    status: i32,
+    last_updated: String,
+    #[serde(default)]
+    lifecycle: Option<LifecycleState>,
+    #[serde(default)]
+    is_active: Option<bool>,
+    #[serde(default)]
+    waste_type: WasteType,
+    #[serde(default)]
+    capacity_liters: Option<u32>,
+    #[serde(default)]
+    container_model: Option<String>,
+}
+
+impl From<BinStatusRecord> for BinStatusDto {
+    fn from(record: BinStatusRecord) -> Self {
+        BinStatusDto {
+            bin_id: record.bin_id,
+            location_id: record.location_id,
+            status: record.status,
+            last_updated: record.last_updated,
+            lifecycle: LifecycleState::or_legacy(record.lifecycle, record.is_active),
+            waste_type: record.waste_type,
+            capacity_liters: record.capacity_liters,
+            container_model: record.container_model,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
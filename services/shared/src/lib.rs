@@ -1,8 +1,12 @@
+pub mod clock;
 pub mod domain;
 pub mod dto;
+pub mod logging;
+pub mod metrics;
 pub mod utils;
 
 // Re-export common types for convenience
+pub use clock::*;
 pub use domain::*;
 pub use dto::*;
 pub use utils::*;
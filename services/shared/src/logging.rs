@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{span, Event, Subscriber};
+use tracing_subscriber::field::MakeExt;
+use tracing_subscriber::fmt::format::{self as format, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Fields whose values never reach the logs, wherever they are recorded.
+pub const REDACTED_FIELDS: &[&str] = &[
+    "reporter_ip",
+    "source_ip",
+    "client_ip",
+    "device_key",
+    "api_key",
+    "authorization",
+    "webhook_secret",
+];
+
+/// Span fields lifted onto every event logged inside the span, so a single
+/// log line can be tied back to its invocation and trace.
+pub const CONTEXT_FIELDS: &[&str] = &["request_id", "trace_id"];
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One JSON object per line, for CloudWatch Logs Insights.
+    #[default]
+    Json,
+    /// Human-readable lines for local development.
+    Pretty,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(LogFormat::Json),
+            "pretty" | "text" => Ok(LogFormat::Pretty),
+            other => Err(format!("Unknown log format: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggingConfig {
+    /// `EnvFilter` directives, e.g. `info` or `warn,bin_status_reporter=debug`.
+    pub directives: String,
+    pub format: LogFormat,
+}
+
+impl LoggingConfig {
+    pub fn new(directives: impl Into<String>, format: LogFormat) -> Self {
+        Self { directives: directives.into(), format }
+    }
+
+    /// Reads `LOG_LEVEL` (default `info`) and `LOG_FORMAT` (default `json`).
+    pub fn from_env() -> Self {
+        let directives = std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+        let format = std::env::var("LOG_FORMAT")
+            .ok()
+            .and_then(|format| format.parse().ok())
+            .unwrap_or_default();
+        Self::new(directives, format)
+    }
+
+    fn filter(&self) -> EnvFilter {
+        EnvFilter::try_new(&self.directives).unwrap_or_else(|e| {
+            eprintln!("Invalid log directives {:?}, falling back to info: {}", self.directives, e);
+            EnvFilter::new("info")
+        })
+    }
+}
+
+/// The filter and formatter every service installs. It is a layer rather
+/// than a subscriber so services can stack tracing exporters next to it.
+pub fn layer<S>(config: &LoggingConfig) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    build(config, std::io::stdout, std::io::stdout().is_terminal())
+}
+
+/// Installs [`layer`] as the global subscriber.
+pub fn init(config: &LoggingConfig) {
+    tracing_subscriber::registry().with(layer(config)).init();
+}
+
+fn build<S, W>(config: &LoggingConfig, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let filter = config.filter();
+    match config.format {
+        LogFormat::Json => filter
+            .and_then(SpanFieldsLayer)
+            .and_then(tracing_subscriber::fmt::layer().event_format(JsonFormat).with_writer(writer))
+            .boxed(),
+        LogFormat::Pretty => {
+            let fields = format::debug_fn(|writer, field, value| {
+                if is_redacted(field.name()) {
+                    write!(writer, "{}={}", field, REDACTED)
+                } else if field.name() == "message" {
+                    write!(writer, "{:?}", value)
+                } else {
+                    write!(writer, "{}={:?}", field, value)
+                }
+            })
+            .delimited(" ");
+            filter
+                .and_then(
+                    tracing_subscriber::fmt::layer()
+                        .fmt_fields(fields)
+                        .with_ansi(ansi)
+                        .with_writer(writer),
+                )
+                .boxed()
+        }
+    }
+}
+
+fn is_redacted(name: &str) -> bool {
+    REDACTED_FIELDS.contains(&name)
+}
+
+/// Field values of an event or span, with sensitive ones already replaced.
+#[derive(Debug, Default)]
+struct FieldMap(BTreeMap<String, Value>);
+
+impl FieldMap {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let value = if is_redacted(field.name()) { Value::from(REDACTED) } else { value };
+        self.0.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for FieldMap {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::from(format!("{:?}", value)));
+    }
+}
+
+/// Keeps each span's fields in its extensions for [`JsonFormat`], including
+/// the ones recorded after the span was created such as `trace_id`.
+struct SpanFieldsLayer;
+
+impl<S> Layer<S> for SpanFieldsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = FieldMap::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<FieldMap>() {
+                values.record(fields);
+            }
+        }
+    }
+}
+
+/// Writes `{timestamp, level, target, message, request_id, trace_id,
+/// fields, spans}` per event. The innermost span that carries a context
+/// field wins.
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'w> FormatFields<'w> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = FieldMap::default();
+        event.record(&mut fields);
+
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Value::from(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)));
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+        if let Some(message) = fields.0.remove("message") {
+            line.insert("message".to_string(), message);
+        }
+
+        let mut spans = Vec::new();
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let mut entry = Map::new();
+                entry.insert("name".to_string(), Value::from(span.name()));
+                if let Some(span_fields) = span.extensions().get::<FieldMap>() {
+                    for (key, value) in &span_fields.0 {
+                        if CONTEXT_FIELDS.contains(&key.as_str()) {
+                            line.insert(key.clone(), value.clone());
+                        }
+                        entry.insert(key.clone(), value.clone());
+                    }
+                }
+                spans.push(Value::Object(entry));
+            }
+        }
+
+        if !fields.0.is_empty() {
+            line.insert("fields".to_string(), Value::Object(fields.0.into_iter().collect()));
+        }
+        if !spans.is_empty() {
+            line.insert("spans".to_string(), Value::Array(spans));
+        }
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing::{field, info, info_span};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(config: LoggingConfig, emit: impl FnOnce()) -> String {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(build(&config, move || writer.clone(), false));
+        tracing::subscriber::with_default(subscriber, emit);
+        buffer.contents()
+    }
+
+    #[test]
+    fn test_json_events_carry_invocation_context() {
+        let output = capture(LoggingConfig::new("info", LogFormat::Json), || {
+            let span = info_span!("invocation", request_id = "req-1", trace_id = field::Empty);
+            span.record("trace_id", "4bf92f3577b34da6a3ce929d0e0e4736");
+            let _entered = span.enter();
+            info!(bin_id = "bin-1", reporter_ip = "203.0.113.7", "Status update received");
+        });
+
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "Status update received");
+        assert_eq!(line["request_id"], "req-1");
+        assert_eq!(line["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(line["fields"]["bin_id"], "bin-1");
+        assert_eq!(line["fields"]["reporter_ip"], REDACTED);
+        assert_eq!(line["spans"][0]["name"], "invocation");
+        assert!(!output.contains("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_pretty_output_redacts_span_and_event_fields() {
+        let output = capture(LoggingConfig::new("info", LogFormat::Pretty), || {
+            let span = info_span!("sensor", device_key = "s3cret");
+            let _entered = span.enter();
+            info!(device_id = "sensor-1", source_ip = "198.51.100.4", "Reading received");
+        });
+
+        assert!(output.contains("Reading received"));
+        assert!(output.contains("device_id=\"sensor-1\""));
+        assert!(output.contains(REDACTED));
+        assert!(!output.contains("s3cret"));
+        assert!(!output.contains("198.51.100.4"));
+    }
+
+    #[test]
+    fn test_directives_filter_events() {
+        let output = capture(LoggingConfig::new("WARN", LogFormat::Json), || {
+            info!("dropped");
+            tracing::warn!("kept");
+        });
+
+        assert!(!output.contains("dropped"));
+        assert!(output.contains("kept"));
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Pretty));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
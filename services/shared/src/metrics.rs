@@ -0,0 +1,124 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Recorded by repository decorators around database calls
+pub const DATABASE_LATENCY: &str = "DatabaseLatency";
+pub const DATABASE_RETRIES: &str = "DatabaseRetries";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Count,
+    Milliseconds,
+}
+
+impl Unit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Count => "Count",
+            Unit::Milliseconds => "Milliseconds",
+        }
+    }
+}
+
+/// Where metrics go. Lambdas emit CloudWatch EMF, long-running services
+/// expose Prometheus, and tests capture samples to assert on them.
+pub trait MetricsRecorder: Send + Sync {
+    fn record(&self, name: &str, value: f64, unit: Unit, dimensions: &[(&str, &str)]);
+
+    fn increment(&self, name: &str, dimensions: &[(&str, &str)]) {
+        self.record(name, 1.0, Unit::Count, dimensions);
+    }
+
+    fn timing(&self, name: &str, elapsed: Duration, dimensions: &[(&str, &str)]) {
+        self.record(name, elapsed.as_secs_f64() * 1000.0, Unit::Milliseconds, dimensions);
+    }
+}
+
+/// Discards everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopRecorder;
+
+impl MetricsRecorder for NoopRecorder {
+    fn record(&self, _name: &str, _value: f64, _unit: Unit, _dimensions: &[(&str, &str)]) {}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub value: f64,
+    pub unit: Unit,
+    pub dimensions: Vec<(String, String)>,
+}
+
+impl Sample {
+    pub fn dimension(&self, key: &str) -> Option<&str> {
+        self.dimensions
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Keeps every sample for tests. Clones share the same samples.
+#[derive(Debug, Clone, Default)]
+pub struct CapturingRecorder {
+    samples: Arc<Mutex<Vec<Sample>>>,
+}
+
+impl CapturingRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn samples(&self) -> Vec<Sample> {
+        self.lock().clone()
+    }
+
+    /// Sum of the values recorded under `name` whose dimensions include
+    /// every pair in `dimensions`.
+    pub fn total(&self, name: &str, dimensions: &[(&str, &str)]) -> f64 {
+        self.lock()
+            .iter()
+            .filter(|sample| sample.name == name)
+            .filter(|sample| dimensions.iter().all(|(key, value)| sample.dimension(key) == Some(*value)))
+            .map(|sample| sample.value)
+            .sum()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Sample>> {
+        self.samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl MetricsRecorder for CapturingRecorder {
+    fn record(&self, name: &str, value: f64, unit: Unit, dimensions: &[(&str, &str)]) {
+        self.lock().push(Sample {
+            name: name.to_string(),
+            value,
+            unit,
+            dimensions: dimensions
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capturing_recorder_totals_by_dimension() {
+        let recorder = CapturingRecorder::new();
+        recorder.increment("reports_rejected", &[("reason", "invalid_request")]);
+        recorder.increment("reports_rejected", &[("reason", "bin_not_found")]);
+        recorder.record("reports_rejected", 2.0, Unit::Count, &[("reason", "invalid_request")]);
+        recorder.timing("db_latency", Duration::from_millis(12), &[]);
+
+        assert_eq!(recorder.total("reports_rejected", &[]), 4.0);
+        assert_eq!(recorder.total("reports_rejected", &[("reason", "invalid_request")]), 3.0);
+        assert_eq!(recorder.samples()[3].unit, Unit::Milliseconds);
+        assert_eq!(recorder.samples()[3].value, 12.0);
+    }
+}
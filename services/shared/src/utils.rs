@@ -1,12 +1,15 @@
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
 
+use crate::clock::{Clock, IdGenerator, SystemClock, UuidGenerator};
+
+/// Prefer taking an `IdGenerator` where ids must be predictable in tests.
 pub fn generate_id() -> String {
-    Uuid::new_v4().to_string()
+    UuidGenerator.next_id().to_string()
 }
 
+/// Prefer taking a `Clock` where time must be controllable in tests.
 pub fn current_timestamp() -> DateTime<Utc> {
-    Utc::now()
+    SystemClock.now()
 }
 
 pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {